@@ -0,0 +1,92 @@
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::oneshot;
+
+use crate::core::ports::approval::{ApprovalDecision, ApprovalPort};
+
+static PENDING_APPROVALS: LazyLock<Mutex<HashMap<String, oneshot::Sender<ApprovalDecision>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Approvals answered through `resolve_approval`, e.g. from the `respond_to_write_approval`
+/// command.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PendingApprovals;
+
+impl PendingApprovals {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Drops the pending entry when the waiting run is cancelled or finishes.
+struct PendingGuard {
+    approval_id: String,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = PENDING_APPROVALS.lock() {
+            pending.remove(&self.approval_id);
+        }
+    }
+}
+
+impl ApprovalPort for PendingApprovals {
+    fn request(&self, approval_id: &str) -> BoxFuture<'static, ApprovalDecision> {
+        let (tx, rx) = oneshot::channel();
+        if let Ok(mut pending) = PENDING_APPROVALS.lock() {
+            pending.insert(approval_id.to_string(), tx);
+        }
+        let guard = PendingGuard {
+            approval_id: approval_id.to_string(),
+        };
+        Box::pin(async move {
+            let _guard = guard;
+            rx.await.unwrap_or(ApprovalDecision::Rejected {
+                reason: Some("Approval request was abandoned".to_string()),
+            })
+        })
+    }
+}
+
+/// Deliver the user's decision. Returns false when no run is waiting on `approval_id`.
+pub fn resolve_approval(approval_id: &str, decision: ApprovalDecision) -> bool {
+    let sender = PENDING_APPROVALS
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(approval_id));
+    match sender {
+        Some(sender) => sender.send(decision).is_ok(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_approval, PendingApprovals};
+    use crate::core::ports::approval::{ApprovalDecision, ApprovalPort};
+
+    #[tokio::test]
+    async fn resolves_pending_approval_once() {
+        let approvals = PendingApprovals::new();
+        let approval_id = uuid::Uuid::new_v4().to_string();
+        let decision = approvals.request(&approval_id);
+
+        assert!(resolve_approval(&approval_id, ApprovalDecision::Approved));
+        assert!(!resolve_approval(&approval_id, ApprovalDecision::Approved));
+        assert_eq!(decision.await, ApprovalDecision::Approved);
+    }
+
+    #[test]
+    fn dropped_request_is_no_longer_pending() {
+        let approvals = PendingApprovals::new();
+        let approval_id = uuid::Uuid::new_v4().to_string();
+        drop(approvals.request(&approval_id));
+
+        assert!(!resolve_approval(
+            &approval_id,
+            ApprovalDecision::Rejected { reason: None }
+        ));
+    }
+}
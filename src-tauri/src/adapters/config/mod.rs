@@ -3,10 +3,12 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{LazyLock, Mutex};
 
+use crate::core::agent::ApprovalPolicy;
+
 /// Bump this when adding new fields with non-trivial defaults.
 /// When a loaded config has a lower version, it is re-saved to disk
 /// so that users see the new keys in their `config.toml`.
-const CURRENT_CONFIG_VERSION: u32 = 2;
+const CURRENT_CONFIG_VERSION: u32 = 3;
 static GLOBAL_SETTINGS_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
 fn default_retrieval_rerank_enabled() -> bool {
@@ -17,11 +19,135 @@ fn default_retrieval_rerank_top_k() -> u32 {
     8
 }
 
+fn default_history_token_window() -> u32 {
+    16_000
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct OauthClientConfig {
     pub client_id: String,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+/// An external MCP server whose tools are mounted into the agent as `name__tool`.
+/// Exactly one of `command` (stdio transport) or `url` (streamable HTTP) is set.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExternalMcpServerConfig {
+    pub name: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for ExternalMcpServerConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            url: None,
+            headers: HashMap::new(),
+            enabled: true,
+        }
+    }
+}
+
+impl ExternalMcpServerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("MCP server name is required".to_string());
+        }
+        if name.contains("__")
+            || !name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+        {
+            return Err(format!(
+                "Invalid MCP server name '{name}'. Use letters, numbers, '-' or '_' (no '__')."
+            ));
+        }
+        let has_command = self
+            .command
+            .as_deref()
+            .is_some_and(|command| !command.trim().is_empty());
+        let has_url = self
+            .url
+            .as_deref()
+            .is_some_and(|url| !url.trim().is_empty());
+        match (has_command, has_url) {
+            (true, false) | (false, true) => Ok(()),
+            _ => Err(format!(
+                "MCP server '{name}' needs exactly one of command or url"
+            )),
+        }
+    }
+}
+
+/// A named OpenAI-compatible endpoint (vLLM, llama.cpp server, Groq, Mistral, a gateway)
+/// registered as provider `id`. Reusing a built-in id such as `ollama` overrides its URL.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProviderInstanceConfig {
+    pub id: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// API root including the version segment, e.g. `http://gpu-box:8000/v1`.
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default = "default_true")]
+    pub chat: bool,
+    #[serde(default)]
+    pub embeddings: bool,
+}
+
+impl ProviderInstanceConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let id = self.id.trim();
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.')
+        {
+            return Err(format!(
+                "Invalid provider id '{id}'. Use letters, numbers, '-', '_' or '.'."
+            ));
+        }
+        let base_url = self.base_url.trim();
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(format!(
+                "Provider '{id}' needs an http(s) base_url, got '{base_url}'"
+            ));
+        }
+        if !self.chat && !self.embeddings {
+            return Err(format!(
+                "Provider '{id}' must enable chat, embeddings, or both"
+            ));
+        }
+        Ok(())
+    }
+
+    /// `base_url` joined with an endpoint path such as `chat/completions`.
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim().trim_end_matches('/'), path)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct OauthTokenConfig {
     pub access_token: String,
@@ -55,10 +181,21 @@ pub struct Settings {
     pub retrieval_rerank_enabled: bool,
     #[serde(default = "default_retrieval_rerank_top_k")]
     pub retrieval_rerank_top_k: u32,
+    #[serde(default = "default_history_token_window")]
+    pub history_token_window: u32,
     pub search_provider: Option<String>,
     pub searxng_base_url: Option<String>,
     #[serde(default)]
     pub recent_vaults: Vec<String>,
+    #[serde(default)]
+    pub mcp_servers: Vec<ExternalMcpServerConfig>,
+    #[serde(default)]
+    pub provider_instances: Vec<ProviderInstanceConfig>,
+    /// `auto` (default), `always`, or `outside_folders`.
+    pub write_approval: Option<String>,
+    /// Folders where writes skip approval under `outside_folders`.
+    #[serde(default)]
+    pub write_approval_folders: Vec<String>,
     pub openai_api_key: Option<String>,
     pub anthropic_api_key: Option<String>,
     pub google_api_key: Option<String>,
@@ -75,9 +212,30 @@ pub struct VaultConfig {
     pub embedding_model_id: Option<String>,
     pub retrieval_rerank_enabled: Option<bool>,
     pub retrieval_rerank_top_k: Option<u32>,
+    pub history_token_window: Option<u32>,
     pub user_language: Option<String>,
     pub search_provider: Option<String>,
     pub searxng_base_url: Option<String>,
+    pub mcp_servers: Option<Vec<ExternalMcpServerConfig>>,
+    pub write_approval: Option<String>,
+    pub write_approval_folders: Option<Vec<String>>,
+    /// Where history is recorded: `isolated` (default, meld's own `.meld/.git`), `head`
+    /// (the vault's git repository, on the checked-out branch) or `branch` (that
+    /// repository, on `git_branch`).
+    pub git_mode: Option<String>,
+    /// Branch meld commits to under `git_mode = "branch"` (default `meld`).
+    pub git_branch: Option<String>,
+    /// Signature on meld's commits in the vault's git repository.
+    pub git_author_name: Option<String>,
+    pub git_author_email: Option<String>,
+    /// Also leave out notes the vault's `.gitignore` ignores (`.meldignore` always applies).
+    pub respect_gitignore: Option<bool>,
+    /// Also leave out Obsidian's excluded files (`userIgnoreFilters` in `.obsidian/app.json`).
+    pub respect_obsidian_excluded: Option<bool>,
+    /// Tags that keep a note away from the agent (default `private`), besides `ai: false`.
+    pub private_tags: Option<Vec<String>>,
+    /// Folders whose notes are kept away from the agent.
+    pub private_folders: Option<Vec<String>>,
 }
 
 impl VaultConfig {
@@ -110,9 +268,14 @@ impl Default for Settings {
             oauth_tokens: HashMap::new(),
             retrieval_rerank_enabled: default_retrieval_rerank_enabled(),
             retrieval_rerank_top_k: default_retrieval_rerank_top_k(),
+            history_token_window: default_history_token_window(),
             search_provider: None,
             searxng_base_url: None,
             recent_vaults: Vec::new(),
+            mcp_servers: Vec::new(),
+            provider_instances: Vec::new(),
+            write_approval: None,
+            write_approval_folders: Vec::new(),
             openai_api_key: None,
             anthropic_api_key: None,
             google_api_key: None,
@@ -275,6 +438,9 @@ impl Settings {
         if let Some(v) = vc.retrieval_rerank_top_k {
             merged.retrieval_rerank_top_k = v;
         }
+        if let Some(v) = vc.history_token_window {
+            merged.history_token_window = v;
+        }
         if let Some(ref v) = vc.user_language {
             merged.user_language = Some(v.clone());
         }
@@ -284,6 +450,20 @@ impl Settings {
         if let Some(ref v) = vc.searxng_base_url {
             merged.searxng_base_url = Some(v.clone());
         }
+        if let Some(ref servers) = vc.mcp_servers {
+            for server in servers {
+                merged
+                    .mcp_servers
+                    .retain(|existing| existing.name != server.name);
+                merged.mcp_servers.push(server.clone());
+            }
+        }
+        if let Some(ref v) = vc.write_approval {
+            merged.write_approval = Some(v.clone());
+        }
+        if let Some(ref v) = vc.write_approval_folders {
+            merged.write_approval_folders = v.clone();
+        }
         merged
     }
 
@@ -563,6 +743,127 @@ impl Settings {
         }
     }
 
+    pub fn set_mcp_servers(&mut self, servers: Vec<ExternalMcpServerConfig>) -> Result<(), String> {
+        let mut names = std::collections::HashSet::new();
+        for server in &servers {
+            server.validate()?;
+            if !names.insert(server.name.trim().to_string()) {
+                return Err(format!(
+                    "Duplicate MCP server name '{}'",
+                    server.name.trim()
+                ));
+            }
+        }
+        self.mcp_servers = servers
+            .into_iter()
+            .map(|server| ExternalMcpServerConfig {
+                name: server.name.trim().to_string(),
+                ..server
+            })
+            .collect();
+        Ok(())
+    }
+
+    pub fn set_write_approval(&mut self, mode: &str, folders: Vec<String>) -> Result<(), String> {
+        let mode = mode.trim().to_ascii_lowercase();
+        if !matches!(mode.as_str(), "auto" | "always" | "outside_folders") {
+            return Err(format!(
+                "Unsupported write approval mode '{mode}'. Supported: auto, always, outside_folders"
+            ));
+        }
+        self.write_approval = Some(mode);
+        self.write_approval_folders = folders
+            .into_iter()
+            .map(|folder| folder.trim().to_string())
+            .filter(|folder| !folder.is_empty())
+            .collect();
+        Ok(())
+    }
+
+    /// Which agent writes wait for the user. Unknown modes fall back to asking every time.
+    pub fn write_approval_policy(&self) -> ApprovalPolicy {
+        match self.write_approval.as_deref().map(str::trim) {
+            None | Some("") | Some("auto") => ApprovalPolicy::Auto,
+            Some("outside_folders") => {
+                ApprovalPolicy::OutsideFolders(self.write_approval_folders.clone())
+            }
+            Some("always") => ApprovalPolicy::Always,
+            Some(other) => {
+                log::warn!("unknown write_approval '{other}', asking for every write");
+                ApprovalPolicy::Always
+            }
+        }
+    }
+
+    /// Enabled, valid external MCP servers. Invalid entries from hand-edited configs are skipped.
+    pub fn mcp_servers(&self) -> Vec<ExternalMcpServerConfig> {
+        self.mcp_servers
+            .iter()
+            .filter(|server| server.enabled)
+            .filter(|server| match server.validate() {
+                Ok(()) => true,
+                Err(error) => {
+                    log::warn!("skipping MCP server config: {error}");
+                    false
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_provider_instances(
+        &mut self,
+        instances: Vec<ProviderInstanceConfig>,
+    ) -> Result<(), String> {
+        let mut ids = std::collections::HashSet::new();
+        for instance in &instances {
+            instance.validate()?;
+            if !ids.insert(instance.id.trim().to_ascii_lowercase()) {
+                return Err(format!("Duplicate provider id '{}'", instance.id.trim()));
+            }
+        }
+        self.provider_instances = instances
+            .into_iter()
+            .map(|instance| ProviderInstanceConfig {
+                id: instance.id.trim().to_ascii_lowercase(),
+                base_url: instance.base_url.trim().to_string(),
+                ..instance
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Valid provider instances. Invalid entries from hand-edited configs are skipped.
+    pub fn provider_instances(&self) -> Vec<ProviderInstanceConfig> {
+        self.provider_instances
+            .iter()
+            .filter(|instance| match instance.validate() {
+                Ok(()) => true,
+                Err(error) => {
+                    log::warn!("skipping provider instance config: {error}");
+                    false
+                }
+            })
+            .map(|instance| ProviderInstanceConfig {
+                id: instance.id.trim().to_ascii_lowercase(),
+                ..instance.clone()
+            })
+            .collect()
+    }
+
+    pub fn provider_instance(&self, provider: &str) -> Option<ProviderInstanceConfig> {
+        let normalized_provider = provider.trim().to_ascii_lowercase();
+        self.provider_instances()
+            .into_iter()
+            .find(|instance| instance.id == normalized_provider)
+    }
+
+    /// Local runtimes and self-hosted instances may run without an API key.
+    pub fn provider_requires_api_key(&self, provider: &str) -> bool {
+        !crate::adapters::providers::is_local_provider(&provider.trim().to_ascii_lowercase())
+            && self.provider_instance(provider).is_none()
+    }
+
     pub fn fallback_chat_model_id(&self) -> Option<String> {
         self.fallback_chat_model_id
             .as_deref()
@@ -661,6 +962,12 @@ impl Settings {
         self.retrieval_rerank_top_k.clamp(1, 50) as usize
     }
 
+    /// Approximate token budget for prior conversation turns replayed into a run.
+    /// Zero disables history replay.
+    pub fn history_token_window(&self) -> u64 {
+        self.history_token_window.min(200_000) as u64
+    }
+
     pub fn push_recent_vault(&mut self, path: &str) {
         let Some(normalized) = Self::normalize_vault_path(path) else {
             return;
@@ -682,7 +989,10 @@ impl Settings {
             "anthropic" => self.anthropic_api_key.clone(),
             "google" => self.google_api_key.clone(),
             "tavily" => self.tavily_api_key.clone(),
-            _ => None,
+            _ => self
+                .provider_instance(&normalized_provider)
+                .and_then(|instance| instance.api_key)
+                .filter(|key| !key.trim().is_empty()),
         }
     }
 
@@ -728,7 +1038,10 @@ impl Settings {
 
 #[cfg(test)]
 mod tests {
-    use super::{Settings, VaultConfig};
+    use super::{
+        ApprovalPolicy, ExternalMcpServerConfig, ProviderInstanceConfig, Settings, VaultConfig,
+    };
+    use std::collections::HashMap;
 
     #[test]
     fn set_fallback_chat_model_accepts_valid_id() {
@@ -773,6 +1086,35 @@ mod tests {
         assert_eq!(settings.openai_api_key, Some("sk-test".to_string()));
     }
 
+    #[test]
+    fn write_approval_policy_is_overridable_per_vault() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.write_approval_policy(), ApprovalPolicy::Auto);
+        assert!(settings
+            .set_write_approval("sometimes", Vec::new())
+            .is_err());
+
+        settings
+            .set_write_approval(
+                "outside_folders",
+                vec![" inbox ".to_string(), "".to_string()],
+            )
+            .expect("valid mode");
+        assert_eq!(
+            settings.write_approval_policy(),
+            ApprovalPolicy::OutsideFolders(vec!["inbox".to_string()])
+        );
+
+        let vc = VaultConfig {
+            write_approval: Some("always".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.merged_with_vault(&vc).write_approval_policy(),
+            ApprovalPolicy::Always
+        );
+    }
+
     #[test]
     fn push_recent_vault_normalizes_and_deduplicates() {
         let mut settings = Settings::default();
@@ -821,6 +1163,22 @@ mod tests {
         assert_eq!(settings.retrieval_rerank_top_k(), 50);
     }
 
+    #[test]
+    fn history_token_window_is_capped_and_overridable_per_vault() {
+        assert_eq!(Settings::default().history_token_window(), 16_000);
+        let settings = Settings {
+            history_token_window: 5_000_000,
+            ..Settings::default()
+        };
+        assert_eq!(settings.history_token_window(), 200_000);
+
+        let vc = VaultConfig {
+            history_token_window: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(settings.merged_with_vault(&vc).history_token_window(), 0);
+    }
+
     #[test]
     fn set_model_keeps_existing_embedding_configuration() {
         let mut settings = Settings::default();
@@ -977,6 +1335,107 @@ mod tests {
         assert_eq!(merged.embedding_model_id(), settings.embedding_model_id());
     }
 
+    #[test]
+    fn merge_vault_mcp_servers_override_by_name() {
+        let mut settings = Settings::default();
+        settings
+            .set_mcp_servers(vec![
+                ExternalMcpServerConfig {
+                    name: "tracker".to_string(),
+                    command: Some("tracker-mcp".to_string()),
+                    ..Default::default()
+                },
+                ExternalMcpServerConfig {
+                    name: "calendar".to_string(),
+                    url: Some("http://localhost:9000/mcp".to_string()),
+                    ..Default::default()
+                },
+            ])
+            .expect("set servers");
+        let vc = VaultConfig {
+            mcp_servers: Some(vec![ExternalMcpServerConfig {
+                name: "tracker".to_string(),
+                command: Some("tracker-mcp".to_string()),
+                enabled: false,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        let merged = settings.merged_with_vault(&vc);
+        let names: Vec<String> = merged
+            .mcp_servers()
+            .into_iter()
+            .map(|server| server.name)
+            .collect();
+        assert_eq!(names, vec!["calendar".to_string()]);
+    }
+
+    #[test]
+    fn set_mcp_servers_rejects_invalid_entries() {
+        let mut settings = Settings::default();
+        let both = ExternalMcpServerConfig {
+            name: "x".to_string(),
+            command: Some("x".to_string()),
+            url: Some("http://localhost".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.set_mcp_servers(vec![both]).is_err());
+        let bad_name = ExternalMcpServerConfig {
+            name: "a__b".to_string(),
+            command: Some("x".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.set_mcp_servers(vec![bad_name]).is_err());
+        let ok = ExternalMcpServerConfig {
+            name: "ok".to_string(),
+            command: Some("x".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.set_mcp_servers(vec![ok.clone(), ok]).is_err());
+    }
+
+    #[test]
+    fn provider_instances_validate_and_supply_api_keys() {
+        let mut settings = Settings::default();
+        let vllm = ProviderInstanceConfig {
+            id: " vLLM ".to_string(),
+            display_name: None,
+            base_url: "http://10.0.0.5:8000/v1".to_string(),
+            api_key: Some("secret".to_string()),
+            headers: HashMap::new(),
+            chat: true,
+            embeddings: false,
+        };
+        settings
+            .set_provider_instances(vec![vllm.clone()])
+            .expect("valid instance");
+        assert_eq!(settings.provider_instances()[0].id, "vllm");
+        assert_eq!(
+            settings.api_key_for_provider("vllm").as_deref(),
+            Some("secret")
+        );
+        assert!(!settings.provider_requires_api_key("vllm"));
+        assert!(!settings.provider_requires_api_key("ollama"));
+        assert!(settings.provider_requires_api_key("openai"));
+
+        let bad_url = ProviderInstanceConfig {
+            base_url: "10.0.0.5:8000".to_string(),
+            ..vllm.clone()
+        };
+        assert!(settings.set_provider_instances(vec![bad_url]).is_err());
+        let nothing_enabled = ProviderInstanceConfig {
+            chat: false,
+            ..vllm.clone()
+        };
+        assert!(settings
+            .set_provider_instances(vec![nothing_enabled])
+            .is_err());
+        assert!(settings
+            .set_provider_instances(vec![vllm.clone(), vllm])
+            .is_err());
+    }
+
     #[test]
     fn merge_preserves_credentials() {
         let mut settings = Settings::default();
@@ -1039,6 +1498,7 @@ vault_path = "/tmp/vault"
         assert_eq!(settings.chat_model, Some("gpt-5.2".to_string()));
         assert!(settings.retrieval_rerank_enabled);
         assert_eq!(settings.retrieval_rerank_top_k, 8);
+        assert_eq!(settings.history_token_window, 16_000);
     }
 
     #[test]
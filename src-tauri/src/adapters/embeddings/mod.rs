@@ -1,9 +1,19 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::adapters::providers::EmbeddingApiError;
+
+/// Output dimensions learned from embedding calls, for providers whose size depends on the model.
+static PROBED_DIMENSIONS: LazyLock<Mutex<HashMap<String, usize>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 pub async fn get_embedding(
     api_key: &str,
     model_id: &str,
     text: &str,
 ) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
-    let registry = crate::adapters::providers::ProviderRegistry::default();
+    let registry = crate::adapters::providers::ProviderRegistry::configured();
     let (provider, model) = registry
         .resolve_embedding(model_id)
         .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
@@ -23,10 +33,118 @@ pub async fn get_embedding(
         .ok_or_else(|| "No embedding returned".into())
 }
 
+const EMBEDDING_MAX_ATTEMPTS: u32 = 5;
+const EMBEDDING_BASE_BACKOFF: Duration = Duration::from_millis(500);
+const EMBEDDING_MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Delay before retry `attempt` (1-based), or `None` when the error should not be retried.
+/// A provider-supplied `Retry-After` wins over exponential backoff.
+fn retry_delay(error: &(dyn std::error::Error + 'static), attempt: u32) -> Option<Duration> {
+    if attempt >= EMBEDDING_MAX_ATTEMPTS {
+        return None;
+    }
+    let backoff = EMBEDDING_BASE_BACKOFF.saturating_mul(1 << (attempt - 1));
+    match error.downcast_ref::<EmbeddingApiError>() {
+        Some(api_error) if !api_error.is_retriable() => None,
+        Some(api_error) => Some(
+            api_error
+                .retry_after
+                .unwrap_or(backoff)
+                .min(EMBEDDING_MAX_BACKOFF),
+        ),
+        None => Some(backoff),
+    }
+}
+
+/// Embed `texts` in provider-sized batches, returning one embedding per text in order.
+/// Each batch is retried on rate limits and transient failures.
+pub async fn get_embeddings(
+    api_key: &str,
+    model_id: &str,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+    let registry = crate::adapters::providers::ProviderRegistry::configured();
+    let (provider, model) = registry
+        .resolve_embedding(model_id)
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(provider.max_batch_size().max(1)) {
+        let mut attempt = 1;
+        let batch_embeddings = loop {
+            let result = provider
+                .embed(crate::adapters::providers::EmbeddingRequest {
+                    api_key,
+                    model,
+                    texts: batch,
+                })
+                .await;
+            match result {
+                Ok(batch_embeddings) => break batch_embeddings,
+                Err(error) => {
+                    let Some(delay) = retry_delay(error.as_ref(), attempt) else {
+                        return Err(error);
+                    };
+                    log::warn!(
+                        "Embedding request failed (attempt {attempt}/{EMBEDDING_MAX_ATTEMPTS}), retrying in {}ms: {error}",
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        };
+        if batch_embeddings.len() != batch.len() {
+            return Err(format!(
+                "Embedding provider returned {} embeddings for {} texts",
+                batch_embeddings.len(),
+                batch.len()
+            )
+            .into());
+        }
+        embeddings.extend(batch_embeddings);
+    }
+    Ok(embeddings)
+}
+
+/// Output dimension of `model_id`, embedding a short probe text when the provider does not
+/// declare one. Also fails fast when a local runtime is not running or lacks the model.
+pub async fn probe_dimensions(
+    api_key: &str,
+    model_id: &str,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let registry = crate::adapters::providers::ProviderRegistry::configured();
+    let (provider, _) = registry
+        .resolve_embedding(model_id)
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+    if provider.dimensions() > 0 {
+        return Ok(provider.dimensions());
+    }
+    if let Some(dimension) = probed_dimensions(model_id) {
+        return Ok(dimension);
+    }
+
+    let dimension = get_embedding(api_key, model_id, "dimension probe")
+        .await?
+        .len();
+    if dimension == 0 {
+        return Err(format!("Embedding model '{model_id}' returned an empty embedding").into());
+    }
+    if let Ok(mut probed) = PROBED_DIMENSIONS.lock() {
+        probed.insert(model_id.to_string(), dimension);
+    }
+    Ok(dimension)
+}
+
+fn probed_dimensions(model_id: &str) -> Option<usize> {
+    PROBED_DIMENSIONS.lock().ok()?.get(model_id).copied()
+}
+
 #[allow(dead_code)]
 pub fn embedding_dimensions(model_id: &str) -> usize {
-    let registry = crate::adapters::providers::ProviderRegistry::default();
+    let registry = crate::adapters::providers::ProviderRegistry::configured();
     match registry.resolve_embedding(model_id) {
+        Ok((provider, _)) if provider.dimensions() == 0 => probed_dimensions(model_id).unwrap_or(0),
         Ok((provider, _)) => provider.dimensions(),
         Err(e) => {
             log::warn!(
@@ -38,3 +156,40 @@ pub fn embedding_dimensions(model_id: &str) -> usize {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{retry_delay, EmbeddingApiError, EMBEDDING_MAX_ATTEMPTS};
+    use std::time::Duration;
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> EmbeddingApiError {
+        EmbeddingApiError {
+            provider: "OpenAI".to_string(),
+            status: reqwest::StatusCode::from_u16(status).expect("status"),
+            retry_after,
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn retry_delay_honours_retry_after_and_skips_client_errors() {
+        let limited = api_error(429, Some(Duration::from_secs(7)));
+        assert_eq!(retry_delay(&limited, 1), Some(Duration::from_secs(7)));
+
+        let unavailable = api_error(503, None);
+        assert_eq!(
+            retry_delay(&unavailable, 1),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(retry_delay(&unavailable, 3), Some(Duration::from_secs(2)));
+        assert_eq!(retry_delay(&unavailable, EMBEDDING_MAX_ATTEMPTS), None);
+
+        assert_eq!(retry_delay(&api_error(401, None), 1), None);
+
+        let network: Box<dyn std::error::Error + Send + Sync> = "connection reset".into();
+        assert_eq!(
+            retry_delay(network.as_ref(), 2),
+            Some(Duration::from_secs(1))
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Line-level authorship of a note: which lines were last written by the agent and
+//! which by the user. Run commits are the agent's by their `Meld-Run-Id` trailer; other
+//! commits go by author, and every snapshot of hand edits is authored by the user.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Path;
+
+use git2::{BlameOptions, Oid};
+use serde::Serialize;
+
+use super::VaultRepo;
+
+/// Consecutive lines last written by the same commit.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlameHunk {
+    /// 1-based line number of the first line.
+    pub start_line: usize,
+    pub lines: Vec<String>,
+    /// `"meld"` for agent and app commits, `"user"` for hand edits, including edits not
+    /// snapshotted yet.
+    pub author: String,
+    /// `None` for lines that are not committed yet.
+    pub commit_id: Option<String>,
+    pub timestamp: Option<i64>,
+    pub run_id: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteBlame {
+    pub path: String,
+    pub hunks: Vec<BlameHunk>,
+    pub agent_lines: usize,
+    pub user_lines: usize,
+}
+
+struct CommitInfo {
+    author: String,
+    timestamp: i64,
+    run_id: Option<String>,
+    provider: Option<String>,
+    model: Option<String>,
+}
+
+fn commit_info(vault_repo: &VaultRepo, oid: Oid) -> Result<CommitInfo, git2::Error> {
+    let commit = vault_repo.repo.find_commit(oid)?;
+    let message = commit.message().unwrap_or("");
+    let (provider, model) = super::runs::commit_run_model(message);
+    Ok(CommitInfo {
+        author: vault_repo.author_of(&commit).to_string(),
+        timestamp: commit.time().seconds(),
+        run_id: super::runs::commit_run_id(message),
+        provider,
+        model,
+    })
+}
+
+/// Blame the note at vault-relative `path` as it is on disk. Lines that differ from the
+/// last commit are unsnapshotted hand edits and count as the user's.
+pub fn blame_note(vault_path: &Path, path: &str) -> Result<NoteBlame, Box<dyn std::error::Error>> {
+    let content = crate::adapters::vault::read_note(vault_path, path)?;
+    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    let vault_repo = VaultRepo::open(vault_path)?;
+    // Latest commit, if the note is in it.
+    let tip = vault_repo.tip()?.filter(|tip| {
+        tip.tree()
+            .is_ok_and(|tree| tree.get_path(Path::new(path)).is_ok())
+    });
+
+    // (first 0-based line, line count, commit) for every blame hunk.
+    let mut spans: Vec<(usize, usize, Option<Oid>)> = Vec::new();
+    if let Some(tip) = &tip {
+        let mut opts = BlameOptions::new();
+        opts.newest_commit(tip.id());
+        let blame = vault_repo
+            .repo
+            .blame_file(Path::new(path), Some(&mut opts))?;
+        let blame = blame.blame_buffer(content.as_bytes())?;
+        for hunk in blame.iter() {
+            let oid = hunk.final_commit_id();
+            spans.push((
+                hunk.final_start_line().saturating_sub(1),
+                hunk.lines_in_hunk(),
+                (!oid.is_zero()).then_some(oid),
+            ));
+        }
+    } else {
+        spans.push((0, lines.len(), None));
+    }
+
+    let mut infos: HashMap<Oid, CommitInfo> = HashMap::new();
+    let mut hunks: Vec<BlameHunk> = Vec::new();
+    for (start, count, oid) in spans {
+        let end = (start + count).min(lines.len());
+        if start >= end {
+            continue;
+        }
+        let commit_id = oid.map(|oid| oid.to_string());
+        if let Some(last) = hunks.last_mut() {
+            if last.commit_id == commit_id && last.start_line + last.lines.len() == start + 1 {
+                last.lines.extend_from_slice(&lines[start..end]);
+                continue;
+            }
+        }
+
+        let info = match oid {
+            Some(oid) => {
+                if let Entry::Vacant(entry) = infos.entry(oid) {
+                    entry.insert(commit_info(&vault_repo, oid)?);
+                }
+                infos.get(&oid)
+            }
+            None => None,
+        };
+        hunks.push(BlameHunk {
+            start_line: start + 1,
+            lines: lines[start..end].to_vec(),
+            author: info.map_or("user", |info| info.author.as_str()).to_string(),
+            commit_id,
+            timestamp: info.map(|info| info.timestamp),
+            run_id: info.and_then(|info| info.run_id.clone()),
+            provider: info.and_then(|info| info.provider.clone()),
+            model: info.and_then(|info| info.model.clone()),
+        });
+    }
+    let user_lines = hunks
+        .iter()
+        .filter(|hunk| hunk.author == "user")
+        .map(|hunk| hunk.lines.len())
+        .sum();
+    let agent_lines = hunks
+        .iter()
+        .filter(|hunk| hunk.author != "user")
+        .map(|hunk| hunk.lines.len())
+        .sum();
+
+    Ok(NoteBlame {
+        path: path.to_string(),
+        hunks,
+        agent_lines,
+        user_lines,
+    })
+}
@@ -1,9 +1,26 @@
-use git2::{DiffFormat, IndexEntry, IndexTime, Repository, Signature};
+use git2::{DiffFormat, IndexEntry, IndexTime, Repository};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-const MAX_WALK: usize = 500;
+mod blame;
+mod repo;
+mod revert;
+mod revisions;
+mod runs;
+mod sandbox;
+
+pub use blame::{blame_note, BlameHunk, NoteBlame};
+pub use repo::GitMode;
+pub use revert::{revert_commit, revert_readback_mismatches, ConflictStyle, RevertOutcome};
+pub use revisions::{read_file_at, restore_file};
+pub use runs::{commit_run, revert_run, snapshot_before_write, GitVersioning};
+pub use sandbox::{
+    commit_sandbox, discard_sandbox, merge_sandbox, open_sandbox, sandbox_diff, BranchDiff,
+    BranchMerge,
+};
+
+use repo::VaultRepo;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HistoryEntry {
@@ -11,6 +28,10 @@ pub struct HistoryEntry {
     pub message: String,
     pub timestamp: i64,
     pub files_changed: Vec<String>,
+    /// `"meld"` for agent and app commits, `"user"` for snapshots of hand edits.
+    pub author: String,
+    /// Agent run that made this commit, from its `Meld-Run-Id` trailer.
+    pub run_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,6 +45,13 @@ pub struct CommitDiff {
 
 const MELD_GITIGNORE_ENTRY: &str = ".meld/";
 
+/// Who a commit is attributed to in the vault history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitAuthor {
+    Meld,
+    User,
+}
+
 fn meld_git_dir(vault_path: &Path) -> PathBuf {
     vault_path.join(".meld").join(".git")
 }
@@ -105,29 +133,39 @@ fn collect_tree_markdown_files(
     Ok(())
 }
 
+/// Write the notes `after` changes relative to `before` into the vault and delete notes
+/// that `before` had and `after` does not. Notes the two trees agree on are left alone,
+/// with any hand edits in them, as are notes history never tracked.
 fn apply_tree_snapshot_to_vault(
     repo: &Repository,
-    tree: &git2::Tree<'_>,
+    before: &git2::Tree<'_>,
+    after: &git2::Tree<'_>,
     vault_path: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let mut previous_files = Vec::new();
+    collect_tree_markdown_files(repo, before, "", &mut previous_files)?;
     let mut snapshot_files = Vec::new();
-    collect_tree_markdown_files(repo, tree, "", &mut snapshot_files)?;
+    collect_tree_markdown_files(repo, after, "", &mut snapshot_files)?;
 
     let snapshot_paths: HashSet<String> = snapshot_files
         .iter()
         .map(|(relative, _)| relative.clone())
         .collect();
+    let previous: HashMap<String, Vec<u8>> = previous_files.into_iter().collect();
 
-    for existing in crate::adapters::vault::list_md_files(vault_path)? {
-        if let Ok(relative) = existing.strip_prefix(vault_path) {
-            let relative = relative.to_string_lossy().replace('\\', "/");
-            if !snapshot_paths.contains(&relative) {
-                std::fs::remove_file(existing)?;
+    for relative in previous.keys() {
+        if !snapshot_paths.contains(relative) {
+            let full_path = vault_path.join(relative);
+            if full_path.exists() {
+                std::fs::remove_file(full_path)?;
             }
         }
     }
 
     for (relative, content) in snapshot_files {
+        if previous.get(&relative) == Some(&content) {
+            continue;
+        }
         let full_path = vault_path.join(&relative);
         if let Some(parent) = full_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -143,7 +181,6 @@ pub fn init_repo(vault_path: &Path) -> Result<Repository, git2::Error> {
     open_or_init_meld_repo(vault_path)
 }
 
-#[allow(dead_code)]
 pub fn auto_commit(vault_path: &Path, message: &str) -> Result<(), Box<dyn std::error::Error>> {
     let files = crate::adapters::vault::list_md_files(vault_path)?;
     auto_commit_files(vault_path, &files, message)
@@ -154,135 +191,333 @@ pub fn auto_commit_files(
     files: &[PathBuf],
     message: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let repo = open_or_init_meld_repo(vault_path)?;
-    ensure_vault_gitignore(vault_path)?;
+    commit_files(vault_path, files, message, CommitAuthor::Meld).map(|_| ())
+}
 
-    let mut index = repo.index()?;
-
-    for file in files {
-        if let Ok(relative) = file.strip_prefix(vault_path) {
-            let content = std::fs::read(file)?;
-            let entry = IndexEntry {
-                ctime: IndexTime::new(0, 0),
-                mtime: IndexTime::new(0, 0),
-                dev: 0,
-                ino: 0,
-                mode: 0o100644,
-                uid: 0,
-                gid: 0,
-                file_size: content.len().min(u32::MAX as usize) as u32,
-                id: git2::Oid::zero(),
-                flags: 0,
-                flags_extended: 0,
-                path: relative.to_string_lossy().replace('\\', "/").into_bytes(),
-            };
-            index.add_frombuffer(&entry, &content)?;
-        }
+/// Commit `files` as they are before meld writes over them. Whatever they hold that is
+/// not committed yet was typed by hand, so the snapshot is the user's.
+pub fn snapshot_files(
+    vault_path: &Path,
+    files: &[PathBuf],
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    commit_files(vault_path, files, message, CommitAuthor::User).map(|_| ())
+}
+
+/// Commit hand edits before meld writes over `paths` (vault-relative). The whole vault is
+/// snapshotted, except under `head`, where only `paths` are.
+fn snapshot_hand_edits(
+    vault_path: &Path,
+    paths: &[String],
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let files = if VaultRepo::open_or_init(vault_path)?.commits_hand_edits() {
+        crate::adapters::vault::list_md_files(vault_path)?
+    } else {
+        paths.iter().map(|path| vault_path.join(path)).collect()
+    };
+    commit_files(vault_path, &files, message, CommitAuthor::User).map(|_| ())
+}
+
+/// Record hand edits picked up by the vault watcher as a `user` commit, so later reverts
+/// of agent commits never silently drop them. Deleted paths are removed from history's
+/// tree. Returns `None` when the files already match HEAD, e.g. after an agent write, and
+/// under `head`, where hand edits are left to the user until meld writes those notes.
+pub fn commit_user_edits(
+    vault_path: &Path,
+    paths: &[String],
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    if paths.is_empty() || !VaultRepo::open_or_init(vault_path)?.commits_hand_edits() {
+        return Ok(None);
     }
+    let message = match paths {
+        [path] => format!("user edit: {path}"),
+        _ => format!("user edit: {} notes", paths.len()),
+    };
+    let files: Vec<PathBuf> = paths.iter().map(|path| vault_path.join(path)).collect();
+    let oid = commit_files(vault_path, &files, &message, CommitAuthor::User)?;
+    Ok(oid.map(|oid| oid.to_string()))
+}
 
-    index.write()?;
+/// Index entry for a regular file at vault-relative `path`.
+fn index_entry(path: &str, id: git2::Oid, len: usize) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: len.min(u32::MAX as usize) as u32,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
 
-    let tree_id = index.write_tree()?;
-    let tree = repo.find_tree(tree_id)?;
+/// Stage `files` and commit them, returning the new commit id, or `None` when nothing
+/// changed since HEAD. Files missing from disk are removed from the tree.
+fn commit_files(
+    vault_path: &Path,
+    files: &[PathBuf],
+    message: &str,
+    author: CommitAuthor,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open_or_init(vault_path)?;
+    ensure_vault_gitignore(vault_path)?;
 
-    let sig = Signature::now("meld", "meld@local")?;
+    let relatives: Vec<(String, &PathBuf)> = files
+        .iter()
+        .filter_map(|file| {
+            let relative = file.strip_prefix(vault_path).ok()?;
+            Some((relative.to_string_lossy().replace('\\', "/"), file))
+        })
+        .filter(|(relative, _)| !vault_repo.is_ignored(Path::new(relative)))
+        .collect();
+    let paths: Vec<String> = relatives.iter().map(|(path, _)| path.clone()).collect();
+    let mut index = vault_repo.staging_index(&paths)?;
 
-    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    for (relative, file) in &relatives {
+        if !file.exists() {
+            if index.get_path(Path::new(relative), 0).is_some() {
+                index.remove_path(Path::new(relative))?;
+            }
+            continue;
+        }
+        // Blobs are written to the repo directly: the in-memory index of branch mode has
+        // no repository to write them through.
+        let content = std::fs::read(file)?;
+        let blob = vault_repo.repo.blob(&content)?;
+        index.add(&index_entry(relative, blob, content.len()))?;
+    }
+
+    // Branch mode stages in memory, leaving the repository's own index alone.
+    if index.path().is_some() {
+        index.write()?;
+    }
 
-    match parent {
+    let tree_id = index.write_tree_to(&vault_repo.repo)?;
+    let tree = vault_repo.repo.find_tree(tree_id)?;
+
+    match vault_repo.tip()? {
         Some(parent_commit) => {
             // Check if there are actual changes
             let parent_tree = parent_commit.tree()?;
-            let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)?;
+            let diff = vault_repo
+                .repo
+                .diff_tree_to_tree(Some(&parent_tree), Some(&tree), None)?;
             if diff.deltas().len() == 0 {
-                return Ok(()); // Nothing to commit
+                return Ok(None); // Nothing to commit
             }
 
-            let oid = repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&parent_commit])?;
+            let oid = vault_repo.commit(author, message, &tree, &[&parent_commit])?;
             log::debug!(
                 "git auto-commit: oid={}, files={}, message={}",
                 oid,
                 files.len(),
                 message
             );
+            Ok(Some(oid))
         }
         None => {
-            let oid = repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[])?;
+            let oid = vault_repo.commit(author, message, &tree, &[])?;
             log::debug!("git initial commit: oid={}, message={}", oid, message);
+            Ok(Some(oid))
         }
     }
+}
 
-    Ok(())
+/// Whether the vault has recorded history to read.
+pub fn has_history(vault_path: &Path) -> bool {
+    VaultRepo::exists(vault_path)
 }
 
-pub fn get_history(
-    vault_path: &Path,
-    path_filter: Option<&str>,
-    limit: Option<usize>,
-) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>> {
-    let repo = Repository::open_bare(meld_git_dir(vault_path))?;
+/// Filters and cursor for a page of history, newest first.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// Only commits that changed this vault-relative path.
+    pub path: Option<String>,
+    /// Cursor: return commits older than this commit id (the previous page's `next_cursor`).
+    pub after: Option<String>,
+    /// Page size (default 50).
+    pub limit: Option<usize>,
+    /// `"meld"` (or `"agent"`) for agent and app commits, `"user"` for hand edits.
+    pub author: Option<String>,
+    /// Unix seconds, inclusive.
+    pub since: Option<i64>,
+    /// Unix seconds, inclusive.
+    pub until: Option<i64>,
+    /// Case-insensitive substring of the full commit message.
+    pub message: Option<String>,
+}
 
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-    revwalk.set_sorting(git2::Sort::TIME)?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Pass as `after` to fetch the next page; `None` once history is exhausted.
+    pub next_cursor: Option<String>,
+}
+
+/// Tree of the commit's first parent; `None` for a root commit, which diffs against
+/// the empty tree.
+fn first_parent_tree<'r>(commit: &git2::Commit<'r>) -> Result<Option<git2::Tree<'r>>, git2::Error> {
+    commit
+        .parents()
+        .next()
+        .map(|parent| parent.tree())
+        .transpose()
+}
+
+/// Whether `commit` changed `path` relative to its first parent.
+fn commit_touches_path(
+    repo: &Repository,
+    commit: &git2::Commit<'_>,
+    path: &str,
+) -> Result<bool, git2::Error> {
+    let mut opts = git2::DiffOptions::new();
+    opts.pathspec(path).disable_pathspec_match(true);
+    let diff = repo.diff_tree_to_tree(
+        first_parent_tree(commit)?.as_ref(),
+        Some(&commit.tree()?),
+        Some(&mut opts),
+    )?;
+    Ok(diff.deltas().len() > 0)
+}
+
+pub fn query_history(
+    vault_path: &Path,
+    query: &HistoryQuery,
+) -> Result<HistoryPage, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let repo = &vault_repo.repo;
+    let revwalk = vault_repo.revwalk()?;
+
+    let target = query.limit.unwrap_or(50).max(1);
+    let author = match query.author.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some("meld") | Some("agent") => Some("meld"),
+        Some("user") => Some("user"),
+        Some(other) => {
+            return Err(format!("Unknown author '{other}'. Use 'agent' or 'user'.").into())
+        }
+    };
+    let message_filter = query
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|needle| !needle.is_empty())
+        .map(str::to_lowercase);
+    let path_filter = query
+        .path
+        .as_deref()
+        .map(|path| path.trim().replace('\\', "/"))
+        .filter(|path| !path.is_empty());
+    let mut cursor = match query.after.as_deref() {
+        Some(after) => Some(repo.revparse_single(after)?.peel_to_commit()?.id()),
+        None => None,
+    };
 
-    let target = limit.unwrap_or(50);
     let mut entries = Vec::new();
+    let mut exhausted = true;
 
-    for (walked, oid) in revwalk.enumerate() {
-        if walked >= MAX_WALK || entries.len() >= target {
+    for oid in revwalk {
+        let oid = oid?;
+        if let Some(after) = cursor {
+            if oid == after {
+                cursor = None;
+            }
+            continue;
+        }
+        if entries.len() >= target {
+            exhausted = false;
             break;
         }
 
-        let oid = oid?;
         let commit = repo.find_commit(oid)?;
-
-        let message = commit.message().unwrap_or("").to_string();
-
-        // Get changed files
-        let mut files_changed = Vec::new();
-        if let Some(parent) = commit.parents().next() {
-            let parent_tree = parent.tree()?;
-            let commit_tree = commit.tree()?;
-            let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?;
-            for delta in diff.deltas() {
-                if let Some(path) = delta.new_file().path() {
-                    files_changed.push(path.to_string_lossy().to_string());
-                }
+        let timestamp = commit.time().seconds();
+        if query.since.is_some_and(|since| timestamp < since)
+            || query.until.is_some_and(|until| timestamp > until)
+        {
+            continue;
+        }
+        let commit_author = vault_repo.author_of(&commit);
+        if author.is_some_and(|author| commit_author != author) {
+            continue;
+        }
+        let full_message = commit.message().unwrap_or("");
+        if let Some(needle) = &message_filter {
+            if !full_message.to_lowercase().contains(needle) {
+                continue;
             }
         }
-
         // Skip commits that don't touch the filtered path
-        if let Some(filter) = path_filter {
-            if !files_changed.iter().any(|f| f == filter) {
+        if let Some(path) = &path_filter {
+            if !commit_touches_path(repo, &commit, path)? {
                 continue;
             }
         }
 
+        // Get changed files
+        let mut files_changed = Vec::new();
+        let parent_tree = first_parent_tree(&commit)?;
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        for delta in diff.deltas() {
+            if let Some(path) = delta.new_file().path() {
+                files_changed.push(path.to_string_lossy().to_string());
+            }
+        }
+
         entries.push(HistoryEntry {
             id: oid.to_string(),
-            message,
-            timestamp: commit.time().seconds(),
+            message: commit.summary().unwrap_or("").to_string(),
+            timestamp,
             files_changed,
+            author: commit_author.to_string(),
+            run_id: runs::commit_run_id(full_message),
         });
     }
 
-    Ok(entries)
+    if cursor.is_some() {
+        return Err("History cursor is not an ancestor of HEAD".into());
+    }
+    let next_cursor = if exhausted {
+        None
+    } else {
+        entries.last().map(|entry| entry.id.clone())
+    };
+    Ok(HistoryPage {
+        entries,
+        next_cursor,
+    })
 }
 
-pub fn get_commit_diff(
+/// First page of history, optionally limited to commits that changed `path_filter`.
+pub fn get_history(
     vault_path: &Path,
-    commit_id: &str,
-) -> Result<CommitDiff, Box<dyn std::error::Error>> {
-    let repo = Repository::open_bare(meld_git_dir(vault_path))?;
-    let oid = git2::Oid::from_str(commit_id)?;
-    let commit = repo.find_commit(oid)?;
-
-    let commit_tree = commit.tree()?;
-    let parent_tree = commit.parents().next().and_then(|p| p.tree().ok());
+    path_filter: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>> {
+    let query = HistoryQuery {
+        path: path_filter.map(str::to_string),
+        limit,
+        ..HistoryQuery::default()
+    };
+    Ok(query_history(vault_path, &query)?.entries)
+}
 
-    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)?;
+/// Paths changed by `diff` and its patch text.
+/// Vault-relative paths of the notes a diff changes.
+fn diff_paths(diff: &git2::Diff<'_>) -> Vec<String> {
+    diff.deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
 
+fn diff_files_and_patch(diff: &git2::Diff<'_>) -> Result<(Vec<String>, String), git2::Error> {
     let mut files_changed = Vec::new();
     for delta in diff.deltas() {
         if let Some(path) = delta.new_file().path() {
@@ -301,6 +536,22 @@ pub fn get_commit_diff(
         }
         true
     })?;
+    Ok((files_changed, patch))
+}
+
+pub fn get_commit_diff(
+    vault_path: &Path,
+    commit_id: &str,
+) -> Result<CommitDiff, Box<dyn std::error::Error>> {
+    let repo = VaultRepo::open(vault_path)?.repo;
+    let oid = git2::Oid::from_str(commit_id)?;
+    let commit = repo.find_commit(oid)?;
+
+    let commit_tree = commit.tree()?;
+    let parent_tree = commit.parents().next().and_then(|p| p.tree().ok());
+
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit_tree), None)?;
+    let (files_changed, patch) = diff_files_and_patch(&diff)?;
 
     Ok(CommitDiff {
         id: oid.to_string(),
@@ -311,39 +562,99 @@ pub fn get_commit_diff(
     })
 }
 
-pub fn revert_commit(vault_path: &Path, commit_id: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let repo = Repository::open_bare(meld_git_dir(vault_path))?;
-    let oid = git2::Oid::from_str(commit_id)?;
-    let commit = repo.find_commit(oid)?;
-    let head = repo.head()?.peel_to_commit()?;
-    let mainline = if commit.parent_count() > 1 { 1 } else { 0 };
+/// Unified diff between two versions of a note, as shown in write previews.
+pub fn text_diff(path: &str, before: &str, after: &str) -> Result<String, git2::Error> {
+    let path = Path::new(path);
+    let mut patch = git2::Patch::from_buffers(
+        before.as_bytes(),
+        Some(path),
+        after.as_bytes(),
+        Some(path),
+        None,
+    )?;
+    let buf = patch.to_buf()?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
 
-    let mut index = repo.revert_commit(&commit, &head, mainline, None)?;
-    if index.has_conflicts() {
-        return Err("Revert has conflicts and cannot be applied automatically".into());
+/// Write staged changes into the vault as one commit. Every note is checked before any
+/// is written: the whole set is refused when a path leaves the vault or a note changed
+/// since it was staged, so nothing is half-applied over a newer edit. A write that fails
+/// anyway puts back the notes written before it.
+pub fn apply_staged_changes(
+    vault_path: &Path,
+    changes: &[crate::adapters::vectordb::StagedChange],
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conflicts = Vec::new();
+    let mut originals = Vec::with_capacity(changes.len());
+    for change in changes {
+        let file = vault_path.join(&change.path);
+        crate::adapters::vault::ensure_within_vault(vault_path, &file)?;
+        if file.is_dir() {
+            return Err(format!("Cannot apply {}: it is a folder", change.path).into());
+        }
+        let current = crate::adapters::vault::read_note_verification(vault_path, &change.path)?;
+        let unchanged = match &change.hash_before {
+            Some(hash) => current.exists && &current.hash == hash,
+            None => !current.exists,
+        };
+        if !unchanged {
+            conflicts.push(change.path.as_str());
+        }
+        originals.push(if current.exists {
+            Some(std::fs::read(&file)?)
+        } else {
+            None
+        });
+    }
+    if !conflicts.is_empty() {
+        return Err(format!(
+            "Notes changed since they were staged: {}",
+            conflicts.join(", ")
+        )
+        .into());
     }
 
-    let tree_id = index.write_tree_to(&repo)?;
-    let tree = repo.find_tree(tree_id)?;
-    let sig = Signature::now("meld", "meld@local")?;
-
-    apply_tree_snapshot_to_vault(&repo, &tree, vault_path)?;
-
-    repo.commit(
-        Some("HEAD"),
-        &sig,
-        &sig,
-        &format!("Revert: {}", commit.message().unwrap_or("unknown")),
-        &tree,
-        &[&head],
-    )?;
+    let existing: Vec<PathBuf> = changes
+        .iter()
+        .filter(|change| change.hash_before.is_some())
+        .map(|change| vault_path.join(&change.path))
+        .collect();
+    if !existing.is_empty() {
+        snapshot_files(vault_path, &existing, "meld: pre-apply snapshot")?;
+    }
 
-    Ok(())
+    let mut files = Vec::with_capacity(changes.len());
+    for (index, change) in changes.iter().enumerate() {
+        if let Err(error) =
+            crate::adapters::vault::write_note(vault_path, &change.path, &change.content)
+        {
+            for (written, original) in changes[..=index].iter().zip(&originals) {
+                let file = vault_path.join(&written.path);
+                let _ = match original {
+                    Some(content) => std::fs::write(&file, content),
+                    None if file.is_file() => std::fs::remove_file(&file),
+                    None => Ok(()),
+                };
+            }
+            return Err(format!("Could not apply {}: {error}", change.path).into());
+        }
+        files.push(vault_path.join(&change.path));
+    }
+    auto_commit_files(vault_path, &files, message)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{auto_commit, auto_commit_files, get_commit_diff, get_history, revert_commit};
+    use super::{
+        apply_staged_changes, auto_commit, auto_commit_files, blame_note, commit_run,
+        commit_sandbox, commit_user_edits, discard_sandbox, get_commit_diff, get_history,
+        merge_sandbox, open_sandbox, query_history, read_file_at, restore_file, revert_commit,
+        revert_run, sandbox_diff, snapshot_before_write, snapshot_files, ConflictStyle,
+        HistoryQuery,
+    };
+    use crate::adapters::vectordb::StagedChange;
+    use crate::core::ports::versioning::RunCommit;
     use std::path::PathBuf;
 
     fn temp_vault() -> PathBuf {
@@ -393,7 +704,7 @@ mod tests {
 
         let history = get_history(&vault, None, None).expect("history");
         let latest_commit = history.first().expect("latest commit");
-        revert_commit(&vault, &latest_commit.id).expect("revert commit");
+        revert_commit(&vault, &latest_commit.id, ConflictStyle::Markers).expect("revert commit");
 
         let restored = std::fs::read_to_string(vault.join("note.md")).expect("read restored note");
         assert_eq!(restored, "v1");
@@ -401,6 +712,583 @@ mod tests {
         let _ = std::fs::remove_dir_all(vault);
     }
 
+    #[test]
+    fn run_writes_commit_once_and_revert_keeps_human_edits() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("a.md"), "v1").expect("write a");
+        auto_commit(&vault, "seed").expect("seed commit");
+        std::fs::write(vault.join("a.md"), "human edit").expect("human edit");
+
+        snapshot_before_write(&vault, "run-1", &["a.md".to_string(), "b.md".to_string()])
+            .expect("snapshot");
+        std::fs::write(vault.join("a.md"), "agent edit").expect("agent edit");
+        std::fs::write(vault.join("b.md"), "agent note").expect("agent create");
+        let run = RunCommit {
+            run_id: "run-1",
+            conversation_id: 7,
+            provider: "openai",
+            model: "gpt-4o",
+        };
+        let commit_id = commit_run(&vault, &["a.md".to_string(), "b.md".to_string()], &run)
+            .expect("commit run")
+            .expect("run commit created");
+
+        let history = get_history(&vault, None, None).expect("history");
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].id, commit_id);
+        assert_eq!(history[0].message, "meld: run wrote 2 notes");
+        assert_eq!(history[0].run_id.as_deref(), Some("run-1"));
+        assert_eq!(history[1].message, "meld: pre-run snapshot");
+        assert_eq!(history[1].run_id, None);
+
+        revert_run(&vault, "run-1", ConflictStyle::Markers).expect("revert run");
+        let restored = std::fs::read_to_string(vault.join("a.md")).expect("read a");
+        assert_eq!(restored, "human edit");
+        assert!(!vault.join("b.md").exists());
+        assert!(revert_run(&vault, "missing-run", ConflictStyle::Markers).is_err());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn user_edits_are_committed_as_user_and_skip_committed_files() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("a.md"), "v1").expect("write a");
+        std::fs::write(vault.join("b.md"), "v1").expect("write b");
+        auto_commit(&vault, "seed").expect("seed commit");
+
+        assert_eq!(
+            commit_user_edits(&vault, &["a.md".to_string()]).expect("no-op snapshot"),
+            None
+        );
+
+        std::fs::write(vault.join("a.md"), "typed by hand").expect("hand edit");
+        std::fs::remove_file(vault.join("b.md")).expect("delete b");
+        commit_user_edits(&vault, &["a.md".to_string(), "b.md".to_string()])
+            .expect("snapshot")
+            .expect("user commit created");
+
+        let latest = get_history(&vault, None, Some(1))
+            .expect("history")
+            .remove(0);
+        assert_eq!(latest.author, "user");
+        assert_eq!(latest.message, "user edit: 2 notes");
+        let mut files = latest.files_changed.clone();
+        files.sort();
+        assert_eq!(files, vec!["a.md".to_string(), "b.md".to_string()]);
+
+        revert_commit(&vault, &latest.id, ConflictStyle::Markers).expect("revert user edit");
+        assert_eq!(
+            std::fs::read_to_string(vault.join("a.md")).expect("read a"),
+            "v1"
+        );
+        assert!(vault.join("b.md").exists());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    fn commit_note(vault: &std::path::Path, content: &str, message: &str) -> String {
+        std::fs::write(vault.join("note.md"), content).expect("write note");
+        auto_commit(vault, message).expect("commit");
+        get_history(vault, None, Some(1))
+            .expect("history")
+            .remove(0)
+            .id
+    }
+
+    #[test]
+    fn revert_merges_with_later_edits_to_other_lines() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        commit_note(&vault, "one\ntwo\nthree\nfour\nfive\n", "seed");
+        let agent_edit = commit_note(&vault, "ONE\ntwo\nthree\nfour\nfive\n", "agent edit");
+        commit_note(&vault, "ONE\ntwo\nthree\nfour\nFIVE\n", "later edit");
+
+        let outcome =
+            revert_commit(&vault, &agent_edit, ConflictStyle::Markers).expect("revert merges");
+
+        assert_eq!(outcome.conflicts, 0);
+        assert_eq!(outcome.files[0].status, "reverted");
+        assert_eq!(
+            std::fs::read_to_string(vault.join("note.md")).expect("read note"),
+            "one\ntwo\nthree\nfour\nFIVE\n"
+        );
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn conflicting_revert_writes_markers_or_a_conflict_copy() {
+        for style in [ConflictStyle::Markers, ConflictStyle::Copy] {
+            let vault = temp_vault();
+            std::fs::create_dir_all(&vault).expect("create temp vault");
+            commit_note(&vault, "a\nb\nc\n", "seed");
+            let agent_edit = commit_note(&vault, "a\nB\nc\n", "agent edit");
+            commit_note(&vault, "a\nBB\nc\n", "later edit");
+
+            let outcome = revert_commit(&vault, &agent_edit, style).expect("revert with conflict");
+            assert_eq!(outcome.conflicts, 1);
+            let note = std::fs::read_to_string(vault.join("note.md")).expect("read note");
+
+            match style {
+                ConflictStyle::Markers => {
+                    assert_eq!(outcome.files[0].status, "conflict");
+                    assert!(note.contains("<<<<<<< current"));
+                    assert!(note.contains("BB"));
+                    assert!(note.contains(">>>>>>> revert"));
+                }
+                ConflictStyle::Copy => {
+                    assert_eq!(outcome.files[0].status, "conflict_copy");
+                    assert_eq!(note, "a\nBB\nc\n");
+                    let copy = outcome.files[0]
+                        .conflict_copy
+                        .as_deref()
+                        .expect("copy path");
+                    assert_eq!(copy, format!("note (revert {}).md", &agent_edit[..7]));
+                    let copy_content =
+                        std::fs::read_to_string(vault.join(copy)).expect("read conflict copy");
+                    assert_eq!(copy_content, "a\nb\nc\n");
+                }
+            }
+
+            let latest = get_history(&vault, None, Some(1))
+                .expect("history")
+                .remove(0);
+            assert!(latest.message.starts_with("Revert: agent edit"));
+
+            let _ = std::fs::remove_dir_all(vault);
+        }
+    }
+
+    #[test]
+    fn restore_file_rewrites_one_note_and_reads_old_revisions() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("other.md"), "o1").expect("write other");
+        let v1 = commit_note(&vault, "v1\n", "v1");
+        std::fs::write(vault.join("other.md"), "o2").expect("edit other");
+        commit_note(&vault, "v2\n", "v2");
+
+        let (resolved, content) = read_file_at(&vault, "note.md", &v1[..7]).expect("read at v1");
+        assert_eq!(resolved, v1);
+        assert_eq!(content, "v1\n");
+        assert!(read_file_at(&vault, "missing.md", &v1).is_err());
+
+        std::fs::write(vault.join("note.md"), "hand edit\n").expect("hand edit");
+        let restored = restore_file(&vault, "note.md", &v1)
+            .expect("restore")
+            .expect("restore commit");
+        assert_eq!(restored.files_changed, vec!["note.md".to_string()]);
+        assert_eq!(
+            std::fs::read_to_string(vault.join("note.md")).expect("read note"),
+            "v1\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(vault.join("other.md")).expect("read other"),
+            "o2"
+        );
+
+        let history = get_history(&vault, None, Some(2)).expect("history");
+        assert_eq!(
+            history[0].message,
+            format!("Restore note.md to {}", &v1[..7])
+        );
+        assert_eq!(history[1].message, "meld: pre-restore snapshot");
+        assert_eq!(history[1].author, "user");
+        assert!(restore_file(&vault, "note.md", &v1)
+            .expect("noop")
+            .is_none());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn blame_attributes_lines_to_runs_and_user() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("note.md"), "mine\nmine too\n").expect("write note");
+        commit_user_edits(&vault, &["note.md".to_string()]).expect("user commit");
+
+        snapshot_before_write(&vault, "run-1", &["note.md".to_string()]).expect("snapshot");
+        std::fs::write(vault.join("note.md"), "mine\nai line\nai line 2\n").expect("agent");
+        let run = RunCommit {
+            run_id: "run-1",
+            conversation_id: 1,
+            provider: "openai",
+            model: "gpt-test",
+        };
+        commit_run(&vault, &["note.md".to_string()], &run).expect("run commit");
+        std::fs::write(vault.join("note.md"), "mine\nai line\nai line 2\nunsaved\n")
+            .expect("hand edit");
+
+        let blame = blame_note(&vault, "note.md").expect("blame");
+        assert_eq!(blame.agent_lines, 2);
+        assert_eq!(blame.user_lines, 2);
+        let summary: Vec<(usize, usize, &str, Option<&str>)> = blame
+            .hunks
+            .iter()
+            .map(|hunk| {
+                (
+                    hunk.start_line,
+                    hunk.lines.len(),
+                    hunk.author.as_str(),
+                    hunk.model.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 1, "user", None),
+                (2, 2, "meld", Some("gpt-test")),
+                (4, 1, "user", None),
+            ]
+        );
+        assert_eq!(blame.hunks[1].run_id.as_deref(), Some("run-1"));
+        assert!(blame.hunks[2].commit_id.is_none());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    fn user_repo_vault(config: &str) -> (PathBuf, git2::Repository) {
+        let vault = temp_vault();
+        std::fs::create_dir_all(vault.join(".meld")).expect("create temp vault");
+        std::fs::write(vault.join(".meld").join("config.toml"), config).expect("write config");
+        let repo = git2::Repository::init(&vault).expect("init user repo");
+        (vault, repo)
+    }
+
+    #[test]
+    fn head_mode_commits_into_the_vault_repo_and_refuses_unrelated_staged_changes() {
+        let (vault, repo) = user_repo_vault(
+            "git_mode = \"head\"\ngit_author_name = \"Meld Bot\"\ngit_author_email = \"bot@example.com\"\n",
+        );
+        std::fs::write(vault.join("note.md"), "v1\n").expect("write v1");
+        snapshot_files(&vault, &[vault.join("note.md")], "meld: pre-edit snapshot")
+            .expect("user commit");
+        std::fs::write(vault.join("note.md"), "v2\n").expect("write v2");
+        auto_commit(&vault, "agent edit").expect("meld commit");
+
+        assert!(!vault.join(".meld").join(".git").exists());
+        let head = repo.head().expect("head").peel_to_commit().expect("commit");
+        assert_eq!(head.author().email(), Some("bot@example.com"));
+        let history = get_history(&vault, None, None).expect("history");
+        let authors: Vec<&str> = history.iter().map(|e| e.author.as_str()).collect();
+        assert_eq!(authors, vec!["meld", "user"]);
+        assert_eq!(history[0].id, head.id().to_string());
+
+        std::fs::write(vault.join("todo.txt"), "unrelated").expect("write todo");
+        let mut index = repo.index().expect("index");
+        index
+            .add_path(std::path::Path::new("todo.txt"))
+            .expect("stage todo");
+        index.write().expect("write index");
+        std::fs::write(vault.join("note.md"), "v3\n").expect("write v3");
+        let error = auto_commit(&vault, "blocked").expect_err("unrelated staged change");
+        assert!(error.to_string().contains("todo.txt"));
+
+        index
+            .remove_path(std::path::Path::new("todo.txt"))
+            .expect("unstage todo");
+        index.write().expect("write index");
+        std::fs::write(vault.join("note.md"), "v2\n").expect("restore v2");
+        revert_commit(&vault, &history[0].id, ConflictStyle::Markers).expect("revert");
+        assert_eq!(
+            std::fs::read_to_string(vault.join("note.md")).expect("read note"),
+            "v1\n"
+        );
+        assert!(vault.join("todo.txt").exists());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn head_mode_leaves_hand_edits_to_untouched_notes_uncommitted() {
+        let (vault, repo) = user_repo_vault("git_mode = \"head\"\n");
+        std::fs::write(vault.join("a.md"), "a1\n").expect("write a");
+        std::fs::write(vault.join("b.md"), "b1\n").expect("write b");
+        auto_commit(&vault, "seed").expect("seed commit");
+        std::fs::write(vault.join("a.md"), "a by hand\n").expect("edit a");
+        std::fs::write(vault.join("b.md"), "b by hand\n").expect("edit b");
+
+        let edits = ["a.md".to_string(), "b.md".to_string()];
+        assert!(commit_user_edits(&vault, &edits)
+            .expect("watcher flush")
+            .is_none());
+        snapshot_before_write(&vault, "run-1", &["a.md".to_string()]).expect("snapshot");
+        std::fs::write(vault.join("a.md"), "agent\n").expect("agent edit");
+        let run = RunCommit {
+            run_id: "run-1",
+            conversation_id: 1,
+            provider: "openai",
+            model: "gpt-4o",
+        };
+        let commit_id = commit_run(&vault, &["a.md".to_string()], &run)
+            .expect("commit run")
+            .expect("run commit created");
+        revert_commit(&vault, &commit_id, ConflictStyle::Markers).expect("revert run");
+        let head_before_branch = repo.head().expect("head").target();
+        let sandbox = open_sandbox(&vault, 1).expect("open sandbox");
+
+        let head = repo.head().expect("head").peel_to_commit().expect("commit");
+        assert_eq!(Some(head.id()), head_before_branch);
+        let history = get_history(&vault, None, None).expect("history");
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[2].message, "meld: pre-run snapshot");
+        assert_eq!(history[2].files_changed, vec!["a.md".to_string()]);
+        assert!(history[..3]
+            .iter()
+            .all(|entry| !entry.files_changed.contains(&"b.md".to_string())));
+        let committed_b = head
+            .tree()
+            .expect("tree")
+            .get_path(std::path::Path::new("b.md"))
+            .expect("b.md in HEAD")
+            .to_object(&repo)
+            .expect("object")
+            .peel_to_blob()
+            .expect("blob");
+        assert_eq!(committed_b.content(), b"b1\n");
+        assert_eq!(
+            std::fs::read_to_string(vault.join("b.md")).expect("read b"),
+            "b by hand\n"
+        );
+        // The conversation branch still sees the hand edit.
+        assert_eq!(
+            std::fs::read_to_string(sandbox.join("b.md")).expect("read shadow b"),
+            "b by hand\n"
+        );
+
+        discard_sandbox(&vault, 1).expect("discard sandbox");
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn branch_mode_commits_on_its_own_branch() {
+        let (vault, repo) = user_repo_vault("git_mode = \"branch\"\ngit_branch = \"notes-ai\"\n");
+        std::fs::write(vault.join("readme.txt"), "project").expect("write readme");
+        let mut index = repo.index().expect("index");
+        index
+            .add_path(std::path::Path::new("readme.txt"))
+            .expect("stage readme");
+        index.write().expect("write index");
+        let tree = repo
+            .find_tree(index.write_tree().expect("tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("someone", "someone@example.com").expect("sig");
+        let base = repo
+            .commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[])
+            .expect("initial commit");
+
+        std::fs::write(vault.join("note.md"), "agent\n").expect("write note");
+        auto_commit(&vault, "agent edit").expect("branch commit");
+
+        assert_eq!(repo.head().expect("head").target(), Some(base));
+        assert!(repo
+            .index()
+            .expect("index")
+            .get_path(std::path::Path::new("note.md"), 0)
+            .is_none());
+        let branch = repo
+            .find_branch("notes-ai", git2::BranchType::Local)
+            .expect("meld branch")
+            .get()
+            .peel_to_commit()
+            .expect("branch tip");
+        assert_eq!(branch.parent_id(0).expect("parent"), base);
+        assert!(branch
+            .tree()
+            .expect("tree")
+            .get_path(std::path::Path::new("readme.txt"))
+            .is_ok());
+
+        let history = get_history(&vault, None, None).expect("history");
+        assert_eq!(history[0].message, "agent edit");
+        assert_eq!(history[0].author, "meld");
+        assert_eq!(history[1].author, "user");
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn sandbox_runs_write_a_branch_that_fast_forwards_into_the_vault() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("a.md"), "v1\n").expect("write a");
+        auto_commit(&vault, "seed").expect("seed commit");
+
+        let sandbox = open_sandbox(&vault, 3).expect("open sandbox");
+        assert_eq!(
+            std::fs::read_to_string(sandbox.join("a.md")).expect("read shadow a"),
+            "v1\n"
+        );
+        std::fs::write(sandbox.join("a.md"), "v2\n").expect("agent edit");
+        std::fs::write(sandbox.join("b.md"), "new\n").expect("agent create");
+        let run = RunCommit {
+            run_id: "run-branch",
+            conversation_id: 3,
+            provider: "openai",
+            model: "gpt-4o",
+        };
+        commit_sandbox(&vault, &["a.md".to_string(), "b.md".to_string()], &run)
+            .expect("commit sandbox")
+            .expect("branch commit created");
+
+        // The vault and its history are untouched until the branch is merged.
+        assert_eq!(
+            std::fs::read_to_string(vault.join("a.md")).expect("read a"),
+            "v1\n"
+        );
+        assert!(!vault.join("b.md").exists());
+        assert_eq!(get_history(&vault, None, None).expect("history").len(), 1);
+
+        let diff = sandbox_diff(&vault, 3)
+            .expect("diff")
+            .expect("branch exists");
+        assert_eq!(diff.branch, "meld/conversation-3");
+        assert_eq!(diff.commits, 1);
+        assert_eq!(diff.files_changed, vec!["a.md", "b.md"]);
+        assert!(diff.patch.contains("+v2"));
+
+        let merge = merge_sandbox(&vault, 3)
+            .expect("merge")
+            .expect("branch merged");
+        assert!(merge.fast_forward);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(vault.join("a.md")).expect("read a"),
+            "v2\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(vault.join("b.md")).expect("read b"),
+            "new\n"
+        );
+        let history = get_history(&vault, None, None).expect("history");
+        assert_eq!(history[0].run_id.as_deref(), Some("run-branch"));
+        assert!(sandbox_diff(&vault, 3).expect("diff").is_none());
+        assert!(!sandbox.exists());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn sandbox_merges_three_way_after_the_vault_moves_on() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("a.md"), "one\ntwo\nthree\nfour\nfive\n").expect("write a");
+        std::fs::write(vault.join("b.md"), "b\n").expect("write b");
+        auto_commit(&vault, "seed").expect("seed commit");
+
+        let sandbox = open_sandbox(&vault, 4).expect("open sandbox");
+        std::fs::write(sandbox.join("a.md"), "one\ntwo\nthree\nfour\nFIVE\n")
+            .expect("agent edit a");
+        std::fs::write(sandbox.join("b.md"), "agent b\n").expect("agent edit b");
+        let run = RunCommit {
+            run_id: "run-merge",
+            conversation_id: 4,
+            provider: "openai",
+            model: "gpt-4o",
+        };
+        commit_sandbox(&vault, &["a.md".to_string(), "b.md".to_string()], &run)
+            .expect("commit sandbox");
+
+        // Hand edits made in the vault meanwhile, picked up by the pre-merge snapshot.
+        std::fs::write(vault.join("a.md"), "ONE\ntwo\nthree\nfour\nfive\n").expect("user edit a");
+        std::fs::write(vault.join("b.md"), "user b\n").expect("user edit b");
+
+        let merge = merge_sandbox(&vault, 4)
+            .expect("merge")
+            .expect("branch merged");
+        assert!(!merge.fast_forward);
+        assert_eq!(merge.conflicts, vec!["b.md"]);
+        assert_eq!(
+            std::fs::read_to_string(vault.join("a.md")).expect("read a"),
+            "ONE\ntwo\nthree\nfour\nFIVE\n"
+        );
+        let b = std::fs::read_to_string(vault.join("b.md")).expect("read b");
+        assert!(b.contains("<<<<<<< vault"));
+        assert!(b.contains("user b"));
+        assert!(b.contains("agent b"));
+
+        let history = get_history(&vault, None, None).expect("history");
+        assert!(history[0].message.starts_with("Merge meld/conversation-4"));
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn apply_staged_changes_commits_once_and_refuses_conflicts() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("a.md"), "a1").expect("write a1");
+        auto_commit(&vault, "seed").expect("seed commit");
+
+        let staged = |path: &str, hash_before: Option<&str>, content: &str| StagedChange {
+            run_id: "run".to_string(),
+            path: path.to_string(),
+            action: if hash_before.is_some() {
+                "edit"
+            } else {
+                "create"
+            }
+            .to_string(),
+            hash_before: hash_before.map(str::to_string),
+            hash_after: crate::adapters::vault::file_hash(content),
+            content: content.to_string(),
+            diff: String::new(),
+            updated_at: String::new(),
+        };
+        let a1 = crate::adapters::vault::file_hash("a1");
+        let changes = vec![
+            staged("a.md", Some(&a1), "a2"),
+            staged("new.md", None, "n1"),
+        ];
+
+        std::fs::write(vault.join("a.md"), "edited by hand").expect("write conflict");
+        let error = apply_staged_changes(&vault, &changes, "apply").expect_err("conflict");
+        assert!(error.to_string().contains("a.md"));
+        assert!(!vault.join("new.md").exists());
+
+        std::fs::write(vault.join("a.md"), "a1").expect("restore a1");
+        let commits_before = get_history(&vault, None, None).expect("history").len();
+        apply_staged_changes(&vault, &changes, "apply staged").expect("apply");
+
+        let history = get_history(&vault, None, None).expect("history");
+        assert_eq!(history.len(), commits_before + 1);
+        assert_eq!(history[0].message, "apply staged");
+        assert_eq!(history[0].files_changed.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(vault.join("a.md")).expect("a"),
+            "a2"
+        );
+        assert_eq!(
+            std::fs::read_to_string(vault.join("new.md")).expect("new"),
+            "n1"
+        );
+
+        // `a.md` is a file, so the second note cannot be written and the first is put back.
+        let a2 = crate::adapters::vault::file_hash("a2");
+        let changes = vec![
+            staged("a.md", Some(&a2), "a3"),
+            staged("a.md/child.md", None, "c1"),
+        ];
+        let commits_before = get_history(&vault, None, None).expect("history").len();
+        let error = apply_staged_changes(&vault, &changes, "apply").expect_err("unwritable");
+        assert!(error.to_string().contains("a.md/child.md"));
+        assert_eq!(
+            std::fs::read_to_string(vault.join("a.md")).expect("a"),
+            "a2"
+        );
+        assert_eq!(
+            get_history(&vault, None, None).expect("history").len(),
+            commits_before
+        );
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
     #[test]
     fn auto_commit_files_stages_only_target_markdown_files() {
         let vault = temp_vault();
@@ -445,7 +1333,8 @@ mod tests {
         assert!(only_a
             .iter()
             .all(|e| e.files_changed.contains(&"a.md".to_string())));
-        assert_eq!(only_a.len(), 1); // edit a (seed has no parent → empty files_changed)
+        let messages: Vec<&str> = only_a.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["edit a", "seed both"]);
 
         let _ = std::fs::remove_dir_all(vault);
     }
@@ -465,6 +1354,98 @@ mod tests {
         let _ = std::fs::remove_dir_all(vault);
     }
 
+    #[test]
+    fn query_history_pages_with_cursor_and_filters() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        for i in 0..5 {
+            std::fs::write(vault.join("note.md"), format!("v{i}")).expect("write");
+            auto_commit(&vault, &format!("commit {i}")).expect("commit");
+        }
+        std::fs::write(vault.join("note.md"), "by hand").expect("hand edit");
+        commit_user_edits(&vault, &["note.md".to_string()]).expect("user commit");
+
+        let first = query_history(
+            &vault,
+            &HistoryQuery {
+                limit: Some(4),
+                ..HistoryQuery::default()
+            },
+        )
+        .expect("first page");
+        assert_eq!(first.entries.len(), 4);
+        let cursor = first.next_cursor.expect("more pages");
+        assert_eq!(cursor, first.entries[3].id);
+
+        let second = query_history(
+            &vault,
+            &HistoryQuery {
+                limit: Some(4),
+                after: Some(cursor),
+                ..HistoryQuery::default()
+            },
+        )
+        .expect("second page");
+        let messages: Vec<&str> = second.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["commit 1", "commit 0"]);
+        assert!(second.next_cursor.is_none());
+
+        let user_only = query_history(
+            &vault,
+            &HistoryQuery {
+                author: Some("user".to_string()),
+                ..HistoryQuery::default()
+            },
+        )
+        .expect("user commits");
+        assert_eq!(user_only.entries.len(), 1);
+        assert_eq!(user_only.entries[0].message, "user edit: note.md");
+
+        let by_message = query_history(
+            &vault,
+            &HistoryQuery {
+                author: Some("agent".to_string()),
+                message: Some("COMMIT 3".to_string()),
+                ..HistoryQuery::default()
+            },
+        )
+        .expect("message filter");
+        assert_eq!(by_message.entries.len(), 1);
+
+        let future = query_history(
+            &vault,
+            &HistoryQuery {
+                since: Some(chrono::Utc::now().timestamp() + 3600),
+                ..HistoryQuery::default()
+            },
+        )
+        .expect("date filter");
+        assert!(future.entries.is_empty());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn path_filter_reaches_past_long_histories() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(&vault).expect("create temp vault");
+        std::fs::write(vault.join("a.md"), "a0").expect("write a0");
+        std::fs::write(vault.join("b.md"), "b0").expect("write b0");
+        auto_commit(&vault, "seed").expect("seed commit");
+        std::fs::write(vault.join("a.md"), "a1").expect("write a1");
+        auto_commit(&vault, "edit a").expect("commit a");
+        for i in 0..520 {
+            std::fs::write(vault.join("b.md"), format!("b{i}")).expect("write b");
+            auto_commit_files(&vault, &[vault.join("b.md")], "edit b").expect("commit b");
+        }
+
+        let only_a = get_history(&vault, Some("a.md"), None).expect("filter a.md");
+        let messages: Vec<&str> = only_a.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["edit a", "seed"]);
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
     #[test]
     fn get_commit_diff_returns_patch() {
         let vault = temp_vault();
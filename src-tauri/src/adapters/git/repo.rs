@@ -0,0 +1,294 @@
+//! The repository that holds a vault's history. By default meld keeps its own bare repo in
+//! `.meld/.git`; with `git_mode` in the vault config it commits into the vault's own git
+//! repository instead, either on the checked-out branch or on a dedicated one.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use git2::{Commit, Index, Oid, Repository, Signature, Tree};
+
+use crate::adapters::config::VaultConfig;
+
+use super::CommitAuthor;
+
+const MELD_NAME: &str = "meld";
+const MELD_EMAIL: &str = "meld@local";
+const USER_NAME: &str = "user";
+const USER_EMAIL: &str = "user@local";
+const DEFAULT_BRANCH: &str = "meld";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitMode {
+    /// meld's own bare repository in `.meld/.git`.
+    Isolated,
+    /// The vault's repository, committing on HEAD and keeping its index in step.
+    Head,
+    /// The vault's repository, committing on this branch without touching HEAD or the index.
+    Branch(String),
+}
+
+impl GitMode {
+    pub fn from_config(config: &VaultConfig) -> Result<Self, String> {
+        match config.git_mode.as_deref().map(str::trim) {
+            None | Some("") | Some("isolated") => Ok(GitMode::Isolated),
+            Some("head") => Ok(GitMode::Head),
+            Some("branch") => {
+                let branch = config
+                    .git_branch
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|branch| !branch.is_empty())
+                    .unwrap_or(DEFAULT_BRANCH);
+                if !git2::Reference::is_valid_name(&format!("refs/heads/{branch}")) {
+                    return Err(format!("Invalid git_branch '{branch}'"));
+                }
+                Ok(GitMode::Branch(branch.to_string()))
+            }
+            Some(other) => Err(format!(
+                "Unknown git_mode '{other}'. Use 'isolated', 'head' or 'branch'."
+            )),
+        }
+    }
+}
+
+pub(super) struct VaultRepo {
+    pub repo: Repository,
+    mode: GitMode,
+    meld_name: String,
+    meld_email: String,
+}
+
+impl VaultRepo {
+    fn configure(repo: Repository, mode: GitMode, config: &VaultConfig) -> Self {
+        let configured = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty() && mode != GitMode::Isolated)
+                .map(str::to_string)
+        };
+        let meld_name =
+            configured(&config.git_author_name).unwrap_or_else(|| MELD_NAME.to_string());
+        let meld_email =
+            configured(&config.git_author_email).unwrap_or_else(|| MELD_EMAIL.to_string());
+        Self {
+            repo,
+            mode,
+            meld_name,
+            meld_email,
+        }
+    }
+
+    fn open_user_repo(vault_path: &Path) -> Result<Repository, Box<dyn std::error::Error>> {
+        let repo = Repository::open(vault_path).map_err(|error| {
+            format!("git_mode needs a git repository at the vault root: {error}")
+        })?;
+        let same_root = repo
+            .workdir()
+            .and_then(|workdir| workdir.canonicalize().ok())
+            .zip(vault_path.canonicalize().ok())
+            .is_some_and(|(workdir, vault)| workdir == vault);
+        if !same_root {
+            return Err("git_mode needs the vault to be the root of its git repository".into());
+        }
+        Ok(repo)
+    }
+
+    /// The history repository, which must already exist.
+    pub fn open(vault_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = VaultConfig::load(vault_path);
+        let mode = GitMode::from_config(&config)?;
+        let repo = match mode {
+            GitMode::Isolated => Repository::open_bare(super::meld_git_dir(vault_path))?,
+            _ => Self::open_user_repo(vault_path)?,
+        };
+        Ok(Self::configure(repo, mode, &config))
+    }
+
+    /// The history repository, creating meld's bare repo on first use.
+    pub fn open_or_init(vault_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = VaultConfig::load(vault_path);
+        let mode = GitMode::from_config(&config)?;
+        let repo = match mode {
+            GitMode::Isolated => super::open_or_init_meld_repo(vault_path)?,
+            _ => Self::open_user_repo(vault_path)?,
+        };
+        Ok(Self::configure(repo, mode, &config))
+    }
+
+    /// Whether the vault has a history repository to read from.
+    pub fn exists(vault_path: &Path) -> bool {
+        let config = VaultConfig::load(vault_path);
+        match GitMode::from_config(&config) {
+            Ok(GitMode::Isolated) => super::meld_git_dir(vault_path).exists(),
+            _ => vault_path.join(".git").exists(),
+        }
+    }
+
+    /// Reference meld commits to.
+    pub fn history_ref(&self) -> String {
+        match &self.mode {
+            GitMode::Branch(branch) => format!("refs/heads/{branch}"),
+            _ => "HEAD".to_string(),
+        }
+    }
+
+    /// Latest commit of the history, or `None` before the first commit. A branch that
+    /// does not exist yet starts from the repository's HEAD.
+    pub fn tip(&self) -> Result<Option<Commit<'_>>, git2::Error> {
+        if let GitMode::Branch(branch) = &self.mode {
+            if let Ok(branch) = self.repo.find_branch(branch, git2::BranchType::Local) {
+                return branch.get().peel_to_commit().map(Some);
+            }
+        }
+        match self.repo.head() {
+            Ok(head) => head.peel_to_commit().map(Some),
+            Err(error) if error.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Latest commit of the history; an error before the first commit.
+    pub fn tip_commit(&self) -> Result<Commit<'_>, git2::Error> {
+        self.tip()?
+            .ok_or_else(|| git2::Error::from_str("No history has been recorded yet"))
+    }
+
+    /// Revwalk over the history, newest first.
+    pub fn revwalk(&self) -> Result<git2::Revwalk<'_>, git2::Error> {
+        let mut revwalk = self.repo.revwalk()?;
+        if let Some(tip) = self.tip()? {
+            revwalk.push(tip.id())?;
+        }
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        Ok(revwalk)
+    }
+
+    pub fn signature(&self, author: CommitAuthor) -> Result<Signature<'static>, git2::Error> {
+        match author {
+            CommitAuthor::Meld => Signature::now(&self.meld_name, &self.meld_email),
+            CommitAuthor::User if self.mode != GitMode::Isolated => self
+                .repo
+                .signature()
+                .or_else(|_| Signature::now(USER_NAME, USER_EMAIL)),
+            CommitAuthor::User => Signature::now(USER_NAME, USER_EMAIL),
+        }
+    }
+
+    /// `"meld"` for the agent's commits, `"user"` for everything else. Run commits are
+    /// recognized by their `Meld-Run-Id` trailer, whoever signed them; agent writes made
+    /// outside a run carry meld's signature.
+    pub fn author_of(&self, commit: &Commit<'_>) -> &'static str {
+        if commit
+            .message()
+            .and_then(super::runs::commit_run_id)
+            .is_some()
+        {
+            return MELD_NAME;
+        }
+        let author = commit.author();
+        let is_meld = match author.email() {
+            Some(email) if self.mode != GitMode::Isolated => email == self.meld_email,
+            _ => author.name() == Some(self.meld_name.as_str()),
+        };
+        if is_meld {
+            MELD_NAME
+        } else {
+            USER_NAME
+        }
+    }
+
+    /// Index to stage `paths` (vault-relative) in. Under `head` this is the repository's
+    /// real index, and the commit is refused when it holds unrelated staged changes.
+    pub fn staging_index(&self, paths: &[String]) -> Result<Index, Box<dyn std::error::Error>> {
+        match &self.mode {
+            GitMode::Isolated => Ok(self.repo.index()?),
+            GitMode::Head => {
+                let index = self.repo.index()?;
+                let head_tree = self.tip()?.map(|tip| tip.tree()).transpose()?;
+                let diff = self
+                    .repo
+                    .diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+                let ours: HashSet<&str> = paths.iter().map(String::as_str).collect();
+                let unrelated: Vec<String> = diff
+                    .deltas()
+                    .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+                    .map(|path| path.to_string_lossy().replace('\\', "/"))
+                    .filter(|path| !ours.contains(path.as_str()))
+                    .collect();
+                if !unrelated.is_empty() {
+                    return Err(format!(
+                        "Refusing to commit: the vault repository has unrelated staged changes ({})",
+                        unrelated.join(", ")
+                    )
+                    .into());
+                }
+                Ok(index)
+            }
+            GitMode::Branch(_) => {
+                let mut index = Index::new()?;
+                if let Some(tip) = self.tip()? {
+                    index.read_tree(&tip.tree()?)?;
+                }
+                Ok(index)
+            }
+        }
+    }
+
+    /// Whether hand edits anywhere in the vault may be committed. Under `head` the
+    /// checked-out branch is the user's, so only notes meld is about to write are.
+    pub fn commits_hand_edits(&self) -> bool {
+        self.mode != GitMode::Head
+    }
+
+    /// Whether `path` is ignored by the vault repository's `.gitignore`.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.mode != GitMode::Isolated && self.repo.is_path_ignored(path).unwrap_or(false)
+    }
+
+    /// Commit `tree` on top of the history and advance it.
+    pub fn commit(
+        &self,
+        author: CommitAuthor,
+        message: &str,
+        tree: &Tree<'_>,
+        parents: &[&Commit<'_>],
+    ) -> Result<Oid, git2::Error> {
+        let sig = self.signature(author)?;
+        self.repo.commit(
+            Some(&self.history_ref()),
+            &sig,
+            &sig,
+            message,
+            tree,
+            parents,
+        )
+    }
+
+    /// Move the history to `oid` without a new commit, as a fast-forward does.
+    pub fn advance(&self, oid: Oid, log_message: &str) -> Result<(), git2::Error> {
+        let name = match &self.mode {
+            GitMode::Branch(_) => self.history_ref(),
+            // HEAD is symbolic; move the branch it points at rather than detaching it.
+            _ => self
+                .repo
+                .find_reference("HEAD")?
+                .symbolic_target()
+                .map(str::to_string)
+                .unwrap_or_else(|| "HEAD".to_string()),
+        };
+        self.repo.reference(&name, oid, true, log_message)?;
+        Ok(())
+    }
+
+    /// Bring the index in line with `tree` after committing a tree built outside it.
+    pub fn sync_index(&self, tree: &Tree<'_>) -> Result<(), git2::Error> {
+        if let GitMode::Branch(_) = self.mode {
+            return Ok(());
+        }
+        let mut index = self.repo.index()?;
+        index.read_tree(tree)?;
+        index.write()
+    }
+}
@@ -0,0 +1,253 @@
+//! Conflict-aware reverts. The revert is merged line by line with everything committed
+//! since; lines that still conflict are written as conflict markers or, on request, as a
+//! side-by-side copy of the note.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use git2::{FileFavor, IndexConflict, IndexEntry, MergeFileOptions, Repository};
+use serde::Serialize;
+
+use super::{
+    apply_tree_snapshot_to_vault, diff_paths, index_entry, snapshot_hand_edits, CommitAuthor,
+    VaultRepo,
+};
+
+/// How lines that cannot be merged automatically are surfaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictStyle {
+    /// Write `<<<<<<<`/`>>>>>>>` markers into the note.
+    #[default]
+    Markers,
+    /// Keep the current lines in the note and write the reverted lines to a copy next to it.
+    Copy,
+}
+
+impl ConflictStyle {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.map(str::trim) {
+            None | Some("") | Some("markers") => Ok(ConflictStyle::Markers),
+            Some("copy") => Ok(ConflictStyle::Copy),
+            Some(other) => Err(format!(
+                "Unknown conflict style '{other}'. Use 'markers' or 'copy'."
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevertFileOutcome {
+    pub path: String,
+    /// `reverted`, `conflict` (markers written), `conflict_copy`, or `kept` when the note
+    /// was deleted or re-created since and the revert left it as it is.
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict_copy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevertOutcome {
+    pub commit_id: String,
+    pub files: Vec<RevertFileOutcome>,
+    pub conflicts: usize,
+}
+
+fn conflict_path(conflict: &IndexConflict) -> Option<String> {
+    [&conflict.our, &conflict.their, &conflict.ancestor]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+}
+
+fn conflict_copy_path(path: &str, short_id: &str) -> String {
+    let stem = path.strip_suffix(".md").unwrap_or(path);
+    format!("{stem} (revert {short_id}).md")
+}
+
+fn merge_sides(
+    repo: &Repository,
+    ancestor: &IndexEntry,
+    ours: &IndexEntry,
+    theirs: &IndexEntry,
+    short_id: &str,
+    favor: FileFavor,
+) -> Result<Vec<u8>, git2::Error> {
+    let mut opts = MergeFileOptions::new();
+    opts.ancestor_label("reverted commit")
+        .our_label("current")
+        .their_label(format!("revert {short_id}"))
+        .favor(favor);
+    let merged = repo.merge_file_from_index(ancestor, ours, theirs, Some(&mut opts))?;
+    Ok(merged.content().to_vec())
+}
+
+/// Notes `commit_id` changed, which reverting it writes over.
+fn reverted_paths(
+    vault_path: &Path,
+    commit_id: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let repo = VaultRepo::open(vault_path)?.repo;
+    let commit = repo.find_commit(git2::Oid::from_str(commit_id)?)?;
+    let parent_tree = commit
+        .parents()
+        .next()
+        .map(|parent| parent.tree())
+        .transpose()?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    Ok(diff_paths(&diff))
+}
+
+/// Revert `commit_id` on top of HEAD. Hand edits not yet snapshotted are committed first
+/// so the revert merges with them instead of overwriting them.
+pub fn revert_commit(
+    vault_path: &Path,
+    commit_id: &str,
+    style: ConflictStyle,
+) -> Result<RevertOutcome, Box<dyn std::error::Error>> {
+    let reverted = reverted_paths(vault_path, commit_id)?;
+    snapshot_hand_edits(vault_path, &reverted, "meld: pre-revert snapshot")?;
+
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let repo = &vault_repo.repo;
+    let oid = git2::Oid::from_str(commit_id)?;
+    let commit = repo.find_commit(oid)?;
+    let head = vault_repo.tip_commit()?;
+    let mainline = if commit.parent_count() > 1 { 1 } else { 0 };
+    let short_id = &commit_id[..commit_id.len().min(7)];
+
+    let mut outcomes = BTreeMap::new();
+    let parent_tree = commit
+        .parents()
+        .next()
+        .map(|parent| parent.tree())
+        .transpose()?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    for delta in diff.deltas() {
+        if let Some(path) = delta.new_file().path() {
+            let path = path.to_string_lossy().replace('\\', "/");
+            outcomes.insert(
+                path.clone(),
+                RevertFileOutcome {
+                    path,
+                    status: "reverted".to_string(),
+                    conflict_copy: None,
+                },
+            );
+        }
+    }
+
+    let mut index = repo.revert_commit(&commit, &head, mainline, None)?;
+    let conflicts = index.conflicts()?.collect::<Result<Vec<_>, _>>()?;
+    for conflict in conflicts {
+        let Some(path) = conflict_path(&conflict) else {
+            continue;
+        };
+        let mut outcome = RevertFileOutcome {
+            path: path.clone(),
+            status: "kept".to_string(),
+            conflict_copy: None,
+        };
+        let resolved = match (&conflict.ancestor, &conflict.our, &conflict.their) {
+            (Some(ancestor), Some(ours), Some(theirs)) => match style {
+                ConflictStyle::Markers => {
+                    outcome.status = "conflict".to_string();
+                    Some(merge_sides(
+                        repo,
+                        ancestor,
+                        ours,
+                        theirs,
+                        short_id,
+                        FileFavor::Normal,
+                    )?)
+                }
+                ConflictStyle::Copy => {
+                    let copy_path = conflict_copy_path(&path, short_id);
+                    let copy =
+                        merge_sides(repo, ancestor, ours, theirs, short_id, FileFavor::Theirs)?;
+                    let copy_blob = repo.blob(&copy)?;
+                    index.add(&index_entry(&copy_path, copy_blob, copy.len()))?;
+                    outcome.status = "conflict_copy".to_string();
+                    outcome.conflict_copy = Some(copy_path);
+                    Some(merge_sides(
+                        repo,
+                        ancestor,
+                        ours,
+                        theirs,
+                        short_id,
+                        FileFavor::Ours,
+                    )?)
+                }
+            },
+            // Deleted or re-created since the commit: keep the note as it is now.
+            (_, Some(ours), _) => Some(repo.find_blob(ours.id)?.content().to_vec()),
+            _ => None,
+        };
+
+        index.conflict_remove(Path::new(&path))?;
+        if let Some(content) = resolved {
+            let blob = repo.blob(&content)?;
+            index.add(&index_entry(&path, blob, content.len()))?;
+        }
+        outcomes.insert(path, outcome);
+    }
+
+    let tree_id = index.write_tree_to(repo)?;
+    let tree = repo.find_tree(tree_id)?;
+    apply_tree_snapshot_to_vault(repo, &head.tree()?, &tree, vault_path)?;
+
+    let files: Vec<RevertFileOutcome> = outcomes.into_values().collect();
+    let conflicted: Vec<&str> = files
+        .iter()
+        .filter(|file| file.status.starts_with("conflict"))
+        .map(|file| file.path.as_str())
+        .collect();
+    let mut message = format!("Revert: {}", commit.summary().unwrap_or("unknown"));
+    if !conflicted.is_empty() {
+        message.push_str("\n\nConflicts:\n");
+        for path in &conflicted {
+            message.push_str(&format!("- {path}\n"));
+        }
+    }
+
+    let revert_oid = vault_repo.commit(CommitAuthor::User, &message, &tree, &[&head])?;
+    // Later commits are built from the repo index, so it must match the reverted tree.
+    vault_repo.sync_index(&tree)?;
+
+    Ok(RevertOutcome {
+        commit_id: revert_oid.to_string(),
+        conflicts: conflicted.len(),
+        files,
+    })
+}
+
+/// Paths among `outcome`'s files and conflict copies whose vault copy does not match the
+/// revert commit: the content differs, or the note exists on one side only.
+pub fn revert_readback_mismatches(
+    vault_path: &Path,
+    outcome: &RevertOutcome,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let repo = &vault_repo.repo;
+    let tree = repo
+        .find_commit(git2::Oid::from_str(&outcome.commit_id)?)?
+        .tree()?;
+
+    let mut mismatches = Vec::new();
+    let paths = outcome
+        .files
+        .iter()
+        .flat_map(|file| std::iter::once(&file.path).chain(file.conflict_copy.as_ref()));
+    for path in paths {
+        let committed = match tree.get_path(Path::new(path)) {
+            Ok(entry) => Some(repo.find_blob(entry.id())?.content().to_vec()),
+            Err(error) if error.code() == git2::ErrorCode::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+        let on_disk = std::fs::read(vault_path.join(path)).ok();
+        if committed != on_disk {
+            mismatches.push(path.clone());
+        }
+    }
+    Ok(mismatches)
+}
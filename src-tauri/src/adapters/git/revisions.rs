@@ -0,0 +1,52 @@
+//! Single-note access to history: read a note as it was at a commit, or restore it to
+//! that version without touching the rest of the vault.
+
+use std::path::Path;
+
+use super::{commit_files, get_commit_diff, CommitAuthor, CommitDiff, VaultRepo};
+
+/// Content of note `path` at `revision` (a commit id, short id, or any git revspec).
+/// Returns the resolved commit id with the content.
+pub fn read_file_at(
+    vault_path: &Path,
+    path: &str,
+    revision: &str,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let repo = VaultRepo::open(vault_path)?.repo;
+    let commit = repo.revparse_single(revision)?.peel_to_commit()?;
+    let entry = commit
+        .tree()?
+        .get_path(Path::new(path))
+        .map_err(|_| format!("{path} did not exist at {}", &commit.id().to_string()[..7]))?;
+    let blob = repo.find_blob(entry.id())?;
+    let content = String::from_utf8_lossy(blob.content()).into_owned();
+    Ok((commit.id().to_string(), content))
+}
+
+/// Write note `path` back to its content at `revision` and commit it. Unsnapshotted hand
+/// edits to the note are committed first so the restore can itself be reverted. Returns
+/// the restore commit, or `None` when the note already matched that version.
+pub fn restore_file(
+    vault_path: &Path,
+    path: &str,
+    revision: &str,
+) -> Result<Option<CommitDiff>, Box<dyn std::error::Error>> {
+    let (commit_id, content) = read_file_at(vault_path, path, revision)?;
+    let file = vault_path.join(path);
+    commit_files(
+        vault_path,
+        std::slice::from_ref(&file),
+        "meld: pre-restore snapshot",
+        CommitAuthor::User,
+    )?;
+
+    crate::adapters::vault::write_note(vault_path, path, &content)?;
+    let oid = commit_files(
+        vault_path,
+        std::slice::from_ref(&file),
+        &format!("Restore {path} to {}", &commit_id[..7]),
+        CommitAuthor::User,
+    )?;
+    oid.map(|oid| get_commit_diff(vault_path, &oid.to_string()))
+        .transpose()
+}
@@ -0,0 +1,159 @@
+//! Agent runs land in history as one commit each, tagged with git trailers so a run can
+//! be found and reverted as a unit.
+
+use std::path::{Path, PathBuf};
+
+use crate::core::ports::versioning::{RunCommit, VersioningPort};
+
+use super::{commit_files, CommitAuthor, ConflictStyle, RevertOutcome, VaultRepo};
+
+const RUN_ID_TRAILER: &str = "Meld-Run-Id";
+const CONVERSATION_ID_TRAILER: &str = "Meld-Conversation-Id";
+const PROVIDER_TRAILER: &str = "Meld-Provider";
+const MODEL_TRAILER: &str = "Meld-Model";
+/// Marks the snapshots taken before a run first writes a note. Kept separate from
+/// `Meld-Run-Id` so reverting a run never reverts the human edits it captured.
+const PRE_RUN_TRAILER: &str = "Meld-Pre-Run";
+
+/// Value of trailer `key` in a commit message, if present.
+fn message_trailer(message: &str, key: &str) -> Option<String> {
+    let trailers = git2::message_trailers_strs(message).ok()?;
+    let value = trailers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.trim().to_string());
+    value
+}
+
+/// Run id recorded on a run commit.
+pub(super) fn commit_run_id(message: &str) -> Option<String> {
+    message_trailer(message, RUN_ID_TRAILER)
+}
+
+/// Provider and model recorded on a run commit.
+pub(super) fn commit_run_model(message: &str) -> (Option<String>, Option<String>) {
+    (
+        message_trailer(message, PROVIDER_TRAILER),
+        message_trailer(message, MODEL_TRAILER),
+    )
+}
+
+pub(super) fn run_commit_message(paths: &[String], run: &RunCommit<'_>) -> String {
+    let subject = match paths {
+        [path] => format!("meld: run wrote {path}"),
+        _ => format!("meld: run wrote {} notes", paths.len()),
+    };
+    let body: Vec<String> = paths.iter().map(|path| format!("- {path}")).collect();
+    format!(
+        "{subject}\n\n{}\n\n{RUN_ID_TRAILER}: {}\n{CONVERSATION_ID_TRAILER}: {}\n{PROVIDER_TRAILER}: {}\n{MODEL_TRAILER}: {}\n",
+        body.join("\n"),
+        run.run_id,
+        run.conversation_id,
+        run.provider,
+        run.model,
+    )
+}
+
+/// Commit the notes a run is about to write for the first time, so uncommitted human
+/// edits in them are attributed to the user rather than to the run. Other notes are left
+/// alone.
+pub fn snapshot_before_write(
+    vault_path: &Path,
+    run_id: &str,
+    paths: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let files: Vec<PathBuf> = paths.iter().map(|path| vault_path.join(path)).collect();
+    commit_files(
+        vault_path,
+        &files,
+        &format!("meld: pre-run snapshot\n\n{PRE_RUN_TRAILER}: {run_id}\n"),
+        CommitAuthor::User,
+    )?;
+    Ok(())
+}
+
+/// Commit every note written by a run as a single commit. Returns the commit id, or
+/// `None` when the run left the notes unchanged.
+pub fn commit_run(
+    vault_path: &Path,
+    paths: &[String],
+    run: &RunCommit<'_>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let files: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| vault_path.join(path))
+        .filter(|file| file.exists())
+        .collect();
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let oid = commit_files(
+        vault_path,
+        &files,
+        &run_commit_message(paths, run),
+        CommitAuthor::Meld,
+    )?;
+    Ok(oid.map(|oid| oid.to_string()))
+}
+
+/// Most recent commit made by `run_id`.
+pub fn find_run_commit(
+    vault_path: &Path,
+    run_id: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open(vault_path)?;
+    for oid in vault_repo.revwalk()? {
+        let commit = vault_repo.repo.find_commit(oid?)?;
+        if commit_run_id(commit.message().unwrap_or("")).as_deref() == Some(run_id) {
+            return Ok(Some(commit.id().to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Undo everything a run wrote by reverting its commit.
+pub fn revert_run(
+    vault_path: &Path,
+    run_id: &str,
+    style: ConflictStyle,
+) -> Result<RevertOutcome, Box<dyn std::error::Error>> {
+    let commit_id = find_run_commit(vault_path, run_id)?
+        .ok_or_else(|| format!("Run {run_id} has no committed changes to revert"))?;
+    super::revert_commit(vault_path, &commit_id, style)
+}
+
+/// Git-backed history for agent runs.
+pub struct GitVersioning;
+
+impl VersioningPort for GitVersioning {
+    fn snapshot_before_write(
+        &self,
+        vault_path: &Path,
+        run_id: &str,
+        paths: &[String],
+    ) -> Result<(), String> {
+        snapshot_before_write(vault_path, run_id, paths).map_err(|e| e.to_string())
+    }
+
+    fn commit_run(
+        &self,
+        vault_path: &Path,
+        paths: &[String],
+        run: &RunCommit<'_>,
+    ) -> Result<Option<String>, String> {
+        commit_run(vault_path, paths, run).map_err(|e| e.to_string())
+    }
+
+    fn open_branch(&self, vault_path: &Path, conversation_id: i64) -> Result<PathBuf, String> {
+        super::open_sandbox(vault_path, conversation_id).map_err(|e| e.to_string())
+    }
+
+    fn commit_branch_run(
+        &self,
+        vault_path: &Path,
+        paths: &[String],
+        run: &RunCommit<'_>,
+    ) -> Result<Option<String>, String> {
+        super::commit_sandbox(vault_path, paths, run).map_err(|e| e.to_string())
+    }
+}
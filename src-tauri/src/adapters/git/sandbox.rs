@@ -0,0 +1,391 @@
+//! Branch mode. A conversation's agent runs commit to their own branch of the history,
+//! `meld/conversation-<id>`, and work on a shadow copy of that branch under
+//! `.meld/sandboxes/` instead of the vault. The branch reaches the vault only when it is
+//! merged, so a long restructuring can be reviewed as a whole first.
+
+use std::path::{Path, PathBuf};
+
+use git2::{Commit, Index, IndexEntry, MergeFileOptions, Repository};
+use serde::Serialize;
+
+use crate::core::ports::versioning::RunCommit;
+
+use super::runs::run_commit_message;
+use super::{
+    apply_tree_snapshot_to_vault, collect_tree_markdown_files, commit_files, diff_files_and_patch,
+    diff_paths, index_entry, snapshot_hand_edits, CommitAuthor, VaultRepo,
+};
+
+/// A conversation branch compared with the vault history it was forked from.
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchDiff {
+    pub branch: String,
+    /// Merge base with the vault history.
+    pub base_id: String,
+    pub head_id: String,
+    /// Commits on the branch since the merge base.
+    pub commits: usize,
+    pub files_changed: Vec<String>,
+    pub patch: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchMerge {
+    pub commit_id: String,
+    pub fast_forward: bool,
+    pub files_changed: Vec<String>,
+    /// Notes written with conflict markers, or kept where one side deleted them.
+    pub conflicts: Vec<String>,
+}
+
+fn branch_name(conversation_id: i64) -> String {
+    format!("meld/conversation-{conversation_id}")
+}
+
+fn branch_ref(conversation_id: i64) -> String {
+    format!("refs/heads/{}", branch_name(conversation_id))
+}
+
+fn sandbox_path(vault_path: &Path, conversation_id: i64) -> PathBuf {
+    vault_path
+        .join(".meld")
+        .join("sandboxes")
+        .join(format!("conversation-{conversation_id}"))
+}
+
+fn branch_tip(repo: &Repository, conversation_id: i64) -> Result<Option<Commit<'_>>, git2::Error> {
+    match repo.find_reference(&branch_ref(conversation_id)) {
+        Ok(reference) => reference.peel_to_commit().map(Some),
+        Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Fork the conversation's branch from the vault history with the vault's hand edits
+/// committed on the branch alone, leaving the user's checked-out branch untouched.
+fn fork_with_hand_edits(
+    vault_repo: &VaultRepo,
+    vault_path: &Path,
+    conversation_id: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let repo = &vault_repo.repo;
+    let tip = vault_repo.tip()?;
+    let mut index = Index::new()?;
+    if let Some(tip) = &tip {
+        index.read_tree(&tip.tree()?)?;
+    }
+    for file in crate::adapters::vault::list_md_files(vault_path)? {
+        let Ok(relative) = file.strip_prefix(vault_path) else {
+            continue;
+        };
+        if vault_repo.is_ignored(relative) {
+            continue;
+        }
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let content = std::fs::read(&file)?;
+        let blob = repo.blob(&content)?;
+        index.add(&index_entry(&relative, blob, content.len()))?;
+    }
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+
+    let fork = match &tip {
+        Some(tip) if tip.tree_id() == tree.id() => tip.id(),
+        _ => {
+            let sig = vault_repo.signature(CommitAuthor::User)?;
+            let parents: Vec<&Commit<'_>> = tip.iter().collect();
+            repo.commit(
+                None,
+                &sig,
+                &sig,
+                "meld: pre-branch snapshot",
+                &tree,
+                &parents,
+            )?
+        }
+    };
+    repo.reference(
+        &branch_ref(conversation_id),
+        fork,
+        false,
+        "meld: open conversation branch",
+    )?;
+    Ok(())
+}
+
+/// Open the conversation's branch for a run and return its shadow worktree. A new branch
+/// forks from the vault history after hand edits are snapshotted; under `head` the
+/// snapshot is committed on the branch only. The worktree is rewritten from the branch
+/// tip each time, so it always shows the branch state. Private notes stay out of it, as
+/// the agent may not see them.
+pub fn open_sandbox(
+    vault_path: &Path,
+    conversation_id: i64,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open_or_init(vault_path)?;
+    let is_new = branch_tip(&vault_repo.repo, conversation_id)?.is_none();
+    if is_new && !vault_repo.commits_hand_edits() {
+        fork_with_hand_edits(&vault_repo, vault_path, conversation_id)?;
+    } else if is_new {
+        let files = crate::adapters::vault::list_md_files(vault_path)?;
+        commit_files(
+            vault_path,
+            &files,
+            "meld: pre-branch snapshot",
+            CommitAuthor::User,
+        )?;
+        let vault_repo = VaultRepo::open(vault_path)?;
+        let tip = vault_repo.tip_commit()?;
+        vault_repo.repo.reference(
+            &branch_ref(conversation_id),
+            tip.id(),
+            false,
+            "meld: open conversation branch",
+        )?;
+    }
+
+    let repo = VaultRepo::open(vault_path)?.repo;
+    let tip = branch_tip(&repo, conversation_id)?
+        .ok_or_else(|| format!("Conversation {conversation_id} has no branch"))?;
+    let mut notes = Vec::new();
+    collect_tree_markdown_files(&repo, &tip.tree()?, "", &mut notes)?;
+
+    let sandbox = sandbox_path(vault_path, conversation_id);
+    if sandbox.exists() {
+        std::fs::remove_dir_all(&sandbox)?;
+    }
+    std::fs::create_dir_all(&sandbox)?;
+    let privacy = crate::adapters::vault::PrivacyRules::load(vault_path);
+    for (relative, content) in notes {
+        if privacy.is_private(&relative, &String::from_utf8_lossy(&content)) {
+            continue;
+        }
+        let file = sandbox.join(&relative);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(file, content)?;
+    }
+    Ok(sandbox)
+}
+
+/// Commit the notes a run wrote in the shadow worktree onto the conversation's branch.
+/// Returns the commit id, or `None` when the notes match the branch already.
+pub fn commit_sandbox(
+    vault_path: &Path,
+    paths: &[String],
+    run: &RunCommit<'_>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let repo = &vault_repo.repo;
+    let tip = branch_tip(repo, run.conversation_id)?
+        .ok_or_else(|| format!("Conversation {} has no branch", run.conversation_id))?;
+    let sandbox = sandbox_path(vault_path, run.conversation_id);
+
+    let mut index = Index::new()?;
+    index.read_tree(&tip.tree()?)?;
+    for path in paths {
+        let file = sandbox.join(path);
+        if file.exists() {
+            let content = std::fs::read(file)?;
+            let blob = repo.blob(&content)?;
+            index.add(&index_entry(path, blob, content.len()))?;
+        } else if index.get_path(Path::new(path), 0).is_some() {
+            index.remove_path(Path::new(path))?;
+        }
+    }
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+    if tree.id() == tip.tree_id() {
+        return Ok(None);
+    }
+
+    let sig = vault_repo.signature(CommitAuthor::Meld)?;
+    let oid = repo.commit(
+        Some(&branch_ref(run.conversation_id)),
+        &sig,
+        &sig,
+        &run_commit_message(paths, run),
+        &tree,
+        &[&tip],
+    )?;
+    Ok(Some(oid.to_string()))
+}
+
+/// What merging the conversation's branch would change, relative to where it forked
+/// from the vault history. `None` when the conversation has no branch.
+pub fn sandbox_diff(
+    vault_path: &Path,
+    conversation_id: i64,
+) -> Result<Option<BranchDiff>, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let repo = &vault_repo.repo;
+    let Some(head) = branch_tip(repo, conversation_id)? else {
+        return Ok(None);
+    };
+    let main = vault_repo.tip_commit()?;
+    let base_id = repo.merge_base(main.id(), head.id())?;
+    let base = repo.find_commit(base_id)?;
+
+    let diff = repo.diff_tree_to_tree(Some(&base.tree()?), Some(&head.tree()?), None)?;
+    let (files_changed, patch) = diff_files_and_patch(&diff)?;
+    let (commits, _) = repo.graph_ahead_behind(head.id(), base_id)?;
+
+    Ok(Some(BranchDiff {
+        branch: branch_name(conversation_id),
+        base_id: base_id.to_string(),
+        head_id: head.id().to_string(),
+        commits,
+        files_changed,
+        patch,
+    }))
+}
+
+fn merge_markers(
+    repo: &Repository,
+    ancestor: &IndexEntry,
+    ours: &IndexEntry,
+    theirs: &IndexEntry,
+    branch: &str,
+) -> Result<Vec<u8>, git2::Error> {
+    let mut opts = MergeFileOptions::new();
+    opts.ancestor_label("base")
+        .our_label("vault")
+        .their_label(branch);
+    let merged = repo.merge_file_from_index(ancestor, ours, theirs, Some(&mut opts))?;
+    Ok(merged.content().to_vec())
+}
+
+/// Notes the conversation's branch changes relative to the vault history, which merging
+/// it writes over.
+fn merged_paths(
+    vault_path: &Path,
+    conversation_id: i64,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let Some(head) = branch_tip(&vault_repo.repo, conversation_id)? else {
+        return Ok(Vec::new());
+    };
+    let main_tree = vault_repo.tip()?.map(|tip| tip.tree()).transpose()?;
+    let diff = vault_repo
+        .repo
+        .diff_tree_to_tree(main_tree.as_ref(), Some(&head.tree()?), None)?;
+    Ok(diff_paths(&diff))
+}
+
+/// Merge the conversation's branch into the vault: a fast-forward when the vault history
+/// has not moved since the branch forked, otherwise a merge commit with conflicting
+/// lines written as conflict markers. The branch and its worktree are removed after.
+/// Returns `None` when the branch had nothing to merge.
+pub fn merge_sandbox(
+    vault_path: &Path,
+    conversation_id: i64,
+) -> Result<Option<BranchMerge>, Box<dyn std::error::Error>> {
+    let merged = merged_paths(vault_path, conversation_id)?;
+    snapshot_hand_edits(vault_path, &merged, "meld: pre-merge snapshot")?;
+
+    let vault_repo = VaultRepo::open(vault_path)?;
+    let repo = &vault_repo.repo;
+    let branch = branch_name(conversation_id);
+    let head = branch_tip(repo, conversation_id)?
+        .ok_or_else(|| format!("Conversation {conversation_id} has no branch to merge"))?;
+    let main = vault_repo.tip_commit()?;
+    let base_id = repo.merge_base(main.id(), head.id())?;
+    if base_id == head.id() {
+        discard_sandbox(vault_path, conversation_id)?;
+        return Ok(None);
+    }
+
+    let main_tree = main.tree()?;
+    let outcome = if base_id == main.id() {
+        let tree = head.tree()?;
+        apply_tree_snapshot_to_vault(repo, &main_tree, &tree, vault_path)?;
+        vault_repo.advance(head.id(), &format!("meld: fast-forward {branch}"))?;
+        vault_repo.sync_index(&tree)?;
+        let diff = repo.diff_tree_to_tree(Some(&main_tree), Some(&tree), None)?;
+        BranchMerge {
+            commit_id: head.id().to_string(),
+            fast_forward: true,
+            files_changed: diff_files_and_patch(&diff)?.0,
+            conflicts: Vec::new(),
+        }
+    } else {
+        let mut index = repo.merge_commits(&main, &head, None)?;
+        let mut conflicts = Vec::new();
+        for conflict in index.conflicts()?.collect::<Result<Vec<_>, _>>()? {
+            let Some(entry) = [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .next()
+            else {
+                continue;
+            };
+            let path = String::from_utf8_lossy(&entry.path).into_owned();
+            let resolved = match (&conflict.ancestor, &conflict.our, &conflict.their) {
+                (Some(ancestor), Some(ours), Some(theirs)) => {
+                    Some(merge_markers(repo, ancestor, ours, theirs, &branch)?)
+                }
+                // Created on both sides: merge against an empty note.
+                (None, Some(ours), Some(theirs)) => {
+                    let empty = index_entry(&path, repo.blob(b"")?, 0);
+                    Some(merge_markers(repo, &empty, ours, theirs, &branch)?)
+                }
+                // Deleted on one side and edited on the other: keep the edited note.
+                (_, Some(kept), None) | (_, None, Some(kept)) => {
+                    Some(repo.find_blob(kept.id)?.content().to_vec())
+                }
+                _ => None,
+            };
+            index.conflict_remove(Path::new(&path))?;
+            if let Some(content) = resolved {
+                let blob = repo.blob(&content)?;
+                index.add(&index_entry(&path, blob, content.len()))?;
+            }
+            conflicts.push(path);
+        }
+
+        let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+        apply_tree_snapshot_to_vault(repo, &main_tree, &tree, vault_path)?;
+        let mut message = format!("Merge {branch}");
+        if !conflicts.is_empty() {
+            message.push_str("\n\nConflicts:\n");
+            for path in &conflicts {
+                message.push_str(&format!("- {path}\n"));
+            }
+        }
+        let oid = vault_repo.commit(CommitAuthor::User, &message, &tree, &[&main, &head])?;
+        vault_repo.sync_index(&tree)?;
+        let diff = repo.diff_tree_to_tree(Some(&main_tree), Some(&tree), None)?;
+        BranchMerge {
+            commit_id: oid.to_string(),
+            fast_forward: false,
+            files_changed: diff_files_and_patch(&diff)?.0,
+            conflicts,
+        }
+    };
+
+    discard_sandbox(vault_path, conversation_id)?;
+    Ok(Some(outcome))
+}
+
+/// Delete the conversation's branch and shadow worktree without merging. Returns whether
+/// there was a branch to delete.
+pub fn discard_sandbox(
+    vault_path: &Path,
+    conversation_id: i64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let sandbox = sandbox_path(vault_path, conversation_id);
+    if sandbox.exists() {
+        std::fs::remove_dir_all(&sandbox)?;
+    }
+    if !super::has_history(vault_path) {
+        return Ok(false);
+    }
+    let repo = VaultRepo::open(vault_path)?.repo;
+    match repo.find_reference(&branch_ref(conversation_id)) {
+        Ok(mut reference) => {
+            reference.delete()?;
+            Ok(true)
+        }
+        Err(error) if error.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
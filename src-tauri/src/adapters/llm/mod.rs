@@ -264,7 +264,7 @@ pub async fn chat_stream(
         }
     };
 
-    let registry = crate::adapters::providers::ProviderRegistry::default();
+    let registry = crate::adapters::providers::ProviderRegistry::configured();
     let (resolved_provider, resolved_model) = registry
         .resolve_llm(&model_id)
         .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
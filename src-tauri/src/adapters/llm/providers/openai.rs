@@ -189,14 +189,22 @@ pub async fn chat_stream_with_endpoint(
     tx: mpsc::UnboundedSender<StreamEvent>,
     endpoint: &str,
     provider_name: &str,
+    extra_headers: &HashMap<String, String>,
     thinking_budget: Option<u32>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let client = reqwest::Client::new();
 
-    let response = client
+    let mut request = client
         .post(endpoint)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("content-type", "application/json")
+        .header("content-type", "application/json");
+    // Self-hosted servers often run without a key.
+    if !api_key.trim().is_empty() {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+    for (name, value) in extra_headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = request
         .json(&OpenAIChatCompletionsRequest {
             model: model.to_string(),
             messages: build_chat_completion_messages(messages),
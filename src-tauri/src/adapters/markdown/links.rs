@@ -0,0 +1,211 @@
+//! Obsidian-style `[[wikilinks]]` and frontmatter `aliases`.
+
+use serde::{Deserialize, Serialize};
+
+/// Embedded attachments (`![[diagram.png]]`) are not notes and are never indexed as links.
+const ATTACHMENT_EXTENSIONS: [&str; 17] = [
+    "png",
+    "jpg",
+    "jpeg",
+    "gif",
+    "svg",
+    "webp",
+    "bmp",
+    "pdf",
+    "mp3",
+    "mp4",
+    "m4a",
+    "wav",
+    "ogg",
+    "webm",
+    "mov",
+    "canvas",
+    "excalidraw",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WikiLink {
+    /// Note name or path as written, without `#heading` or `|alias`. Empty for `[[#Heading]]`.
+    pub target: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+    pub embed: bool,
+}
+
+fn is_attachment(target: &str) -> bool {
+    target
+        .rsplit_once('.')
+        .map(|(_, ext)| {
+            ATTACHMENT_EXTENSIONS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(ext.trim()))
+        })
+        .unwrap_or(false)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn parse_link(inner: &str, embed: bool) -> Option<WikiLink> {
+    // Inside tables the alias pipe is escaped as `\|`.
+    let (target_part, alias) = match inner.split_once('|') {
+        Some((target, alias)) => (target.trim_end_matches('\\'), non_empty(alias)),
+        None => (inner, None),
+    };
+    let (target, heading) = match target_part.split_once('#') {
+        Some((target, heading)) => (target, non_empty(heading)),
+        None => (target_part, None),
+    };
+    let target = target.trim().to_string();
+    if (target.is_empty() && heading.is_none()) || is_attachment(&target) {
+        return None;
+    }
+    Some(WikiLink {
+        target,
+        heading,
+        alias,
+        embed,
+    })
+}
+
+/// Remove inline code spans so `` `[[not a link]]` `` is ignored.
+fn strip_inline_code(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut in_code = false;
+    for ch in line.chars() {
+        if ch == '`' {
+            in_code = !in_code;
+            continue;
+        }
+        if !in_code {
+            output.push(ch);
+        }
+    }
+    output
+}
+
+/// Extract wikilinks and embeds in document order, skipping fenced and inline code.
+pub fn extract_wikilinks(markdown: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            fence = Some("```");
+            continue;
+        }
+        if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+            continue;
+        }
+
+        let text = strip_inline_code(line);
+        let mut rest = text.as_str();
+        while let Some(open) = rest.find("[[") {
+            let embed = rest[..open].ends_with('!');
+            let after_open = &rest[open + 2..];
+            let Some(close) = after_open.find("]]") else {
+                break;
+            };
+            let inner = &after_open[..close];
+            // `[[a [[b]]` — restart from the innermost opener.
+            if let Some(nested) = inner.rfind("[[") {
+                rest = &after_open[nested..];
+                continue;
+            }
+            if let Some(link) = parse_link(inner, embed) {
+                links.push(link);
+            }
+            rest = &after_open[close + 2..];
+        }
+    }
+
+    links
+}
+
+/// Frontmatter `aliases` (list or single string; `alias` is accepted too).
+pub fn frontmatter_aliases(markdown: &str) -> Vec<String> {
+    let mut lines = markdown.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return Vec::new();
+    }
+    let yaml: Vec<&str> = lines.take_while(|line| line.trim_end() != "---").collect();
+    let Ok(value) = serde_yaml_ng::from_str::<serde_yaml_ng::Value>(&yaml.join("\n")) else {
+        return Vec::new();
+    };
+
+    let mut aliases = Vec::new();
+    for key in ["aliases", "alias"] {
+        match value.get(key) {
+            Some(serde_yaml_ng::Value::String(alias)) => aliases.extend(non_empty(alias)),
+            Some(serde_yaml_ng::Value::Sequence(items)) => aliases.extend(
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .filter_map(non_empty),
+            ),
+            _ => {}
+        }
+    }
+    aliases.dedup();
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_wikilinks, frontmatter_aliases, WikiLink};
+
+    fn link(target: &str, heading: Option<&str>, alias: Option<&str>, embed: bool) -> WikiLink {
+        WikiLink {
+            target: target.to_string(),
+            heading: heading.map(str::to_string),
+            alias: alias.map(str::to_string),
+            embed,
+        }
+    }
+
+    #[test]
+    fn extracts_headings_aliases_and_embeds() {
+        let markdown = "See [[Project Plan#Risks|the risks]] and [[#Local]].\n![[Meeting Notes]] ![[diagram.png]]\n| [[Table\\|cell]] |";
+        assert_eq!(
+            extract_wikilinks(markdown),
+            vec![
+                link("Project Plan", Some("Risks"), Some("the risks"), false),
+                link("", Some("Local"), None, false),
+                link("Meeting Notes", None, None, true),
+                link("Table", None, Some("cell"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_links_in_code() {
+        let markdown = "```\n[[Fenced]]\n```\nUse `[[Inline]]` or [[Real]]";
+        assert_eq!(
+            extract_wikilinks(markdown),
+            vec![link("Real", None, None, false)]
+        );
+    }
+
+    #[test]
+    fn reads_frontmatter_aliases() {
+        assert_eq!(
+            frontmatter_aliases("---\naliases: [PP, Plan]\n---\n# Project Plan"),
+            vec!["PP".to_string(), "Plan".to_string()]
+        );
+        assert_eq!(
+            frontmatter_aliases("---\nalias: Roadmap\n---\n"),
+            vec!["Roadmap".to_string()]
+        );
+        assert!(frontmatter_aliases("# No frontmatter").is_empty());
+    }
+}
@@ -1,5 +1,8 @@
 use pulldown_cmark::{Event, Parser, TagEnd};
 
+pub mod links;
+pub mod patch;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MarkdownChunk {
     pub content: String,
@@ -0,0 +1,325 @@
+//! Structured, section-level edits applied to a note's raw markdown.
+//!
+//! Headings are addressed by path (`"Project > Risks"`, matched against the trailing
+//! segments of the heading stack). A section spans from its heading line to the next
+//! heading of the same or higher level, so it includes its subsections.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOperation {
+    /// Replace an exact text snippet. Fails when absent, or ambiguous unless `all` is set.
+    ReplaceText {
+        find: String,
+        replace: String,
+        #[serde(default)]
+        all: bool,
+    },
+    /// Insert content directly below the heading line.
+    InsertUnderHeading { heading: String, content: String },
+    /// Insert content at the end of the section, before any trailing blank lines.
+    AppendToSection { heading: String, content: String },
+    /// Replace the YAML frontmatter (without `---` fences). Empty removes it.
+    ReplaceFrontmatter { frontmatter: String },
+}
+
+struct Heading {
+    path: Vec<String>,
+    level: usize,
+    line_start: usize,
+    line_end: usize,
+}
+
+/// Byte length of the leading `---` frontmatter block, including its closing fence line.
+fn frontmatter_len(markdown: &str) -> usize {
+    let mut lines = markdown.split_inclusive('\n');
+    let Some(first) = lines.next().filter(|line| line.trim_end() == "---") else {
+        return 0;
+    };
+    let mut offset = first.len();
+    for line in lines {
+        offset += line.len();
+        if line.trim_end() == "---" {
+            return offset;
+        }
+    }
+    0
+}
+
+fn collect_headings(markdown: &str, body_start: usize) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut offset = body_start;
+
+    for line in markdown[body_start..].split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            fence = Some("```");
+            continue;
+        }
+        if trimmed.starts_with("~~~") {
+            fence = Some("~~~");
+            continue;
+        }
+
+        let Some((level, title)) = super::parse_heading(line) else {
+            continue;
+        };
+        // `#tag` is not a heading; ATX headings need a space after the hashes.
+        if !trimmed[level..].starts_with([' ', '\t']) {
+            continue;
+        }
+        let title = title.trim_end_matches('#').trim().to_string();
+        while stack.last().is_some_and(|(open, _)| *open >= level) {
+            stack.pop();
+        }
+        stack.push((level, title));
+        headings.push(Heading {
+            path: stack.iter().map(|(_, title)| title.clone()).collect(),
+            level,
+            line_start,
+            line_end: offset,
+        });
+    }
+    headings
+}
+
+fn find_section(markdown: &str, heading: &str) -> Result<(usize, usize), String> {
+    let wanted: Vec<&str> = heading
+        .split('>')
+        .map(|segment| segment.trim().trim_start_matches('#').trim())
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if wanted.is_empty() {
+        return Err("Heading path is empty".to_string());
+    }
+
+    let headings = collect_headings(markdown, frontmatter_len(markdown));
+    let matches: Vec<usize> = headings
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| {
+            candidate.path.len() >= wanted.len()
+                && candidate.path[candidate.path.len() - wanted.len()..]
+                    .iter()
+                    .zip(&wanted)
+                    .all(|(have, want)| have.eq_ignore_ascii_case(want))
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    let index = match matches.as_slice() {
+        [index] => *index,
+        [] => return Err(format!("Heading not found: {heading}")),
+        _ => {
+            return Err(format!(
+                "Heading '{heading}' matches {} sections; use a longer path like 'Parent > Child'",
+                matches.len()
+            ))
+        }
+    };
+
+    let section = &headings[index];
+    let section_end = headings[index + 1..]
+        .iter()
+        .find(|next| next.level <= section.level)
+        .map_or(markdown.len(), |next| next.line_start);
+    Ok((section.line_end, section_end))
+}
+
+fn as_block(content: &str) -> String {
+    let mut block = content.trim_end_matches(['\n', '\r']).to_string();
+    block.push('\n');
+    block
+}
+
+fn insert_block(markdown: &str, at: usize, content: &str) -> String {
+    let mut output = String::with_capacity(markdown.len() + content.len() + 2);
+    output.push_str(&markdown[..at]);
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(&as_block(content));
+    output.push_str(&markdown[at..]);
+    output
+}
+
+fn apply_operation(markdown: &str, operation: &PatchOperation) -> Result<String, String> {
+    match operation {
+        PatchOperation::ReplaceText { find, replace, all } => {
+            if find.is_empty() {
+                return Err("replace_text requires a non-empty find".to_string());
+            }
+            match markdown.matches(find.as_str()).count() {
+                0 => Err(format!("Text not found: {find}")),
+                1 => Ok(markdown.replacen(find.as_str(), replace, 1)),
+                _ if *all => Ok(markdown.replace(find.as_str(), replace)),
+                count => Err(format!(
+                    "Text occurs {count} times; include more context or set all=true"
+                )),
+            }
+        }
+        PatchOperation::InsertUnderHeading { heading, content } => {
+            let (body_start, _) = find_section(markdown, heading)?;
+            Ok(insert_block(markdown, body_start, content))
+        }
+        PatchOperation::AppendToSection { heading, content } => {
+            let (body_start, section_end) = find_section(markdown, heading)?;
+            let mut at = body_start;
+            let mut offset = body_start;
+            for line in markdown[body_start..section_end].split_inclusive('\n') {
+                offset += line.len();
+                if !line.trim().is_empty() {
+                    at = offset;
+                }
+            }
+            Ok(insert_block(markdown, at, content))
+        }
+        PatchOperation::ReplaceFrontmatter { frontmatter } => {
+            let yaml = frontmatter.trim();
+            if !yaml.is_empty() {
+                let parsed: serde_yaml_ng::Value = serde_yaml_ng::from_str(yaml)
+                    .map_err(|e| format!("Invalid frontmatter YAML: {e}"))?;
+                if !parsed.is_mapping() {
+                    return Err("Frontmatter must be a YAML mapping".to_string());
+                }
+            }
+            let body = &markdown[frontmatter_len(markdown)..];
+            if yaml.is_empty() {
+                return Ok(body.trim_start_matches('\n').to_string());
+            }
+            Ok(format!("---\n{yaml}\n---\n{body}"))
+        }
+    }
+}
+
+/// Apply operations in order. Nothing is returned unless every operation applies.
+pub fn apply_patch(markdown: &str, operations: &[PatchOperation]) -> Result<String, String> {
+    operations
+        .iter()
+        .enumerate()
+        .try_fold(markdown.to_string(), |current, (index, operation)| {
+            apply_operation(&current, operation)
+                .map_err(|error| format!("Operation {} failed: {error}", index + 1))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_patch, PatchOperation};
+
+    const NOTE: &str =
+        "---\ntags: [plan]\n---\n# Plan\n\nIntro.\n\n## Risks\n- late\n\n## Next\nShip it.\n";
+
+    #[test]
+    fn append_and_insert_target_heading_sections() {
+        let patched = apply_patch(
+            NOTE,
+            &[
+                PatchOperation::AppendToSection {
+                    heading: "Plan > Risks".to_string(),
+                    content: "- over budget".to_string(),
+                },
+                PatchOperation::InsertUnderHeading {
+                    heading: "Next".to_string(),
+                    content: "Owner: Sam".to_string(),
+                },
+            ],
+        )
+        .expect("patch");
+
+        assert!(
+            patched.contains("## Risks\n- late\n- over budget\n\n## Next\nOwner: Sam\nShip it.\n")
+        );
+        assert!(patched.starts_with("---\ntags: [plan]\n---\n"));
+    }
+
+    #[test]
+    fn append_to_section_includes_subsections() {
+        let note = "# A\ntext\n## A1\nsub\n# B\n";
+        let patched = apply_patch(
+            note,
+            &[PatchOperation::AppendToSection {
+                heading: "A".to_string(),
+                content: "tail".to_string(),
+            }],
+        )
+        .expect("patch");
+        assert_eq!(patched, "# A\ntext\n## A1\nsub\ntail\n# B\n");
+    }
+
+    #[test]
+    fn replace_text_rejects_missing_and_ambiguous_matches() {
+        let replaced = apply_patch(
+            NOTE,
+            &[PatchOperation::ReplaceText {
+                find: "Ship it.".to_string(),
+                replace: "Ship Friday.".to_string(),
+                all: false,
+            }],
+        )
+        .expect("patch");
+        assert!(replaced.contains("Ship Friday."));
+
+        let missing = apply_patch(
+            NOTE,
+            &[PatchOperation::ReplaceText {
+                find: "absent".to_string(),
+                replace: String::new(),
+                all: false,
+            }],
+        );
+        assert!(missing.unwrap_err().contains("Operation 1 failed"));
+
+        let ambiguous = apply_patch(
+            "a a",
+            &[PatchOperation::ReplaceText {
+                find: "a".to_string(),
+                replace: "b".to_string(),
+                all: false,
+            }],
+        );
+        assert!(ambiguous.is_err());
+    }
+
+    #[test]
+    fn replace_frontmatter_validates_yaml_and_ignores_code_fence_headings() {
+        let patched = apply_patch(
+            NOTE,
+            &[PatchOperation::ReplaceFrontmatter {
+                frontmatter: "tags: [plan, q3]\nstatus: draft".to_string(),
+            }],
+        )
+        .expect("patch");
+        assert!(patched.starts_with("---\ntags: [plan, q3]\nstatus: draft\n---\n# Plan\n"));
+
+        let invalid = apply_patch(
+            NOTE,
+            &[PatchOperation::ReplaceFrontmatter {
+                frontmatter: "- just\n- a list".to_string(),
+            }],
+        );
+        assert!(invalid.is_err());
+
+        let fenced = "# Real\n```\n# Not a heading\n```\n";
+        let missing = apply_patch(
+            fenced,
+            &[PatchOperation::InsertUnderHeading {
+                heading: "Not a heading".to_string(),
+                content: "x".to_string(),
+            }],
+        );
+        assert!(missing.unwrap_err().contains("Heading not found"));
+    }
+}
@@ -0,0 +1,585 @@
+//! Model Context Protocol client for mounting external MCP servers as agent tools.
+//!
+//! Supports the stdio transport (spawned command) and streamable HTTP (single endpoint,
+//! JSON or SSE responses). Each remote tool is registered as `server__tool` and wrapped in
+//! the same envelope shape as the built-in tools.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+use super::server::LATEST_PROTOCOL_VERSION;
+use super::{envelope, error_envelope, Permission, ToolContext, ToolDefinition, ToolExecutor};
+use crate::adapters::config::ExternalMcpServerConfig;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const CALL_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_TOOL_NAME_LEN: usize = 64;
+const SUMMARY_MAX_CHARS: usize = 200;
+
+struct StdioTransport {
+    // Held so the process is killed when the client is dropped.
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    session_id: Option<String>,
+    protocol_version: Option<String>,
+}
+
+enum Transport {
+    Stdio(Box<StdioTransport>),
+    Http(HttpTransport),
+}
+
+pub struct McpClient {
+    server_name: String,
+    transport: Mutex<Transport>,
+    next_id: AtomicU64,
+    /// Set when a request fails to reach the server or times out.
+    broken: AtomicBool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteTool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    pub read_only: bool,
+}
+
+fn response_matches(message: &Value, id: u64) -> bool {
+    message.get("id").and_then(|v| v.as_u64()) == Some(id)
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+fn into_result(response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error");
+        return Err(format!("MCP error: {message}"));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+/// Collect the JSON payloads of an SSE body (`data:` lines, events split by blank lines).
+fn parse_sse_messages(body: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    let mut data = String::new();
+    for line in body.lines().chain(std::iter::once("")) {
+        if let Some(rest) = line.strip_prefix("data:") {
+            if !data.is_empty() {
+                data.push('\n');
+            }
+            data.push_str(rest.strip_prefix(' ').unwrap_or(rest));
+        } else if line.trim().is_empty() && !data.is_empty() {
+            if let Ok(value) = serde_json::from_str::<Value>(&data) {
+                messages.push(value);
+            }
+            data.clear();
+        }
+    }
+    messages
+}
+
+impl StdioTransport {
+    fn spawn(config: &ExternalMcpServerConfig) -> Result<Self, String> {
+        let command = config.command.as_deref().unwrap_or_default().trim();
+        let mut child = tokio::process::Command::new(command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("failed to start '{command}': {e}"))?;
+        let stdin = child.stdin.take().ok_or("stdin unavailable")?;
+        let stdout = child.stdout.take().ok_or("stdout unavailable")?;
+        Ok(Self {
+            _child: child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<(), String> {
+        let mut encoded = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        encoded.push('\n');
+        self.stdin
+            .write_all(encoded.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        self.stdin.flush().await.map_err(|e| e.to_string())
+    }
+
+    async fn request(&mut self, message: &Value, id: u64) -> Result<Value, String> {
+        self.send(message).await?;
+        loop {
+            let line = self
+                .stdout
+                .next_line()
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or("server closed stdout")?;
+            let Ok(incoming) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if response_matches(&incoming, id) {
+                return Ok(incoming);
+            }
+            // Server-initiated requests (sampling, roots, ...) are not supported.
+            if let (Some(request_id), Some(_)) = (incoming.get("id"), incoming.get("method")) {
+                self.send(&json!({
+                    "jsonrpc": "2.0",
+                    "id": request_id,
+                    "error": { "code": -32601, "message": "Method not supported by meld" },
+                }))
+                .await?;
+            }
+        }
+    }
+}
+
+impl HttpTransport {
+    fn new(config: &ExternalMcpServerConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: config.url.as_deref().unwrap_or_default().trim().to_string(),
+            headers: config.headers.clone(),
+            session_id: None,
+            protocol_version: None,
+        }
+    }
+
+    async fn post(&mut self, message: &Value, id: Option<u64>) -> Result<Option<Value>, String> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        if let Some(session_id) = &self.session_id {
+            request = request.header("Mcp-Session-Id", session_id);
+        }
+        if let Some(version) = &self.protocol_version {
+            request = request.header("MCP-Protocol-Version", version);
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {status}: {body}"));
+        }
+        if let Some(session_id) = response
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|v| v.to_str().ok())
+        {
+            self.session_id = Some(session_id.to_string());
+        }
+        let Some(id) = id else {
+            return Ok(None);
+        };
+
+        let is_sse = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        let candidates = if is_sse {
+            parse_sse_messages(&body)
+        } else {
+            match serde_json::from_str::<Value>(&body).map_err(|e| e.to_string())? {
+                Value::Array(batch) => batch,
+                single => vec![single],
+            }
+        };
+        candidates
+            .into_iter()
+            .find(|candidate| response_matches(candidate, id))
+            .map(Some)
+            .ok_or_else(|| "no JSON-RPC response in HTTP body".to_string())
+    }
+}
+
+impl McpClient {
+    pub async fn connect(config: &ExternalMcpServerConfig) -> Result<Self, String> {
+        let transport = if config.url.as_deref().is_some_and(|u| !u.trim().is_empty()) {
+            Transport::Http(HttpTransport::new(config))
+        } else {
+            Transport::Stdio(Box::new(StdioTransport::spawn(config)?))
+        };
+        let client = Self {
+            server_name: config.name.trim().to_string(),
+            transport: Mutex::new(transport),
+            next_id: AtomicU64::new(1),
+            broken: AtomicBool::new(false),
+        };
+
+        let initialized = tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            client.request(
+                "initialize",
+                json!({
+                    "protocolVersion": LATEST_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "meld", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            ),
+        )
+        .await
+        .map_err(|_| "initialize timed out".to_string())??;
+
+        if let Transport::Http(http) = &mut *client.transport.lock().await {
+            http.protocol_version = initialized
+                .get("protocolVersion")
+                .and_then(|v| v.as_str())
+                .map(ToOwned::to_owned);
+        }
+        client
+            .notify("notifications/initialized", json!({}))
+            .await?;
+        Ok(client)
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// Whether a request failed to reach the server or timed out, so the connection
+    /// should be replaced. Errors the server itself reports leave it usable.
+    pub fn is_broken(&self) -> bool {
+        self.broken.load(Ordering::Relaxed)
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let response = match &mut *self.transport.lock().await {
+            Transport::Stdio(stdio) => stdio.request(&message, id).await,
+            Transport::Http(http) => http
+                .post(&message, Some(id))
+                .await
+                .and_then(|response| response.ok_or_else(|| "empty HTTP response".to_string())),
+        };
+        let response = response.inspect_err(|_| self.broken.store(true, Ordering::Relaxed))?;
+        into_result(response)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        match &mut *self.transport.lock().await {
+            Transport::Stdio(stdio) => stdio.send(&message).await,
+            Transport::Http(http) => http.post(&message, None).await.map(|_| ()),
+        }
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<RemoteTool>, String> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page = tokio::time::timeout(CONNECT_TIMEOUT, self.request("tools/list", params))
+                .await
+                .map_err(|_| "tools/list timed out".to_string())??;
+            if let Some(items) = page.get("tools").and_then(|v| v.as_array()) {
+                tools.extend(items.iter().filter_map(remote_tool_from_value));
+            }
+            cursor = page
+                .get("nextCursor")
+                .and_then(|v| v.as_str())
+                .map(ToOwned::to_owned);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, String> {
+        tokio::time::timeout(
+            CALL_TIMEOUT,
+            self.request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            ),
+        )
+        .await
+        .map_err(|_| {
+            self.broken.store(true, Ordering::Relaxed);
+            format!("tools/call {name} timed out")
+        })?
+    }
+}
+
+fn remote_tool_from_value(value: &Value) -> Option<RemoteTool> {
+    let name = value.get("name")?.as_str()?.to_string();
+    Some(RemoteTool {
+        description: value
+            .get("description")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string(),
+        input_schema: value
+            .get("inputSchema")
+            .cloned()
+            .unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+        // MCP defaults readOnlyHint to false, so unannotated tools are treated as writes.
+        read_only: value
+            .pointer("/annotations/readOnlyHint")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        name,
+    })
+}
+
+/// `server__tool`, restricted to the `[a-zA-Z0-9_-]{1,64}` names LLM providers accept.
+pub fn namespaced_tool_name(server: &str, tool: &str) -> String {
+    let sanitized: String = format!("{server}__{tool}")
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    sanitized.chars().take(MAX_TOOL_NAME_LEN).collect()
+}
+
+fn truncate_chars(input: &str, max_chars: usize) -> String {
+    if input.chars().count() <= max_chars {
+        return input.to_string();
+    }
+    let head: String = input.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{head}...")
+}
+
+/// Wrap a `tools/call` result into the registry envelope.
+fn remote_result_envelope(
+    tool: &str,
+    server: &str,
+    remote_name: &str,
+    result: &Value,
+    started: Instant,
+    trace_id: String,
+) -> Value {
+    let text = result
+        .get("content")
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| match item.get("type").and_then(|v| v.as_str()) {
+                    Some("text") => item
+                        .get("text")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string),
+                    Some(other) => Some(format!("[{other} content]")),
+                    None => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    let proof = json!({ "server": server, "remote_tool": remote_name });
+
+    if result.get("isError").and_then(|v| v.as_bool()) == Some(true) {
+        return error_envelope(
+            tool,
+            "mcp.remote",
+            None,
+            proof,
+            "remote_tool_error",
+            if text.is_empty() {
+                format!("{server} reported an error for {remote_name}")
+            } else {
+                text
+            },
+            false,
+            started,
+            trace_id,
+        );
+    }
+
+    let mut payload = json!({
+        "summary": if text.is_empty() {
+            format!("{server}/{remote_name} completed")
+        } else {
+            truncate_chars(&text, SUMMARY_MAX_CHARS)
+        },
+        "content": text,
+    });
+    if let Some(structured) = result.get("structuredContent") {
+        payload["structured"] = structured.clone();
+    }
+    envelope(
+        tool,
+        "mcp.remote",
+        true,
+        None,
+        payload,
+        proof,
+        None,
+        started,
+        trace_id,
+    )
+}
+
+pub struct ExternalMcpTool {
+    definition: ToolDefinition,
+    remote_name: String,
+    client: Arc<McpClient>,
+}
+
+impl ExternalMcpTool {
+    pub fn new(client: Arc<McpClient>, remote: RemoteTool) -> Self {
+        let server = client.server_name().to_string();
+        Self {
+            definition: ToolDefinition {
+                name: namespaced_tool_name(&server, &remote.name),
+                description: format!("[{server}] {}", remote.description.trim()),
+                input_schema: remote.input_schema,
+                permission: if remote.read_only {
+                    Permission::Read
+                } else {
+                    Permission::Write
+                },
+            },
+            remote_name: remote.name,
+            client,
+        }
+    }
+}
+
+impl ToolExecutor for ExternalMcpTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn verifies_writes(&self) -> bool {
+        false
+    }
+
+    fn execute<'a>(&'a self, args: Value, _ctx: &'a ToolContext<'a>) -> super::ToolFuture<'a> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let trace_id = uuid::Uuid::new_v4().to_string();
+            let server = self.client.server_name();
+            match self.client.call_tool(&self.remote_name, args).await {
+                Ok(result) => remote_result_envelope(
+                    &self.definition.name,
+                    server,
+                    &self.remote_name,
+                    &result,
+                    started,
+                    trace_id,
+                ),
+                Err(error) => error_envelope(
+                    &self.definition.name,
+                    "mcp.remote",
+                    None,
+                    json!({ "server": server, "remote_tool": self.remote_name }),
+                    "remote_call_failed",
+                    error,
+                    true,
+                    started,
+                    trace_id,
+                ),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::time::Instant;
+
+    use super::{
+        namespaced_tool_name, parse_sse_messages, remote_result_envelope, remote_tool_from_value,
+    };
+
+    #[test]
+    fn namespaced_tool_name_sanitizes_and_caps_length() {
+        assert_eq!(
+            namespaced_tool_name("tracker", "create.issue"),
+            "tracker__create_issue"
+        );
+        assert_eq!(namespaced_tool_name("s", &"x".repeat(100)).len(), 64);
+    }
+
+    #[test]
+    fn remote_tool_permission_follows_read_only_hint() {
+        let read = remote_tool_from_value(&json!({
+            "name": "list_events",
+            "annotations": { "readOnlyHint": true }
+        }))
+        .expect("tool");
+        assert!(read.read_only);
+
+        let unannotated = remote_tool_from_value(&json!({ "name": "create_event" })).expect("tool");
+        assert!(!unannotated.read_only);
+        assert_eq!(unannotated.input_schema["type"], "object");
+    }
+
+    #[test]
+    fn sse_body_yields_json_messages() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}\n\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n";
+        let messages = parse_sse_messages(body);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["id"], 1);
+    }
+
+    #[test]
+    fn remote_result_maps_is_error_to_failed_envelope() {
+        let ok = remote_result_envelope(
+            "tracker__get_issue",
+            "tracker",
+            "get_issue",
+            &json!({ "content": [{ "type": "text", "text": "Issue 42: open" }] }),
+            Instant::now(),
+            "trace".to_string(),
+        );
+        assert_eq!(ok["ok"], true);
+        assert_eq!(
+            ok.pointer("/result/content"),
+            Some(&json!("Issue 42: open"))
+        );
+
+        let failed = remote_result_envelope(
+            "tracker__get_issue",
+            "tracker",
+            "get_issue",
+            &json!({ "isError": true, "content": [{ "type": "text", "text": "not found" }] }),
+            Instant::now(),
+            "trace".to_string(),
+        );
+        assert_eq!(failed["ok"], false);
+        assert_eq!(
+            failed.pointer("/error/code"),
+            Some(&json!("remote_tool_error"))
+        );
+    }
+}
@@ -1,14 +1,22 @@
+use crate::adapters::config::ExternalMcpServerConfig;
 use crate::adapters::llm::{
     FunctionDefinition as LlmFunctionDefinition, ToolDefinition as LlmToolDefinition,
 };
+use crate::adapters::markdown::patch::{apply_patch, PatchOperation};
 use crate::core::ports::tools::{ToolExecutionContext, ToolPort};
+use notes::NoteStore;
 use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::future::Future;
 use std::path::Path;
 use std::pin::Pin;
+use std::sync::{Arc, LazyLock};
 use std::time::Instant;
 
+pub mod client;
+mod notes;
+pub mod server;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Permission {
     Read,
@@ -24,7 +32,10 @@ pub struct ToolDefinition {
 }
 
 pub struct ToolContext<'a> {
+    /// Where notes are read and written: the vault, or a conversation branch's worktree.
     pub vault_path: &'a Path,
+    /// The vault itself. Its ignore and privacy rules apply wherever `vault_path` points.
+    pub vault_root: &'a Path,
     pub db_path: &'a Path,
     pub embedding_key: &'a str,
     pub embedding_model_id: &'a str,
@@ -32,15 +43,82 @@ pub struct ToolContext<'a> {
     pub search_provider: &'a str,
     pub searxng_base_url: &'a str,
     pub brave_api_key: &'a str,
+    /// Set for dry runs: note writes are staged under this run id instead of applied.
+    pub staged_run_id: Option<&'a str>,
+    /// Set for agent runs, which commit all of their writes together when they end.
+    pub defer_commits: bool,
+}
+
+impl ToolContext<'_> {
+    /// Whether the tools work on a conversation branch's worktree instead of the vault.
+    pub fn in_branch(&self) -> bool {
+        self.vault_path != self.vault_root
+    }
 }
 
 pub type McpContext<'a> = ToolContext<'a>;
 
+/// Tools that read the vault history. A conversation branch's worktree has no history of
+/// its own, so they refuse to run on one.
+/// A connected external MCP server and the tools it listed.
+struct MountedServer {
+    config: ExternalMcpServerConfig,
+    client: Arc<client::McpClient>,
+    tools: Vec<client::RemoteTool>,
+}
+
+impl MountedServer {
+    async fn connect(config: &ExternalMcpServerConfig) -> Option<Self> {
+        let client = match client::McpClient::connect(config).await {
+            Ok(client) => Arc::new(client),
+            Err(error) => {
+                log::warn!("MCP server '{}' unavailable: {error}", config.name);
+                return None;
+            }
+        };
+        let tools = match client.list_tools().await {
+            Ok(tools) => tools,
+            Err(error) => {
+                log::warn!("MCP server '{}' tools/list failed: {error}", config.name);
+                return None;
+            }
+        };
+        Some(Self {
+            config: config.clone(),
+            client,
+            tools,
+        })
+    }
+}
+
+/// External servers kept connected across runs, so stdio servers are not respawned for
+/// every message.
+static MOUNTED_SERVERS: LazyLock<tokio::sync::Mutex<Vec<MountedServer>>> =
+    LazyLock::new(|| tokio::sync::Mutex::new(Vec::new()));
+
+const HISTORY_TOOLS: [&str; 4] = ["kb_history", "kb_diff", "kb_revert", "kb_blame"];
+
+/// Whether `name` called with `args` needs the vault history: a history tool, or
+/// `kb_read` at a `revision`.
+fn reads_history(name: &str, args: &Value) -> bool {
+    HISTORY_TOOLS.contains(&name)
+        || (name == "kb_read"
+            && args
+                .get("revision")
+                .and_then(|v| v.as_str())
+                .is_some_and(|revision| !revision.trim().is_empty()))
+}
+
 type ToolFuture<'a> = Pin<Box<dyn Future<Output = Value> + Send + 'a>>;
 
 pub trait ToolExecutor: Send + Sync {
     fn definition(&self) -> &ToolDefinition;
     fn execute<'a>(&'a self, args: Value, ctx: &'a ToolContext<'a>) -> ToolFuture<'a>;
+
+    /// Whether write results carry a `readback_ok` proof. Remote tools cannot.
+    fn verifies_writes(&self) -> bool {
+        true
+    }
 }
 
 pub struct ToolRegistry {
@@ -58,13 +136,25 @@ impl ToolRegistry {
         registry.register(KbReadTool::new());
         registry.register(KbCreateTool::new());
         registry.register(KbUpdateTool::new());
+        registry.register(KbPatchTool::new());
         registry.register(KbListTool::new());
         registry.register(KbHistoryTool::new());
         registry.register(KbDiffTool::new());
+        registry.register(KbRevertTool::new());
+        registry.register(KbBlameTool::new());
+        registry.register(KbLinksTool::new());
         registry.register(WebSearchTool::new(has_web_search));
         registry
     }
 
+    pub fn definitions(&self) -> Vec<&ToolDefinition> {
+        self.order
+            .iter()
+            .filter_map(|name| self.tools.get(name))
+            .map(|tool| tool.definition())
+            .collect()
+    }
+
     pub fn tool_definitions_for_llm(&self) -> Vec<LlmToolDefinition> {
         self.order
             .iter()
@@ -110,10 +200,73 @@ impl ToolRegistry {
             );
         };
 
+        if ctx.in_branch() && reads_history(name, &args) {
+            return error_envelope(
+                name,
+                "mcp.branch_mode",
+                None,
+                json!({}),
+                "branch_mode_unsupported",
+                format!(
+                    "{name} cannot read the vault history in branch mode; the conversation branch has no history of its own"
+                ),
+                false,
+                Instant::now(),
+                uuid::Uuid::new_v4().to_string(),
+            );
+        }
+
         let result = tool.execute(args, ctx).await;
+        if !tool.verifies_writes() {
+            return result;
+        }
         enforce_registry_write_verification(tool.definition(), result)
     }
 
+    /// What a built-in write would leave on disk, computed without writing.
+    pub fn preview_write(&self, name: &str, args: &Value, ctx: &ToolContext<'_>) -> Value {
+        match name {
+            "kb_create" | "kb_update" | "kb_patch" => preview_note_write(ctx, name, args),
+            "kb_revert" => preview_revert(ctx, args),
+            _ => json!({}),
+        }
+    }
+
+    pub fn is_write_tool(&self, name: &str) -> bool {
+        self.tools
+            .get(name)
+            .is_some_and(|tool| tool.definition().permission == Permission::Write)
+    }
+
+    /// Register the tools of each configured external MCP server as `server__tool`.
+    /// Servers stay connected between runs and are reconnected only when their config
+    /// changes or a call to them failed. Servers that fail to start or list tools are
+    /// skipped.
+    pub async fn mount_external_servers(&mut self, servers: &[ExternalMcpServerConfig]) {
+        let mut mounted = MOUNTED_SERVERS.lock().await;
+        // Dropping a server's last client stops its process.
+        mounted.retain(|server| !server.client.is_broken() && servers.contains(&server.config));
+        for config in servers {
+            if !mounted.iter().any(|server| &server.config == config) {
+                if let Some(server) = MountedServer::connect(config).await {
+                    mounted.push(server);
+                }
+            }
+            let Some(server) = mounted.iter().find(|server| &server.config == config) else {
+                continue;
+            };
+            for remote in server.tools.iter().cloned() {
+                let tool = client::ExternalMcpTool::new(Arc::clone(&server.client), remote);
+                let name = tool.definition().name.clone();
+                if self.tools.contains_key(&name) {
+                    log::warn!("Skipping duplicate MCP tool name: {name}");
+                    continue;
+                }
+                self.register(tool);
+            }
+        }
+    }
+
     fn register<T>(&mut self, executor: T)
     where
         T: ToolExecutor + 'static,
@@ -133,6 +286,54 @@ impl ToolPort for ToolRegistry {
         self.prompt_tool_lines()
     }
 
+    fn is_write_tool(&self, name: &str) -> bool {
+        self.is_write_tool(name)
+    }
+
+    fn available_in_branch(&self, name: &str) -> bool {
+        !HISTORY_TOOLS.contains(&name)
+    }
+
+    fn verifies_writes(&self, name: &str) -> bool {
+        self.tools
+            .get(name)
+            .is_none_or(|tool| tool.verifies_writes())
+    }
+
+    fn write_paths(&self, name: &str, args: &Value) -> Vec<String> {
+        match name {
+            "kb_create" | "kb_update" | "kb_patch" => {
+                normalize_path_arg(args, "path").into_iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn available_with_deferred_commits(&self, name: &str) -> bool {
+        name != "kb_revert"
+    }
+
+    fn stages_dry_run_writes(&self, name: &str) -> bool {
+        matches!(name, "kb_create" | "kb_update" | "kb_patch")
+    }
+
+    fn preview_write(&self, name: &str, args: &Value, ctx: &ToolExecutionContext<'_>) -> Value {
+        let port_ctx = ToolContext {
+            vault_path: ctx.vault_path,
+            vault_root: ctx.vault_root,
+            db_path: ctx.db_path,
+            embedding_key: ctx.embedding_key,
+            embedding_model_id: ctx.embedding_model_id,
+            tavily_api_key: ctx.tavily_api_key,
+            search_provider: ctx.search_provider,
+            searxng_base_url: ctx.searxng_base_url,
+            brave_api_key: ctx.brave_api_key,
+            staged_run_id: ctx.staged_run_id,
+            defer_commits: ctx.defer_commits,
+        };
+        ToolRegistry::preview_write(self, name, args, &port_ctx)
+    }
+
     fn execute<'a>(
         &'a self,
         name: &'a str,
@@ -142,6 +343,7 @@ impl ToolPort for ToolRegistry {
         Box::pin(async move {
             let port_ctx = ToolContext {
                 vault_path: ctx.vault_path,
+                vault_root: ctx.vault_root,
                 db_path: ctx.db_path,
                 embedding_key: ctx.embedding_key,
                 embedding_model_id: ctx.embedding_model_id,
@@ -149,6 +351,8 @@ impl ToolPort for ToolRegistry {
                 search_provider: ctx.search_provider,
                 searxng_base_url: ctx.searxng_base_url,
                 brave_api_key: ctx.brave_api_key,
+                staged_run_id: ctx.staged_run_id,
+                defer_commits: ctx.defer_commits,
             };
             ToolRegistry::execute(self, name, args, &port_ctx).await
         })
@@ -209,7 +413,7 @@ impl KbSearchTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_search".to_string(),
-                description: "Use this when you need semantic retrieval from the vault and do not know the exact note path. Do not use when you already have a concrete path (use kb_read). Errors: invalid_arguments (missing query), search_failed (retrieval/index issue, retriable). Edge cases: if results are empty or weak, retry with a narrower query or use kb_list to discover file names."
+                description: "Use this when you need semantic retrieval from the vault and do not know the exact note path. Do not use when you already have a concrete path (use kb_read). Errors: invalid_arguments (missing query), search_failed (retrieval/index issue, retriable). Private notes are never returned. Edge cases: if results are empty or weak, retry with a narrower query or use kb_list to discover file names."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
@@ -248,12 +452,13 @@ impl KbReadTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_read".to_string(),
-                description: "Use this when you know the exact note path from kb_list, kb_search, or a [[wikilink]]. Do not use for broad discovery across many notes (use kb_search/kb_list). Errors: invalid_arguments (missing path), not_found (path absent), verify_failed (readback failed, retriable). Edge cases: input path is normalized inside the vault root."
+                description: "Use this when you know the exact note path from kb_list, kb_search, or a [[wikilink]]. Do not use for broad discovery across many notes (use kb_search/kb_list). Set revision to a commit id from kb_history to read the note as it was at that commit, e.g. to answer what it said last week. Errors: invalid_arguments (missing path), not_found (path absent, or absent at revision), forbidden (the note is private; do not try to reach it another way), verify_failed (readback failed, retriable). Edge cases: input path is normalized inside the vault root."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
-                        "path": { "type": "string", "description": "Relative path to .md" }
+                        "path": { "type": "string", "description": "Relative path to .md" },
+                        "revision": { "type": "string", "description": "Commit id to read the note at (default: current content)" }
                     },
                     "required": ["path"]
                 }),
@@ -286,7 +491,7 @@ impl KbCreateTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_create".to_string(),
-                description: "Use this to create a new note at a path that must not exist yet. Do not use for editing an existing note (use kb_update). Before creating, quickly check for similar notes via kb_search or kb_list when overlap is likely. Errors: invalid_arguments, file_exists (non-retriable; kb_read then kb_update), write_failed/verify_failed (retriable), verify_mismatch (non-retriable). Edge cases: path normalization preserves explicit roots like zettel/ and para/. Required arguments: path and content."
+                description: "Use this to create a new note at a path that must not exist yet. Do not use for editing an existing note (use kb_update). Before creating, quickly check for similar notes via kb_search or kb_list when overlap is likely. Errors: invalid_arguments, file_exists (non-retriable; kb_read then kb_update), forbidden (private folder or content marked private), write_failed/verify_failed (retriable), verify_mismatch (non-retriable). Edge cases: path normalization preserves explicit roots like zettel/ and para/. Required arguments: path and content."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
@@ -325,7 +530,7 @@ impl KbUpdateTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_update".to_string(),
-                description: "Use this to replace full content of an existing note when the path should already exist. Do not use for first-time writes (use kb_create). After updating, sanity-check that the edited note still matches nearby linked context. Errors: not_found (switch to kb_create), invalid_arguments, write_failed/verify_failed (retriable), verify_mismatch (non-retriable; kb_read and retry once). Edge case: returns noop=true and write_action=noop when content is unchanged. Required arguments: path and content."
+                description: "Use this to replace full content of an existing note when the path should already exist. Do not use for first-time writes (use kb_create). After updating, sanity-check that the edited note still matches nearby linked context. Errors: not_found (switch to kb_create), invalid_arguments, forbidden (private note), write_failed/verify_failed (retriable), verify_mismatch (non-retriable; kb_read and retry once). Edge case: returns noop=true and write_action=noop when content is unchanged. Required arguments: path and content."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
@@ -355,6 +560,64 @@ impl ToolExecutor for KbUpdateTool {
     }
 }
 
+struct KbPatchTool {
+    definition: ToolDefinition,
+}
+
+impl KbPatchTool {
+    fn new() -> Self {
+        Self {
+            definition: ToolDefinition {
+                name: "kb_patch".to_string(),
+                description: "Use this for targeted edits to an existing note instead of re-sending the full body with kb_update. Operations apply in order and all-or-nothing: replace_text (exact find/replace; set all=true for every occurrence), insert_under_heading (directly below the heading line), append_to_section (end of the section, including its subsections), replace_frontmatter (YAML without --- fences; empty removes it). Headings are paths like \"Project > Risks\"; the last segments are enough when unique. Errors: not_found (use kb_create), invalid_arguments, forbidden (private note), patch_failed (text or heading not found or ambiguous; kb_read and retry), write_failed/verify_failed (retriable), verify_mismatch. Required arguments: path and operations."
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Relative path to existing note" },
+                        "operations": {
+                            "type": "array",
+                            "minItems": 1,
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "op": {
+                                        "type": "string",
+                                        "enum": ["replace_text", "insert_under_heading", "append_to_section", "replace_frontmatter"]
+                                    },
+                                    "find": { "type": "string", "description": "replace_text: exact text to find" },
+                                    "replace": { "type": "string", "description": "replace_text: replacement text" },
+                                    "all": { "type": "boolean", "description": "replace_text: replace every occurrence" },
+                                    "heading": { "type": "string", "description": "Heading path, e.g. \"Project > Risks\"" },
+                                    "content": { "type": "string", "description": "Markdown to insert" },
+                                    "frontmatter": { "type": "string", "description": "replace_frontmatter: YAML body" }
+                                },
+                                "required": ["op"]
+                            }
+                        }
+                    },
+                    "required": ["path", "operations"]
+                }),
+                permission: Permission::Write,
+            },
+        }
+    }
+}
+
+impl ToolExecutor for KbPatchTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn execute<'a>(&'a self, args: Value, ctx: &'a ToolContext<'a>) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let trace_id = uuid::Uuid::new_v4().to_string();
+            execute_kb_patch(ctx, &args, &trace_id, started)
+        })
+    }
+}
+
 struct KbListTool {
     definition: ToolDefinition,
 }
@@ -364,7 +627,7 @@ impl KbListTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_list".to_string(),
-                description: "Use this to inspect vault structure, discover candidate paths, or recover when semantic retrieval is weak. Do not use for semantic Q&A over note content (use kb_search). Errors: list_failed (retriable). Edge cases: folder is optional; root listing is returned when omitted."
+                description: "Use this to inspect vault structure, discover candidate paths, or recover when semantic retrieval is weak. Do not use for semantic Q&A over note content (use kb_search). Errors: list_failed (retriable). Edge cases: folder is optional; root listing is returned when omitted. Private notes are never listed."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
@@ -401,13 +664,18 @@ impl KbHistoryTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_history".to_string(),
-                description: "Use this to inspect the change history of the vault or a specific note. Returns a list of commits with metadata (id, message, timestamp, files_changed). Errors: history_failed (retriable). Edge cases: if no git history exists, returns empty array."
+                description: "Use this to inspect the change history of the vault or a specific note. Returns a page of commits, newest first, with metadata (id, message, timestamp, files_changed, author, run_id) and next_cursor; pass next_cursor as after to get older commits. Filter by author (agent or user), since/until (RFC 3339 or YYYY-MM-DD) and message substring. Errors: invalid_arguments (bad author or date), forbidden (path is a private note), history_failed (retriable). Edge cases: if no git history exists, returns empty array."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
                         "path": { "type": "string", "description": "Optional file path to filter history for a specific note" },
-                        "limit": { "type": "integer", "description": "Max commits to return (default 20)" }
+                        "limit": { "type": "integer", "description": "Max commits to return (default 20)" },
+                        "after": { "type": "string", "description": "next_cursor from the previous page" },
+                        "author": { "type": "string", "enum": ["agent", "user"], "description": "Only agent commits or only hand edits" },
+                        "since": { "type": "string", "description": "Oldest commit time, RFC 3339 or YYYY-MM-DD" },
+                        "until": { "type": "string", "description": "Newest commit time, RFC 3339 or YYYY-MM-DD (whole day included)" },
+                        "message": { "type": "string", "description": "Case-insensitive substring of the commit message" }
                     }
                 }),
                 permission: Permission::Read,
@@ -439,7 +707,7 @@ impl KbDiffTool {
         Self {
             definition: ToolDefinition {
                 name: "kb_diff".to_string(),
-                description: "Use this to see the unified diff (patch) of a specific commit. Returns commit metadata plus a patch string. Use kb_history first to discover commit IDs. Errors: invalid_arguments (missing commit_id), diff_failed (retriable)."
+                description: "Use this to see the unified diff (patch) of a specific commit. Returns commit metadata plus a patch string. Use kb_history first to discover commit IDs. Errors: invalid_arguments (missing commit_id), diff_failed (retriable), forbidden (the commit changes a private note)."
                     .to_string(),
                 input_schema: json!({
                     "type": "object",
@@ -468,6 +736,125 @@ impl ToolExecutor for KbDiffTool {
     }
 }
 
+struct KbRevertTool {
+    definition: ToolDefinition,
+}
+
+impl KbRevertTool {
+    fn new() -> Self {
+        Self {
+            definition: ToolDefinition {
+                name: "kb_revert".to_string(),
+                description: "Use this to undo a commit from kb_history. The revert is merged line by line with later edits; lines that still conflict get conflict markers (conflict_style \"markers\", default) or stay as they are with the reverted lines written to a copy next to the note (\"copy\"). Returns per-file outcomes: reverted, conflict, conflict_copy, or kept (note deleted or re-created since). Errors: invalid_arguments (missing commit_id, unknown conflict_style), revert_failed (unknown commit), forbidden (the commit changes a private note), dry_run_unsupported, agent_run_unsupported (inside an agent run that commits its writes at the end), verify_mismatch (a reverted note does not match the revert commit)."
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "commit_id": { "type": "string", "description": "Git commit SHA to revert" },
+                        "conflict_style": { "type": "string", "enum": ["markers", "copy"], "description": "How to surface unmergeable lines (default markers)" }
+                    },
+                    "required": ["commit_id"]
+                }),
+                permission: Permission::Write,
+            },
+        }
+    }
+}
+
+impl ToolExecutor for KbRevertTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn execute<'a>(&'a self, args: Value, ctx: &'a ToolContext<'a>) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let trace_id = uuid::Uuid::new_v4().to_string();
+            execute_kb_revert(ctx, &args, &trace_id, started)
+        })
+    }
+}
+
+struct KbBlameTool {
+    definition: ToolDefinition,
+}
+
+impl KbBlameTool {
+    fn new() -> Self {
+        Self {
+            definition: ToolDefinition {
+                name: "kb_blame".to_string(),
+                description: "Use this to see who last wrote each line of a note: the agent (author meld, with run_id, provider and model when written by a run) or the user. Returns hunks of consecutive lines with the same commit plus agent_lines/user_lines totals. Use it before sharing a note to point out AI-generated parts. Errors: invalid_arguments (missing path), not_found (path absent), forbidden (private note), blame_failed (no history yet). Edge cases: lines edited by hand but not snapshotted yet count as user lines with no commit_id."
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Relative path to .md" }
+                    },
+                    "required": ["path"]
+                }),
+                permission: Permission::Read,
+            },
+        }
+    }
+}
+
+impl ToolExecutor for KbBlameTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn execute<'a>(&'a self, args: Value, ctx: &'a ToolContext<'a>) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let trace_id = uuid::Uuid::new_v4().to_string();
+            execute_kb_blame(ctx, &args, &trace_id, started)
+        })
+    }
+}
+
+struct KbLinksTool {
+    definition: ToolDefinition,
+}
+
+impl KbLinksTool {
+    fn new() -> Self {
+        Self {
+            definition: ToolDefinition {
+                name: "kb_links".to_string(),
+                description: "Use this to navigate the [[wikilink]] graph: outgoing links and backlinks of a note, orphan notes (no links in or out), or unresolved links (targets with no matching note). Links are resolved by path, file name, then frontmatter aliases. Do not use for content search (use kb_search). Errors: invalid_arguments (missing path for note/outgoing/backlinks, unknown kind), forbidden (path is a private note), links_failed (index unavailable, retriable). Edge cases: the graph is refreshed on reindex, so notes written in this run may not appear yet."
+                    .to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Note path. Required for note, outgoing and backlinks; filters unresolved by source" },
+                        "kind": {
+                            "type": "string",
+                            "enum": ["note", "outgoing", "backlinks", "orphans", "unresolved"],
+                            "description": "note (default) returns outgoing links and backlinks"
+                        }
+                    }
+                }),
+                permission: Permission::Read,
+            },
+        }
+    }
+}
+
+impl ToolExecutor for KbLinksTool {
+    fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    fn execute<'a>(&'a self, args: Value, ctx: &'a ToolContext<'a>) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let trace_id = uuid::Uuid::new_v4().to_string();
+            execute_kb_links(ctx, &args, &trace_id, started)
+        })
+    }
+}
+
 struct WebSearchTool {
     definition: ToolDefinition,
 }
@@ -655,6 +1042,46 @@ fn envelope(
     Value::Object(payload)
 }
 
+/// Error code and retriability for a failed note access: `forbidden`, which is never
+/// retriable, when the vault refused a private note, otherwise `code` as given.
+fn note_error(
+    error: &(dyn std::error::Error + 'static),
+    code: &'static str,
+    retriable: bool,
+) -> (&'static str, bool) {
+    if crate::adapters::vault::is_forbidden(error) {
+        ("forbidden", false)
+    } else {
+        (code, retriable)
+    }
+}
+
+/// Whether note `path`, as changed by `commit_id`, is hidden from the agent: it is
+/// private now, or its content at that commit or at the commit's parent was.
+fn private_in_commit(
+    ctx: &McpContext<'_>,
+    privacy: &crate::adapters::vault::PrivacyRules,
+    commit_id: &str,
+    path: &str,
+) -> bool {
+    privacy.is_private_in(ctx.vault_path, path)
+        || [commit_id.to_string(), format!("{commit_id}^")]
+            .iter()
+            .any(|revision| {
+                crate::adapters::git::read_file_at(ctx.vault_path, path, revision)
+                    .is_ok_and(|(_, content)| privacy.marks_private(&content))
+            })
+}
+
+/// The first of `paths` changed by `commit_id` that is hidden from the agent.
+fn private_note<'p>(ctx: &McpContext<'_>, commit_id: &str, paths: &'p [String]) -> Option<&'p str> {
+    let privacy = crate::adapters::vault::PrivacyRules::load(ctx.vault_root);
+    paths
+        .iter()
+        .map(String::as_str)
+        .find(|path| private_in_commit(ctx, &privacy, commit_id, path))
+}
+
 #[allow(clippy::too_many_arguments)]
 fn error_envelope(
     tool: &str,
@@ -686,11 +1113,15 @@ fn error_envelope(
     )
 }
 
-fn build_write_result(summary: String, write_action: &str, noop: bool) -> Value {
+fn build_write_result(summary: String, write_action: &str, noop: bool, staged: bool) -> Value {
     let mut result = Map::new();
     result.insert("summary".to_string(), json!(summary));
     result.insert("write_action".to_string(), json!(write_action));
     result.insert("noop".to_string(), json!(noop));
+    if staged {
+        // Dry run: the change is in the run's staged changeset, not the vault.
+        result.insert("staged".to_string(), json!(true));
+    }
     Value::Object(result)
 }
 
@@ -712,41 +1143,57 @@ fn execute_kb_read(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started:
         }
     };
     let resolved_path = requested_path.clone();
+    if let Some(revision) = args
+        .get("revision")
+        .and_then(|v| v.as_str())
+        .filter(|revision| !revision.trim().is_empty())
+    {
+        return read_note_at_revision(
+            ctx,
+            &requested_path,
+            &resolved_path,
+            revision.trim(),
+            trace_id,
+            started,
+        );
+    }
+    let store = NoteStore::for_context(ctx);
 
-    let content = match crate::adapters::vault::read_note(ctx.vault_path, &resolved_path) {
+    let content = match store.read(&resolved_path) {
         Ok(content) => content,
         Err(error) => {
+            let (code, retriable) = note_error(&*error, "not_found", false);
             return error_envelope(
                 "kb_read",
                 "kb.read",
                 Some(target_payload(&requested_path, &resolved_path)),
                 json!({}),
-                "not_found",
+                code,
                 error.to_string(),
-                false,
+                retriable,
                 started,
                 trace_id.to_string(),
-            )
+            );
         }
     };
 
-    let verification =
-        match crate::adapters::vault::read_note_verification(ctx.vault_path, &resolved_path) {
-            Ok(verification) => verification,
-            Err(error) => {
-                return error_envelope(
-                    "kb_read",
-                    "kb.read",
-                    Some(target_payload(&requested_path, &resolved_path)),
-                    json!({}),
-                    "verify_failed",
-                    error.to_string(),
-                    true,
-                    started,
-                    trace_id.to_string(),
-                )
-            }
-        };
+    let verification = match store.verification(&resolved_path) {
+        Ok(verification) => verification,
+        Err(error) => {
+            let (code, retriable) = note_error(&*error, "verify_failed", true);
+            return error_envelope(
+                "kb_read",
+                "kb.read",
+                Some(target_payload(&requested_path, &resolved_path)),
+                json!({}),
+                code,
+                error.to_string(),
+                retriable,
+                started,
+                trace_id.to_string(),
+            );
+        }
+    };
 
     envelope(
         "kb_read",
@@ -769,30 +1216,99 @@ fn execute_kb_read(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started:
     )
 }
 
-fn execute_kb_create(
+/// `kb_read` with a `revision`: the note as committed at that point in history. A note
+/// that is private now, or was private at that revision, is refused.
+fn read_note_at_revision(
     ctx: &McpContext<'_>,
-    args: &Value,
+    requested_path: &str,
+    resolved_path: &str,
+    revision: &str,
     trace_id: &str,
     started: Instant,
 ) -> Value {
-    let requested_path = match normalize_path_arg(args, "path") {
-        Ok(path) => path,
-        Err(error) => {
-            return error_envelope(
-                "kb_create",
-                "kb.create",
-                None,
-                json!({}),
-                "invalid_arguments",
-                error,
-                false,
-                started,
-                trace_id.to_string(),
-            )
-        }
-    };
-    let resolved_path = requested_path.clone();
-    let target = target_payload(&requested_path, &resolved_path);
+    let (commit_id, content) =
+        match crate::adapters::git::read_file_at(ctx.vault_path, resolved_path, revision) {
+            Ok(found) => found,
+            Err(error) => {
+                return error_envelope(
+                    "kb_read",
+                    "kb.read",
+                    Some(target_payload(requested_path, resolved_path)),
+                    json!({}),
+                    "not_found",
+                    error.to_string(),
+                    false,
+                    started,
+                    trace_id.to_string(),
+                )
+            }
+        };
+    if let Err(error) = crate::adapters::vault::ensure_agent_access(
+        ctx.vault_root,
+        ctx.vault_path,
+        resolved_path,
+        Some(&content),
+    ) {
+        return error_envelope(
+            "kb_read",
+            "kb.read",
+            Some(target_payload(requested_path, resolved_path)),
+            json!({}),
+            "forbidden",
+            error.to_string(),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+
+    envelope(
+        "kb_read",
+        "kb.read",
+        true,
+        Some(target_payload(requested_path, resolved_path)),
+        json!({
+            "summary": format!("Read note {resolved_path} at {}", &commit_id[..7]),
+            "content": content,
+            "revision": commit_id,
+        }),
+        json!({
+            "exists": true,
+            "bytes": content.len(),
+            "hash_after": crate::adapters::vault::file_hash(&content),
+            "readback_ok": true,
+        }),
+        None,
+        started,
+        trace_id.to_string(),
+    )
+}
+
+fn execute_kb_create(
+    ctx: &McpContext<'_>,
+    args: &Value,
+    trace_id: &str,
+    started: Instant,
+) -> Value {
+    let requested_path = match normalize_path_arg(args, "path") {
+        Ok(path) => path,
+        Err(error) => {
+            return error_envelope(
+                "kb_create",
+                "kb.create",
+                None,
+                json!({}),
+                "invalid_arguments",
+                error,
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+    let resolved_path = requested_path.clone();
+    let store = NoteStore::for_context(ctx);
+    let target = target_payload(&requested_path, &resolved_path);
 
     let content = match args.get("content").and_then(|value| value.as_str()) {
         Some(content) => content,
@@ -811,23 +1327,23 @@ fn execute_kb_create(
         }
     };
 
-    let before =
-        match crate::adapters::vault::read_note_verification(ctx.vault_path, &resolved_path) {
-            Ok(value) => value,
-            Err(error) => {
-                return error_envelope(
-                    "kb_create",
-                    "kb.create",
-                    Some(target),
-                    json!({}),
-                    "verify_failed",
-                    error.to_string(),
-                    true,
-                    started,
-                    trace_id.to_string(),
-                )
-            }
-        };
+    let before = match store.verification(&resolved_path) {
+        Ok(value) => value,
+        Err(error) => {
+            let (code, retriable) = note_error(&*error, "verify_failed", true);
+            return error_envelope(
+                "kb_create",
+                "kb.create",
+                Some(target),
+                json!({}),
+                code,
+                error.to_string(),
+                retriable,
+                started,
+                trace_id.to_string(),
+            );
+        }
+    };
 
     if before.exists {
         return error_envelope(
@@ -850,8 +1366,8 @@ fn execute_kb_create(
         );
     }
 
-    if let Err(error) = crate::adapters::vault::write_note(ctx.vault_path, &resolved_path, content)
-    {
+    if let Err(error) = store.write(&resolved_path, content) {
+        let (code, retriable) = note_error(&*error, "write_failed", true);
         return error_envelope(
             "kb_create",
             "kb.create",
@@ -860,25 +1376,21 @@ fn execute_kb_create(
                 "exists": false,
                 "bytes": 0,
             }),
-            "write_failed",
+            code,
             error.to_string(),
-            true,
+            retriable,
             started,
             trace_id.to_string(),
         );
     }
 
-    maybe_corrupt_after_write(ctx.vault_path, &resolved_path);
+    if !store.is_staged() {
+        maybe_corrupt_after_write(ctx.vault_path, &resolved_path);
+    }
 
-    let target_file = [ctx.vault_path.join(&resolved_path)];
-    let _ = crate::adapters::git::auto_commit_files(
-        ctx.vault_path,
-        &target_file,
-        &format!("meld: created {resolved_path}"),
-    );
+    store.commit(&resolved_path, &format!("meld: created {resolved_path}"));
 
-    let after = match crate::adapters::vault::read_note_verification(ctx.vault_path, &resolved_path)
-    {
+    let after = match store.verification(&resolved_path) {
         Ok(value) => value,
         Err(error) => {
             return error_envelope(
@@ -898,8 +1410,7 @@ fn execute_kb_create(
         }
     };
 
-    let after_content =
-        crate::adapters::vault::read_note(ctx.vault_path, &resolved_path).unwrap_or_default();
+    let after_content = store.read(&resolved_path).unwrap_or_default();
     let readback_ok = after.exists && after_content == content;
     let proof = json!({
         "exists": after.exists,
@@ -929,7 +1440,12 @@ fn execute_kb_create(
         "kb.create",
         true,
         Some(target),
-        build_write_result(format!("Created note {resolved_path}"), "create", false),
+        build_write_result(
+            format!("Created note {resolved_path}"),
+            "create",
+            false,
+            store.is_staged(),
+        ),
         proof,
         None,
         started,
@@ -979,29 +1495,228 @@ fn execute_kb_update(
         }
     };
 
-    let before =
-        match crate::adapters::vault::read_note_verification(ctx.vault_path, &resolved_path) {
-            Ok(value) => value,
-            Err(error) => {
-                return error_envelope(
-                    "kb_update",
-                    "kb.update",
-                    Some(target),
-                    json!({}),
-                    "verify_failed",
-                    error.to_string(),
-                    true,
-                    started,
-                    trace_id.to_string(),
-                )
-            }
-        };
+    let (before, before_content) = match load_existing_note(
+        ctx,
+        "kb_update",
+        "kb.update",
+        &target,
+        &resolved_path,
+        trace_id,
+        started,
+    ) {
+        Ok(loaded) => loaded,
+        Err(error) => return error,
+    };
+
+    write_existing_note(
+        ctx,
+        "kb_update",
+        "kb.update",
+        target,
+        &resolved_path,
+        &before,
+        &before_content,
+        content,
+        format!("Updated note {resolved_path}"),
+        trace_id,
+        started,
+    )
+}
+
+fn execute_kb_patch(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started: Instant) -> Value {
+    let requested_path = match normalize_path_arg(args, "path") {
+        Ok(path) => path,
+        Err(error) => {
+            return error_envelope(
+                "kb_patch",
+                "kb.patch",
+                None,
+                json!({}),
+                "invalid_arguments",
+                error,
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+    let resolved_path = requested_path.clone();
+    let target = target_payload(&requested_path, &resolved_path);
+
+    let operations = match args
+        .get("operations")
+        .cloned()
+        .map(serde_json::from_value::<Vec<PatchOperation>>)
+    {
+        Some(Ok(operations)) if !operations.is_empty() => operations,
+        Some(Err(error)) => {
+            return error_envelope(
+                "kb_patch",
+                "kb.patch",
+                Some(target),
+                json!({}),
+                "invalid_arguments",
+                format!("Invalid operations: {error}"),
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+        _ => {
+            return error_envelope(
+                "kb_patch",
+                "kb.patch",
+                Some(target),
+                json!({}),
+                "invalid_arguments",
+                "Missing operations",
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+
+    let (before, before_content) = match load_existing_note(
+        ctx,
+        "kb_patch",
+        "kb.patch",
+        &target,
+        &resolved_path,
+        trace_id,
+        started,
+    ) {
+        Ok(loaded) => loaded,
+        Err(error) => return error,
+    };
+
+    let content = match apply_patch(&before_content, &operations) {
+        Ok(content) => content,
+        Err(error) => {
+            return error_envelope(
+                "kb_patch",
+                "kb.patch",
+                Some(target),
+                json!({
+                    "exists": true,
+                    "bytes": before.bytes,
+                    "hash_before": before.hash,
+                }),
+                "patch_failed",
+                format!("{error}. kb_read the note and retry with its current text."),
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+
+    let summary = format!(
+        "Patched note {resolved_path} ({} operation{})",
+        operations.len(),
+        if operations.len() == 1 { "" } else { "s" }
+    );
+    write_existing_note(
+        ctx,
+        "kb_patch",
+        "kb.patch",
+        target,
+        &resolved_path,
+        &before,
+        &before_content,
+        &content,
+        summary,
+        trace_id,
+        started,
+    )
+}
+
+/// Proposed content and diff for a note write. Errors the write itself would report
+/// (missing arguments, failed patches, private notes) are returned as `error` instead of
+/// a diff.
+fn preview_note_write(ctx: &McpContext<'_>, tool: &str, args: &Value) -> Value {
+    let path = match normalize_path_arg(args, "path") {
+        Ok(path) => path,
+        Err(error) => return json!({ "error": error }),
+    };
+    let before = match NoteStore::for_context(ctx).read(&path) {
+        Ok(content) => Some(content),
+        Err(error) if crate::adapters::vault::is_forbidden(&*error) => {
+            return json!({ "path": path, "error": error.to_string() })
+        }
+        Err(_) => None,
+    };
+    let after = match tool {
+        "kb_patch" => args
+            .get("operations")
+            .cloned()
+            .ok_or_else(|| "Missing operations".to_string())
+            .and_then(|operations| {
+                serde_json::from_value::<Vec<PatchOperation>>(operations)
+                    .map_err(|error| format!("Invalid operations: {error}"))
+            })
+            .and_then(|operations| apply_patch(before.as_deref().unwrap_or_default(), &operations)),
+        _ => args
+            .get("content")
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| "Missing content".to_string()),
+    };
+    let after = match after {
+        Ok(after) => after,
+        Err(error) => {
+            return json!({
+                "path": path,
+                "exists": before.is_some(),
+                "error": error,
+            })
+        }
+    };
+    let diff =
+        crate::adapters::git::text_diff(&path, before.as_deref().unwrap_or_default(), &after)
+            .unwrap_or_default();
+    json!({
+        "path": path,
+        "exists": before.is_some(),
+        "content": after,
+        "diff": diff,
+    })
+}
+
+/// Read an existing note's verification and content, or the error envelope to return.
+fn load_existing_note(
+    ctx: &McpContext<'_>,
+    tool: &str,
+    action: &str,
+    target: &Value,
+    resolved_path: &str,
+    trace_id: &str,
+    started: Instant,
+) -> Result<(crate::adapters::vault::NoteVerification, String), Value> {
+    let store = NoteStore::for_context(ctx);
+    let before = match store.verification(resolved_path) {
+        Ok(value) => value,
+        Err(error) => {
+            let (code, retriable) = note_error(&*error, "verify_failed", true);
+            return Err(error_envelope(
+                tool,
+                action,
+                Some(target.clone()),
+                json!({}),
+                code,
+                error.to_string(),
+                retriable,
+                started,
+                trace_id.to_string(),
+            ));
+        }
+    };
 
     if !before.exists {
-        return error_envelope(
-            "kb_update",
-            "kb.update",
-            Some(target),
+        return Err(error_envelope(
+            tool,
+            action,
+            Some(target.clone()),
             json!({
                 "exists": false,
             }),
@@ -1010,22 +1725,41 @@ fn execute_kb_update(
             false,
             started,
             trace_id.to_string(),
-        );
+        ));
     }
 
-    let before_content =
-        crate::adapters::vault::read_note(ctx.vault_path, &resolved_path).unwrap_or_default();
+    let before_content = store.read(resolved_path).unwrap_or_default();
+    Ok((before, before_content))
+}
+
+/// Snapshot, write, commit and read back new content for an existing note.
+#[allow(clippy::too_many_arguments)]
+fn write_existing_note(
+    ctx: &McpContext<'_>,
+    tool: &str,
+    action: &str,
+    target: Value,
+    resolved_path: &str,
+    before: &crate::adapters::vault::NoteVerification,
+    before_content: &str,
+    content: &str,
+    summary: String,
+    trace_id: &str,
+    started: Instant,
+) -> Value {
+    let store = NoteStore::for_context(ctx);
     let desired_hash = crate::adapters::vault::file_hash(content);
     if before.hash == desired_hash {
         return envelope(
-            "kb_update",
-            "kb.update",
+            tool,
+            action,
             true,
             Some(target),
             build_write_result(
                 format!("No changes needed for {resolved_path}"),
                 "noop",
                 true,
+                store.is_staged(),
             ),
             json!({
                 "exists": true,
@@ -1033,7 +1767,7 @@ fn execute_kb_update(
                 "hash_before": before.hash,
                 "hash_after": before.hash,
                 "readback_ok": true,
-                "diff_stats": diff_stats(&before_content, &before_content),
+                "diff_stats": diff_stats(before_content, before_content),
             }),
             None,
             started,
@@ -1041,47 +1775,42 @@ fn execute_kb_update(
         );
     }
 
-    let target_file = [ctx.vault_path.join(&resolved_path)];
-    let _ = crate::adapters::git::auto_commit_files(
-        ctx.vault_path,
-        &target_file,
+    store.snapshot(
+        resolved_path,
         &format!("meld: pre-edit snapshot of {resolved_path}"),
     );
 
-    if let Err(error) = crate::adapters::vault::write_note(ctx.vault_path, &resolved_path, content)
-    {
+    if let Err(error) = store.write(resolved_path, content) {
+        let (code, retriable) = note_error(&*error, "write_failed", true);
         return error_envelope(
-            "kb_update",
-            "kb.update",
+            tool,
+            action,
             Some(target),
             json!({
                 "exists": true,
                 "bytes": before.bytes,
                 "hash_before": before.hash,
             }),
-            "write_failed",
+            code,
             error.to_string(),
-            true,
+            retriable,
             started,
             trace_id.to_string(),
         );
     }
 
-    maybe_corrupt_after_write(ctx.vault_path, &resolved_path);
+    if !store.is_staged() {
+        maybe_corrupt_after_write(ctx.vault_path, resolved_path);
+    }
 
-    let _ = crate::adapters::git::auto_commit_files(
-        ctx.vault_path,
-        &target_file,
-        &format!("meld: edited {resolved_path}"),
-    );
+    store.commit(resolved_path, &format!("meld: edited {resolved_path}"));
 
-    let after = match crate::adapters::vault::read_note_verification(ctx.vault_path, &resolved_path)
-    {
+    let after = match store.verification(resolved_path) {
         Ok(value) => value,
         Err(error) => {
             return error_envelope(
-                "kb_update",
-                "kb.update",
+                tool,
+                action,
                 Some(target),
                 json!({
                     "exists": true,
@@ -1097,8 +1826,7 @@ fn execute_kb_update(
         }
     };
 
-    let after_content =
-        crate::adapters::vault::read_note(ctx.vault_path, &resolved_path).unwrap_or_default();
+    let after_content = store.read(resolved_path).unwrap_or_default();
     let readback_ok = after.exists && after_content == content;
     let proof = json!({
         "exists": after.exists,
@@ -1106,13 +1834,13 @@ fn execute_kb_update(
         "hash_before": before.hash,
         "hash_after": after.hash,
         "readback_ok": readback_ok,
-        "diff_stats": diff_stats(&before_content, &after_content),
+        "diff_stats": diff_stats(before_content, &after_content),
     });
 
     if !readback_ok {
         return error_envelope(
-            "kb_update",
-            "kb.update",
+            tool,
+            action,
             Some(target),
             proof,
             "verify_mismatch",
@@ -1124,11 +1852,11 @@ fn execute_kb_update(
     }
 
     envelope(
-        "kb_update",
-        "kb.update",
+        tool,
+        action,
         true,
         Some(target),
-        build_write_result(format!("Updated note {resolved_path}"), "edit", false),
+        build_write_result(summary, "edit", false, store.is_staged()),
         proof,
         None,
         started,
@@ -1138,7 +1866,7 @@ fn execute_kb_update(
 
 fn execute_kb_list(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started: Instant) -> Value {
     let folder = args.get("folder").and_then(|value| value.as_str());
-    let notes = match crate::adapters::vault::list_notes(ctx.vault_path, folder) {
+    let notes = match crate::adapters::vault::list_notes(ctx.vault_root, ctx.vault_path, folder) {
         Ok(notes) => notes,
         Err(error) => {
             return error_envelope(
@@ -1173,21 +1901,97 @@ fn execute_kb_list(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started:
     )
 }
 
+/// Unix seconds for a `kb_history` date bound. A bare date covers the whole day.
+fn parse_history_time(value: &str, end_of_day: bool) -> Result<i64, String> {
+    let value = value.trim();
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp());
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{value}'. Use RFC 3339 or YYYY-MM-DD."))?;
+    let (hour, minute, second) = if end_of_day { (23, 59, 59) } else { (0, 0, 0) };
+    date.and_hms_opt(hour, minute, second)
+        .map(|time| time.and_utc().timestamp())
+        .ok_or_else(|| format!("Invalid date '{value}'"))
+}
+
+fn history_query_from_args(args: &Value) -> Result<crate::adapters::git::HistoryQuery, String> {
+    let text = |key: &str| {
+        args.get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let author = match text("author") {
+        None => None,
+        Some("agent") | Some("meld") => Some("meld".to_string()),
+        Some("user") => Some("user".to_string()),
+        Some(other) => return Err(format!("Unknown author '{other}'. Use 'agent' or 'user'.")),
+    };
+    Ok(crate::adapters::git::HistoryQuery {
+        path: text("path").map(str::to_string),
+        after: text("after").map(str::to_string),
+        limit: Some(
+            args.get("limit")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize)
+                .unwrap_or(20),
+        ),
+        author,
+        since: text("since")
+            .map(|value| parse_history_time(value, false))
+            .transpose()?,
+        until: text("until")
+            .map(|value| parse_history_time(value, true))
+            .transpose()?,
+        message: text("message").map(str::to_string),
+    })
+}
+
 fn execute_kb_history(
     ctx: &McpContext<'_>,
     args: &Value,
     trace_id: &str,
     started: Instant,
 ) -> Value {
-    let path_filter = args.get("path").and_then(|v| v.as_str());
-    let limit = args
-        .get("limit")
-        .and_then(|v| v.as_u64())
-        .map(|v| v as usize)
-        .or(Some(20));
+    let query = match history_query_from_args(args) {
+        Ok(query) => query,
+        Err(error) => {
+            return error_envelope(
+                "kb_history",
+                "kb.history",
+                None,
+                json!({}),
+                "invalid_arguments",
+                error,
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
 
-    let commits = match crate::adapters::git::get_history(ctx.vault_path, path_filter, limit) {
-        Ok(entries) => entries,
+    let privacy = crate::adapters::vault::PrivacyRules::load(ctx.vault_root);
+    if let Some(path) = query
+        .path
+        .as_deref()
+        .filter(|path| privacy.is_private_in(ctx.vault_path, path))
+    {
+        return error_envelope(
+            "kb_history",
+            "kb.history",
+            None,
+            json!({}),
+            "forbidden",
+            format!("Note {path} is private and not available to the agent"),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+
+    let mut page = match crate::adapters::git::query_history(ctx.vault_path, &query) {
+        Ok(page) => page,
         Err(error) => {
             return error_envelope(
                 "kb_history",
@@ -1203,15 +2007,29 @@ fn execute_kb_history(
         }
     };
 
+    // Paths of private notes can name what the note is about, so they are left out of
+    // the changed files and the commit messages alike.
+    for entry in &mut page.entries {
+        let (hidden, visible): (Vec<String>, Vec<String>) =
+            std::mem::take(&mut entry.files_changed)
+                .into_iter()
+                .partition(|path| private_in_commit(ctx, &privacy, &entry.id, path));
+        entry.files_changed = visible;
+        for path in hidden {
+            entry.message = entry.message.replace(&path, "[private note]");
+        }
+    }
+
     envelope(
         "kb_history",
         "kb.history",
         true,
         None,
         json!({
-            "summary": format!("Found {} commits", commits.len()),
-            "count": commits.len(),
-            "commits": commits,
+            "summary": format!("Found {} commits", page.entries.len()),
+            "count": page.entries.len(),
+            "commits": page.entries,
+            "next_cursor": page.next_cursor,
         }),
         json!({}),
         None,
@@ -1254,6 +2072,19 @@ fn execute_kb_diff(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started:
             )
         }
     };
+    if let Some(path) = private_note(ctx, &diff.id, &diff.files_changed) {
+        return error_envelope(
+            "kb_diff",
+            "kb.diff",
+            None,
+            json!({}),
+            "forbidden",
+            format!("Commit {commit_id} changes the private note {path}"),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
 
     envelope(
         "kb_diff",
@@ -1275,6 +2106,397 @@ fn execute_kb_diff(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started:
     )
 }
 
+fn execute_kb_revert(
+    ctx: &McpContext<'_>,
+    args: &Value,
+    trace_id: &str,
+    started: Instant,
+) -> Value {
+    let commit_id = match args.get("commit_id").and_then(|v| v.as_str()) {
+        Some(id) => id,
+        None => {
+            return error_envelope(
+                "kb_revert",
+                "kb.revert",
+                None,
+                json!({}),
+                "invalid_arguments",
+                "Missing commit_id",
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+    let style = match crate::adapters::git::ConflictStyle::parse(
+        args.get("conflict_style").and_then(|v| v.as_str()),
+    ) {
+        Ok(style) => style,
+        Err(error) => {
+            return error_envelope(
+                "kb_revert",
+                "kb.revert",
+                None,
+                json!({}),
+                "invalid_arguments",
+                error,
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+    if ctx.staged_run_id.is_some() {
+        return error_envelope(
+            "kb_revert",
+            "kb.revert",
+            None,
+            json!({}),
+            "dry_run_unsupported",
+            "Reverts cannot be staged in a dry run",
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+    // A revert commits on its own, snapshotting the vault first, which would sweep the
+    // writes a run commits at its end into that snapshot.
+    if ctx.defer_commits {
+        return error_envelope(
+            "kb_revert",
+            "kb.revert",
+            None,
+            json!({}),
+            "agent_run_unsupported",
+            "Reverts cannot run inside an agent run; the user can revert the commit from history",
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+
+    if let Some(path) = crate::adapters::git::get_commit_diff(ctx.vault_path, commit_id)
+        .ok()
+        .and_then(|diff| private_note(ctx, &diff.id, &diff.files_changed).map(str::to_string))
+    {
+        return error_envelope(
+            "kb_revert",
+            "kb.revert",
+            None,
+            json!({}),
+            "forbidden",
+            format!("Commit {commit_id} changes the private note {path}"),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+
+    let outcome = match crate::adapters::git::revert_commit(ctx.vault_path, commit_id, style) {
+        Ok(outcome) => outcome,
+        Err(error) => {
+            return error_envelope(
+                "kb_revert",
+                "kb.revert",
+                None,
+                json!({}),
+                "revert_failed",
+                error.to_string(),
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+
+    let mismatches = crate::adapters::git::revert_readback_mismatches(ctx.vault_path, &outcome)
+        .unwrap_or_else(|error| vec![error.to_string()]);
+
+    envelope(
+        "kb_revert",
+        "kb.revert",
+        true,
+        None,
+        json!({
+            "summary": format!(
+                "Reverted {} in {} ({} with conflicts)",
+                commit_id,
+                outcome.commit_id,
+                outcome.conflicts
+            ),
+            "commit_id": outcome.commit_id,
+            "conflicts": outcome.conflicts,
+            "files": outcome.files,
+        }),
+        json!({
+            "readback_ok": mismatches.is_empty(),
+            "mismatches": mismatches,
+        }),
+        None,
+        started,
+        trace_id.to_string(),
+    )
+}
+
+/// The commit a `kb_revert` call would undo, shown to the user before approval.
+fn preview_revert(ctx: &ToolContext<'_>, args: &Value) -> Value {
+    let Some(commit_id) = args.get("commit_id").and_then(|v| v.as_str()) else {
+        return json!({});
+    };
+    match crate::adapters::git::get_commit_diff(ctx.vault_path, commit_id) {
+        Ok(diff) => json!({
+            "path": diff.files_changed.join(", "),
+            "diff": diff.patch,
+        }),
+        Err(error) => json!({ "error": error.to_string() }),
+    }
+}
+
+fn execute_kb_blame(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started: Instant) -> Value {
+    let requested_path = match normalize_path_arg(args, "path") {
+        Ok(path) => path,
+        Err(error) => {
+            return error_envelope(
+                "kb_blame",
+                "kb.blame",
+                None,
+                json!({}),
+                "invalid_arguments",
+                error,
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+    let resolved_path = requested_path.clone();
+    let target = target_payload(&requested_path, &resolved_path);
+    if !ctx.vault_path.join(&resolved_path).is_file() {
+        return error_envelope(
+            "kb_blame",
+            "kb.blame",
+            Some(target),
+            json!({}),
+            "not_found",
+            format!("Note not found: {resolved_path}"),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+    if let Err(error) = crate::adapters::vault::ensure_agent_access(
+        ctx.vault_root,
+        ctx.vault_path,
+        &resolved_path,
+        None,
+    ) {
+        return error_envelope(
+            "kb_blame",
+            "kb.blame",
+            Some(target),
+            json!({}),
+            "forbidden",
+            error.to_string(),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+
+    let blame = match crate::adapters::git::blame_note(ctx.vault_path, &resolved_path) {
+        Ok(blame) => blame,
+        Err(error) => {
+            return error_envelope(
+                "kb_blame",
+                "kb.blame",
+                Some(target),
+                json!({}),
+                "blame_failed",
+                error.to_string(),
+                false,
+                started,
+                trace_id.to_string(),
+            )
+        }
+    };
+
+    envelope(
+        "kb_blame",
+        "kb.blame",
+        true,
+        Some(target),
+        json!({
+            "summary": format!(
+                "{} of {} lines in {resolved_path} last written by the agent",
+                blame.agent_lines,
+                blame.agent_lines + blame.user_lines
+            ),
+            "agent_lines": blame.agent_lines,
+            "user_lines": blame.user_lines,
+            "hunks": blame.hunks,
+        }),
+        json!({}),
+        None,
+        started,
+        trace_id.to_string(),
+    )
+}
+
+fn execute_kb_links(ctx: &McpContext<'_>, args: &Value, trace_id: &str, started: Instant) -> Value {
+    let kind = args
+        .get("kind")
+        .and_then(|v| v.as_str())
+        .unwrap_or("note")
+        .to_string();
+    let path = match args.get("path").filter(|v| !v.is_null()) {
+        Some(_) => match normalize_path_arg(args, "path") {
+            Ok(path) => Some(path),
+            Err(error) => {
+                return error_envelope(
+                    "kb_links",
+                    "kb.links",
+                    None,
+                    json!({}),
+                    "invalid_arguments",
+                    error,
+                    false,
+                    started,
+                    trace_id.to_string(),
+                )
+            }
+        },
+        None => None,
+    };
+    let target = path.as_deref().map(|path| target_payload(path, path));
+
+    let requires_path = matches!(kind.as_str(), "note" | "outgoing" | "backlinks");
+    if !requires_path && !matches!(kind.as_str(), "orphans" | "unresolved") {
+        return error_envelope(
+            "kb_links",
+            "kb.links",
+            target,
+            json!({}),
+            "invalid_arguments",
+            format!("Unknown kind '{kind}'. Use note, outgoing, backlinks, orphans or unresolved."),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+    if requires_path && path.is_none() {
+        return error_envelope(
+            "kb_links",
+            "kb.links",
+            None,
+            json!({}),
+            "invalid_arguments",
+            format!("Missing path (required for kind={kind})"),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+
+    let privacy = crate::adapters::vault::PrivacyRules::load(ctx.vault_root);
+    if let Some(note) = path
+        .as_deref()
+        .filter(|note| privacy.is_private_in(ctx.vault_path, note))
+    {
+        return error_envelope(
+            "kb_links",
+            "kb.links",
+            target,
+            json!({}),
+            "forbidden",
+            format!("Note {note} is private and not available to the agent"),
+            false,
+            started,
+            trace_id.to_string(),
+        );
+    }
+    // Links from or to private notes are left out, so their paths never reach the agent.
+    let visible = |links: Vec<crate::adapters::vectordb::NoteLink>| -> Vec<_> {
+        links
+            .into_iter()
+            .filter(|link| {
+                !privacy.is_private_in(ctx.vault_path, &link.source_path)
+                    && !link
+                        .resolved_path
+                        .as_deref()
+                        .is_some_and(|resolved| privacy.is_private_in(ctx.vault_path, resolved))
+            })
+            .collect()
+    };
+
+    let lookup = || -> Result<Value, Box<dyn std::error::Error>> {
+        let db = crate::adapters::vectordb::VectorDb::open(ctx.db_path)?;
+        let note = path.as_deref().unwrap_or_default();
+        Ok(match kind.as_str() {
+            "note" => {
+                let outgoing = visible(db.outgoing_links(note)?);
+                let backlinks = visible(db.backlinks(note)?);
+                json!({
+                    "summary": format!(
+                        "{note}: {} outgoing links, {} backlinks",
+                        outgoing.len(),
+                        backlinks.len()
+                    ),
+                    "outgoing": outgoing,
+                    "backlinks": backlinks,
+                })
+            }
+            "outgoing" => {
+                let links = visible(db.outgoing_links(note)?);
+                json!({ "summary": format!("{note}: {} outgoing links", links.len()), "links": links })
+            }
+            "backlinks" => {
+                let links = visible(db.backlinks(note)?);
+                json!({ "summary": format!("{note}: {} backlinks", links.len()), "links": links })
+            }
+            "orphans" => {
+                let notes: Vec<_> = db
+                    .orphan_notes()?
+                    .into_iter()
+                    .filter(|orphan| !privacy.is_private_in(ctx.vault_path, orphan))
+                    .collect();
+                json!({ "summary": format!("Found {} orphan notes", notes.len()), "notes": notes })
+            }
+            _ => {
+                let links: Vec<_> = visible(db.unresolved_links()?)
+                    .into_iter()
+                    .filter(|link| path.is_none() || link.source_path == note)
+                    .collect();
+                json!({ "summary": format!("Found {} unresolved links", links.len()), "links": links })
+            }
+        })
+    };
+
+    match lookup() {
+        Ok(result) => envelope(
+            "kb_links",
+            "kb.links",
+            true,
+            target,
+            result,
+            json!({}),
+            None,
+            started,
+            trace_id.to_string(),
+        ),
+        Err(error) => error_envelope(
+            "kb_links",
+            "kb.links",
+            target,
+            json!({}),
+            "links_failed",
+            error.to_string(),
+            true,
+            started,
+            trace_id.to_string(),
+        ),
+    }
+}
+
 async fn execute_kb_search(
     ctx: &McpContext<'_>,
     args: &Value,
@@ -1312,7 +2534,7 @@ async fn execute_kb_search(
     .await
     .unwrap_or(0);
 
-    let results = match crate::adapters::rag::query(
+    let mut results = match crate::adapters::rag::query(
         ctx.db_path,
         ctx.embedding_key,
         ctx.embedding_model_id,
@@ -1338,6 +2560,13 @@ async fn execute_kb_search(
         }
     };
 
+    // The index drops private notes, but one marked private since the last reindex may
+    // still have chunks.
+    let privacy = crate::adapters::vault::PrivacyRules::load(ctx.vault_root);
+    results
+        .chunks
+        .retain(|chunk| !privacy.is_private_in(ctx.vault_path, &chunk.file_path));
+
     let sources: Vec<String> = results
         .chunks
         .iter()
@@ -0,0 +1,199 @@
+//! Note storage behind the `kb_*` tools. Normal runs read and write the vault; dry runs
+//! write into the run's staged changeset and read their own staged copies back. Either
+//! way private notes are refused, as the vault layer decides.
+
+use std::path::Path;
+
+use crate::adapters::vault::NoteVerification;
+use crate::adapters::vectordb::{StagedChange, VectorDb};
+
+use super::ToolContext;
+
+pub(super) enum NoteStore<'a> {
+    /// `vault_path` is the vault, or a conversation branch's worktree of it whose ignore
+    /// and privacy rules come from `vault_root`.
+    Vault {
+        vault_path: &'a Path,
+        vault_root: &'a Path,
+        defer_commits: bool,
+    },
+    Staged {
+        vault_path: &'a Path,
+        db_path: &'a Path,
+        run_id: &'a str,
+    },
+}
+
+impl<'a> NoteStore<'a> {
+    pub(super) fn for_context(ctx: &ToolContext<'a>) -> Self {
+        match ctx.staged_run_id {
+            Some(run_id) => NoteStore::Staged {
+                vault_path: ctx.vault_path,
+                db_path: ctx.db_path,
+                run_id,
+            },
+            None => NoteStore::Vault {
+                vault_path: ctx.vault_path,
+                vault_root: ctx.vault_root,
+                defer_commits: ctx.defer_commits,
+            },
+        }
+    }
+
+    pub(super) fn is_staged(&self) -> bool {
+        matches!(self, NoteStore::Staged { .. })
+    }
+
+    fn vault_path(&self) -> &'a Path {
+        match self {
+            NoteStore::Vault { vault_path, .. } | NoteStore::Staged { vault_path, .. } => {
+                vault_path
+            }
+        }
+    }
+
+    /// The vault whose privacy rules apply. Dry runs never work in a branch worktree.
+    fn vault_root(&self) -> &'a Path {
+        match self {
+            NoteStore::Vault { vault_root, .. } => vault_root,
+            NoteStore::Staged { vault_path, .. } => vault_path,
+        }
+    }
+
+    fn staged_change(
+        &self,
+        path: &str,
+    ) -> Result<Option<StagedChange>, Box<dyn std::error::Error>> {
+        match self {
+            NoteStore::Vault { .. } => Ok(None),
+            NoteStore::Staged {
+                db_path, run_id, ..
+            } => VectorDb::open(db_path)?.staged_change(run_id, path),
+        }
+    }
+
+    pub(super) fn verification(
+        &self,
+        path: &str,
+    ) -> Result<NoteVerification, Box<dyn std::error::Error>> {
+        if let Some(change) = self.staged_change(path)? {
+            crate::adapters::vault::ensure_agent_access(
+                self.vault_root(),
+                self.vault_path(),
+                path,
+                Some(&change.content),
+            )?;
+            return Ok(NoteVerification {
+                exists: true,
+                bytes: change.content.len() as u64,
+                hash: change.hash_after,
+            });
+        }
+        crate::adapters::vault::read_agent_note_verification(
+            self.vault_root(),
+            self.vault_path(),
+            path,
+        )
+    }
+
+    pub(super) fn read(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(change) = self.staged_change(path)? {
+            crate::adapters::vault::ensure_agent_access(
+                self.vault_root(),
+                self.vault_path(),
+                path,
+                Some(&change.content),
+            )?;
+            return Ok(change.content);
+        }
+        Ok(crate::adapters::vault::read_agent_note(
+            self.vault_root(),
+            self.vault_path(),
+            path,
+        )?)
+    }
+
+    pub(super) fn write(
+        &self,
+        path: &str,
+        content: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let NoteStore::Staged {
+            vault_path,
+            db_path,
+            run_id,
+        } = self
+        else {
+            return Ok(crate::adapters::vault::write_agent_note(
+                self.vault_root(),
+                self.vault_path(),
+                path,
+                content,
+            )?);
+        };
+
+        crate::adapters::vault::ensure_within_vault(vault_path, &vault_path.join(path))?;
+        crate::adapters::vault::ensure_agent_access(vault_path, vault_path, path, Some(content))?;
+        let mut db = VectorDb::open(db_path)?;
+        let original = crate::adapters::vault::read_note(vault_path, path).ok();
+        // Keep the hash from the first time this note was staged so apply can detect
+        // edits made to the vault copy since the run started touching it.
+        let hash_before = match db.staged_change(run_id, path)? {
+            Some(existing) => existing.hash_before,
+            None => original.as_deref().map(crate::adapters::vault::file_hash),
+        };
+        let diff = crate::adapters::git::text_diff(
+            path,
+            original.as_deref().unwrap_or_default(),
+            content,
+        )?;
+        db.stage_change(&StagedChange {
+            run_id: run_id.to_string(),
+            path: path.to_string(),
+            action: if hash_before.is_some() {
+                "edit"
+            } else {
+                "create"
+            }
+            .to_string(),
+            hash_before,
+            hash_after: crate::adapters::vault::file_hash(content),
+            content: content.to_string(),
+            diff,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Commit the vault copy of `path` as the agent's write. Staged writes are committed
+    /// when applied, and agent runs commit all of their writes once the run ends.
+    pub(super) fn commit(&self, path: &str, message: &str) {
+        if let Some(vault_path) = self.commits_each_write() {
+            let _ = crate::adapters::git::auto_commit_files(
+                vault_path,
+                &[vault_path.join(path)],
+                message,
+            );
+        }
+    }
+
+    /// Snapshot the vault copy of `path` before it is overwritten, as the user's: any
+    /// uncommitted change in it is a hand edit. Agent runs snapshot each note before
+    /// their first write to it instead.
+    pub(super) fn snapshot(&self, path: &str, message: &str) {
+        if let Some(vault_path) = self.commits_each_write() {
+            let _ =
+                crate::adapters::git::snapshot_files(vault_path, &[vault_path.join(path)], message);
+        }
+    }
+
+    fn commits_each_write(&self) -> Option<&'a Path> {
+        match self {
+            NoteStore::Vault {
+                vault_path,
+                defer_commits: false,
+                ..
+            } => Some(vault_path),
+            _ => None,
+        }
+    }
+}
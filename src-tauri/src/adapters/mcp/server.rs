@@ -0,0 +1,219 @@
+//! Model Context Protocol server: newline-delimited JSON-RPC 2.0 over stdio.
+//!
+//! Exposes the vault tools of [`ToolRegistry`] to external MCP clients. Tool results are
+//! the same envelopes the in-app agent receives, so the `readback_ok` proof contract and
+//! git auto-commits apply unchanged.
+
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{Permission, ToolContext, ToolDefinition, ToolRegistry};
+
+pub const SERVER_NAME: &str = "meld";
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Built-in tools published over MCP. `web_search` stays app-only.
+const SERVED_TOOLS: [&str; 11] = [
+    "kb_search",
+    "kb_read",
+    "kb_create",
+    "kb_update",
+    "kb_patch",
+    "kb_list",
+    "kb_history",
+    "kb_diff",
+    "kb_revert",
+    "kb_blame",
+    "kb_links",
+];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub struct McpServerConfig {
+    pub vault_path: PathBuf,
+    pub db_path: PathBuf,
+    pub embedding_key: String,
+    pub embedding_model_id: String,
+}
+
+pub struct McpServer {
+    registry: ToolRegistry,
+    config: McpServerConfig,
+}
+
+impl McpServer {
+    pub fn new(config: McpServerConfig) -> Self {
+        Self {
+            registry: ToolRegistry::new(false),
+            config,
+        }
+    }
+
+    fn served_definitions(&self) -> Vec<&ToolDefinition> {
+        self.registry
+            .definitions()
+            .into_iter()
+            .filter(|definition| SERVED_TOOLS.contains(&definition.name.as_str()))
+            .collect()
+    }
+
+    fn tools_list(&self) -> Value {
+        let tools: Vec<Value> = self
+            .served_definitions()
+            .into_iter()
+            .map(|definition| {
+                json!({
+                    "name": definition.name,
+                    "description": definition.description,
+                    "inputSchema": definition.input_schema,
+                    "annotations": {
+                        "readOnlyHint": definition.permission == Permission::Read,
+                        "destructiveHint": false,
+                        "openWorldHint": false,
+                    },
+                })
+            })
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    async fn tools_call(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        if !SERVED_TOOLS.contains(&name) {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {name}")));
+        }
+        let args = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        let ctx = ToolContext {
+            vault_path: &self.config.vault_path,
+            vault_root: &self.config.vault_path,
+            db_path: &self.config.db_path,
+            embedding_key: &self.config.embedding_key,
+            embedding_model_id: &self.config.embedding_model_id,
+            tavily_api_key: "",
+            search_provider: "tavily",
+            searxng_base_url: "",
+            brave_api_key: "",
+            staged_run_id: None,
+            defer_commits: false,
+        };
+        let envelope = self.registry.execute(name, args, &ctx).await;
+        let ok = envelope
+            .get("ok")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let text = serde_json::to_string(&envelope).unwrap_or_default();
+
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "structuredContent": envelope,
+            "isError": !ok,
+        }))
+    }
+
+    /// Handle one decoded JSON-RPC message. Returns `None` for notifications.
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(|v| v.as_str()) else {
+            return id.map(|id| error_response(id, INVALID_REQUEST, "Missing method"));
+        };
+        // Notifications carry no id and never get a response.
+        let id = id?;
+        let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let result = match method {
+            "initialize" => Ok(initialize_result(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.tools_list()),
+            "tools/call" => self.tools_call(&params).await,
+            other => Err((METHOD_NOT_FOUND, format!("Method not found: {other}"))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    /// Serve until the reader reaches EOF.
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> std::io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(Value::Array(batch)) => {
+                    let mut responses = Vec::new();
+                    for message in batch {
+                        if let Some(response) = self.handle_message(message).await {
+                            responses.push(response);
+                        }
+                    }
+                    (!responses.is_empty()).then_some(Value::Array(responses))
+                }
+                Ok(message) => self.handle_message(message).await,
+                Err(error) => Some(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    &format!("Parse error: {error}"),
+                )),
+            };
+
+            if let Some(response) = response {
+                let mut encoded = serde_json::to_string(&response).unwrap_or_default();
+                encoded.push('\n');
+                writer.write_all(encoded.as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn initialize_result(params: &Value) -> Value {
+    let requested = params
+        .get("protocolVersion")
+        .and_then(|v| v.as_str())
+        .unwrap_or(LATEST_PROTOCOL_VERSION);
+    let protocol_version = if SUPPORTED_PROTOCOL_VERSIONS.contains(&requested) {
+        requested
+    } else {
+        LATEST_PROTOCOL_VERSION
+    };
+
+    json!({
+        "protocolVersion": protocol_version,
+        "capabilities": {
+            "tools": { "listChanged": false },
+        },
+        "serverInfo": {
+            "name": SERVER_NAME,
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "instructions": "Tools operate on a meld vault. Writes are git-committed to the vault's meld history and verified by readback.",
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
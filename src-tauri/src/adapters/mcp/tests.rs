@@ -23,6 +23,7 @@ async fn create_on_existing_file_returns_file_exists() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -30,6 +31,8 @@ async fn create_on_existing_file_returns_file_exists() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -70,6 +73,7 @@ async fn update_on_missing_file_returns_not_found() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -77,6 +81,8 @@ async fn update_on_missing_file_returns_not_found() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -121,6 +127,7 @@ async fn create_new_file_succeeds() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -128,6 +135,8 @@ async fn create_new_file_succeeds() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -178,6 +187,7 @@ async fn update_existing_file_succeeds() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -185,6 +195,8 @@ async fn update_existing_file_succeeds() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -255,6 +267,7 @@ async fn update_noop_on_same_content() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -262,6 +275,8 @@ async fn update_noop_on_same_content() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -319,6 +334,7 @@ async fn legacy_tool_name_is_rejected() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -326,6 +342,8 @@ async fn legacy_tool_name_is_rejected() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(&ctx, "search_notes", &json!({ "query": "rust" })).await;
@@ -351,6 +369,7 @@ async fn update_reports_verify_mismatch_on_post_write_corruption() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -358,6 +377,8 @@ async fn update_reports_verify_mismatch_on_post_write_corruption() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     trigger_test_corruption_once();
@@ -413,6 +434,7 @@ async fn create_with_explicit_zettel_prefix_does_not_double_prefix() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -420,6 +442,8 @@ async fn create_with_explicit_zettel_prefix_does_not_double_prefix() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -452,6 +476,7 @@ async fn create_with_para_prefix_is_respected() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -459,6 +484,8 @@ async fn create_with_para_prefix_is_respected() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -494,6 +521,7 @@ async fn kb_history_returns_commits() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -501,6 +529,8 @@ async fn kb_history_returns_commits() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(&ctx, "kb_history", &json!({})).await;
@@ -536,6 +566,7 @@ async fn kb_history_path_filter_works() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -543,6 +574,8 @@ async fn kb_history_path_filter_works() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(&ctx, "kb_history", &json!({ "path": "a.md" })).await;
@@ -552,7 +585,186 @@ async fn kb_history_path_filter_works() {
         .pointer("/result/commits")
         .and_then(|v| v.as_array())
         .expect("commits array");
-    assert_eq!(commits.len(), 1); // edit a (seed has no parent → empty files_changed)
+    let messages: Vec<&str> = commits
+        .iter()
+        .filter_map(|commit| commit.get("message").and_then(|v| v.as_str()))
+        .collect();
+    assert_eq!(messages, vec!["edit a", "seed"]);
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn kb_history_pages_and_validates_filters() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    for i in 0..3 {
+        crate::adapters::vault::write_note(&vault, "a.md", &format!("a{i}")).expect("write a");
+        crate::adapters::git::auto_commit(&vault, &format!("edit {i}")).expect("commit");
+    }
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    let first = execute_tool(&ctx, "kb_history", &json!({ "limit": 2 })).await;
+    let cursor = first
+        .pointer("/result/next_cursor")
+        .and_then(|v| v.as_str())
+        .expect("next cursor");
+    let second = execute_tool(
+        &ctx,
+        "kb_history",
+        &json!({ "limit": 2, "after": cursor, "since": "2000-01-01", "author": "agent" }),
+    )
+    .await;
+    assert_eq!(
+        second
+            .pointer("/result/commits/0/message")
+            .and_then(|v| v.as_str()),
+        Some("edit 0")
+    );
+    assert!(second
+        .pointer("/result/next_cursor")
+        .is_some_and(|v| v.is_null()));
+
+    for args in [
+        json!({ "author": "robot" }),
+        json!({ "until": "last week" }),
+    ] {
+        let result = execute_tool(&ctx, "kb_history", &args).await;
+        assert_eq!(
+            result.pointer("/error/code").and_then(|v| v.as_str()),
+            Some("invalid_arguments")
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn kb_blame_reports_line_authors() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "note.md", "by hand\n").expect("write v1");
+    crate::adapters::git::commit_user_edits(&vault, &["note.md".to_string()]).expect("v1");
+    crate::adapters::vault::write_note(&vault, "note.md", "by hand\nby agent\n").expect("write v2");
+    crate::adapters::git::auto_commit(&vault, "agent write").expect("v2");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    let result = execute_tool(&ctx, "kb_blame", &json!({ "path": "note.md" })).await;
+    assert_eq!(result.get("ok").and_then(|v| v.as_bool()), Some(true));
+    assert_eq!(
+        result
+            .pointer("/result/agent_lines")
+            .and_then(|v| v.as_u64()),
+        Some(1)
+    );
+    assert_eq!(
+        result
+            .pointer("/result/hunks/0/author")
+            .and_then(|v| v.as_str()),
+        Some("user")
+    );
+    assert_eq!(
+        result
+            .pointer("/result/hunks/1/lines/0")
+            .and_then(|v| v.as_str()),
+        Some("by agent")
+    );
+
+    let missing = execute_tool(&ctx, "kb_blame", &json!({ "path": "missing.md" })).await;
+    assert_eq!(
+        missing.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("not_found")
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn kb_blame_keeps_hand_edits_snapshotted_before_an_agent_edit() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "note.md", "by hand\n").expect("write v1");
+    crate::adapters::git::commit_user_edits(&vault, &["note.md".to_string()]).expect("v1");
+    crate::adapters::vault::write_note(&vault, "note.md", "by hand\nunsaved edit\n")
+        .expect("hand edit");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+    let update = execute_tool(
+        &ctx,
+        "kb_update",
+        &json!({ "path": "note.md", "content": "by hand\nunsaved edit\nby agent\n" }),
+    )
+    .await;
+    assert_eq!(update.get("ok").and_then(|v| v.as_bool()), Some(true));
+
+    let result = execute_tool(&ctx, "kb_blame", &json!({ "path": "note.md" })).await;
+    assert_eq!(
+        result
+            .pointer("/result/user_lines")
+            .and_then(|v| v.as_u64()),
+        Some(2)
+    );
+    assert_eq!(
+        result
+            .pointer("/result/agent_lines")
+            .and_then(|v| v.as_u64()),
+        Some(1)
+    );
+    let authors: Vec<&str> = result
+        .pointer("/result/hunks")
+        .and_then(|v| v.as_array())
+        .expect("hunks")
+        .iter()
+        .filter_map(|hunk| hunk.get("author").and_then(|v| v.as_str()))
+        .collect();
+    assert_eq!(authors, vec!["user", "user", "meld"]);
 
     let _ = std::fs::remove_dir_all(vault);
 }
@@ -576,6 +788,7 @@ async fn kb_diff_returns_patch() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -583,6 +796,8 @@ async fn kb_diff_returns_patch() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(&ctx, "kb_diff", &json!({ "commit_id": commit_id })).await;
@@ -598,6 +813,66 @@ async fn kb_diff_returns_patch() {
     let _ = std::fs::remove_dir_all(vault);
 }
 
+#[tokio::test]
+async fn kb_read_with_revision_returns_committed_content() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "note.md", "line one\n").expect("write v1");
+    crate::adapters::git::auto_commit(&vault, "v1").expect("commit v1");
+    let history = crate::adapters::git::get_history(&vault, None, Some(1)).expect("history");
+    let commit_id = history.first().expect("v1").id.clone();
+
+    crate::adapters::vault::write_note(&vault, "note.md", "line one\nline two\n")
+        .expect("write v2");
+    crate::adapters::git::auto_commit(&vault, "v2").expect("commit v2");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    let result = execute_tool(
+        &ctx,
+        "kb_read",
+        &json!({ "path": "note.md", "revision": &commit_id[..7] }),
+    )
+    .await;
+    assert_eq!(result.get("ok").and_then(|v| v.as_bool()), Some(true));
+    assert_eq!(
+        result.pointer("/result/content").and_then(|v| v.as_str()),
+        Some("line one\n")
+    );
+    assert_eq!(
+        result.pointer("/result/revision").and_then(|v| v.as_str()),
+        Some(commit_id.as_str())
+    );
+
+    let missing = execute_tool(
+        &ctx,
+        "kb_read",
+        &json!({ "path": "other.md", "revision": commit_id }),
+    )
+    .await;
+    assert_eq!(
+        missing.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("not_found")
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
 #[tokio::test]
 async fn kb_diff_missing_commit_id_returns_error() {
     let _guard = test_guard();
@@ -607,6 +882,7 @@ async fn kb_diff_missing_commit_id_returns_error() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -614,6 +890,8 @@ async fn kb_diff_missing_commit_id_returns_error() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(&ctx, "kb_diff", &json!({})).await;
@@ -638,6 +916,7 @@ async fn kb_diff_invalid_commit_returns_error() {
 
     let ctx = McpContext {
         vault_path: &vault,
+        vault_root: &vault,
         db_path: &db_path,
         embedding_key: "",
         embedding_model_id: "openai:text-embedding-3-small",
@@ -645,6 +924,8 @@ async fn kb_diff_invalid_commit_returns_error() {
         search_provider: "tavily",
         searxng_base_url: "http://localhost:8080",
         brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
     };
 
     let result = execute_tool(
@@ -661,3 +942,838 @@ async fn kb_diff_invalid_commit_returns_error() {
 
     let _ = std::fs::remove_dir_all(vault);
 }
+
+#[tokio::test]
+async fn kb_patch_appends_to_section_with_readback_proof() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(
+        &vault,
+        "plan.md",
+        "# Plan\n\n## Risks\n- late\n\n## Next\nShip it.\n",
+    )
+    .expect("seed note");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    let result = execute_tool(
+        &ctx,
+        "kb_patch",
+        &json!({
+            "path": "plan.md",
+            "operations": [
+                { "op": "append_to_section", "heading": "Risks", "content": "- over budget" },
+                { "op": "replace_text", "find": "Ship it.", "replace": "Ship Friday." }
+            ]
+        }),
+    )
+    .await;
+
+    assert_eq!(result.get("ok").and_then(|v| v.as_bool()), Some(true));
+    assert_eq!(
+        result
+            .pointer("/proof/readback_ok")
+            .and_then(|v| v.as_bool()),
+        Some(true)
+    );
+    assert_eq!(
+        result
+            .pointer("/proof/diff_stats/added_lines")
+            .and_then(|v| v.as_u64()),
+        Some(2)
+    );
+    let content = crate::adapters::vault::read_note(&vault, "plan.md").expect("read");
+    assert_eq!(
+        content,
+        "# Plan\n\n## Risks\n- late\n- over budget\n\n## Next\nShip Friday.\n"
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn kb_patch_failed_operation_leaves_note_untouched() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\nbody\n").expect("seed note");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    let result = execute_tool(
+        &ctx,
+        "kb_patch",
+        &json!({
+            "path": "plan.md",
+            "operations": [
+                { "op": "replace_text", "find": "body", "replace": "changed" },
+                { "op": "insert_under_heading", "heading": "Missing", "content": "x" }
+            ]
+        }),
+    )
+    .await;
+
+    assert_eq!(result.get("ok").and_then(|v| v.as_bool()), Some(false));
+    assert_eq!(
+        result.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("patch_failed")
+    );
+    assert_eq!(
+        crate::adapters::vault::read_note(&vault, "plan.md").expect("read"),
+        "# Plan\nbody\n"
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn dry_run_writes_are_staged_and_read_back() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(vault.join(".meld")).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\nbody\n").expect("seed note");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: Some("dry-run"),
+        defer_commits: false,
+    };
+
+    let created = execute_tool(
+        &ctx,
+        "kb_create",
+        &json!({ "path": "ideas.md", "content": "new idea" }),
+    )
+    .await;
+    assert_eq!(created.get("ok").and_then(|v| v.as_bool()), Some(true));
+    assert_eq!(created.pointer("/result/staged"), Some(&json!(true)));
+    assert_eq!(created.pointer("/proof/readback_ok"), Some(&json!(true)));
+    assert!(!vault.join("ideas.md").exists());
+
+    let patched = execute_tool(
+        &ctx,
+        "kb_patch",
+        &json!({
+            "path": "plan.md",
+            "operations": [{ "op": "replace_text", "find": "body", "replace": "changed" }]
+        }),
+    )
+    .await;
+    assert_eq!(patched.get("ok").and_then(|v| v.as_bool()), Some(true));
+    let patched_again = execute_tool(
+        &ctx,
+        "kb_patch",
+        &json!({
+            "path": "plan.md",
+            "operations": [{ "op": "replace_text", "find": "changed", "replace": "twice" }]
+        }),
+    )
+    .await;
+    assert_eq!(
+        patched_again.get("ok").and_then(|v| v.as_bool()),
+        Some(true)
+    );
+
+    let read = execute_tool(&ctx, "kb_read", &json!({ "path": "plan.md" })).await;
+    assert_eq!(
+        read.pointer("/result/content").and_then(|v| v.as_str()),
+        Some("# Plan\ntwice\n")
+    );
+    assert_eq!(
+        crate::adapters::vault::read_note(&vault, "plan.md").expect("read"),
+        "# Plan\nbody\n"
+    );
+
+    let db = crate::adapters::vectordb::VectorDb::open(&db_path).expect("open db");
+    let changes = db.list_staged_changes("dry-run").expect("staged changes");
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].path, "ideas.md");
+    assert_eq!(changes[0].action, "create");
+    assert_eq!(changes[1].action, "edit");
+    assert_eq!(
+        changes[1].hash_before.as_deref(),
+        Some(crate::adapters::vault::file_hash("# Plan\nbody\n").as_str())
+    );
+    assert!(changes[1].diff.contains("-body") && changes[1].diff.contains("+twice"));
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[test]
+fn only_note_writes_are_staged_in_dry_runs() {
+    use crate::core::ports::tools::ToolPort;
+
+    let registry = super::ToolRegistry::new(false);
+    for tool in ["kb_create", "kb_update", "kb_patch"] {
+        assert!(registry.stages_dry_run_writes(tool), "{tool}");
+    }
+    for tool in ["kb_revert", "remote__write_file"] {
+        assert!(!registry.stages_dry_run_writes(tool), "{tool}");
+    }
+}
+
+/// Stands in for a mounted `server__tool` whose writes meld cannot read back.
+struct RemoteWriteTool(super::ToolDefinition);
+
+impl super::ToolExecutor for RemoteWriteTool {
+    fn definition(&self) -> &super::ToolDefinition {
+        &self.0
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _args: serde_json::Value,
+        _ctx: &'a super::ToolContext<'a>,
+    ) -> super::ToolFuture<'a> {
+        Box::pin(async { json!({ "ok": true }) })
+    }
+
+    fn verifies_writes(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn only_built_in_writes_are_verified() {
+    use crate::core::ports::tools::ToolPort;
+
+    let mut registry = super::ToolRegistry::new(false);
+    registry.register(RemoteWriteTool(super::ToolDefinition {
+        name: "tracker__create_issue".to_string(),
+        description: "[tracker] Create an issue".to_string(),
+        input_schema: json!({ "type": "object" }),
+        permission: super::Permission::Write,
+    }));
+    for tool in ["kb_create", "kb_update", "kb_patch", "kb_revert"] {
+        assert!(ToolPort::verifies_writes(&registry, tool), "{tool}");
+    }
+    assert!(!ToolPort::verifies_writes(
+        &registry,
+        "tracker__create_issue"
+    ));
+}
+
+#[test]
+fn preview_write_shows_patched_content_without_writing() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\nbody\n").expect("seed note");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+    let registry = super::ToolRegistry::new(false);
+
+    let preview = registry.preview_write(
+        "kb_patch",
+        &json!({
+            "path": "plan.md",
+            "operations": [{ "op": "replace_text", "find": "body", "replace": "changed" }]
+        }),
+        &ctx,
+    );
+    assert_eq!(preview["exists"], json!(true));
+    assert_eq!(preview["content"], json!("# Plan\nchanged\n"));
+    let diff = preview["diff"].as_str().expect("diff");
+    assert!(diff.contains("-body") && diff.contains("+changed"));
+    assert_eq!(
+        crate::adapters::vault::read_note(&vault, "plan.md").expect("read"),
+        "# Plan\nbody\n"
+    );
+
+    let create = registry.preview_write(
+        "kb_create",
+        &json!({ "path": "ideas.md", "content": "new" }),
+        &ctx,
+    );
+    assert_eq!(create["exists"], json!(false));
+    assert!(create["diff"].as_str().expect("diff").contains("+new"));
+    assert!(!vault.join("ideas.md").exists());
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+fn test_server(vault: &std::path::Path) -> super::server::McpServer {
+    super::server::McpServer::new(super::server::McpServerConfig {
+        vault_path: vault.to_path_buf(),
+        db_path: vault.join(".meld").join("index.db"),
+        embedding_key: String::new(),
+        embedding_model_id: "openai:text-embedding-3-small".to_string(),
+    })
+}
+
+#[tokio::test]
+async fn mcp_server_initialize_and_list_tools() {
+    let vault = temp_vault();
+    let server = test_server(&vault);
+
+    let init = server
+        .handle_message(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {} }
+        }))
+        .await
+        .expect("initialize response");
+    assert_eq!(
+        init.pointer("/result/protocolVersion")
+            .and_then(|v| v.as_str()),
+        Some("2024-11-05")
+    );
+
+    let notification = server
+        .handle_message(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+        .await;
+    assert!(notification.is_none());
+
+    let list = server
+        .handle_message(json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+        .await
+        .expect("tools/list response");
+    let tools = list
+        .pointer("/result/tools")
+        .and_then(|v| v.as_array())
+        .expect("tools array");
+    let names: Vec<&str> = tools
+        .iter()
+        .filter_map(|tool| tool.get("name").and_then(|v| v.as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "kb_search",
+            "kb_read",
+            "kb_create",
+            "kb_update",
+            "kb_patch",
+            "kb_list",
+            "kb_history",
+            "kb_diff",
+            "kb_revert",
+            "kb_blame",
+            "kb_links"
+        ]
+    );
+    let create = tools
+        .iter()
+        .find(|tool| tool.get("name").and_then(|v| v.as_str()) == Some("kb_create"))
+        .expect("kb_create tool");
+    assert_eq!(
+        create
+            .pointer("/annotations/readOnlyHint")
+            .and_then(|v| v.as_bool()),
+        Some(false)
+    );
+    assert!(create.pointer("/inputSchema/required").is_some());
+
+    let unknown = server
+        .handle_message(json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" }))
+        .await
+        .expect("error response");
+    assert_eq!(
+        unknown.pointer("/error/code").and_then(|v| v.as_i64()),
+        Some(-32601)
+    );
+}
+
+#[tokio::test]
+async fn mcp_server_tools_call_returns_envelope() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let server = test_server(&vault);
+
+    let created = server
+        .handle_message(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {
+                "name": "kb_create",
+                "arguments": { "path": "inbox/mcp.md", "content": "# From MCP\n" }
+            }
+        }))
+        .await
+        .expect("tools/call response");
+    assert_eq!(
+        created.pointer("/result/isError").and_then(|v| v.as_bool()),
+        Some(false)
+    );
+    assert_eq!(
+        created
+            .pointer("/result/structuredContent/proof/readback_ok")
+            .and_then(|v| v.as_bool()),
+        Some(true)
+    );
+    assert_eq!(
+        std::fs::read_to_string(vault.join("inbox/mcp.md")).expect("read created note"),
+        "# From MCP\n"
+    );
+
+    let missing = server
+        .handle_message(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": "kb_read", "arguments": { "path": "missing.md" } }
+        }))
+        .await
+        .expect("tools/call response");
+    assert_eq!(
+        missing.pointer("/result/isError").and_then(|v| v.as_bool()),
+        Some(true)
+    );
+
+    let hidden = server
+        .handle_message(json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": { "name": "web_search", "arguments": { "query": "x" } }
+        }))
+        .await
+        .expect("tools/call response");
+    assert_eq!(
+        hidden.pointer("/error/code").and_then(|v| v.as_i64()),
+        Some(-32602)
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn mcp_server_serves_newline_delimited_stream() {
+    let vault = temp_vault();
+    let server = test_server(&vault);
+    let input = concat!(
+        "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}\n",
+        "not json\n",
+        "{\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}\n",
+    );
+    let mut output = Vec::new();
+
+    server
+        .serve(input.as_bytes(), &mut output)
+        .await
+        .expect("serve stream");
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(output)
+        .expect("utf8 output")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].get("result"), Some(&json!({})));
+    assert_eq!(
+        lines[1].pointer("/error/code").and_then(|v| v.as_i64()),
+        Some(-32700)
+    );
+}
+
+#[tokio::test]
+async fn deferred_commits_leave_writes_to_the_run_commit() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: true,
+    };
+
+    let created = execute_tool(
+        &ctx,
+        "kb_create",
+        &json!({ "path": "note.md", "content": "v1" }),
+    )
+    .await;
+    assert_eq!(created.get("ok").and_then(|v| v.as_bool()), Some(true));
+    let updated = execute_tool(
+        &ctx,
+        "kb_update",
+        &json!({ "path": "note.md", "content": "v2" }),
+    )
+    .await;
+    assert_eq!(updated.get("ok").and_then(|v| v.as_bool()), Some(true));
+
+    assert_eq!(
+        std::fs::read_to_string(vault.join("note.md")).expect("read note"),
+        "v2"
+    );
+    assert!(!vault.join(".meld").join(".git").exists());
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn kb_revert_undoes_commit_and_reports_files() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+
+    crate::adapters::vault::write_note(&vault, "note.md", "line one\n").expect("write v1");
+    crate::adapters::git::auto_commit(&vault, "v1").expect("commit v1");
+    crate::adapters::vault::write_note(&vault, "note.md", "line one\nline two\n")
+        .expect("write v2");
+    crate::adapters::git::auto_commit(&vault, "v2").expect("commit v2");
+
+    let history = crate::adapters::git::get_history(&vault, None, Some(1)).expect("history");
+    let commit_id = history.first().expect("latest").id.clone();
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: Some("run-1"),
+        defer_commits: false,
+    };
+    let refused = execute_tool(&ctx, "kb_revert", &json!({ "commit_id": commit_id })).await;
+    assert_eq!(
+        refused.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("dry_run_unsupported")
+    );
+
+    let ctx = McpContext {
+        staged_run_id: None,
+        defer_commits: true,
+        ..ctx
+    };
+    let refused = execute_tool(&ctx, "kb_revert", &json!({ "commit_id": commit_id })).await;
+    assert_eq!(
+        refused.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("agent_run_unsupported")
+    );
+    assert_eq!(
+        std::fs::read_to_string(vault.join("note.md")).expect("read note"),
+        "line one\nline two\n"
+    );
+
+    let ctx = McpContext {
+        defer_commits: false,
+        ..ctx
+    };
+    let result = execute_tool(&ctx, "kb_revert", &json!({ "commit_id": commit_id })).await;
+    assert_eq!(result.get("ok").and_then(|v| v.as_bool()), Some(true));
+    assert_eq!(
+        result
+            .pointer("/proof/readback_ok")
+            .and_then(|v| v.as_bool()),
+        Some(true)
+    );
+    assert_eq!(
+        result
+            .pointer("/result/files/0/status")
+            .and_then(|v| v.as_str()),
+        Some("reverted")
+    );
+    assert_eq!(
+        result.pointer("/result/conflicts").and_then(|v| v.as_u64()),
+        Some(0)
+    );
+    assert_eq!(
+        std::fs::read_to_string(vault.join("note.md")).expect("read note"),
+        "line one\n"
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn private_notes_are_forbidden_to_agent_tools() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(vault.join(".meld")).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+    std::fs::write(
+        vault.join(".meld").join("config.toml"),
+        "private_folders = [\"journal\"]\n",
+    )
+    .expect("write config");
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\n").expect("seed plan");
+    crate::adapters::vault::write_note(&vault, "salary.md", "---\nai: false\n---\n# Salary\n")
+        .expect("seed private note");
+    crate::adapters::vault::write_note(&vault, "journal/today.md", "# Today\n")
+        .expect("seed journal");
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    for path in ["salary.md", "journal/today.md"] {
+        let read = execute_tool(&ctx, "kb_read", &json!({ "path": path })).await;
+        assert_eq!(read.get("ok").and_then(|v| v.as_bool()), Some(false));
+        assert_eq!(
+            read.pointer("/error/code").and_then(|v| v.as_str()),
+            Some("forbidden")
+        );
+        assert!(read.pointer("/result/content").is_none());
+
+        let update = execute_tool(
+            &ctx,
+            "kb_update",
+            &json!({ "path": path, "content": "overwritten" }),
+        )
+        .await;
+        assert_eq!(
+            update.pointer("/error/code").and_then(|v| v.as_str()),
+            Some("forbidden")
+        );
+    }
+    let create = execute_tool(
+        &ctx,
+        "kb_create",
+        &json!({ "path": "journal/tomorrow.md", "content": "# Tomorrow" }),
+    )
+    .await;
+    assert_eq!(
+        create.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("forbidden")
+    );
+    assert!(!vault.join("journal").join("tomorrow.md").exists());
+    assert_eq!(
+        std::fs::read_to_string(vault.join("salary.md")).expect("read private note"),
+        "---\nai: false\n---\n# Salary\n"
+    );
+
+    let list = execute_tool(&ctx, "kb_list", &json!({})).await;
+    assert_eq!(list.pointer("/result/notes"), Some(&json!(["plan.md"])));
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn private_notes_are_forbidden_in_branch_mode() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(vault.join(".meld")).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+    std::fs::write(
+        vault.join(".meld").join("config.toml"),
+        "private_folders = [\"journal\"]\n",
+    )
+    .expect("write config");
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\n").expect("seed plan");
+    crate::adapters::vault::write_note(&vault, "journal/today.md", "# Today\n")
+        .expect("seed journal");
+    let sandbox = crate::adapters::git::open_sandbox(&vault, 1).expect("open sandbox");
+    assert!(sandbox.join("plan.md").exists());
+    assert!(!sandbox.join("journal").join("today.md").exists());
+
+    let ctx = McpContext {
+        vault_path: &sandbox,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: true,
+    };
+
+    let read = execute_tool(&ctx, "kb_read", &json!({ "path": "journal/today.md" })).await;
+    assert_eq!(
+        read.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("forbidden")
+    );
+    let create = execute_tool(
+        &ctx,
+        "kb_create",
+        &json!({ "path": "journal/today.md", "content": "# Overwritten" }),
+    )
+    .await;
+    assert_eq!(
+        create.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("forbidden")
+    );
+    let list = execute_tool(&ctx, "kb_list", &json!({})).await;
+    assert_eq!(list.pointer("/result/notes"), Some(&json!(["plan.md"])));
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn history_tools_are_refused_in_branch_mode() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(vault.join(".meld")).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\n").expect("seed plan");
+    crate::adapters::git::auto_commit(&vault, "seed").expect("seed commit");
+    let commit_id = crate::adapters::git::get_history(&vault, None, Some(1)).expect("history")[0]
+        .id
+        .clone();
+    let sandbox = crate::adapters::git::open_sandbox(&vault, 1).expect("open sandbox");
+
+    let ctx = McpContext {
+        vault_path: &sandbox,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: true,
+    };
+
+    let calls = [
+        ("kb_history", json!({})),
+        ("kb_diff", json!({ "commit_id": commit_id })),
+        ("kb_revert", json!({ "commit_id": commit_id })),
+        ("kb_blame", json!({ "path": "plan.md" })),
+        (
+            "kb_read",
+            json!({ "path": "plan.md", "revision": commit_id }),
+        ),
+    ];
+    for (tool, args) in calls {
+        let result = execute_tool(&ctx, tool, &args).await;
+        assert_eq!(
+            result.pointer("/error/code").and_then(|v| v.as_str()),
+            Some("branch_mode_unsupported"),
+            "{tool}"
+        );
+    }
+    let read = execute_tool(&ctx, "kb_read", &json!({ "path": "plan.md" })).await;
+    assert_eq!(read.get("ok").and_then(|v| v.as_bool()), Some(true));
+    assert!(!sandbox.join(".meld").join(".git").exists());
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn notes_private_at_a_commit_stay_hidden_in_history() {
+    let _guard = test_guard();
+    let vault = temp_vault();
+    std::fs::create_dir_all(vault.join(".meld")).expect("create temp vault");
+    let db_path = vault.join(".meld").join("index.db");
+    crate::adapters::vault::write_note(&vault, "plan.md", "# Plan\n").expect("seed plan");
+    crate::adapters::vault::write_note(&vault, "salary.md", "# Salary #private\n")
+        .expect("seed private note");
+    crate::adapters::git::auto_commit(&vault, "seed salary.md").expect("commit v1");
+    crate::adapters::vault::write_note(&vault, "salary.md", "# Salary\n").expect("unmark");
+    crate::adapters::git::auto_commit(&vault, "unmark salary.md").expect("commit v2");
+    let history = crate::adapters::git::get_history(&vault, None, Some(1)).expect("history");
+    let unmarked = history[0].id.clone();
+
+    let ctx = McpContext {
+        vault_path: &vault,
+        vault_root: &vault,
+        db_path: &db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        staged_run_id: None,
+        defer_commits: false,
+    };
+
+    // The note is public now, but its parent content in this commit was private.
+    let diff = execute_tool(&ctx, "kb_diff", &json!({ "commit_id": unmarked })).await;
+    assert_eq!(
+        diff.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("forbidden")
+    );
+    assert!(diff.pointer("/result/patch").is_none());
+
+    let history = execute_tool(&ctx, "kb_history", &json!({})).await;
+    let commits = history
+        .pointer("/result/commits")
+        .and_then(|v| v.as_array())
+        .expect("commits");
+    for commit in commits {
+        let files = commit.get("files_changed").expect("files_changed");
+        assert!(!files.to_string().contains("salary.md"));
+        let message = commit.get("message").and_then(|v| v.as_str()).unwrap();
+        assert!(!message.contains("salary.md"));
+    }
+
+    let _ = std::fs::remove_dir_all(vault);
+}
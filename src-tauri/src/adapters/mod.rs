@@ -1,3 +1,4 @@
+pub mod approval;
 pub mod config;
 pub mod embeddings;
 pub mod emitter;
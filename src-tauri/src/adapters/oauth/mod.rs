@@ -511,6 +511,10 @@ pub async fn resolve_provider_credential(
         return Ok(refreshed.access_token);
     }
 
+    if !settings.provider_requires_api_key(&provider) {
+        return Ok(settings.api_key_for_provider(&provider).unwrap_or_default());
+    }
+
     settings.api_key_for_provider(&provider).ok_or_else(|| {
         format!(
             "No API key configured for provider '{}'. Add one in Settings or switch to OAuth.",
@@ -1,11 +1,15 @@
+use crate::adapters::config::{ProviderInstanceConfig, Settings};
 use crate::adapters::llm::{self, ChatMessage, StreamEvent, ToolDefinition};
 use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::LazyLock;
 use tokio::sync::mpsc;
 
 type DynError = Box<dyn std::error::Error + Send + Sync>;
 
+static NO_HEADERS: LazyLock<HashMap<String, String>> = LazyLock::new(HashMap::new);
+
 #[derive(Debug, Clone)]
 pub struct ChatRequest<'a> {
     pub api_key: &'a str,
@@ -36,7 +40,13 @@ pub trait LlmProvider: Send + Sync {
 
 pub trait EmbeddingProvider: Send + Sync {
     fn id(&self) -> &str;
+    /// Fixed output dimension, or 0 when it depends on the model (local runtimes) and has
+    /// to be probed with an embedding call.
     fn dimensions(&self) -> usize;
+    /// Largest number of texts sent in one `embed` call.
+    fn max_batch_size(&self) -> usize {
+        64
+    }
     fn embed<'a>(
         &'a self,
         request: EmbeddingRequest<'a>,
@@ -55,6 +65,7 @@ pub struct ProviderCatalogEntry {
 pub struct ProviderRegistry {
     llm: HashMap<String, Box<dyn LlmProvider>>,
     embedding: HashMap<String, Box<dyn EmbeddingProvider>>,
+    display_names: HashMap<String, String>,
 }
 
 impl Default for ProviderRegistry {
@@ -62,6 +73,7 @@ impl Default for ProviderRegistry {
         let mut registry = Self {
             llm: HashMap::new(),
             embedding: HashMap::new(),
+            display_names: HashMap::new(),
         };
 
         registry.register_llm(Box::new(OpenAiLlmProvider));
@@ -73,12 +85,49 @@ impl Default for ProviderRegistry {
 
         registry.register_embedding(Box::new(OpenAiEmbeddingProvider));
         registry.register_embedding(Box::new(GoogleEmbeddingProvider));
+        registry.register_embedding(Box::new(OllamaEmbeddingProvider));
+        registry.register_embedding(Box::new(LmStudioEmbeddingProvider));
 
         registry
     }
 }
 
 impl ProviderRegistry {
+    /// Built-in providers plus the provider instances from the global config.
+    pub fn configured() -> Self {
+        Self::with_instances(&Settings::load_global().provider_instances())
+    }
+
+    /// Built-in providers plus `instances`; an instance replaces a built-in with the same id.
+    pub fn with_instances(instances: &[ProviderInstanceConfig]) -> Self {
+        let mut registry = Self::default();
+        for instance in instances {
+            if instance.chat {
+                registry.register_llm(Box::new(OpenAiCompatibleProvider::new(instance)));
+            }
+            if instance.embeddings {
+                registry.register_embedding(Box::new(OpenAiCompatibleProvider::new(instance)));
+            }
+            if let Some(name) = instance
+                .display_name
+                .as_deref()
+                .filter(|name| !name.trim().is_empty())
+            {
+                registry
+                    .display_names
+                    .insert(instance.id.clone(), name.trim().to_string());
+            }
+        }
+        registry
+    }
+
+    fn display_name(&self, provider_id: &str) -> String {
+        self.display_names
+            .get(provider_id)
+            .cloned()
+            .unwrap_or_else(|| provider_display_name(provider_id))
+    }
+
     pub fn register_llm(&mut self, provider: Box<dyn LlmProvider>) {
         self.llm.insert(provider.id().to_string(), provider);
     }
@@ -120,7 +169,7 @@ impl ProviderRegistry {
                 .entry(id.clone())
                 .or_insert_with(|| ProviderCatalogEntry {
                     id: id.clone(),
-                    display_name: provider_display_name(&id),
+                    display_name: self.display_name(&id),
                     supports_llm: false,
                     supports_embeddings: false,
                     auth_modes: provider_auth_modes(&id),
@@ -134,7 +183,7 @@ impl ProviderRegistry {
                 .entry(id.clone())
                 .or_insert_with(|| ProviderCatalogEntry {
                     id: id.clone(),
-                    display_name: provider_display_name(&id),
+                    display_name: self.display_name(&id),
                     supports_llm: false,
                     supports_embeddings: false,
                     auth_modes: provider_auth_modes(&id),
@@ -184,6 +233,27 @@ fn provider_auth_modes(provider_id: &str) -> Vec<String> {
     }
 }
 
+/// Providers served from the user's machine; they work without an API key.
+pub fn is_local_provider(provider_id: &str) -> bool {
+    matches!(provider_id, "ollama" | "lm_studio")
+}
+
+/// Whether the local runtime `provider_id` answers at its default address, so it can be
+/// picked for indexing without the user naming it.
+pub async fn local_provider_reachable(provider_id: &str) -> bool {
+    let endpoint = match provider_id {
+        "ollama" => "http://localhost:11434/api/tags",
+        "lm_studio" => "http://localhost:1234/v1/models",
+        _ => return false,
+    };
+    reqwest::Client::new()
+        .get(endpoint)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
 pub fn split_model_id(model_id: &str) -> Result<(&str, &str), String> {
     let trimmed = model_id.trim();
     let (provider, model) = trimmed
@@ -202,6 +272,64 @@ pub fn split_model_id(model_id: &str) -> Result<(&str, &str), String> {
     Ok((provider, model))
 }
 
+/// Non-success response from an embedding API, with the provider's requested backoff.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} embedding API error ({status}): {body}")]
+pub struct EmbeddingApiError {
+    pub provider: String,
+    pub status: reqwest::StatusCode,
+    pub retry_after: Option<std::time::Duration>,
+    pub body: String,
+}
+
+impl EmbeddingApiError {
+    async fn from_response(provider: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let header_delay = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+        let retry_after = header_delay.or_else(|| parse_retry_delay_from_body(&body));
+        Self {
+            provider: provider.to_string(),
+            status,
+            retry_after,
+            body,
+        }
+    }
+
+    /// Rate limits, timeouts and server errors are worth retrying; other client errors are not.
+    pub fn is_retriable(&self) -> bool {
+        self.status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || self.status == reqwest::StatusCode::REQUEST_TIMEOUT
+            || self.status.is_server_error()
+    }
+}
+
+/// `Retry-After` as delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds.is_finite() && seconds >= 0.0)
+            .then(|| std::time::Duration::from_secs_f64(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = at.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or_default())
+}
+
+/// Google reports rate-limit backoff as `"retryDelay": "30s"` in the error details.
+fn parse_retry_delay_from_body(body: &str) -> Option<std::time::Duration> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let details = value.get("error")?.get("details")?.as_array()?;
+    details.iter().find_map(|detail| {
+        let delay = detail.get("retryDelay")?.as_str()?;
+        parse_retry_after(delay.trim().strip_suffix('s')?)
+    })
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct OpenAIEmbeddingRequest {
     input: Vec<String>,
@@ -215,11 +343,30 @@ struct OpenAIEmbeddingResponse {
 
 #[derive(Debug, Deserialize)]
 struct OpenAIEmbeddingData {
+    #[serde(default)]
+    index: usize,
     embedding: Vec<f32>,
 }
 
+#[derive(Debug, Serialize)]
+struct OllamaEmbeddingRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddingResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Serialize)]
+struct GoogleBatchEmbeddingRequest {
+    requests: Vec<GoogleEmbeddingRequest>,
+}
+
 #[derive(Debug, Serialize)]
 struct GoogleEmbeddingRequest {
+    model: String,
     content: GoogleEmbeddingContent,
 }
 
@@ -234,8 +381,8 @@ struct GoogleEmbeddingPart {
 }
 
 #[derive(Debug, Deserialize)]
-struct GoogleEmbeddingResponse {
-    embedding: GoogleEmbeddingValues,
+struct GoogleBatchEmbeddingResponse {
+    embeddings: Vec<GoogleEmbeddingValues>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -251,6 +398,81 @@ struct OllamaLlmProvider;
 struct LmStudioLlmProvider;
 struct OpenAiEmbeddingProvider;
 struct GoogleEmbeddingProvider;
+struct OllamaEmbeddingProvider;
+struct LmStudioEmbeddingProvider;
+
+/// A user-configured OpenAI-compatible endpoint; see [`ProviderInstanceConfig`].
+struct OpenAiCompatibleProvider {
+    id: String,
+    display_name: String,
+    chat_endpoint: String,
+    embeddings_endpoint: String,
+    headers: HashMap<String, String>,
+}
+
+impl OpenAiCompatibleProvider {
+    fn new(instance: &ProviderInstanceConfig) -> Self {
+        Self {
+            id: instance.id.clone(),
+            display_name: instance
+                .display_name
+                .clone()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| provider_display_name(&instance.id)),
+            chat_endpoint: instance.endpoint("chat/completions"),
+            embeddings_endpoint: instance.endpoint("embeddings"),
+            headers: instance.headers.clone(),
+        }
+    }
+}
+
+async fn send_embedding_request<T: Serialize>(
+    provider: &str,
+    endpoint: &str,
+    api_key: &str,
+    headers: &HashMap<String, String>,
+    body: &T,
+) -> Result<reqwest::Response, DynError> {
+    let mut request = reqwest::Client::new().post(endpoint).json(body);
+    if !api_key.trim().is_empty() {
+        request = request.bearer_auth(api_key.trim());
+    }
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach {provider} at {endpoint}: {e}"))?;
+    if !response.status().is_success() {
+        return Err(EmbeddingApiError::from_response(provider, response)
+            .await
+            .into());
+    }
+    Ok(response)
+}
+
+async fn openai_compatible_embeddings(
+    provider: &str,
+    endpoint: &str,
+    headers: &HashMap<String, String>,
+    request: EmbeddingRequest<'_>,
+) -> Result<Vec<Vec<f32>>, DynError> {
+    let response = send_embedding_request(
+        provider,
+        endpoint,
+        request.api_key,
+        headers,
+        &OpenAIEmbeddingRequest {
+            input: request.texts.to_vec(),
+            model: request.model.to_string(),
+        },
+    )
+    .await?;
+    let mut data = response.json::<OpenAIEmbeddingResponse>().await?.data;
+    data.sort_by_key(|item| item.index);
+    Ok(data.into_iter().map(|item| item.embedding).collect())
+}
 
 impl LlmProvider for OpenAiLlmProvider {
     fn id(&self) -> &str {
@@ -306,6 +528,7 @@ impl LlmProvider for OpenRouterLlmProvider {
                 request.tx,
                 "https://openrouter.ai/api/v1/chat/completions",
                 "OpenRouter",
+                &NO_HEADERS,
                 request.thinking_budget,
             )
             .await
@@ -347,6 +570,7 @@ impl LlmProvider for OllamaLlmProvider {
                 request.tx,
                 "http://localhost:11434/v1/chat/completions",
                 "Ollama",
+                &NO_HEADERS,
                 request.thinking_budget,
             )
             .await
@@ -369,6 +593,7 @@ impl LlmProvider for LmStudioLlmProvider {
                 request.tx,
                 "http://localhost:1234/v1/chat/completions",
                 "LM Studio",
+                &NO_HEADERS,
                 request.thinking_budget,
             )
             .await
@@ -385,39 +610,116 @@ impl EmbeddingProvider for OpenAiEmbeddingProvider {
         1536
     }
 
+    fn embed<'a>(
+        &'a self,
+        request: EmbeddingRequest<'a>,
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, DynError>> {
+        Box::pin(openai_compatible_embeddings(
+            "OpenAI",
+            "https://api.openai.com/v1/embeddings",
+            &NO_HEADERS,
+            request,
+        ))
+    }
+}
+
+impl EmbeddingProvider for OllamaEmbeddingProvider {
+    fn id(&self) -> &str {
+        "ollama"
+    }
+
+    fn dimensions(&self) -> usize {
+        0
+    }
+
     fn embed<'a>(
         &'a self,
         request: EmbeddingRequest<'a>,
     ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, DynError>> {
         Box::pin(async move {
-            let client = reqwest::Client::new();
-            let response = client
-                .post("https://api.openai.com/v1/embeddings")
-                .header("Authorization", format!("Bearer {}", request.api_key))
-                .json(&OpenAIEmbeddingRequest {
-                    input: request.texts.to_vec(),
+            let response = send_embedding_request(
+                "Ollama",
+                "http://localhost:11434/api/embed",
+                request.api_key,
+                &NO_HEADERS,
+                &OllamaEmbeddingRequest {
                     model: request.model.to_string(),
-                })
-                .send()
-                .await?;
+                    input: request.texts.to_vec(),
+                },
+            )
+            .await?;
+            Ok(response.json::<OllamaEmbeddingResponse>().await?.embeddings)
+        })
+    }
+}
 
-            if !response.status().is_success() {
-                let status = response.status();
-                let body = response.text().await.unwrap_or_default();
-                return Err(format!("OpenAI embedding API error ({status}): {body}").into());
-            }
+impl EmbeddingProvider for LmStudioEmbeddingProvider {
+    fn id(&self) -> &str {
+        "lm_studio"
+    }
 
-            let response = response.json::<OpenAIEmbeddingResponse>().await?;
-            let embeddings = response
-                .data
-                .into_iter()
-                .map(|item| item.embedding)
-                .collect::<Vec<_>>();
-            Ok(embeddings)
+    fn dimensions(&self) -> usize {
+        0
+    }
+
+    fn embed<'a>(
+        &'a self,
+        request: EmbeddingRequest<'a>,
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, DynError>> {
+        Box::pin(openai_compatible_embeddings(
+            "LM Studio",
+            "http://localhost:1234/v1/embeddings",
+            &NO_HEADERS,
+            request,
+        ))
+    }
+}
+
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn chat<'a>(&'a self, request: ChatRequest<'a>) -> BoxFuture<'a, Result<(), DynError>> {
+        Box::pin(async move {
+            llm::providers::openai::chat_stream_with_endpoint(
+                request.api_key,
+                request.model,
+                request.messages,
+                request.tools,
+                request.tx,
+                &self.chat_endpoint,
+                &self.display_name,
+                &self.headers,
+                request.thinking_budget,
+            )
+            .await
         })
     }
 }
 
+impl EmbeddingProvider for OpenAiCompatibleProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn dimensions(&self) -> usize {
+        0
+    }
+
+    fn embed<'a>(
+        &'a self,
+        request: EmbeddingRequest<'a>,
+    ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, DynError>> {
+        Box::pin(openai_compatible_embeddings(
+            &self.display_name,
+            &self.embeddings_endpoint,
+            &self.headers,
+            request,
+        ))
+    }
+}
+
 impl EmbeddingProvider for GoogleEmbeddingProvider {
     fn id(&self) -> &str {
         "google"
@@ -427,62 +729,86 @@ impl EmbeddingProvider for GoogleEmbeddingProvider {
         768
     }
 
+    fn max_batch_size(&self) -> usize {
+        100
+    }
+
     fn embed<'a>(
         &'a self,
         request: EmbeddingRequest<'a>,
     ) -> BoxFuture<'a, Result<Vec<Vec<f32>>, DynError>> {
         Box::pin(async move {
             let client = reqwest::Client::new();
-            let mut embeddings = Vec::with_capacity(request.texts.len());
-
-            for text in request.texts {
-                let base_url = format!(
-                    "https://generativelanguage.googleapis.com/v1beta/models/{}:embedContent",
-                    request.model
-                );
-
-                let request_body = GoogleEmbeddingRequest {
-                    content: GoogleEmbeddingContent {
-                        parts: vec![GoogleEmbeddingPart {
-                            text: text.to_string(),
-                        }],
-                    },
-                };
-
-                let response = if request.api_key.trim().starts_with("AIza") {
-                    let url_with_key = format!("{base_url}?key={}", request.api_key.trim());
-                    client
-                        .post(&url_with_key)
-                        .json(&request_body)
-                        .send()
-                        .await?
-                } else {
-                    client
-                        .post(&base_url)
-                        .bearer_auth(request.api_key.trim())
-                        .json(&request_body)
-                        .send()
-                        .await?
-                };
-
-                if !response.status().is_success() {
-                    let status = response.status();
-                    let body = response.text().await.unwrap_or_default();
-                    return Err(format!("Google embedding API error ({status}): {body}").into());
-                }
-
-                let response = response.json::<GoogleEmbeddingResponse>().await?;
-                embeddings.push(response.embedding.values);
+            let base_url = format!(
+                "https://generativelanguage.googleapis.com/v1beta/models/{}:batchEmbedContents",
+                request.model
+            );
+            let request_body = GoogleBatchEmbeddingRequest {
+                requests: request
+                    .texts
+                    .iter()
+                    .map(|text| GoogleEmbeddingRequest {
+                        model: format!("models/{}", request.model),
+                        content: GoogleEmbeddingContent {
+                            parts: vec![GoogleEmbeddingPart { text: text.clone() }],
+                        },
+                    })
+                    .collect(),
+            };
+
+            let response = if request.api_key.trim().starts_with("AIza") {
+                let url_with_key = format!("{base_url}?key={}", request.api_key.trim());
+                client
+                    .post(&url_with_key)
+                    .json(&request_body)
+                    .send()
+                    .await?
+            } else {
+                client
+                    .post(&base_url)
+                    .bearer_auth(request.api_key.trim())
+                    .json(&request_body)
+                    .send()
+                    .await?
+            };
+
+            if !response.status().is_success() {
+                return Err(EmbeddingApiError::from_response("Google", response)
+                    .await
+                    .into());
             }
 
-            Ok(embeddings)
+            let response = response.json::<GoogleBatchEmbeddingResponse>().await?;
+            Ok(response
+                .embeddings
+                .into_iter()
+                .map(|embedding| embedding.values)
+                .collect())
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ProviderRegistry;
+    use super::{parse_retry_after, parse_retry_delay_from_body, ProviderRegistry};
+    use std::time::Duration;
+
+    #[test]
+    fn parses_retry_after_seconds_dates_and_google_retry_delay() {
+        assert_eq!(parse_retry_after("12"), Some(Duration::from_secs(12)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let body = r#"{"error":{"code":429,"details":[{"@type":"type.googleapis.com/google.rpc.RetryInfo","retryDelay":"31s"}]}}"#;
+        assert_eq!(
+            parse_retry_delay_from_body(body),
+            Some(Duration::from_secs(31))
+        );
+        assert_eq!(parse_retry_delay_from_body("not json"), None);
+    }
 
     #[test]
     fn resolve_llm_openai_model_id() {
@@ -503,6 +829,66 @@ mod tests {
         assert_eq!(model, "gemini-embedding-001");
     }
 
+    #[test]
+    fn local_runtimes_are_embedding_providers() {
+        let registry = ProviderRegistry::default();
+        for model_id in ["ollama:nomic-embed-text", "lm_studio:text-embedding-nomic"] {
+            let (provider, _) = registry
+                .resolve_embedding(model_id)
+                .expect("resolve local embedding");
+            assert_eq!(provider.dimensions(), 0);
+            assert!(super::is_local_provider(provider.id()));
+        }
+        assert!(registry
+            .catalog()
+            .iter()
+            .filter(|entry| entry.id == "ollama" || entry.id == "lm_studio")
+            .all(|entry| entry.supports_llm && entry.supports_embeddings));
+    }
+
+    #[test]
+    fn provider_instances_register_and_override_builtins() {
+        let gpu_box = crate::adapters::config::ProviderInstanceConfig {
+            id: "ollama".to_string(),
+            display_name: None,
+            base_url: "http://gpu-box:11434/v1/".to_string(),
+            api_key: None,
+            headers: Default::default(),
+            chat: true,
+            embeddings: true,
+        };
+        let groq = crate::adapters::config::ProviderInstanceConfig {
+            id: "groq".to_string(),
+            display_name: Some("Groq".to_string()),
+            base_url: "https://api.groq.com/openai/v1".to_string(),
+            embeddings: false,
+            ..gpu_box.clone()
+        };
+        assert_eq!(
+            gpu_box.endpoint("chat/completions"),
+            "http://gpu-box:11434/v1/chat/completions"
+        );
+
+        let registry = ProviderRegistry::with_instances(&[gpu_box, groq]);
+        let (provider, model) = registry
+            .resolve_llm("groq:llama-3.3-70b")
+            .expect("resolve instance");
+        assert_eq!(provider.id(), "groq");
+        assert_eq!(model, "llama-3.3-70b");
+        assert!(registry.resolve_embedding("groq:any").is_err());
+        assert!(registry
+            .resolve_embedding("ollama:nomic-embed-text")
+            .is_ok());
+
+        let catalog = registry.catalog();
+        let groq_entry = catalog
+            .iter()
+            .find(|entry| entry.id == "groq")
+            .expect("groq entry");
+        assert_eq!(groq_entry.display_name, "Groq");
+        assert!(groq_entry.supports_llm && !groq_entry.supports_embeddings);
+    }
+
     #[test]
     fn catalog_includes_supported_capabilities() {
         let registry = ProviderRegistry::default();
@@ -164,11 +164,17 @@ pub async fn query(
     // Open DB in a blocking task to avoid Send issues with rusqlite
     let db_path = db_path.to_path_buf();
     let query_text = query.to_string();
+    let query_model_id = embedding_model_id.to_string();
     let chunks = tokio::task::spawn_blocking(move || {
         let db = VectorDb::open(&db_path)
             .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.to_string().into() })?;
-        db.search_hybrid(&retrieval_embedding, &query_text, retrieval_limit)
-            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.to_string().into() })
+        db.search_hybrid(
+            &retrieval_embedding,
+            &query_model_id,
+            &query_text,
+            retrieval_limit,
+        )
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.to_string().into() })
     })
     .await??;
 
@@ -0,0 +1,148 @@
+//! Which vault paths meld leaves alone. Hidden entries and `node_modules` are always
+//! skipped; on top of that come the patterns in the vault's `.meldignore` (gitignore
+//! syntax) and, when the vault config asks for them, the vault's `.gitignore` and
+//! Obsidian's excluded files. Excluded notes are not indexed, listed or watched.
+
+use std::path::{Component, Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::adapters::config::VaultConfig;
+
+const MELDIGNORE_FILE: &str = ".meldignore";
+const GITIGNORE_FILE: &str = ".gitignore";
+const OBSIDIAN_APP_CONFIG: &str = ".obsidian/app.json";
+const VAULT_CONFIG: &str = ".meld/config.toml";
+
+#[derive(Debug)]
+pub struct VaultIgnore {
+    root: PathBuf,
+    gitignores: Vec<Gitignore>,
+    /// Obsidian's `userIgnoreFilters`, which Obsidian matches as path prefixes.
+    obsidian_prefixes: Vec<String>,
+    /// The rule files' contents, to tell whether a reload changed anything.
+    sources: Vec<String>,
+}
+
+fn load_gitignore(vault_path: &Path, file: &str, sources: &mut Vec<String>) -> Option<Gitignore> {
+    let path = vault_path.join(file);
+    sources.push(std::fs::read_to_string(&path).unwrap_or_default());
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(vault_path);
+    if let Some(error) = builder.add(&path) {
+        log::warn!("Skipping invalid patterns in {}: {}", file, error);
+    }
+    match builder.build() {
+        Ok(gitignore) => Some(gitignore),
+        Err(error) => {
+            log::warn!("Failed to load {}: {}", file, error);
+            None
+        }
+    }
+}
+
+fn obsidian_excluded(vault_path: &Path) -> Vec<String> {
+    let Ok(raw) = std::fs::read_to_string(vault_path.join(OBSIDIAN_APP_CONFIG)) else {
+        return Vec::new();
+    };
+    let Ok(config) = serde_json::from_str::<serde_json::Value>(&raw) else {
+        return Vec::new();
+    };
+    config
+        .get("userIgnoreFilters")
+        .and_then(|filters| filters.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|filter| filter.as_str())
+        .map(str::trim)
+        // `/.../` filters are regular expressions, which are not supported.
+        .filter(|filter| {
+            !filter.is_empty()
+                && !(filter.len() > 1 && filter.starts_with('/') && filter.ends_with('/'))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_hidden_component(component: Component<'_>) -> bool {
+    match component {
+        Component::Normal(name) => {
+            let name = name.to_string_lossy();
+            name.starts_with('.') || name == "node_modules"
+        }
+        _ => false,
+    }
+}
+
+impl VaultIgnore {
+    pub fn load(vault_path: &Path) -> Self {
+        let config = VaultConfig::load(vault_path);
+        let mut files = vec![MELDIGNORE_FILE];
+        if config.respect_gitignore.unwrap_or(false) {
+            files.push(GITIGNORE_FILE);
+        }
+        let mut sources = Vec::new();
+        let gitignores = files
+            .into_iter()
+            .filter_map(|file| load_gitignore(vault_path, file, &mut sources))
+            .collect();
+        let obsidian_prefixes = if config.respect_obsidian_excluded.unwrap_or(false) {
+            obsidian_excluded(vault_path)
+        } else {
+            Vec::new()
+        };
+        Self {
+            root: vault_path.to_path_buf(),
+            gitignores,
+            obsidian_prefixes,
+            sources,
+        }
+    }
+
+    /// Whether `path`, absolute or relative to the vault, is excluded. Everything inside
+    /// an excluded folder is excluded too.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        if relative.as_os_str().is_empty() || relative.has_root() {
+            return false;
+        }
+        if relative.components().any(is_hidden_component) {
+            return true;
+        }
+        if self.gitignores.iter().any(|gitignore| {
+            gitignore
+                .matched_path_or_any_parents(relative, is_dir)
+                .is_ignore()
+        }) {
+            return true;
+        }
+
+        let mut normalized = relative.to_string_lossy().replace('\\', "/");
+        if is_dir {
+            normalized.push('/');
+        }
+        self.obsidian_prefixes
+            .iter()
+            .any(|prefix| normalized.starts_with(prefix.as_str()))
+    }
+
+    /// Whether `other` excludes the same paths, judged by the rules it was loaded from.
+    pub fn same_rules(&self, other: &Self) -> bool {
+        self.sources == other.sources && self.obsidian_prefixes == other.obsidian_prefixes
+    }
+
+    /// Whether `path` is a file the rules are read from, so changing it may change what is
+    /// excluded.
+    pub fn is_source(vault_path: &Path, path: &Path) -> bool {
+        [
+            MELDIGNORE_FILE,
+            GITIGNORE_FILE,
+            OBSIDIAN_APP_CONFIG,
+            VAULT_CONFIG,
+        ]
+        .iter()
+        .any(|file| path == vault_path.join(file))
+    }
+}
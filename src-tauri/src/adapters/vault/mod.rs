@@ -3,6 +3,12 @@ use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+mod ignore_rules;
+mod privacy;
+
+pub use ignore_rules::VaultIgnore;
+pub use privacy::PrivacyRules;
+
 pub const DEFAULT_AGENTS_MD_TEMPLATE: &str = r#"# Vault Rules
 
 This file tells the AI agent how to work with your knowledge base.
@@ -53,6 +59,7 @@ pub const DEFAULT_MELD_HINTS_TEMPLATE: &str = r#"# Navigation
 
 # Tools
 - kb_create for new notes, kb_update for existing. kb_create fails if file exists - read first, then decide.
+- For small edits to a long note (one paragraph, one section, tags), use kb_patch instead of re-sending the whole note with kb_update.
 - Before kb_create, do a quick kb_search (or kb_list) when topic overlap is possible to avoid duplicate notes.
 - Use the right tool for the task. Creating = kb_create. Finding info = kb_search. Reading a specific note = kb_read.
 - If one user message contains multiple independent ideas, split into separate notes (one idea = one note).
@@ -188,14 +195,32 @@ pub fn scan_vault(vault_path: &Path) -> Result<VaultInfo, Box<dyn std::error::Er
 }
 
 pub fn list_md_files(vault_path: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    list_md_files_in(vault_path, vault_path)
+}
+
+/// Markdown files under `folder`, leaving out what the vault's ignore rules exclude.
+pub fn list_md_files_in(
+    vault_path: &Path,
+    folder: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    walk_md_files(&VaultIgnore::load(vault_path), vault_path, folder)
+}
+
+/// Markdown files under `folder`, which lies in `base`: the vault or a worktree of it.
+/// `ignore` is matched against paths relative to `base`.
+fn walk_md_files(
+    ignore: &VaultIgnore,
+    base: &Path,
+    folder: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
     let mut files = Vec::new();
 
-    for entry in WalkDir::new(vault_path)
+    for entry in WalkDir::new(folder)
         .follow_links(true)
         .into_iter()
         .filter_entry(|e| {
-            let name = e.file_name().to_string_lossy();
-            !name.starts_with('.') && name != "node_modules"
+            let relative = e.path().strip_prefix(base).unwrap_or(e.path());
+            !ignore.is_ignored(relative, e.file_type().is_dir())
         })
     {
         let entry = entry?;
@@ -326,28 +351,100 @@ pub fn write_note(
     std::fs::write(full_path, content)
 }
 
+fn forbidden(relative_path: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        format!("Note {relative_path} is private and not available to the agent"),
+    )
+}
+
+/// Whether `error` refused access to a note: a private note, or a path outside the vault.
+pub fn is_forbidden(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|error| error.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+/// Refuse agent access to `relative_path` when the note is private, judged by the rules
+/// of the vault at `vault_root`: as it is in the vault now, as it is under `notes_path`
+/// (the vault, or a conversation branch's worktree of it), or because `content` about to
+/// be written or shown would make it private.
+pub fn ensure_agent_access(
+    vault_root: &Path,
+    notes_path: &Path,
+    relative_path: &str,
+    content: Option<&str>,
+) -> Result<(), std::io::Error> {
+    let privacy = PrivacyRules::load(vault_root);
+    if privacy.is_private_in(notes_path, relative_path)
+        || content.is_some_and(|content| privacy.marks_private(content))
+    {
+        return Err(forbidden(relative_path));
+    }
+    Ok(())
+}
+
+/// [`read_note`] for agent tools, which may not read private notes.
+pub fn read_agent_note(
+    vault_root: &Path,
+    notes_path: &Path,
+    relative_path: &str,
+) -> Result<String, std::io::Error> {
+    ensure_agent_access(vault_root, notes_path, relative_path, None)?;
+    read_note(notes_path, relative_path)
+}
+
+/// [`read_note_verification`] for agent tools, which may not probe private notes.
+pub fn read_agent_note_verification(
+    vault_root: &Path,
+    notes_path: &Path,
+    relative_path: &str,
+) -> Result<NoteVerification, Box<dyn std::error::Error>> {
+    ensure_agent_access(vault_root, notes_path, relative_path, None)?;
+    read_note_verification(notes_path, relative_path)
+}
+
+/// [`write_note`] for agent tools, which may neither overwrite a private note nor write
+/// one.
+pub fn write_agent_note(
+    vault_root: &Path,
+    notes_path: &Path,
+    relative_path: &str,
+    content: &str,
+) -> Result<(), std::io::Error> {
+    ensure_agent_access(vault_root, notes_path, relative_path, Some(content))?;
+    write_note(notes_path, relative_path, content)
+}
+
+/// Notes the agent may see under `subfolder` of `notes_path`, as relative paths. The
+/// ignore and privacy rules of the vault at `vault_root` apply, so private notes are left
+/// out.
 pub fn list_notes(
-    vault_path: &Path,
+    vault_root: &Path,
+    notes_path: &Path,
     subfolder: Option<&str>,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let search_path = match subfolder {
-        Some(folder) => vault_path.join(folder),
-        None => vault_path.to_path_buf(),
+        Some(folder) => notes_path.join(folder),
+        None => notes_path.to_path_buf(),
     };
 
-    let files = list_md_files(&search_path)?;
+    let privacy = PrivacyRules::load(vault_root);
+    let files = walk_md_files(&VaultIgnore::load(vault_root), notes_path, &search_path)?;
     Ok(files
         .iter()
-        .filter_map(|f| f.strip_prefix(vault_path).ok())
+        .filter_map(|f| f.strip_prefix(notes_path).ok())
         .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !privacy.is_private_in(notes_path, p))
         .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        ensure_vault_initialized, normalize_note_path, read_agents_md, read_meld_hints,
-        read_meld_rules, DEFAULT_AGENTS_MD_TEMPLATE, DEFAULT_MELD_HINTS_TEMPLATE,
+        ensure_vault_initialized, is_forbidden, list_md_files, list_notes, normalize_note_path,
+        read_agent_note, read_agents_md, read_meld_hints, read_meld_rules, write_agent_note,
+        PrivacyRules, DEFAULT_AGENTS_MD_TEMPLATE, DEFAULT_MELD_HINTS_TEMPLATE,
     };
 
     fn temp_vault() -> std::path::PathBuf {
@@ -441,4 +538,119 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(vault);
     }
+
+    #[test]
+    fn ignore_rules_leave_notes_out_of_listings() {
+        let vault = temp_vault();
+        for note in [
+            "keep.md",
+            "private/secret.md",
+            "exports/big.md",
+            "Templates/daily.md",
+            "notes/draft.md",
+            ".archive/old.md",
+        ] {
+            let path = vault.join(note);
+            std::fs::create_dir_all(path.parent().expect("parent")).expect("create dir");
+            std::fs::write(path, "# note").expect("write note");
+        }
+        std::fs::write(vault.join(".meldignore"), "private/\n*draft.md\n").expect("meldignore");
+        std::fs::write(vault.join(".gitignore"), "exports/\n").expect("gitignore");
+        std::fs::create_dir_all(vault.join(".obsidian")).expect("obsidian dir");
+        std::fs::write(
+            vault.join(".obsidian").join("app.json"),
+            r#"{"userIgnoreFilters":["Templates/","/^re.*gex$/"]}"#,
+        )
+        .expect("obsidian config");
+
+        let listed = |vault: &std::path::Path| {
+            let mut notes = list_notes(vault, vault, None).expect("list notes");
+            notes.sort();
+            notes
+        };
+        // `.gitignore` and Obsidian's filters only apply when the vault config opts in.
+        assert_eq!(
+            listed(&vault),
+            vec!["Templates/daily.md", "exports/big.md", "keep.md"]
+        );
+
+        std::fs::create_dir_all(vault.join(".meld")).expect("create meld dir");
+        std::fs::write(
+            vault.join(".meld").join("config.toml"),
+            "respect_gitignore = true\nrespect_obsidian_excluded = true\n",
+        )
+        .expect("write config");
+        assert_eq!(listed(&vault), vec!["keep.md"]);
+        assert_eq!(list_md_files(&vault).expect("list files").len(), 1);
+        assert!(list_notes(&vault, &vault, Some("private"))
+            .expect("list folder")
+            .is_empty());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn privacy_rules_match_frontmatter_tags_and_folders() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(vault.join(".meld")).expect("create meld dir");
+        let privacy = PrivacyRules::load(&vault);
+
+        assert!(privacy.marks_private("---\nai: false\n---\n# Diary"));
+        assert!(privacy.marks_private("---\ntags: [work, Private]\n---\n"));
+        assert!(privacy.marks_private("Salary talk #private/finance today"));
+        assert!(!privacy.marks_private("---\nai: true\n---\n# Private matters"));
+        assert!(!privacy.marks_private("See https://example.com/#private and #privateer"));
+        assert!(!privacy.marks_private("```\n#private\n```\n"));
+        assert!(!privacy.in_private_folder("journal/today.md"));
+
+        std::fs::write(
+            vault.join(".meld").join("config.toml"),
+            "private_tags = [\"#secret\"]\nprivate_folders = [\"./journal/\"]\n",
+        )
+        .expect("write config");
+        let privacy = PrivacyRules::load(&vault);
+        assert!(privacy.marks_private("#secret"));
+        assert!(!privacy.marks_private("#private"));
+        assert!(privacy.in_private_folder("journal/2024/today.md"));
+        assert!(!privacy.in_private_folder("journals/today.md"));
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn agent_access_refuses_private_notes() {
+        let vault = temp_vault();
+        std::fs::create_dir_all(vault.join("journal")).expect("create journal");
+        std::fs::create_dir_all(vault.join(".meld")).expect("create meld dir");
+        std::fs::write(
+            vault.join(".meld").join("config.toml"),
+            "private_folders = [\"journal\"]\n",
+        )
+        .expect("write config");
+        std::fs::write(vault.join("plan.md"), "# Plan").expect("write plan");
+        std::fs::write(vault.join("diary.md"), "---\nai: false\n---\n# Diary").expect("diary");
+        std::fs::write(vault.join("journal").join("today.md"), "# Today").expect("journal");
+
+        assert_eq!(
+            read_agent_note(&vault, &vault, "plan.md").expect("read plan"),
+            "# Plan"
+        );
+        for note in ["diary.md", "journal/today.md"] {
+            let error = read_agent_note(&vault, &vault, note).expect_err("private note is refused");
+            assert!(is_forbidden(&error));
+            assert!(write_agent_note(&vault, &vault, note, "overwritten").is_err());
+        }
+        assert!(write_agent_note(&vault, &vault, "journal/new.md", "# New").is_err());
+        assert!(write_agent_note(&vault, &vault, "plan.md", "# Plan #private").is_err());
+        assert_eq!(
+            std::fs::read_to_string(vault.join("diary.md")).expect("read diary"),
+            "---\nai: false\n---\n# Diary"
+        );
+        assert_eq!(
+            list_notes(&vault, &vault, None).expect("list notes"),
+            vec!["plan.md"]
+        );
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
 }
@@ -0,0 +1,176 @@
+//! Notes the agent must not see. A note is private when its frontmatter sets `ai: false`,
+//! when it carries one of the vault's private tags (`#private` unless the vault config
+//! names others), in its frontmatter `tags` or inline, or when it sits in one of the
+//! vault's private folders. Private notes are never embedded, and the agent tools can
+//! neither list, search, read nor write them.
+
+use std::path::{Path, PathBuf};
+
+use crate::adapters::config::VaultConfig;
+
+const DEFAULT_PRIVATE_TAG: &str = "private";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivacyRules {
+    root: PathBuf,
+    /// Vault-relative folders without leading or trailing slashes.
+    folders: Vec<String>,
+    /// Lowercase tags without the leading `#`.
+    tags: Vec<String>,
+}
+
+fn normalize_folder(raw: &str) -> String {
+    raw.trim()
+        .replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_matches('/')
+        .to_string()
+}
+
+fn normalize_tag(raw: &str) -> String {
+    raw.trim().trim_start_matches('#').to_lowercase()
+}
+
+fn is_tag_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '/')
+}
+
+/// Parsed frontmatter, if the note starts with a `---` block.
+fn frontmatter(markdown: &str) -> Option<serde_yaml_ng::Value> {
+    let mut lines = markdown.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return None;
+    }
+    let yaml: Vec<&str> = lines.take_while(|line| line.trim_end() != "---").collect();
+    serde_yaml_ng::from_str(&yaml.join("\n")).ok()
+}
+
+/// Frontmatter `tags`, as a list or a comma or space separated string.
+fn frontmatter_tags(frontmatter: &serde_yaml_ng::Value) -> Vec<String> {
+    match frontmatter.get("tags") {
+        Some(serde_yaml_ng::Value::String(tags)) => tags
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .map(normalize_tag)
+            .collect(),
+        Some(serde_yaml_ng::Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(normalize_tag)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `#tags` in the note body, outside fenced code blocks. A `#` only starts a tag at the
+/// beginning of a word, so headings and URL fragments are not mistaken for one.
+fn inline_tags(markdown: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut in_code_block = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let mut previous = ' ';
+        for (index, ch) in line.char_indices() {
+            if ch == '#' && previous.is_whitespace() {
+                let tag: String = line[index + 1..]
+                    .chars()
+                    .take_while(|&ch| is_tag_char(ch))
+                    .collect();
+                if !tag.is_empty() {
+                    tags.push(tag.to_lowercase());
+                }
+            }
+            previous = ch;
+        }
+    }
+    tags
+}
+
+impl PrivacyRules {
+    pub fn load(vault_path: &Path) -> Self {
+        let config = VaultConfig::load(vault_path);
+        let folders = config
+            .private_folders
+            .unwrap_or_default()
+            .iter()
+            .map(|folder| normalize_folder(folder))
+            .filter(|folder| !folder.is_empty())
+            .collect();
+        let tags = config
+            .private_tags
+            .unwrap_or_else(|| vec![DEFAULT_PRIVATE_TAG.to_string()])
+            .iter()
+            .map(|tag| normalize_tag(tag))
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        Self {
+            root: vault_path.to_path_buf(),
+            folders,
+            tags,
+        }
+    }
+
+    /// Whether the vault-relative `relative_path` is inside a private folder.
+    pub fn in_private_folder(&self, relative_path: &str) -> bool {
+        let path = normalize_folder(relative_path);
+        self.folders.iter().any(|folder| {
+            path.strip_prefix(folder.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Whether `content` marks its note private: `ai: false` or a private tag. Nested
+    /// tags count too, so `#private/finance` is private.
+    pub fn marks_private(&self, content: &str) -> bool {
+        let frontmatter = frontmatter(content);
+        if frontmatter
+            .as_ref()
+            .and_then(|value| value.get("ai"))
+            .and_then(|value| value.as_bool())
+            == Some(false)
+        {
+            return true;
+        }
+        let mut tags = inline_tags(content);
+        if let Some(frontmatter) = &frontmatter {
+            tags.extend(frontmatter_tags(frontmatter));
+        }
+        tags.iter().any(|tag| {
+            self.tags.iter().any(|private| {
+                tag == private
+                    || tag
+                        .strip_prefix(private.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+    }
+
+    pub fn is_private(&self, relative_path: &str, content: &str) -> bool {
+        self.in_private_folder(relative_path) || self.marks_private(content)
+    }
+
+    /// Whether the note at `relative_path` is private as it is in the vault now. A note
+    /// that does not exist yet is private only through its folder.
+    pub fn is_private_note(&self, relative_path: &str) -> bool {
+        self.is_private_in(&self.root, relative_path)
+    }
+
+    /// Whether the note at `relative_path` is private as it is in the vault now or as it
+    /// is under `notes_path`, such as a conversation branch's worktree of the vault.
+    pub fn is_private_in(&self, notes_path: &Path, relative_path: &str) -> bool {
+        if self.in_private_folder(relative_path) {
+            return true;
+        }
+        let marks_private = |dir: &Path| {
+            std::fs::read_to_string(dir.join(relative_path))
+                .is_ok_and(|content| self.marks_private(&content))
+        };
+        marks_private(&self.root) || (notes_path != self.root && marks_private(notes_path))
+    }
+}
@@ -0,0 +1,271 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::VectorDb;
+use crate::adapters::markdown::links::WikiLink;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct NoteLink {
+    pub source_path: String,
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    pub embed: bool,
+    pub resolved_path: Option<String>,
+}
+
+fn note_key(path: &str) -> String {
+    let lower = path.trim().trim_start_matches('/').to_lowercase();
+    lower
+        .strip_suffix(".md")
+        .map(str::to_string)
+        .unwrap_or(lower)
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Name → note lookup for link resolution: full path, file stem, then frontmatter alias.
+struct LinkResolver {
+    by_path: HashMap<String, String>,
+    by_stem: HashMap<String, Vec<String>>,
+    by_alias: HashMap<String, Vec<String>>,
+}
+
+impl LinkResolver {
+    fn new(notes: Vec<(String, Vec<String>)>) -> Self {
+        let mut resolver = Self {
+            by_path: HashMap::new(),
+            by_stem: HashMap::new(),
+            by_alias: HashMap::new(),
+        };
+        for (path, aliases) in notes {
+            let key = note_key(&path);
+            let stem = key.rsplit('/').next().unwrap_or(&key).to_string();
+            resolver.by_stem.entry(stem).or_default().push(path.clone());
+            for alias in aliases {
+                resolver
+                    .by_alias
+                    .entry(alias.trim().to_lowercase())
+                    .or_default()
+                    .push(path.clone());
+            }
+            resolver.by_path.insert(key, path);
+        }
+        resolver
+    }
+
+    /// Same folder as the source wins, then the shortest path (closest to the root).
+    fn pick(candidates: &[String], source_path: &str) -> Option<String> {
+        let source_dir = parent_dir(source_path);
+        candidates
+            .iter()
+            .min_by_key(|path| (parent_dir(path) != source_dir, path.len(), path.as_str()))
+            .cloned()
+    }
+
+    fn resolve(&self, source_path: &str, target: &str) -> Option<String> {
+        let key = note_key(target);
+        if key.is_empty() {
+            return Some(source_path.to_string());
+        }
+        if let Some(path) = self.by_path.get(&key) {
+            return Some(path.clone());
+        }
+        if key.contains('/') {
+            let suffix = format!("/{key}");
+            let candidates: Vec<String> = self
+                .by_path
+                .iter()
+                .filter(|(candidate, _)| candidate.ends_with(&suffix))
+                .map(|(_, path)| path.clone())
+                .collect();
+            return Self::pick(&candidates, source_path);
+        }
+        if let Some(candidates) = self.by_stem.get(&key) {
+            return Self::pick(candidates, source_path);
+        }
+        self.by_alias
+            .get(&target.trim().to_lowercase())
+            .and_then(|candidates| Self::pick(candidates, source_path))
+    }
+}
+
+fn row_to_link(row: &rusqlite::Row<'_>) -> rusqlite::Result<NoteLink> {
+    Ok(NoteLink {
+        source_path: row.get(0)?,
+        target: row.get(1)?,
+        heading: row.get(2)?,
+        alias: row.get(3)?,
+        embed: row.get::<_, i64>(4)? == 1,
+        resolved_path: row.get(5)?,
+    })
+}
+
+const LINK_COLUMNS: &str = "source_path, target, heading, alias, is_embed, resolved_path";
+
+impl VectorDb {
+    pub fn link_note_is_current(&self, path: &str, hash: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT hash FROM link_notes WHERE path = ?1",
+                params![path],
+                |row| row.get::<_, String>(0),
+            )
+            .map(|h| h == hash)
+            .unwrap_or(false)
+    }
+
+    /// Replace a note's outgoing links and aliases. Call [`VectorDb::resolve_links`] once
+    /// all notes are stored, since resolution depends on the whole vault.
+    pub fn replace_note_links(
+        &mut self,
+        path: &str,
+        hash: &str,
+        aliases: &[String],
+        links: &[WikiLink],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM links WHERE source_path = ?1", params![path])?;
+        for link in links {
+            tx.execute(
+                "INSERT INTO links (source_path, target, heading, alias, is_embed)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    path,
+                    link.target,
+                    link.heading,
+                    link.alias,
+                    link.embed as i64
+                ],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO link_notes (path, hash, aliases) VALUES (?1, ?2, ?3)",
+            params![path, hash, serde_json::to_string(aliases)?],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn remove_note_links(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.conn
+            .execute("DELETE FROM links WHERE source_path = ?1", params![path])?;
+        self.conn
+            .execute("DELETE FROM link_notes WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    pub fn list_link_notes(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM link_notes ORDER BY path ASC")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    /// Recompute `resolved_path` for every stored link. Returns the number of unresolved links.
+    pub fn resolve_links(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        let notes = {
+            let mut stmt = self.conn.prepare("SELECT path, aliases FROM link_notes")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            let mut notes = Vec::new();
+            for row in rows {
+                let (path, aliases) = row?;
+                let aliases: Vec<String> = serde_json::from_str(&aliases).unwrap_or_default();
+                notes.push((path, aliases));
+            }
+            notes
+        };
+        let resolver = LinkResolver::new(notes);
+
+        let tx = self.conn.transaction()?;
+        let links = {
+            let mut stmt = tx.prepare("SELECT id, source_path, target FROM links")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?;
+            let mut links = Vec::new();
+            for row in rows {
+                links.push(row?);
+            }
+            links
+        };
+
+        let mut unresolved = 0usize;
+        for (id, source_path, target) in links {
+            let resolved = resolver.resolve(&source_path, &target);
+            if resolved.is_none() {
+                unresolved += 1;
+            }
+            tx.execute(
+                "UPDATE links SET resolved_path = ?1 WHERE id = ?2",
+                params![resolved, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(unresolved)
+    }
+
+    fn query_links(
+        &self,
+        sql_filter: &str,
+        args: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<NoteLink>, Box<dyn std::error::Error>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {LINK_COLUMNS} FROM links WHERE {sql_filter} ORDER BY source_path ASC, id ASC"
+        ))?;
+        let rows = stmt.query_map(args, row_to_link)?;
+        let mut links = Vec::new();
+        for row in rows {
+            links.push(row?);
+        }
+        Ok(links)
+    }
+
+    pub fn outgoing_links(&self, path: &str) -> Result<Vec<NoteLink>, Box<dyn std::error::Error>> {
+        self.query_links("source_path = ?1", &[&path])
+    }
+
+    pub fn backlinks(&self, path: &str) -> Result<Vec<NoteLink>, Box<dyn std::error::Error>> {
+        self.query_links("resolved_path = ?1 AND source_path != ?1", &[&path])
+    }
+
+    pub fn unresolved_links(&self) -> Result<Vec<NoteLink>, Box<dyn std::error::Error>> {
+        self.query_links("resolved_path IS NULL", &[])
+    }
+
+    /// Notes with no resolved links in either direction (self-links do not count).
+    pub fn orphan_notes(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT n.path FROM link_notes n
+             WHERE NOT EXISTS (
+                SELECT 1 FROM links l
+                WHERE l.resolved_path IS NOT NULL
+                  AND l.resolved_path != l.source_path
+                  AND (l.source_path = n.path OR l.resolved_path = n.path)
+             )
+             ORDER BY n.path ASC",
+        )?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+}
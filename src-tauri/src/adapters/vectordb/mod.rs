@@ -7,9 +7,15 @@ use std::path::{Path, PathBuf};
 use crate::adapters::llm::TokenUsage;
 use crate::adapters::providers::split_model_id;
 use crate::core::agent::state::AgentState;
-use crate::core::ports::store::{RunFinishRecord, RunStartRecord, StorePort};
+use crate::core::ports::store::{HistoryMessage, RunFinishRecord, RunStartRecord, StorePort};
 
+mod links;
 mod math;
+mod staging;
+mod vec_index;
+
+pub use links::NoteLink;
+pub use staging::StagedChange;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChunkResult {
@@ -44,6 +50,8 @@ pub struct FolderSummary {
     pub icon: Option<String>,
     pub custom_instruction: Option<String>,
     pub default_model_id: Option<String>,
+    /// Agent runs in this folder's conversations write to a per-conversation branch.
+    pub branch_mode: bool,
     pub parent_id: Option<String>,
     pub pinned: bool,
     pub archived: bool,
@@ -178,15 +186,30 @@ impl StorePort for SqliteRunStore {
             );
         }
     }
+
+    fn conversation_history(&self, conversation_id: i64) -> Vec<HistoryMessage> {
+        VectorDb::open(&self.db_path)
+            .and_then(|db| db.get_conversation_messages(conversation_id))
+            .map(|messages| {
+                messages
+                    .into_iter()
+                    .map(|message| HistoryMessage {
+                        role: message.role,
+                        content: message.content,
+                        tool_calls: message.tool_calls,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 }
 
 impl VectorDb {
     pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        vec_index::register_sqlite_vec();
         let conn = Connection::open(path)?;
         conn.busy_timeout(std::time::Duration::from_secs(5))?;
 
-        // Load sqlite-vec extension
-        // For now, we use standard tables — sqlite-vec will be loaded when available
         conn.execute_batch(
             "
             PRAGMA journal_mode = WAL;
@@ -279,7 +302,37 @@ impl VectorDb {
                 FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE SET NULL
             );
 
+            CREATE TABLE IF NOT EXISTS link_notes (
+                path TEXT PRIMARY KEY,
+                hash TEXT NOT NULL,
+                aliases TEXT NOT NULL DEFAULT '[]'
+            );
+
+            CREATE TABLE IF NOT EXISTS links (
+                id INTEGER PRIMARY KEY,
+                source_path TEXT NOT NULL,
+                target TEXT NOT NULL,
+                heading TEXT,
+                alias TEXT,
+                is_embed INTEGER NOT NULL DEFAULT 0,
+                resolved_path TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS staged_changes (
+                run_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                action TEXT NOT NULL,
+                hash_before TEXT,
+                hash_after TEXT NOT NULL,
+                content TEXT NOT NULL,
+                diff TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (run_id, path)
+            );
+
             CREATE INDEX IF NOT EXISTS idx_chunks_file ON chunks(file_path);
+            CREATE INDEX IF NOT EXISTS idx_links_source ON links(source_path);
+            CREATE INDEX IF NOT EXISTS idx_links_resolved ON links(resolved_path);
             CREATE INDEX IF NOT EXISTS idx_messages_conv ON messages(conversation_id);
             CREATE INDEX IF NOT EXISTS idx_messages_created ON messages(created_at);
             CREATE INDEX IF NOT EXISTS idx_run_events_run ON run_events(run_id);
@@ -332,6 +385,12 @@ impl VectorDb {
         ignore_duplicate_column_error(
             conn.execute("ALTER TABLE chunks ADD COLUMN heading_path TEXT", []),
         )?;
+        ignore_duplicate_column_error(
+            conn.execute("ALTER TABLE chunks ADD COLUMN embedding_model TEXT", []),
+        )?;
+        ignore_duplicate_column_error(
+            conn.execute("ALTER TABLE chunks ADD COLUMN embedding_dim INTEGER", []),
+        )?;
         ignore_duplicate_column_error(
             conn.execute("ALTER TABLE runs ADD COLUMN policy_fingerprint TEXT", []),
         )?;
@@ -342,6 +401,14 @@ impl VectorDb {
         ignore_duplicate_column_error(
             conn.execute("ALTER TABLE folders ADD COLUMN default_model_id TEXT", []),
         )?;
+        ignore_duplicate_column_error(conn.execute(
+            "ALTER TABLE folders ADD COLUMN branch_mode INTEGER NOT NULL DEFAULT 0",
+            [],
+        ))?;
+        ignore_duplicate_column_error(conn.execute(
+            "ALTER TABLE conversations ADD COLUMN branch_mode INTEGER NOT NULL DEFAULT 0",
+            [],
+        ))?;
 
         sync_chunks_fts(&conn)?;
 
@@ -446,17 +513,31 @@ impl VectorDb {
         Ok(Self { conn })
     }
 
-    pub fn file_is_current(&self, file_path: &str, hash: &str) -> bool {
+    /// True when the file was indexed at `hash` and every chunk was embedded by
+    /// `embedding_model_id`. Chunks from another (or an unrecorded) model are stale.
+    pub fn file_is_current(&self, file_path: &str, hash: &str, embedding_model_id: &str) -> bool {
         self.conn
             .query_row(
-                "SELECT hash FROM files WHERE path = ?1",
-                params![file_path],
-                |row| row.get::<_, String>(0),
+                "SELECT hash = ?2 AND NOT EXISTS (
+                    SELECT 1 FROM chunks
+                    WHERE file_path = ?1
+                      AND embedding IS NOT NULL
+                      AND (embedding_model IS NULL OR embedding_model != ?3)
+                 )
+                 FROM files WHERE path = ?1",
+                params![file_path, hash, embedding_model_id],
+                |row| row.get::<_, bool>(0),
             )
-            .map(|h| h == hash)
             .unwrap_or(false)
     }
 
+    /// Bring the vector index in line with `chunks`. Runs once per full reindex rather
+    /// than on every open, since it counts both tables.
+    pub fn sync_vector_index(&self) -> Result<(), Box<dyn std::error::Error>> {
+        vec_index::sync(&self.conn)?;
+        Ok(())
+    }
+
     pub fn remove_file_chunks(
         &mut self,
         file_path: &str,
@@ -467,6 +548,7 @@ impl VectorDb {
                 params![file_path],
             )?;
         }
+        vec_index::delete_file(&self.conn, file_path)?;
         self.conn.execute(
             "DELETE FROM chunks WHERE file_path = ?1",
             params![file_path],
@@ -476,10 +558,77 @@ impl VectorDb {
         Ok(())
     }
 
+    /// Indexed paths whose content hashed to `hash`.
+    pub fn indexed_paths_with_hash(
+        &self,
+        hash: &str,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM files WHERE hash = ?1 ORDER BY path ASC")?;
+        let rows = stmt.query_map(params![hash], |row| row.get::<_, String>(0))?;
+        let mut paths = Vec::new();
+        for row in rows {
+            paths.push(row?);
+        }
+        Ok(paths)
+    }
+
+    /// Re-key the index of a renamed note from `from` to `to`, keeping its embeddings.
+    /// Whatever was indexed at `to` is replaced. Returns false when `from` is not indexed.
+    pub fn move_file_chunks(
+        &mut self,
+        from: &str,
+        to: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let has_fts = table_exists(&self.conn, "chunks_fts")?;
+        let tx = self.conn.transaction()?;
+
+        let indexed: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM files WHERE path = ?1)",
+            params![from],
+            |row| row.get(0),
+        )?;
+        if !indexed || from == to {
+            return Ok(indexed);
+        }
+
+        if has_fts {
+            tx.execute(
+                "DELETE FROM chunks_fts WHERE rowid IN (SELECT id FROM chunks WHERE file_path = ?1)",
+                params![to],
+            )?;
+        }
+        vec_index::delete_file(&tx, to)?;
+        tx.execute("DELETE FROM chunks WHERE file_path = ?1", params![to])?;
+        tx.execute("DELETE FROM files WHERE path = ?1", params![to])?;
+
+        // Vector rows are keyed by chunk id and follow the chunks without a rewrite.
+        tx.execute(
+            "UPDATE chunks SET file_path = ?2 WHERE file_path = ?1",
+            params![from, to],
+        )?;
+        if has_fts {
+            tx.execute(
+                "UPDATE chunks_fts SET file_path = ?1
+                 WHERE rowid IN (SELECT id FROM chunks WHERE file_path = ?1)",
+                params![to],
+            )?;
+        }
+        tx.execute(
+            "UPDATE files SET path = ?2 WHERE path = ?1",
+            params![from, to],
+        )?;
+
+        tx.commit()?;
+        Ok(true)
+    }
+
     pub fn replace_file_chunks_atomically(
         &mut self,
         file_path: &str,
         hash: &str,
+        embedding_model_id: &str,
         chunks: &[PreparedChunkEmbedding],
     ) -> Result<(), Box<dyn std::error::Error>> {
         let has_fts = table_exists(&self.conn, "chunks_fts")?;
@@ -491,22 +640,24 @@ impl VectorDb {
                 params![file_path],
             )?;
         }
+        vec_index::delete_file(&tx, file_path)?;
         tx.execute(
             "DELETE FROM chunks WHERE file_path = ?1",
             params![file_path],
         )?;
         tx.execute("DELETE FROM files WHERE path = ?1", params![file_path])?;
 
+        let vec_dimension = match chunks.first().map(|chunk| chunk.embedding.len()) {
+            Some(dimension) if vec_index::ensure_index(&tx, dimension)? => Some(dimension),
+            _ => None,
+        };
+
         for chunk in chunks {
-            let embedding_bytes: Vec<u8> = chunk
-                .embedding
-                .iter()
-                .flat_map(|value| value.to_le_bytes())
-                .collect();
+            let embedding_bytes = vec_index::embedding_to_bytes(&chunk.embedding);
 
             tx.execute(
-                "INSERT INTO chunks (file_path, chunk_index, heading_path, content, char_start, char_end, file_hash, embedding)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                "INSERT INTO chunks (file_path, chunk_index, heading_path, content, char_start, char_end, file_hash, embedding, embedding_model, embedding_dim)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
                 params![
                     file_path,
                     chunk.chunk_index as i64,
@@ -516,11 +667,14 @@ impl VectorDb {
                     chunk.char_end as i64,
                     hash,
                     embedding_bytes,
+                    embedding_model_id,
+                    chunk.embedding.len() as i64,
                 ],
             )?;
 
+            let chunk_id = tx.last_insert_rowid();
+            vec_index::insert(&tx, chunk_id, &chunk.embedding, vec_dimension)?;
             if has_fts {
-                let chunk_id = tx.last_insert_rowid();
                 tx.execute(
                     "INSERT INTO chunks_fts(rowid, content, file_path, chunk_index, heading_path)
                      VALUES (?1, ?2, ?3, ?4, ?5)",
@@ -555,13 +709,16 @@ impl VectorDb {
         char_start: usize,
         char_end: usize,
         file_hash: &str,
+        embedding_model_id: &str,
         embedding: &[f32],
     ) -> Result<i64, Box<dyn std::error::Error>> {
-        let embedding_bytes: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+        let embedding_bytes = vec_index::embedding_to_bytes(embedding);
+        let vec_dimension =
+            vec_index::ensure_index(&self.conn, embedding.len())?.then_some(embedding.len());
 
         self.conn.execute(
-            "INSERT INTO chunks (file_path, chunk_index, heading_path, content, char_start, char_end, file_hash, embedding)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO chunks (file_path, chunk_index, heading_path, content, char_start, char_end, file_hash, embedding, embedding_model, embedding_dim)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 file_path,
                 chunk_index as i64,
@@ -571,10 +728,13 @@ impl VectorDb {
                 char_end as i64,
                 file_hash,
                 embedding_bytes,
+                embedding_model_id,
+                embedding.len() as i64,
             ],
         )?;
 
         let chunk_id = self.conn.last_insert_rowid();
+        vec_index::insert(&self.conn, chunk_id, embedding, vec_dimension)?;
         if table_exists(&self.conn, "chunks_fts")? {
             self.conn.execute(
                 "INSERT INTO chunks_fts(rowid, content, file_path, chunk_index, heading_path)
@@ -630,20 +790,91 @@ impl VectorDb {
         Ok((file_count, chunk_count))
     }
 
+    /// True when some embedded chunk was produced by a model other than `embedding_model_id`
+    /// (including chunks indexed before the model was recorded).
+    fn has_foreign_embeddings(
+        &self,
+        embedding_model_id: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM chunks
+                WHERE embedding IS NOT NULL
+                  AND (embedding_model IS NULL OR embedding_model != ?1)
+             )",
+            params![embedding_model_id],
+            |row| row.get::<_, bool>(0),
+        )?)
+    }
+
+    /// Nearest chunks embedded by `embedding_model_id`. The vector index only holds one
+    /// dimension and no model, so it serves queries only while the index is single-model.
     fn search_vector_candidates(
         &self,
         query_embedding: &[f32],
+        embedding_model_id: &str,
+        limit: usize,
+    ) -> Result<Vec<ChunkResult>, Box<dyn std::error::Error>> {
+        if !self.has_foreign_embeddings(embedding_model_id)? {
+            match vec_index::nearest(&self.conn, query_embedding, limit) {
+                Ok(Some(neighbours)) => return self.load_vector_neighbours(&neighbours),
+                Ok(None) => {}
+                Err(error) => log::warn!("Vector index query failed, scanning instead: {error}"),
+            }
+        }
+        self.search_vector_candidates_exhaustive(query_embedding, embedding_model_id, limit)
+    }
+
+    fn load_vector_neighbours(
+        &self,
+        neighbours: &[(i64, f64)],
+    ) -> Result<Vec<ChunkResult>, Box<dyn std::error::Error>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_path, chunk_index, heading_path, content FROM chunks WHERE id = ?1",
+        )?;
+        let mut results = Vec::with_capacity(neighbours.len());
+        for (chunk_id, distance) in neighbours {
+            let row = stmt.query_row(params![chunk_id], |row| {
+                Ok(ChunkResult {
+                    chunk_id: *chunk_id,
+                    file_path: row.get(0)?,
+                    chunk_index: row.get::<_, i64>(1)? as usize,
+                    heading_path: normalize_heading_path(row.get(2)?),
+                    content: row.get(3)?,
+                    distance: *distance,
+                    retrieval_score: None,
+                })
+            });
+            match row {
+                Ok(result) => results.push(result),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(results)
+    }
+
+    /// Brute-force cosine scan over embeddings from `embedding_model_id`. Unrecorded
+    /// legacy chunks are compared only when their dimension matches the query. Used when
+    /// the vector index cannot serve the query, and as the reference for accuracy tests.
+    fn search_vector_candidates_exhaustive(
+        &self,
+        query_embedding: &[f32],
+        embedding_model_id: &str,
         limit: usize,
     ) -> Result<Vec<ChunkResult>, Box<dyn std::error::Error>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, file_path, chunk_index, heading_path, content, embedding
              FROM chunks
-             WHERE embedding IS NOT NULL",
+             WHERE embedding IS NOT NULL
+               AND (embedding_model = ?1
+                    OR (embedding_model IS NULL AND length(embedding) = ?2))",
         )?;
 
         let mut results: Vec<(ChunkResult, f64)> = Vec::new();
 
-        let rows = stmt.query_map([], |row| {
+        let query_bytes = (query_embedding.len() * 4) as i64;
+        let rows = stmt.query_map(params![embedding_model_id, query_bytes], |row| {
             let chunk_id: i64 = row.get(0)?;
             let file_path: String = row.get(1)?;
             let chunk_index: i64 = row.get(2)?;
@@ -740,11 +971,13 @@ impl VectorDb {
     pub fn search_hybrid(
         &self,
         query_embedding: &[f32],
+        embedding_model_id: &str,
         query: &str,
         limit: usize,
     ) -> Result<Vec<ChunkResult>, Box<dyn std::error::Error>> {
         let candidate_limit = (limit.max(1) * 4).min(200);
-        let vector = self.search_vector_candidates(query_embedding, candidate_limit)?;
+        let vector =
+            self.search_vector_candidates(query_embedding, embedding_model_id, candidate_limit)?;
         let keyword = self.search_keyword_candidates(query, candidate_limit)?;
 
         if keyword.is_empty() {
@@ -793,9 +1026,10 @@ impl VectorDb {
     pub fn search(
         &self,
         query_embedding: &[f32],
+        embedding_model_id: &str,
         limit: usize,
     ) -> Result<Vec<ChunkResult>, Box<dyn std::error::Error>> {
-        self.search_vector_candidates(query_embedding, limit)
+        self.search_vector_candidates(query_embedding, embedding_model_id, limit)
     }
 
     pub fn save_message(
@@ -991,6 +1225,11 @@ impl VectorDb {
             "DELETE FROM messages WHERE conversation_id = ?1",
             params![conversation_id],
         )?;
+        tx.execute(
+            "DELETE FROM staged_changes
+             WHERE run_id IN (SELECT run_id FROM runs WHERE conversation_id = ?1)",
+            params![conversation_id.to_string()],
+        )?;
         let deleted = tx.execute(
             "DELETE FROM conversations WHERE id = ?1",
             params![conversation_id],
@@ -1371,7 +1610,7 @@ impl VectorDb {
 
     pub fn get_folder(&self, folder_id: i64) -> Result<FolderSummary, Box<dyn std::error::Error>> {
         let folder = self.conn.query_row(
-            "SELECT id, name, icon, custom_instruction, default_model_id, parent_id, pinned, archived, sort_order, created_at, updated_at
+            "SELECT id, name, icon, custom_instruction, default_model_id, parent_id, pinned, archived, sort_order, created_at, updated_at, branch_mode
              FROM folders WHERE id = ?1",
             params![folder_id],
             |row| {
@@ -1387,6 +1626,7 @@ impl VectorDb {
                     sort_order: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    branch_mode: row.get::<_, i64>(11)? == 1,
                 })
             },
         )?;
@@ -1395,7 +1635,7 @@ impl VectorDb {
 
     pub fn list_folders(&self) -> Result<Vec<FolderSummary>, Box<dyn std::error::Error>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, icon, custom_instruction, default_model_id, parent_id, pinned, archived, sort_order, created_at, updated_at
+            "SELECT id, name, icon, custom_instruction, default_model_id, parent_id, pinned, archived, sort_order, created_at, updated_at, branch_mode
              FROM folders
              WHERE archived = 0
              ORDER BY pinned DESC, sort_order ASC, datetime(updated_at) DESC, id DESC",
@@ -1414,6 +1654,7 @@ impl VectorDb {
                 sort_order: row.get(8)?,
                 created_at: row.get(9)?,
                 updated_at: row.get(10)?,
+                branch_mode: row.get::<_, i64>(11)? == 1,
             })
         })?;
 
@@ -1599,6 +1840,79 @@ impl VectorDb {
         Ok(())
     }
 
+    pub fn set_conversation_branch_mode(
+        &mut self,
+        conversation_id: i64,
+        enabled: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let updated = self.conn.execute(
+            "UPDATE conversations SET branch_mode = ?1 WHERE id = ?2",
+            params![enabled as i64, conversation_id],
+        )?;
+        if updated == 0 {
+            return Err(format!("Conversation {} not found", conversation_id).into());
+        }
+        Ok(())
+    }
+
+    pub fn set_folder_branch_mode(
+        &mut self,
+        folder_id: i64,
+        enabled: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let updated = self.conn.execute(
+            "UPDATE folders SET branch_mode = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![enabled as i64, folder_id],
+        )?;
+        if updated == 0 {
+            return Err(format!("Folder {} not found", folder_id).into());
+        }
+        Ok(())
+    }
+
+    /// The conversation's own branch-mode flag, ignoring its folders.
+    pub fn get_conversation_branch_mode(
+        &self,
+        conversation_id: i64,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let enabled = self.conn.query_row(
+            "SELECT branch_mode FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get::<_, i64>(0),
+        )?;
+        Ok(enabled == 1)
+    }
+
+    /// Whether the conversation, or any folder above it, runs in branch mode.
+    pub fn resolve_conversation_branch_mode(
+        &self,
+        conversation_id: i64,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        if self.get_conversation_branch_mode(conversation_id)? {
+            return Ok(true);
+        }
+
+        let mut current = self.get_conversation_folder_id(conversation_id)?;
+        let mut visited = std::collections::HashSet::new();
+        while let Some(folder_id) = current {
+            if !visited.insert(folder_id) {
+                break;
+            }
+            let row = self.conn.query_row(
+                "SELECT branch_mode, parent_id FROM folders WHERE id = ?1",
+                params![folder_id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?)),
+            );
+            match row {
+                Ok((1, _)) => return Ok(true),
+                Ok((_, parent_id)) => current = parent_id,
+                Err(rusqlite::Error::QueryReturnedNoRows) => break,
+                Err(error) => return Err(Box::new(error)),
+            }
+        }
+        Ok(false)
+    }
+
     pub fn get_folder_instruction_chain(
         &self,
         folder_id: i64,
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::VectorDb;
+
+/// A note write recorded by a dry run instead of being applied to the vault.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct StagedChange {
+    pub run_id: String,
+    pub path: String,
+    /// `create` or `edit`.
+    pub action: String,
+    /// Vault hash when the note was first staged; `None` when it did not exist.
+    pub hash_before: Option<String>,
+    pub hash_after: String,
+    pub content: String,
+    /// Unified diff against the vault copy the change was staged over.
+    pub diff: String,
+    pub updated_at: String,
+}
+
+impl VectorDb {
+    /// Insert or replace the staged copy of `change.path` for its run.
+    pub fn stage_change(
+        &mut self,
+        change: &StagedChange,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.conn.execute(
+            "INSERT INTO staged_changes (run_id, path, action, hash_before, hash_after, content, diff, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(run_id, path) DO UPDATE SET
+                 action = excluded.action,
+                 hash_before = excluded.hash_before,
+                 hash_after = excluded.hash_after,
+                 content = excluded.content,
+                 diff = excluded.diff,
+                 updated_at = excluded.updated_at",
+            params![
+                change.run_id,
+                change.path,
+                change.action,
+                change.hash_before,
+                change.hash_after,
+                change.content,
+                change.diff,
+                change.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn staged_change(
+        &self,
+        run_id: &str,
+        path: &str,
+    ) -> Result<Option<StagedChange>, Box<dyn std::error::Error>> {
+        let change = self
+            .conn
+            .query_row(
+                "SELECT run_id, path, action, hash_before, hash_after, content, diff, updated_at
+                 FROM staged_changes WHERE run_id = ?1 AND path = ?2",
+                params![run_id, path],
+                staged_change_from_row,
+            )
+            .optional()?;
+        Ok(change)
+    }
+
+    pub fn list_staged_changes(
+        &self,
+        run_id: &str,
+    ) -> Result<Vec<StagedChange>, Box<dyn std::error::Error>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, path, action, hash_before, hash_after, content, diff, updated_at
+             FROM staged_changes WHERE run_id = ?1 ORDER BY path",
+        )?;
+        let rows = stmt.query_map(params![run_id], staged_change_from_row)?;
+        let mut changes = Vec::new();
+        for row in rows {
+            changes.push(row?);
+        }
+        Ok(changes)
+    }
+
+    /// Pending staged change counts of every run in `conversation_id`, keyed by run id.
+    pub fn staged_change_counts(
+        &self,
+        conversation_id: i64,
+    ) -> Result<BTreeMap<String, usize>, Box<dyn std::error::Error>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT staged_changes.run_id, COUNT(*)
+             FROM staged_changes
+             JOIN runs ON runs.run_id = staged_changes.run_id
+             WHERE runs.conversation_id = ?1
+             GROUP BY staged_changes.run_id",
+        )?;
+        let rows = stmt.query_map(params![conversation_id.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        let mut counts = BTreeMap::new();
+        for row in rows {
+            let (run_id, count) = row?;
+            counts.insert(run_id, count as usize);
+        }
+        Ok(counts)
+    }
+
+    /// Drop staged changes for `paths`, or the whole changeset when `paths` is `None`.
+    pub fn remove_staged_changes(
+        &mut self,
+        run_id: &str,
+        paths: Option<&[String]>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let Some(paths) = paths else {
+            let removed = self.conn.execute(
+                "DELETE FROM staged_changes WHERE run_id = ?1",
+                params![run_id],
+            )?;
+            return Ok(removed);
+        };
+        let tx = self.conn.transaction()?;
+        let mut removed = 0;
+        for path in paths {
+            removed += tx.execute(
+                "DELETE FROM staged_changes WHERE run_id = ?1 AND path = ?2",
+                params![run_id, path],
+            )?;
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+}
+
+fn staged_change_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<StagedChange> {
+    Ok(StagedChange {
+        run_id: row.get(0)?,
+        path: row.get(1)?,
+        action: row.get(2)?,
+        hash_before: row.get(3)?,
+        hash_after: row.get(4)?,
+        content: row.get(5)?,
+        diff: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
@@ -429,6 +429,48 @@ fn resolve_conversation_chat_model_id_inherits_parent_folder_model() {
     let _ = std::fs::remove_file(db_path);
 }
 
+#[test]
+fn resolve_conversation_branch_mode_inherits_from_folders() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+
+    let parent_folder_id = db
+        .create_folder("Parent", None)
+        .expect("create parent folder");
+    let child_folder_id = db
+        .create_folder("Child", Some(parent_folder_id))
+        .expect("create child folder");
+    let conversation_id = db
+        .create_conversation("branch-mode")
+        .expect("create conversation");
+    db.set_conversation_folder(conversation_id, Some(child_folder_id))
+        .expect("assign conversation to child folder");
+    assert!(!db
+        .resolve_conversation_branch_mode(conversation_id)
+        .expect("resolve branch mode"));
+
+    db.set_folder_branch_mode(parent_folder_id, true)
+        .expect("enable branch mode on parent");
+    assert!(db
+        .resolve_conversation_branch_mode(conversation_id)
+        .expect("resolve branch mode through folder chain"));
+    assert!(
+        db.get_folder(parent_folder_id)
+            .expect("get folder")
+            .branch_mode
+    );
+
+    db.set_folder_branch_mode(parent_folder_id, false)
+        .expect("disable branch mode on parent");
+    db.set_conversation_branch_mode(conversation_id, true)
+        .expect("enable branch mode on conversation");
+    assert!(db
+        .resolve_conversation_branch_mode(conversation_id)
+        .expect("resolve conversation branch mode"));
+
+    let _ = std::fs::remove_file(db_path);
+}
+
 #[test]
 fn resolve_conversation_chat_model_id_skips_invalid_models_in_folder_chain() {
     let db_path = temp_db_path();
@@ -463,3 +505,356 @@ fn resolve_conversation_chat_model_id_skips_invalid_models_in_folder_chain() {
 
     let _ = std::fs::remove_file(db_path);
 }
+
+#[test]
+fn link_graph_resolves_names_aliases_and_reports_orphans() {
+    use crate::adapters::markdown::links::{extract_wikilinks, frontmatter_aliases};
+
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+
+    let notes = [
+        (
+            "Projects/Plan.md",
+            "---\naliases: [Roadmap]\n---\nSee [[Meeting Notes#Decisions]] and [[Missing Note]].",
+        ),
+        ("Meeting Notes.md", "Follow-up for the [[roadmap|plan]]."),
+        ("Inbox/Loose.md", "No links, only [[#Self]]."),
+    ];
+    for (path, content) in notes {
+        db.replace_note_links(
+            path,
+            &crate::adapters::vault::file_hash(content),
+            &frontmatter_aliases(content),
+            &extract_wikilinks(content),
+        )
+        .expect("store links");
+    }
+    assert_eq!(db.resolve_links().expect("resolve"), 1);
+
+    let outgoing = db.outgoing_links("Projects/Plan.md").expect("outgoing");
+    assert_eq!(outgoing.len(), 2);
+    assert_eq!(
+        outgoing[0].resolved_path.as_deref(),
+        Some("Meeting Notes.md")
+    );
+    assert_eq!(outgoing[0].heading.as_deref(), Some("Decisions"));
+
+    let backlinks = db.backlinks("Projects/Plan.md").expect("backlinks");
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].source_path, "Meeting Notes.md");
+
+    let unresolved = db.unresolved_links().expect("unresolved");
+    assert_eq!(unresolved.len(), 1);
+    assert_eq!(unresolved[0].target, "Missing Note");
+
+    assert_eq!(
+        db.orphan_notes().expect("orphans"),
+        vec!["Inbox/Loose.md".to_string()]
+    );
+
+    db.remove_note_links("Meeting Notes.md").expect("remove");
+    db.resolve_links().expect("re-resolve");
+    assert!(db
+        .backlinks("Projects/Plan.md")
+        .expect("backlinks")
+        .is_empty());
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+fn pseudo_random_embedding(seed: u64, dimension: usize) -> Vec<f32> {
+    let mut state = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    (0..dimension)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+        })
+        .collect()
+}
+
+fn prepared_chunks(
+    seed: u64,
+    count: usize,
+    dimension: usize,
+) -> Vec<super::PreparedChunkEmbedding> {
+    (0..count)
+        .map(|index| super::PreparedChunkEmbedding {
+            chunk_index: index,
+            heading_path: None,
+            content: format!("chunk {seed}-{index}"),
+            char_start: 0,
+            char_end: 0,
+            embedding: pseudo_random_embedding(seed * 1_000 + index as u64, dimension),
+        })
+        .collect()
+}
+
+#[test]
+fn vector_index_matches_exhaustive_scan() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+    assert!(super::vec_index::is_available(&db.conn));
+
+    for file in 0..20u64 {
+        db.replace_file_chunks_atomically(
+            &format!("note-{file}.md"),
+            "hash",
+            "test:model",
+            &prepared_chunks(file, 10, 64),
+        )
+        .expect("store chunks");
+    }
+
+    for probe in 0..5u64 {
+        let query = pseudo_random_embedding(90_000 + probe, 64);
+        let indexed = db
+            .search_vector_candidates(&query, "test:model", 10)
+            .expect("ann");
+        let scanned = db
+            .search_vector_candidates_exhaustive(&query, "test:model", 10)
+            .expect("scan");
+        let indexed_ids: Vec<i64> = indexed.iter().map(|chunk| chunk.chunk_id).collect();
+        let scanned_ids: Vec<i64> = scanned.iter().map(|chunk| chunk.chunk_id).collect();
+        assert_eq!(indexed_ids, scanned_ids);
+        assert!((indexed[0].distance - scanned[0].distance).abs() < 1e-4);
+    }
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn vector_index_follows_chunk_replacement_and_removal() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+    let count = |db: &VectorDb| -> i64 {
+        db.conn
+            .query_row("SELECT COUNT(*) FROM chunks_vec", [], |row| row.get(0))
+            .expect("count index rows")
+    };
+
+    db.replace_file_chunks_atomically("a.md", "h1", "test:model", &prepared_chunks(1, 3, 8))
+        .expect("store a");
+    db.replace_file_chunks_atomically("b.md", "h1", "test:model", &prepared_chunks(2, 2, 8))
+        .expect("store b");
+    assert_eq!(count(&db), 5);
+
+    db.replace_file_chunks_atomically("a.md", "h2", "test:model", &prepared_chunks(3, 1, 8))
+        .expect("replace a");
+    assert_eq!(count(&db), 3);
+
+    db.remove_file_chunks("b.md").expect("remove b");
+    assert_eq!(count(&db), 1);
+
+    // A new embedding dimension rebuilds the index with the new size.
+    db.replace_file_chunks_atomically("a.md", "h3", "test:model", &prepared_chunks(4, 2, 16))
+        .expect("replace with new dimension");
+    assert_eq!(
+        super::vec_index::index_dimension(&db.conn).expect("dimension"),
+        Some(16)
+    );
+    assert_eq!(count(&db), 2);
+
+    drop(db);
+    let reopened = VectorDb::open(&db_path).expect("reopen");
+    assert_eq!(count(&reopened), 2);
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn vector_index_is_rebuilt_by_an_explicit_sync_not_by_open() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+    db.replace_file_chunks_atomically("a.md", "h1", "test:model", &prepared_chunks(1, 3, 8))
+        .expect("store a");
+    db.conn
+        .execute("DROP TABLE chunks_vec", [])
+        .expect("drop index");
+    drop(db);
+
+    let reopened = VectorDb::open(&db_path).expect("reopen");
+    assert_eq!(
+        super::vec_index::index_dimension(&reopened.conn).expect("dimension"),
+        None
+    );
+
+    reopened.sync_vector_index().expect("sync index");
+    let indexed: i64 = reopened
+        .conn
+        .query_row("SELECT COUNT(*) FROM chunks_vec", [], |row| row.get(0))
+        .expect("count index rows");
+    assert_eq!(indexed, 3);
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn moving_file_chunks_rekeys_a_renamed_note_without_touching_embeddings() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+    let vec_count = |db: &VectorDb| -> i64 {
+        db.conn
+            .query_row("SELECT COUNT(*) FROM chunks_vec", [], |row| row.get(0))
+            .expect("count index rows")
+    };
+
+    db.replace_file_chunks_atomically("a.md", "h1", "test:model", &prepared_chunks(1, 3, 8))
+        .expect("store a");
+    db.replace_file_chunks_atomically("b.md", "h2", "test:model", &prepared_chunks(2, 2, 8))
+        .expect("store b");
+    let ids_before: Vec<i64> = db
+        .conn
+        .prepare("SELECT id FROM chunks WHERE file_path = 'a.md' ORDER BY id")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<Result<_, _>>()
+        .expect("ids");
+    assert_eq!(
+        db.indexed_paths_with_hash("h1").expect("by hash"),
+        vec!["a.md".to_string()]
+    );
+
+    // Moving onto an indexed path replaces what was there.
+    assert!(db.move_file_chunks("a.md", "b.md").expect("move"));
+    assert!(!db
+        .move_file_chunks("missing.md", "c.md")
+        .expect("move missing"));
+    assert_eq!(
+        db.list_indexed_files().expect("files"),
+        vec!["b.md".to_string()]
+    );
+    assert!(db.file_is_current("b.md", "h1", "test:model"));
+    let ids_after: Vec<i64> = db
+        .conn
+        .prepare("SELECT id FROM chunks WHERE file_path = 'b.md' ORDER BY id")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<Result<_, _>>()
+        .expect("ids");
+    assert_eq!(ids_after, ids_before);
+    assert_eq!(vec_count(&db), 3);
+
+    let fts_paths: Vec<String> = db
+        .conn
+        .prepare("SELECT DISTINCT file_path FROM chunks_fts")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<Result<_, _>>()
+        .expect("fts paths");
+    assert_eq!(fts_paths, vec!["b.md".to_string()]);
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn chunks_from_another_embedding_model_are_stale_and_excluded_from_search() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+
+    db.replace_file_chunks_atomically("a.md", "h1", "openai:small", &prepared_chunks(1, 3, 8))
+        .expect("store a");
+    db.replace_file_chunks_atomically("b.md", "h1", "google:gemini", &prepared_chunks(2, 2, 16))
+        .expect("store b");
+
+    assert!(db.file_is_current("a.md", "h1", "openai:small"));
+    assert!(!db.file_is_current("a.md", "h1", "google:gemini"));
+    assert!(!db.file_is_current("a.md", "h2", "openai:small"));
+
+    let query = pseudo_random_embedding(7, 16);
+    let results = db
+        .search_hybrid(&query, "google:gemini", "unrelated", 10)
+        .expect("search");
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|chunk| chunk.file_path == "b.md"));
+
+    // Chunks indexed before the model was recorded count as stale.
+    db.conn
+        .execute(
+            "UPDATE chunks SET embedding_model = NULL WHERE file_path = 'b.md'",
+            [],
+        )
+        .expect("clear model");
+    assert!(!db.file_is_current("b.md", "h1", "google:gemini"));
+
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn staged_changes_are_counted_per_run_and_dropped_with_their_conversation() {
+    let db_path = temp_db_path();
+    let mut db = VectorDb::open(&db_path).expect("open db");
+
+    let conversation_id = db
+        .create_conversation("staged test")
+        .expect("create conversation");
+    let other_conversation_id = db
+        .create_conversation("other")
+        .expect("create other conversation");
+    for (run_id, conversation) in [
+        ("run-a", conversation_id),
+        ("run-b", conversation_id),
+        ("run-c", other_conversation_id),
+    ] {
+        db.create_run(
+            run_id,
+            &conversation.to_string(),
+            "2026-02-14T10:00:00Z",
+            "completed",
+            None,
+            None,
+            None,
+            None,
+        )
+        .expect("create run");
+    }
+    for (run_id, path) in [
+        ("run-a", "one.md"),
+        ("run-a", "two.md"),
+        ("run-b", "three.md"),
+        ("run-c", "four.md"),
+    ] {
+        db.stage_change(&super::StagedChange {
+            run_id: run_id.to_string(),
+            path: path.to_string(),
+            action: "create".to_string(),
+            hash_before: None,
+            hash_after: "hash".to_string(),
+            content: "body".to_string(),
+            diff: String::new(),
+            updated_at: "2026-02-14T10:00:00Z".to_string(),
+        })
+        .expect("stage change");
+    }
+
+    let counts = db
+        .staged_change_counts(conversation_id)
+        .expect("count staged changes");
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts.get("run-a"), Some(&2));
+    assert_eq!(counts.get("run-b"), Some(&1));
+
+    db.delete_conversation(conversation_id)
+        .expect("delete conversation");
+    assert!(db
+        .list_staged_changes("run-a")
+        .expect("list run-a")
+        .is_empty());
+    assert!(db
+        .list_staged_changes("run-b")
+        .expect("list run-b")
+        .is_empty());
+    assert_eq!(
+        db.list_staged_changes("run-c").expect("list run-c").len(),
+        1
+    );
+
+    let _ = std::fs::remove_file(db_path);
+}
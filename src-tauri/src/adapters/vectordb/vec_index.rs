@@ -0,0 +1,188 @@
+//! `sqlite-vec` index over chunk embeddings (`chunks_vec`, rowid = `chunks.id`).
+//!
+//! The virtual table is created lazily with the dimension of the first stored embedding.
+//! When the extension is unavailable, or the query dimension does not match the index,
+//! callers fall back to the brute-force scan in `search_vector_candidates`.
+
+use rusqlite::{params, Connection};
+use std::sync::Once;
+
+static REGISTER_SQLITE_VEC: Once = Once::new();
+
+type ExtensionEntryPoint = unsafe extern "C" fn(
+    *mut rusqlite::ffi::sqlite3,
+    *mut *mut std::ffi::c_char,
+    *const rusqlite::ffi::sqlite3_api_routines,
+) -> std::ffi::c_int;
+
+/// Register sqlite-vec for every connection opened afterwards in this process.
+pub(super) fn register_sqlite_vec() {
+    REGISTER_SQLITE_VEC.call_once(|| {
+        // SAFETY: `sqlite3_vec_init` is the extension entry point with the standard
+        // `sqlite3_extension_init` signature; the crate declares it without arguments.
+        unsafe {
+            let entry_point = std::mem::transmute::<*const (), ExtensionEntryPoint>(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            );
+            rusqlite::ffi::sqlite3_auto_extension(Some(entry_point));
+        }
+    });
+}
+
+pub(super) fn is_available(conn: &Connection) -> bool {
+    conn.query_row("SELECT vec_version()", [], |row| row.get::<_, String>(0))
+        .is_ok()
+}
+
+pub(super) fn embedding_to_bytes(embedding: &[f32]) -> Vec<u8> {
+    embedding
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Dimension declared by the existing `chunks_vec` table, if any.
+pub(super) fn index_dimension(conn: &Connection) -> rusqlite::Result<Option<usize>> {
+    let sql: Option<String> = match conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'chunks_vec'",
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(sql) => sql,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    Ok(sql.and_then(|sql| {
+        let start = sql.find("float[")? + "float[".len();
+        let end = start + sql[start..].find(']')?;
+        sql[start..end].trim().parse().ok()
+    }))
+}
+
+/// Make sure `chunks_vec` exists with `dimension`, rebuilding it from `chunks` when the
+/// dimension changed. Returns false when sqlite-vec is not loaded.
+pub(super) fn ensure_index(conn: &Connection, dimension: usize) -> rusqlite::Result<bool> {
+    if dimension == 0 || !is_available(conn) {
+        return Ok(false);
+    }
+    match index_dimension(conn)? {
+        Some(existing) if existing == dimension => return Ok(true),
+        Some(_) => conn.execute("DROP TABLE chunks_vec", []).map(|_| ())?,
+        None => {}
+    }
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE chunks_vec USING vec0(embedding float[{dimension}] distance_metric=cosine)"
+        ),
+        [],
+    )?;
+    backfill(conn, dimension)?;
+    Ok(true)
+}
+
+fn backfill(conn: &Connection, dimension: usize) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO chunks_vec(rowid, embedding)
+         SELECT id, embedding FROM chunks
+         WHERE embedding IS NOT NULL AND length(embedding) = ?1",
+        params![(dimension * 4) as i64],
+    )?;
+    Ok(())
+}
+
+/// Bring an existing database in line with `chunks`: create the index for vaults indexed
+/// before sqlite-vec was loaded, and rebuild it if row counts drifted.
+pub(super) fn sync(conn: &Connection) -> rusqlite::Result<()> {
+    if !is_available(conn) {
+        return Ok(());
+    }
+    let dimension = match index_dimension(conn)? {
+        Some(dimension) => dimension,
+        None => {
+            let bytes: Option<i64> = conn
+                .query_row(
+                    "SELECT length(embedding) FROM chunks WHERE embedding IS NOT NULL LIMIT 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .or_else(|error| match error {
+                    rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                    other => Err(other),
+                })?;
+            match bytes {
+                Some(bytes) if bytes > 0 => {
+                    return ensure_index(conn, bytes as usize / 4).map(|_| ())
+                }
+                _ => return Ok(()),
+            }
+        }
+    };
+
+    let expected: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM chunks WHERE embedding IS NOT NULL AND length(embedding) = ?1",
+        params![(dimension * 4) as i64],
+        |row| row.get(0),
+    )?;
+    let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM chunks_vec", [], |row| row.get(0))?;
+    if expected != indexed {
+        conn.execute("DELETE FROM chunks_vec", [])?;
+        backfill(conn, dimension)?;
+    }
+    Ok(())
+}
+
+pub(super) fn delete_file(conn: &Connection, file_path: &str) -> rusqlite::Result<()> {
+    if index_dimension(conn)?.is_none() || !is_available(conn) {
+        return Ok(());
+    }
+    conn.execute(
+        "DELETE FROM chunks_vec WHERE rowid IN (SELECT id FROM chunks WHERE file_path = ?1)",
+        params![file_path],
+    )?;
+    Ok(())
+}
+
+pub(super) fn insert(
+    conn: &Connection,
+    chunk_id: i64,
+    embedding: &[f32],
+    dimension: Option<usize>,
+) -> rusqlite::Result<()> {
+    if dimension != Some(embedding.len()) {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO chunks_vec(rowid, embedding) VALUES (?1, ?2)",
+        params![chunk_id, embedding_to_bytes(embedding)],
+    )?;
+    Ok(())
+}
+
+/// Nearest chunks as `(chunk_id, cosine_distance)`, or `None` when the index cannot serve
+/// this query and the caller should scan instead.
+pub(super) fn nearest(
+    conn: &Connection,
+    query_embedding: &[f32],
+    limit: usize,
+) -> rusqlite::Result<Option<Vec<(i64, f64)>>> {
+    if query_embedding.is_empty()
+        || !is_available(conn)
+        || index_dimension(conn)? != Some(query_embedding.len())
+    {
+        return Ok(None);
+    }
+    let mut stmt = conn.prepare(
+        "SELECT rowid, distance FROM chunks_vec
+         WHERE embedding MATCH ?1 AND k = ?2
+         ORDER BY distance",
+    )?;
+    let rows = stmt.query_map(
+        params![embedding_to_bytes(query_embedding), limit.max(1) as i64],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+    )?;
+    let mut neighbours = Vec::new();
+    for row in rows {
+        neighbours.push(row?);
+    }
+    Ok(Some(neighbours))
+}
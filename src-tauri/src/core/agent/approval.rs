@@ -0,0 +1,98 @@
+use serde_json::Value;
+
+/// When write tools pause the run for a human decision.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    /// Writes run immediately.
+    #[default]
+    Auto,
+    /// Every write waits for approval.
+    Always,
+    /// Writes inside these vault folders run immediately; anything else waits.
+    OutsideFolders(Vec<String>),
+}
+
+fn normalize_folder(raw: &str) -> String {
+    raw.trim()
+        .replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_matches('/')
+        .to_string()
+}
+
+impl ApprovalPolicy {
+    /// Whether a write tool called with `args` needs approval. Writes without a `path`
+    /// argument (external MCP tools) cannot be scoped to a folder and always ask.
+    pub fn requires_approval(&self, args: &Value) -> bool {
+        match self {
+            ApprovalPolicy::Auto => false,
+            ApprovalPolicy::Always => true,
+            ApprovalPolicy::OutsideFolders(folders) => {
+                let Some(path) = args.get("path").and_then(|value| value.as_str()) else {
+                    return true;
+                };
+                let path = normalize_folder(path);
+                if path.split('/').any(|part| part == "..") {
+                    return true;
+                }
+                !folders
+                    .iter()
+                    .map(|folder| normalize_folder(folder))
+                    .any(|folder| {
+                        folder.is_empty()
+                            || path
+                                .strip_prefix(&folder)
+                                .is_some_and(|rest| rest.starts_with('/'))
+                    })
+            }
+        }
+    }
+
+    /// Whether a write in a run needs approval. Writes that a dry run only `staged` never
+    /// do: the user reviews the staged changeset before anything reaches the vault.
+    /// Writes that are not `verified` by a readback, such as external MCP tools, always
+    /// do, whatever the policy.
+    pub fn requires_approval_in_run(&self, args: &Value, staged: bool, verified: bool) -> bool {
+        !staged && (!verified || self.requires_approval(args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApprovalPolicy;
+    use serde_json::json;
+
+    #[test]
+    fn outside_folders_policy_only_trusts_listed_folders() {
+        let policy = ApprovalPolicy::OutsideFolders(vec!["inbox/".to_string(), "./ai".to_string()]);
+
+        assert!(!policy.requires_approval(&json!({ "path": "inbox/idea.md" })));
+        assert!(!policy.requires_approval(&json!({ "path": "ai/drafts/plan.md" })));
+        assert!(policy.requires_approval(&json!({ "path": "inbox.md" })));
+        assert!(policy.requires_approval(&json!({ "path": "inboxes/idea.md" })));
+        assert!(policy.requires_approval(&json!({ "path": "inbox/../journal/today.md" })));
+        assert!(policy.requires_approval(&json!({ "query": "no path" })));
+    }
+
+    #[test]
+    fn auto_and_always_ignore_the_path() {
+        let args = json!({ "path": "inbox/idea.md" });
+        assert!(!ApprovalPolicy::Auto.requires_approval(&args));
+        assert!(ApprovalPolicy::Always.requires_approval(&args));
+    }
+
+    #[test]
+    fn staged_dry_run_writes_skip_approval() {
+        let args = json!({ "path": "journal/today.md" });
+        let policy = ApprovalPolicy::Always;
+        assert!(!policy.requires_approval_in_run(&args, true, true));
+        assert!(policy.requires_approval_in_run(&args, false, true));
+    }
+
+    #[test]
+    fn unverified_writes_always_ask() {
+        let args = json!({ "title": "Follow up" });
+        assert!(ApprovalPolicy::Auto.requires_approval_in_run(&args, false, false));
+        assert!(!ApprovalPolicy::Auto.requires_approval_in_run(&args, false, true));
+    }
+}
@@ -9,6 +9,8 @@ pub struct RunBudget {
     pub token_budget: Option<u64>,
     pub time_budget_ms: u64,
     pub llm_response_timeout_ms: u64,
+    /// Approximate token budget for prior conversation turns replayed into the run.
+    pub history_token_window: u64,
 }
 
 impl Default for RunBudget {
@@ -19,6 +21,7 @@ impl Default for RunBudget {
             token_budget: None,
             time_budget_ms: 300_000,
             llm_response_timeout_ms: 180_000,
+            history_token_window: 16_000,
         }
     }
 }
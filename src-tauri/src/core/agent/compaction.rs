@@ -37,7 +37,7 @@ fn has_verified_write_in_messages(messages: &[ChatMessage]) -> bool {
     messages.iter().any(|message| {
         let is_write_tool = matches!(
             message.tool_name.as_deref(),
-            Some("kb_create") | Some("kb_update")
+            Some("kb_create") | Some("kb_update") | Some("kb_patch")
         );
         if !is_write_tool {
             return false;
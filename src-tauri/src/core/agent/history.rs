@@ -0,0 +1,214 @@
+use serde_json::Value;
+
+use crate::core::ports::llm::ChatMessage;
+use crate::core::ports::store::HistoryMessage;
+
+use super::budget::approximate_token_count;
+use super::verification::args_preview;
+
+const MAX_TOOL_CALLS_IN_SUMMARY: usize = 8;
+
+fn text_message(role: &str, content: String) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        tool_calls: None,
+        tool_call_id: None,
+        tool_name: None,
+        thought_signatures: None,
+    }
+}
+
+fn parse_tool_args(args: &Value) -> Value {
+    match args {
+        Value::String(raw) => serde_json::from_str::<Value>(raw).unwrap_or(Value::Null),
+        other => other.clone(),
+    }
+}
+
+/// Persisted tool calls have no matching tool results, so they cannot be replayed as
+/// structured calls. Render them as a short trailer so follow-ups can refer to them.
+fn summarize_tool_calls(tool_calls: &Value) -> Option<String> {
+    let calls = tool_calls.as_array()?;
+    let mut lines = Vec::new();
+    for call in calls.iter().take(MAX_TOOL_CALLS_IN_SUMMARY) {
+        let Some(tool) = call.get("tool").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let args = call.get("args").map(parse_tool_args).unwrap_or(Value::Null);
+        let detail = args_preview(&args)
+            .and_then(|preview| preview.as_object().cloned())
+            .map(|preview| {
+                preview
+                    .iter()
+                    .filter_map(|(key, value)| value.as_str().map(|v| format!("{key}={v}")))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default();
+        lines.push(format!("- {tool}({detail})"));
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    if calls.len() > MAX_TOOL_CALLS_IN_SUMMARY {
+        lines.push(format!(
+            "- ... {} more",
+            calls.len() - MAX_TOOL_CALLS_IN_SUMMARY
+        ));
+    }
+    Some(format!("[Tools used in this turn]\n{}", lines.join("\n")))
+}
+
+fn history_entry_to_message(entry: &HistoryMessage) -> Option<ChatMessage> {
+    let role = entry.role.as_str();
+    if role != "user" && role != "assistant" {
+        return None;
+    }
+
+    let mut content = entry.content.trim().to_string();
+    if role == "assistant" {
+        if let Some(summary) = entry.tool_calls.as_ref().and_then(summarize_tool_calls) {
+            if content.is_empty() {
+                content = summary;
+            } else {
+                content = format!("{content}\n\n{summary}");
+            }
+        }
+    }
+
+    (!content.is_empty()).then(|| text_message(role, content))
+}
+
+/// Rebuild prior user/assistant turns into chat messages, newest first until the
+/// token window is exhausted. The current user message is already persisted when a
+/// run starts, so a trailing copy of it is dropped here.
+pub(super) fn build_history_messages(
+    history: &[HistoryMessage],
+    current_user_message: &str,
+    token_window: u64,
+) -> Vec<ChatMessage> {
+    if token_window == 0 {
+        return Vec::new();
+    }
+
+    let mut entries = history;
+    if let Some((last, rest)) = entries.split_last() {
+        if last.role == "user" && last.content.trim() == current_user_message.trim() {
+            entries = rest;
+        }
+    }
+
+    let mut selected: Vec<ChatMessage> = Vec::new();
+    let mut used_tokens = 0u64;
+    for entry in entries.iter().rev() {
+        let Some(message) = history_entry_to_message(entry) else {
+            continue;
+        };
+        let tokens = approximate_token_count(std::slice::from_ref(&message));
+        if used_tokens + tokens > token_window {
+            break;
+        }
+        used_tokens += tokens;
+        selected.push(message);
+    }
+    selected.reverse();
+
+    // Providers expect alternating turns that open with the user.
+    let first_user = selected
+        .iter()
+        .position(|message| message.role == "user")
+        .unwrap_or(selected.len());
+    selected.drain(..first_user);
+
+    let mut merged: Vec<ChatMessage> = Vec::with_capacity(selected.len());
+    for message in selected {
+        match merged.last_mut() {
+            Some(previous) if previous.role == message.role => {
+                previous.content.push_str("\n\n");
+                previous.content.push_str(&message.content);
+            }
+            _ => merged.push(message),
+        }
+    }
+    // The current user message follows, so history must end on an assistant turn.
+    if merged.last().is_some_and(|message| message.role == "user") {
+        merged.pop();
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::build_history_messages;
+    use crate::core::ports::store::HistoryMessage;
+
+    fn entry(role: &str, content: &str) -> HistoryMessage {
+        HistoryMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+        }
+    }
+
+    #[test]
+    fn drops_persisted_current_message_and_keeps_prior_turns() {
+        let history = vec![
+            entry("user", "Summarize the meeting"),
+            entry("assistant", "Decided to ship on Friday."),
+            entry("user", "now put that into a note"),
+        ];
+
+        let messages = build_history_messages(&history, "now put that into a note", 10_000);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].content, "Decided to ship on Friday.");
+    }
+
+    #[test]
+    fn token_window_keeps_newest_turns_starting_with_user() {
+        let long = "x".repeat(400);
+        let history = vec![
+            entry("user", &long),
+            entry("assistant", &long),
+            entry("user", "short question"),
+            entry("assistant", "short answer"),
+        ];
+
+        let messages = build_history_messages(&history, "next", 120);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "short question");
+        assert_eq!(messages[1].content, "short answer");
+        assert!(build_history_messages(&history, "next", 0).is_empty());
+    }
+
+    #[test]
+    fn assistant_tool_calls_are_summarized_and_same_role_turns_merged() {
+        let history = vec![
+            entry("user", "first try"),
+            entry("user", "second try"),
+            HistoryMessage {
+                role: "assistant".to_string(),
+                content: "Created the note.".to_string(),
+                tool_calls: Some(json!([{
+                    "tool": "kb_create",
+                    "args": "{\"path\":\"Notes/Plan.md\",\"content\":\"...\"}"
+                }])),
+            },
+        ];
+
+        let messages = build_history_messages(&history, "next", 10_000);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "first try\n\nsecond try");
+        assert!(messages[1]
+            .content
+            .contains("- kb_create(path=Notes/Plan.md)"));
+    }
+}
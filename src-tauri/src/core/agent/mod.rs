@@ -1,6 +1,8 @@
+mod approval;
 mod budget;
 mod compaction;
 mod events;
+mod history;
 pub mod instructions;
 mod ledger;
 pub mod run;
@@ -9,8 +11,12 @@ mod verification;
 
 use std::sync::Arc;
 
-use crate::core::ports::{emitter::EmitterPort, llm::LlmPort, store::StorePort, tools::ToolPort};
+use crate::core::ports::{
+    approval::ApprovalPort, emitter::EmitterPort, llm::LlmPort, store::StorePort, tools::ToolPort,
+    versioning::VersioningPort,
+};
 
+pub use approval::ApprovalPolicy;
 pub use budget::RunBudget;
 pub use run::{RunRequest, RunResult};
 pub use state::{is_indexing_active, set_indexing_active};
@@ -20,6 +26,9 @@ pub struct Agent {
     pub(crate) llm: Arc<dyn LlmPort>,
     pub(crate) store: Arc<dyn StorePort>,
     pub(crate) emitter: Arc<dyn EmitterPort>,
+    pub(crate) approvals: Option<Arc<dyn ApprovalPort>>,
+    pub(crate) approval_policy: ApprovalPolicy,
+    pub(crate) versioning: Option<Arc<dyn VersioningPort>>,
 }
 
 impl Agent {
@@ -34,6 +43,30 @@ impl Agent {
             llm,
             store,
             emitter,
+            approvals: None,
+            approval_policy: ApprovalPolicy::Auto,
+            versioning: None,
         }
     }
+
+    /// Gate write tools behind `policy`, waiting on `approvals` for each decision.
+    pub fn with_approvals(
+        mut self,
+        approvals: Arc<dyn ApprovalPort>,
+        policy: ApprovalPolicy,
+    ) -> Self {
+        self.approvals = Some(approvals);
+        self.approval_policy = policy;
+        self
+    }
+
+    /// Commit each run's note writes together through `versioning` instead of letting
+    /// tools commit every write on their own.
+    pub fn with_versioning(mut self, versioning: Arc<dyn VersioningPort>) -> Self {
+        self.versioning = Some(versioning);
+        self
+    }
 }
+
+#[cfg(test)]
+mod tests;
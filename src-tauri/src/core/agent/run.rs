@@ -1,18 +1,23 @@
 use serde_json::{json, Value};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 use crate::adapters::llm::{TokenUsage, ToolCall};
+use crate::core::ports::approval::ApprovalDecision;
 use crate::core::ports::emitter::EmitterPort;
 use crate::core::ports::llm::{ChatMessage, DynError, LlmChatRequest, RecoveryEvent, StreamEvent};
 use crate::core::ports::store::StorePort;
-use crate::core::ports::tools::ToolExecutionContext;
+use crate::core::ports::tools::{ToolDefinition, ToolExecutionContext};
+use crate::core::ports::versioning::RunCommit;
 
 use super::budget::{budget_timeout_reason, RunBudget};
 use super::compaction::maybe_compact_context;
 use super::events::{emit_run_state, emit_timeline_done, emit_timeline_step, now_iso};
+use super::history::build_history_messages;
 use super::ledger::{append_run_event_ledger, finish_run_ledger, start_run_ledger};
 use super::state::{is_indexing_active, AgentState};
 use super::verification::{
@@ -21,6 +26,9 @@ use super::verification::{
 };
 use super::Agent;
 
+const DRY_RUN_INSTRUCTION: &str = "Dry run: kb_create, kb_update and kb_patch stage changes for the user to review instead of writing the vault. kb_read returns your staged copies. Other write tools are unavailable. Work as if the writes were applied and summarize the staged changes at the end.";
+const BRANCH_MODE_INSTRUCTION: &str = "Branch mode: your writes go to this conversation's branch, which the user reviews and merges into the vault later. kb_read and kb_list see the branch, including your earlier writes in this conversation. The vault history is unavailable: there is no kb_history, kb_diff, kb_revert or kb_blame, and kb_read cannot take a revision. Summarize what you changed on the branch at the end.";
+
 pub struct RunRequest<'a> {
     pub conversation_id: i64,
     pub user_message: &'a str,
@@ -43,6 +51,11 @@ pub struct RunRequest<'a> {
     pub indexed_files: usize,
     pub indexed_chunks: usize,
     pub budget: RunBudget,
+    /// Stage note writes into a changeset kept with the run instead of writing the vault.
+    pub dry_run: bool,
+    /// Write to the conversation's branch in a shadow worktree instead of the vault.
+    /// Ignored for dry runs.
+    pub branch_mode: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -104,6 +117,23 @@ fn emit_state_and_finish(
     }
 }
 
+/// Result reported for a write a dry run cannot stage; the tool is not called.
+fn dry_run_refusal(tool: &str) -> Value {
+    json!({
+        "ok": false,
+        "tool": tool,
+        "action": "agent.dry_run",
+        "result": { "summary": "Not run in a dry run" },
+        "proof": {},
+        "ts": now_iso(),
+        "error": {
+            "code": "dry_run_unsupported",
+            "message": format!("{tool} cannot be staged, so it is unavailable in a dry run"),
+            "retriable": false
+        }
+    })
+}
+
 fn normalize_thinking_summary(text: &str) -> String {
     text.split_whitespace().collect::<Vec<_>>().join(" ")
 }
@@ -115,8 +145,44 @@ fn append_thinking_chunk(buffer: &mut String, chunk: &str) {
     buffer.push_str(chunk);
 }
 
-fn is_write_tool(name: &str) -> bool {
-    matches!(name, "kb_create" | "kb_update")
+/// Notes written during a run, committed together once the run ends.
+#[derive(Default)]
+struct RunWrites {
+    /// Set for branch runs, which write a worktree forked from a fresh snapshot.
+    skip_snapshots: AtomicBool,
+    /// Notes snapshotted before the run first wrote them.
+    snapshotted: Mutex<BTreeSet<String>>,
+    paths: Mutex<BTreeSet<String>>,
+}
+
+/// Commits the run's writes. A run that ends normally commits through [`Self::commit`];
+/// dropping the guard uncommitted is the best-effort fallback for a run cancelled
+/// mid-flight (its future dropped), so its writes still land as a run commit.
+struct RunCommitGuard<'a, 'r> {
+    agent: &'a Agent,
+    request: &'a RunRequest<'r>,
+    run_id: &'a str,
+    writes: &'a RunWrites,
+    committed: bool,
+}
+
+impl RunCommitGuard<'_, '_> {
+    fn commit(mut self) {
+        self.committed = true;
+        self.agent
+            .commit_run_writes(self.request, self.run_id, self.writes);
+    }
+}
+
+impl Drop for RunCommitGuard<'_, '_> {
+    fn drop(&mut self) {
+        // Never risk a second panic while unwinding; the writes stay in the vault and
+        // the next snapshot picks them up.
+        if !self.committed && !std::thread::panicking() {
+            self.agent
+                .commit_run_writes(self.request, self.run_id, self.writes);
+        }
+    }
 }
 
 struct ToolExecOutcome {
@@ -128,6 +194,8 @@ struct ToolExecOutcome {
     verify_failed: bool,
     tool_ok: bool,
     invalid_args: bool,
+    /// Time spent waiting for the user to approve a write; excluded from the time budget.
+    approval_wait: Duration,
 }
 
 impl Agent {
@@ -138,8 +206,9 @@ impl Agent {
         iteration: usize,
         run_id: &str,
         tool_ctx: &ToolExecutionContext<'_>,
+        writes: &RunWrites,
     ) -> ToolExecOutcome {
-        let is_write = is_write_tool(&tc.function.name);
+        let is_write = self.tools.is_write_tool(&tc.function.name);
         let args: Value =
             serde_json::from_str(&tc.function.arguments).unwrap_or_else(|_| json!({}));
 
@@ -188,10 +257,27 @@ impl Agent {
             None,
         );
 
-        let result = self
-            .tools
-            .execute(&tc.function.name, args.clone(), tool_ctx)
-            .await;
+        let (rejection, approval_wait) = if self.unstaged_in_dry_run(&tc.function.name, tool_ctx) {
+            (Some(dry_run_refusal(&tc.function.name)), Duration::ZERO)
+        } else if is_write {
+            self.await_write_approval(tc, &args, iteration, run_id, tool_ctx)
+                .await
+        } else {
+            (None, Duration::ZERO)
+        };
+        let write_rejected = rejection.is_some();
+        let result = match rejection {
+            Some(rejection) => rejection,
+            None => {
+                if is_write && tool_ctx.defer_commits {
+                    let paths = self.tools.write_paths(&tc.function.name, &args);
+                    self.snapshot_before_write(&paths, run_id, iteration, tool_ctx, writes);
+                }
+                self.tools
+                    .execute(&tc.function.name, args.clone(), tool_ctx)
+                    .await
+            }
+        };
         let result_str = serde_json::to_string(&result).unwrap_or_else(|_| {
             json!({
                 "ok": false,
@@ -210,6 +296,15 @@ impl Agent {
         let tool_ok = result.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
         let invalid_args = !tool_ok
             && result.pointer("/error/code").and_then(|v| v.as_str()) == Some("invalid_arguments");
+        if is_write && tool_ok && tool_ctx.defer_commits {
+            let changed = extract_file_changes(&result).unwrap_or_default();
+            let mut paths = writes.paths.lock().unwrap_or_else(PoisonError::into_inner);
+            for change in changed.as_array().into_iter().flatten() {
+                if let Some(path) = change.get("path").and_then(|v| v.as_str()) {
+                    paths.insert(path.to_string());
+                }
+            }
+        }
 
         let tool_result_payload = json!({
             "run_id": run_id,
@@ -296,22 +391,311 @@ impl Agent {
             tool_call_id: tc.id.clone(),
             tool_name: tc.function.name.clone(),
             result_str,
-            is_write,
+            is_write: is_write && !write_rejected,
             verify_failed,
             tool_ok,
             invalid_args,
+            approval_wait,
+        }
+    }
+
+    /// Tools offered to the model, without those the run's mode cannot use.
+    fn run_tool_definitions(
+        &self,
+        tool_ctx: &ToolExecutionContext<'_>,
+        in_branch: bool,
+    ) -> Vec<ToolDefinition> {
+        let mut tools = self.tools.tool_definitions_for_llm();
+        tools.retain(|tool| {
+            let name = tool.function.name.as_str();
+            (!in_branch || self.tools.available_in_branch(name))
+                && (!tool_ctx.defer_commits || self.tools.available_with_deferred_commits(name))
+                && !self.unstaged_in_dry_run(name, tool_ctx)
+        });
+        tools
+    }
+
+    /// Whether `name` is a write a dry run cannot stage, and so must not run at all.
+    fn unstaged_in_dry_run(&self, name: &str, tool_ctx: &ToolExecutionContext<'_>) -> bool {
+        tool_ctx.staged_run_id.is_some()
+            && self.tools.is_write_tool(name)
+            && !self.tools.stages_dry_run_writes(name)
+    }
+
+    /// Snapshot the notes a write is about to change, before the run first writes them, so
+    /// hand edits in them stay out of the run commit. Other notes are left alone.
+    fn snapshot_before_write(
+        &self,
+        paths: &[String],
+        run_id: &str,
+        iteration: usize,
+        tool_ctx: &ToolExecutionContext<'_>,
+        writes: &RunWrites,
+    ) {
+        let Some(versioning) = self.versioning.as_ref() else {
+            return;
+        };
+        if writes.skip_snapshots.load(Ordering::SeqCst) {
+            return;
+        }
+        let new_paths: Vec<String> = {
+            let mut snapshotted = writes
+                .snapshotted
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            paths
+                .iter()
+                .filter(|path| snapshotted.insert((*path).clone()))
+                .cloned()
+                .collect()
+        };
+        if new_paths.is_empty() {
+            return;
+        }
+        if let Err(error) =
+            versioning.snapshot_before_write(tool_ctx.vault_path, run_id, &new_paths)
+        {
+            append_run_event_ledger(
+                self.store.as_ref(),
+                run_id,
+                iteration,
+                "lifecycle",
+                "agent:run_snapshot_failed",
+                &json!({ "run_id": run_id, "error": error, "ts": now_iso() }),
+            );
+        }
+    }
+
+    /// Commit everything the run wrote as one commit and record it in the run ledger.
+    fn commit_run_writes(&self, request: &RunRequest<'_>, run_id: &str, writes: &RunWrites) {
+        let Some(versioning) = self.versioning.as_ref() else {
+            return;
+        };
+        let paths: Vec<String> =
+            std::mem::take(&mut *writes.paths.lock().unwrap_or_else(PoisonError::into_inner))
+                .into_iter()
+                .collect();
+        if paths.is_empty() {
+            return;
+        }
+
+        let commit = RunCommit {
+            run_id,
+            conversation_id: request.conversation_id,
+            provider: request.provider,
+            model: request.model,
+        };
+        let committed = if request.branch_mode && !request.dry_run {
+            versioning.commit_branch_run(request.vault_path, &paths, &commit)
+        } else {
+            versioning.commit_run(request.vault_path, &paths, &commit)
+        };
+        let payload = match committed {
+            Ok(Some(commit_id)) => json!({
+                "run_id": run_id,
+                "commit_id": commit_id,
+                "paths": paths,
+                "ts": now_iso(),
+            }),
+            Ok(None) => return,
+            Err(error) => json!({
+                "run_id": run_id,
+                "paths": paths,
+                "error": error,
+                "ts": now_iso(),
+            }),
+        };
+        append_run_event_ledger(
+            self.store.as_ref(),
+            run_id,
+            0,
+            "lifecycle",
+            "agent:run_commit",
+            &payload,
+        );
+    }
+
+    /// Pause the run for a human decision when the approval policy covers this write, or
+    /// when the write cannot be verified, as with external MCP tools. Returns the tool result to report instead of executing a rejected write, and how
+    /// long the user took to decide.
+    async fn await_write_approval(
+        &self,
+        tc: &ToolCall,
+        args: &Value,
+        iteration: usize,
+        run_id: &str,
+        tool_ctx: &ToolExecutionContext<'_>,
+    ) -> (Option<Value>, Duration) {
+        let Some(approvals) = self.approvals.as_ref() else {
+            return (None, Duration::ZERO);
+        };
+        let staged =
+            tool_ctx.staged_run_id.is_some() && self.tools.stages_dry_run_writes(&tc.function.name);
+        let verified = self.tools.verifies_writes(&tc.function.name);
+        if !self
+            .approval_policy
+            .requires_approval_in_run(args, staged, verified)
+        {
+            return (None, Duration::ZERO);
+        }
+
+        let approval_id = uuid::Uuid::new_v4().to_string();
+        let decision = approvals.request(&approval_id);
+        let preview = self.tools.preview_write(&tc.function.name, args, tool_ctx);
+
+        let awaiting_payload = emit_run_state(
+            self.emitter.as_ref(),
+            run_id,
+            AgentState::AwaitingApproval,
+            iteration,
+            Some(&tc.function.name),
+        );
+        append_run_event_ledger(
+            self.store.as_ref(),
+            run_id,
+            iteration,
+            "lifecycle",
+            "agent:run_state",
+            &awaiting_payload,
+        );
+
+        let request_payload = json!({
+            "run_id": run_id,
+            "approval_id": approval_id,
+            "id": tc.id,
+            "iteration": iteration,
+            "tool": tc.function.name,
+            "args": args,
+            "preview": preview,
+            "ts": now_iso(),
+        });
+        self.emitter
+            .emit("agent:approval_request", &request_payload);
+        append_run_event_ledger(
+            self.store.as_ref(),
+            run_id,
+            iteration,
+            "approval",
+            "agent:approval_request",
+            &request_payload,
+        );
+
+        let wait_started = Instant::now();
+        let decision = decision.await;
+        let waited = wait_started.elapsed();
+
+        let reason = match &decision {
+            ApprovalDecision::Approved => None,
+            ApprovalDecision::Rejected { reason } => reason.clone(),
+        };
+        let approved = decision == ApprovalDecision::Approved;
+        let decision_payload = json!({
+            "run_id": run_id,
+            "approval_id": approval_id,
+            "id": tc.id,
+            "iteration": iteration,
+            "tool": tc.function.name,
+            "approved": approved,
+            "reason": reason,
+            "waited_ms": waited.as_millis() as u64,
+            "ts": now_iso(),
+        });
+        self.emitter
+            .emit("agent:approval_decision", &decision_payload);
+        append_run_event_ledger(
+            self.store.as_ref(),
+            run_id,
+            iteration,
+            "approval",
+            "agent:approval_decision",
+            &decision_payload,
+        );
+
+        if approved {
+            let resumed_payload = emit_run_state(
+                self.emitter.as_ref(),
+                run_id,
+                AgentState::ToolCalling,
+                iteration,
+                Some(&tc.function.name),
+            );
+            append_run_event_ledger(
+                self.store.as_ref(),
+                run_id,
+                iteration,
+                "lifecycle",
+                "agent:run_state",
+                &resumed_payload,
+            );
+            return (None, waited);
         }
+
+        let message = match reason.as_deref().map(str::trim) {
+            Some(reason) if !reason.is_empty() => {
+                format!("The user rejected this write: {reason}")
+            }
+            _ => "The user rejected this write. Do not retry it unless asked.".to_string(),
+        };
+        let rejection = json!({
+            "ok": false,
+            "tool": tc.function.name,
+            "action": "agent.approval",
+            "result": { "summary": "Write rejected by the user" },
+            "proof": {},
+            "ts": now_iso(),
+            "error": {
+                "code": "write_rejected",
+                "message": message,
+                "retriable": false
+            }
+        });
+        (Some(rejection), waited)
     }
 
     pub async fn run(&self, request: RunRequest<'_>) -> Result<RunResult, DynError> {
         let run_id = uuid::Uuid::new_v4().to_string();
+        let run_writes = RunWrites::default();
+        let commit_guard = RunCommitGuard {
+            agent: self,
+            request: &request,
+            run_id: &run_id,
+            writes: &run_writes,
+            committed: false,
+        };
+        let result = self.run_loop(&request, run_id.clone(), &run_writes).await;
+        commit_guard.commit();
+        result
+    }
+
+    async fn run_loop(
+        &self,
+        request: &RunRequest<'_>,
+        run_id: String,
+        run_writes: &RunWrites,
+    ) -> Result<RunResult, DynError> {
         let run_budget = request.budget.clone();
         let run_started = Instant::now();
+        // Shifted forward by time spent waiting on write approvals.
+        let mut budget_clock = run_started;
         let mut total_write_calls = 0u32;
         let mut verify_failures = 0u32;
         let mut total_tool_calls = 0u32;
         let mut total_token_usage = TokenUsage::default();
 
+        let sandbox = match self.versioning.as_ref() {
+            Some(versioning) if request.branch_mode && !request.dry_run => Some(
+                versioning
+                    .open_branch(request.vault_path, request.conversation_id)
+                    .map_err(|error| format!("Could not open the conversation branch: {error}"))?,
+            ),
+            _ => None,
+        };
+        if sandbox.is_some() {
+            // The branch forks from a fresh snapshot of the vault, so the run's writes
+            // need no snapshots of their own.
+            run_writes.skip_snapshots.store(true, Ordering::SeqCst);
+        }
+
         start_run_ledger(
             self.store.as_ref(),
             &run_id,
@@ -322,24 +706,36 @@ impl Agent {
             &request.policy_fingerprint,
         );
 
-        let mut messages = vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: request.instructions,
-                tool_calls: None,
-                tool_call_id: None,
-                tool_name: None,
-                thought_signatures: None,
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: request.user_message.to_string(),
-                tool_calls: None,
-                tool_call_id: None,
-                tool_name: None,
-                thought_signatures: None,
-            },
-        ];
+        let history = build_history_messages(
+            &self.store.conversation_history(request.conversation_id),
+            request.user_message,
+            run_budget.history_token_window,
+        );
+        let mut messages = Vec::with_capacity(history.len() + 2);
+        let instructions = if request.dry_run {
+            format!("{}\n\n{DRY_RUN_INSTRUCTION}", request.instructions)
+        } else if sandbox.is_some() {
+            format!("{}\n\n{BRANCH_MODE_INSTRUCTION}", request.instructions)
+        } else {
+            request.instructions.clone()
+        };
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: instructions,
+            tool_calls: None,
+            tool_call_id: None,
+            tool_name: None,
+            thought_signatures: None,
+        });
+        messages.extend(history);
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: request.user_message.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            tool_name: None,
+            thought_signatures: None,
+        });
 
         let accepted_payload = emit_run_state(
             self.emitter.as_ref(),
@@ -360,6 +756,30 @@ impl Agent {
             "agent:run_state",
             &accepted_payload,
         );
+        if request.dry_run {
+            append_run_event_ledger(
+                self.store.as_ref(),
+                &run_id,
+                0,
+                "lifecycle",
+                "agent:dry_run",
+                &json!({ "run_id": run_id, "ts": now_iso() }),
+            );
+        }
+        if let Some(sandbox) = &sandbox {
+            append_run_event_ledger(
+                self.store.as_ref(),
+                &run_id,
+                0,
+                "lifecycle",
+                "agent:branch_mode",
+                &json!({
+                    "run_id": run_id,
+                    "worktree": sandbox.to_string_lossy(),
+                    "ts": now_iso(),
+                }),
+            );
+        }
 
         let mut timeline_steps = 0usize;
         let is_cold_start = request.note_count == 0;
@@ -404,9 +824,9 @@ impl Agent {
             return Err(user_message.into());
         }
 
-        let tools = self.tools.tool_definitions_for_llm();
         let tool_ctx = ToolExecutionContext {
-            vault_path: request.vault_path,
+            vault_path: sandbox.as_deref().unwrap_or(request.vault_path),
+            vault_root: request.vault_path,
             db_path: request.db_path,
             embedding_key: request.embedding_key,
             embedding_model_id: request.embedding_model_id,
@@ -414,12 +834,23 @@ impl Agent {
             search_provider: request.search_provider,
             searxng_base_url: request.searxng_base_url,
             brave_api_key: request.brave_api_key,
+            staged_run_id: request.dry_run.then_some(run_id.as_str()),
+            defer_commits: self.versioning.is_some() && !request.dry_run,
+        };
+        let tools = self.run_tool_definitions(&tool_ctx, sandbox.is_some());
+        // The pre-compaction flush is the agent's own scratch file under `.meld/`, not a
+        // vault change, so it is never staged and never goes to a branch.
+        let compaction_ctx = ToolExecutionContext {
+            vault_path: request.vault_path,
+            staged_run_id: None,
+            defer_commits: false,
+            ..tool_ctx
         };
 
         for iteration in 0..run_budget.max_iterations as usize {
             if let Some(reason) = budget_timeout_reason(
                 &run_budget,
-                run_started,
+                budget_clock,
                 iteration,
                 total_tool_calls,
                 &messages,
@@ -894,7 +1325,7 @@ impl Agent {
             // Budget check once before batch
             if let Some(reason) = budget_timeout_reason(
                 &run_budget,
-                run_started,
+                budget_clock,
                 iteration,
                 total_tool_calls,
                 &messages,
@@ -931,19 +1362,19 @@ impl Agent {
             let (reads, writes): (Vec<_>, Vec<_>) = tool_calls
                 .iter()
                 .enumerate()
-                .partition(|(_, tc)| !is_write_tool(&tc.function.name));
+                .partition(|(_, tc)| !self.tools.is_write_tool(&tc.function.name));
 
             // Parallel reads via join_all (no 'static needed — borrows &self, &tool_ctx)
-            let read_futures = reads
-                .iter()
-                .map(|(idx, tc)| self.execute_one_tool(tc, *idx, iteration, &run_id, &tool_ctx));
+            let read_futures = reads.iter().map(|(idx, tc)| {
+                self.execute_one_tool(tc, *idx, iteration, &run_id, &tool_ctx, run_writes)
+            });
             let read_outcomes = futures::future::join_all(read_futures).await;
 
             // Sequential writes
             let mut write_outcomes = Vec::new();
             for (idx, tc) in &writes {
                 let outcome = self
-                    .execute_one_tool(tc, *idx, iteration, &run_id, &tool_ctx)
+                    .execute_one_tool(tc, *idx, iteration, &run_id, &tool_ctx, run_writes)
                     .await;
                 write_outcomes.push(outcome);
             }
@@ -958,6 +1389,7 @@ impl Agent {
 
             for outcome in &all_outcomes {
                 total_tool_calls += 1;
+                budget_clock += outcome.approval_wait;
                 if outcome.is_write {
                     total_write_calls += 1;
                 }
@@ -1026,7 +1458,7 @@ impl Agent {
                 request.api_key,
                 self.llm.as_ref(),
                 self.tools.as_ref(),
-                &tool_ctx,
+                &compaction_ctx,
             )
             .await;
             if compaction.compacted {
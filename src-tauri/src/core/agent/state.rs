@@ -17,6 +17,7 @@ pub enum AgentState {
     Planning,
     Thinking,
     ToolCalling,
+    AwaitingApproval,
     Verifying,
     Responding,
     Completed,
@@ -32,6 +33,7 @@ impl AgentState {
             AgentState::Planning => "planning",
             AgentState::Thinking => "thinking",
             AgentState::ToolCalling => "tool_calling",
+            AgentState::AwaitingApproval => "awaiting_approval",
             AgentState::Verifying => "verifying",
             AgentState::Responding => "responding",
             AgentState::Completed => "completed",
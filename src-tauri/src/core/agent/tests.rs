@@ -0,0 +1,399 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+
+use super::{Agent, ApprovalPolicy, RunBudget, RunRequest};
+use crate::adapters::llm::{FunctionCall, FunctionDefinition, ToolCall};
+use crate::adapters::mcp::ToolRegistry;
+use crate::core::ports::approval::{ApprovalDecision, ApprovalPort};
+use crate::core::ports::emitter::EmitterPort;
+use crate::core::ports::llm::{DynError, LlmChatRequest, LlmPort, StreamEvent};
+use crate::core::ports::store::{HistoryMessage, RunFinishRecord, RunStartRecord, StorePort};
+use crate::core::ports::tools::{ToolDefinition, ToolExecutionContext, ToolPort};
+use crate::core::ports::versioning::{RunCommit, VersioningPort};
+
+/// Answers each model turn with the next scripted batch of tool calls, then with plain
+/// text, and records the tool names offered on every turn.
+#[derive(Default)]
+struct ScriptedLlm {
+    turns: Mutex<VecDeque<Vec<ToolCall>>>,
+    offered: Mutex<Vec<Vec<String>>>,
+}
+
+impl ScriptedLlm {
+    fn calling(turns: Vec<Vec<ToolCall>>) -> Arc<Self> {
+        Arc::new(Self {
+            turns: Mutex::new(turns.into()),
+            offered: Mutex::default(),
+        })
+    }
+
+    /// Tools offered on the run's first turn.
+    fn first_offer(&self) -> Vec<String> {
+        self.offered
+            .lock()
+            .expect("offered lock")
+            .first()
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl LlmPort for ScriptedLlm {
+    fn chat_stream<'a>(
+        &'a self,
+        request: LlmChatRequest<'a>,
+    ) -> BoxFuture<'a, Result<(), DynError>> {
+        Box::pin(async move {
+            let offered = request
+                .tools
+                .unwrap_or_default()
+                .iter()
+                .map(|tool| tool.function.name.clone())
+                .collect();
+            self.offered.lock().expect("offered lock").push(offered);
+            match self.turns.lock().expect("turns lock").pop_front() {
+                Some(calls) => {
+                    for call in calls {
+                        let _ = request.tx.send(StreamEvent::ToolCall(call));
+                    }
+                }
+                None => {
+                    let _ = request.tx.send(StreamEvent::Text("Done.".to_string()));
+                }
+            }
+            let _ = request.tx.send(StreamEvent::Done);
+            Ok(())
+        })
+    }
+}
+
+fn tool_call(name: &str, args: Value) -> ToolCall {
+    ToolCall {
+        id: format!("call-{name}"),
+        r#type: "function".to_string(),
+        function: FunctionCall {
+            name: name.to_string(),
+            arguments: args.to_string(),
+        },
+        thought_signature: None,
+    }
+}
+
+/// A note writer that dry runs stage, and an external `tracker__create_issue` write
+/// tool whose effect cannot be read back. Records every call it executes.
+#[derive(Default)]
+struct NoteAndTrackerTools {
+    executed: Mutex<Vec<String>>,
+}
+
+const TRACKER_TOOL: &str = "tracker__create_issue";
+
+impl NoteAndTrackerTools {
+    fn executed(&self) -> Vec<String> {
+        self.executed.lock().expect("executed lock").clone()
+    }
+}
+
+impl ToolPort for NoteAndTrackerTools {
+    fn tool_definitions_for_llm(&self) -> Vec<ToolDefinition> {
+        ["kb_create", TRACKER_TOOL]
+            .into_iter()
+            .map(|name| ToolDefinition {
+                r#type: "function".to_string(),
+                function: FunctionDefinition {
+                    name: name.to_string(),
+                    description: String::new(),
+                    parameters: json!({ "type": "object" }),
+                },
+            })
+            .collect()
+    }
+
+    fn prompt_tool_lines(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn is_write_tool(&self, _name: &str) -> bool {
+        true
+    }
+
+    fn stages_dry_run_writes(&self, name: &str) -> bool {
+        name == "kb_create"
+    }
+
+    fn verifies_writes(&self, name: &str) -> bool {
+        name != TRACKER_TOOL
+    }
+
+    fn execute<'a>(
+        &'a self,
+        name: &'a str,
+        _args: Value,
+        _ctx: &'a ToolExecutionContext<'a>,
+    ) -> BoxFuture<'a, Value> {
+        Box::pin(async move {
+            self.executed
+                .lock()
+                .expect("executed lock")
+                .push(name.to_string());
+            json!({ "ok": true, "result": { "summary": "done" }, "proof": {} })
+        })
+    }
+}
+
+/// Approves every request at once and counts them.
+#[derive(Default)]
+struct ApproveAll {
+    requests: Mutex<usize>,
+}
+
+impl ApprovalPort for ApproveAll {
+    fn request(&self, _approval_id: &str) -> BoxFuture<'static, ApprovalDecision> {
+        *self.requests.lock().expect("requests lock") += 1;
+        Box::pin(async { ApprovalDecision::Approved })
+    }
+}
+
+/// Keeps every emitted event; the run ledger is discarded.
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<(String, Value)>>,
+}
+
+impl Recorder {
+    /// Parsed result of the first call to `tool`.
+    fn tool_result(&self, tool: &str) -> Option<Value> {
+        self.events
+            .lock()
+            .expect("events lock")
+            .iter()
+            .find(|(channel, payload)| {
+                channel == "agent:tool_result" && payload["tool"].as_str() == Some(tool)
+            })
+            .and_then(|(_, payload)| serde_json::from_str(payload["result"].as_str()?).ok())
+    }
+
+    /// Tools that paused the run for an approval, in order.
+    fn approval_requests(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .expect("events lock")
+            .iter()
+            .filter(|(channel, _)| channel == "agent:approval_request")
+            .filter_map(|(_, payload)| payload["tool"].as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+impl EmitterPort for Recorder {
+    fn emit(&self, channel: &str, payload: &Value) {
+        self.events
+            .lock()
+            .expect("events lock")
+            .push((channel.to_string(), payload.clone()));
+    }
+}
+
+impl StorePort for Recorder {
+    fn start_run(&self, _record: RunStartRecord<'_>) {}
+    fn log_event(
+        &self,
+        _run_id: &str,
+        _iteration: usize,
+        _channel: &str,
+        _event_type: &str,
+        _payload: &Value,
+    ) {
+    }
+    fn finish_run(&self, _record: RunFinishRecord<'_>) {}
+    fn conversation_history(&self, _conversation_id: i64) -> Vec<HistoryMessage> {
+        Vec::new()
+    }
+}
+
+/// Versioning that accepts every call and commits nothing.
+struct NoCommits;
+
+impl VersioningPort for NoCommits {
+    fn snapshot_before_write(
+        &self,
+        _vault_path: &Path,
+        _run_id: &str,
+        _paths: &[String],
+    ) -> Result<(), String> {
+        Ok(())
+    }
+    fn commit_run(
+        &self,
+        _vault_path: &Path,
+        _paths: &[String],
+        _run: &RunCommit<'_>,
+    ) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+    fn open_branch(&self, _vault_path: &Path, _conversation_id: i64) -> Result<PathBuf, String> {
+        Err("branches are not supported here".to_string())
+    }
+    fn commit_branch_run(
+        &self,
+        _vault_path: &Path,
+        _paths: &[String],
+        _run: &RunCommit<'_>,
+    ) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+}
+
+fn temp_vault() -> PathBuf {
+    let vault = std::env::temp_dir().join(format!("meld-agent-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&vault).expect("create temp vault");
+    vault
+}
+
+fn request<'a>(vault: &'a Path, db_path: &'a Path) -> RunRequest<'a> {
+    RunRequest {
+        conversation_id: 1,
+        user_message: "Tidy up my notes",
+        instructions: "You manage a markdown vault.".to_string(),
+        policy_version: "test".to_string(),
+        policy_fingerprint: "test".to_string(),
+        api_key: "",
+        provider: "test",
+        model: "test-model",
+        is_regeneration: false,
+        vault_path: vault,
+        db_path,
+        embedding_key: "",
+        embedding_model_id: "openai:text-embedding-3-small",
+        tavily_api_key: "",
+        search_provider: "tavily",
+        searxng_base_url: "http://localhost:8080",
+        brave_api_key: "",
+        note_count: 0,
+        indexed_files: 0,
+        indexed_chunks: 0,
+        budget: RunBudget::default(),
+        dry_run: false,
+        branch_mode: false,
+    }
+}
+
+#[tokio::test]
+async fn kb_revert_is_left_out_of_runs_that_commit_at_the_end() {
+    let vault = temp_vault();
+    let db_path = vault.join(".meld").join("index.db");
+
+    let llm = ScriptedLlm::calling(vec![vec![tool_call(
+        "kb_revert",
+        json!({ "commit_id": "abc123" }),
+    )]]);
+    let recorder = Arc::new(Recorder::default());
+    let agent = Agent::new(
+        Arc::new(ToolRegistry::new(false)),
+        llm.clone(),
+        recorder.clone(),
+        recorder.clone(),
+    )
+    .with_versioning(Arc::new(NoCommits));
+    agent
+        .run(request(&vault, &db_path))
+        .await
+        .expect("deferred run");
+
+    let offered = llm.first_offer();
+    assert!(offered.iter().any(|name| name == "kb_history"));
+    assert!(!offered.iter().any(|name| name == "kb_revert"));
+    // A model that calls it anyway is refused before anything is reverted.
+    let result = recorder.tool_result("kb_revert").expect("kb_revert result");
+    assert_eq!(
+        result.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("agent_run_unsupported")
+    );
+
+    // Without run-level commits each write commits on its own, so reverts work.
+    let llm = ScriptedLlm::calling(Vec::new());
+    let recorder = Arc::new(Recorder::default());
+    let agent = Agent::new(
+        Arc::new(ToolRegistry::new(false)),
+        llm.clone(),
+        recorder.clone(),
+        recorder,
+    );
+    agent
+        .run(request(&vault, &db_path))
+        .await
+        .expect("per-write run");
+    assert!(llm.first_offer().iter().any(|name| name == "kb_revert"));
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn external_writes_wait_for_approval_under_the_auto_policy() {
+    let vault = temp_vault();
+    let db_path = vault.join(".meld").join("index.db");
+
+    let llm = ScriptedLlm::calling(vec![vec![
+        tool_call("kb_create", json!({ "path": "idea.md", "content": "x" })),
+        tool_call(TRACKER_TOOL, json!({ "title": "Follow up" })),
+    ]]);
+    let tools = Arc::new(NoteAndTrackerTools::default());
+    let recorder = Arc::new(Recorder::default());
+    let approvals = Arc::new(ApproveAll::default());
+    let agent = Agent::new(tools.clone(), llm, recorder.clone(), recorder.clone())
+        .with_approvals(approvals.clone(), ApprovalPolicy::Auto);
+    agent.run(request(&vault, &db_path)).await.expect("run");
+
+    assert_eq!(recorder.approval_requests(), vec![TRACKER_TOOL.to_string()]);
+    assert_eq!(*approvals.requests.lock().expect("requests lock"), 1);
+    assert_eq!(
+        tools.executed(),
+        vec!["kb_create".to_string(), TRACKER_TOOL.to_string()]
+    );
+
+    let _ = std::fs::remove_dir_all(vault);
+}
+
+#[tokio::test]
+async fn dry_runs_leave_out_writes_they_cannot_stage() {
+    let vault = temp_vault();
+    let db_path = vault.join(".meld").join("index.db");
+
+    let llm = ScriptedLlm::calling(vec![vec![
+        tool_call("kb_create", json!({ "path": "idea.md", "content": "x" })),
+        tool_call(TRACKER_TOOL, json!({ "title": "Follow up" })),
+    ]]);
+    let tools = Arc::new(NoteAndTrackerTools::default());
+    let recorder = Arc::new(Recorder::default());
+    let agent = Agent::new(
+        tools.clone(),
+        llm.clone(),
+        recorder.clone(),
+        recorder.clone(),
+    )
+    .with_approvals(Arc::new(ApproveAll::default()), ApprovalPolicy::Always)
+    .with_versioning(Arc::new(NoCommits));
+    agent
+        .run(RunRequest {
+            dry_run: true,
+            ..request(&vault, &db_path)
+        })
+        .await
+        .expect("dry run");
+
+    assert_eq!(llm.first_offer(), vec!["kb_create".to_string()]);
+    // A call the model makes anyway is refused without reaching the tool.
+    assert_eq!(tools.executed(), vec!["kb_create".to_string()]);
+    let refused = recorder.tool_result(TRACKER_TOOL).expect("tracker result");
+    assert_eq!(
+        refused.pointer("/error/code").and_then(|v| v.as_str()),
+        Some("dry_run_unsupported")
+    );
+    assert!(recorder.approval_requests().is_empty());
+
+    let _ = std::fs::remove_dir_all(vault);
+}
@@ -0,0 +1,13 @@
+use futures::future::BoxFuture;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Approved,
+    Rejected { reason: Option<String> },
+}
+
+pub trait ApprovalPort: Send + Sync {
+    /// Register `approval_id` as pending and wait for the user's decision. Registration
+    /// happens before the future is returned, so the request can be emitted afterwards.
+    fn request(&self, approval_id: &str) -> BoxFuture<'static, ApprovalDecision>;
+}
@@ -1,4 +1,6 @@
+pub mod approval;
 pub mod emitter;
 pub mod llm;
 pub mod store;
 pub mod tools;
+pub mod versioning;
@@ -26,6 +26,13 @@ pub struct RunFinishRecord<'a> {
     pub cache_write_tokens: Option<u64>,
 }
 
+/// A persisted conversation turn, as replayed into a new run.
+pub struct HistoryMessage {
+    pub role: String,
+    pub content: String,
+    pub tool_calls: Option<Value>,
+}
+
 pub trait StorePort: Send + Sync {
     fn start_run(&self, record: RunStartRecord<'_>);
     fn log_event(
@@ -37,4 +44,5 @@ pub trait StorePort: Send + Sync {
         payload: &Value,
     );
     fn finish_run(&self, record: RunFinishRecord<'_>);
+    fn conversation_history(&self, conversation_id: i64) -> Vec<HistoryMessage>;
 }
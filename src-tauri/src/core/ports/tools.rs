@@ -1,12 +1,16 @@
 use std::path::Path;
 
 use futures::future::BoxFuture;
-use serde_json::Value;
+use serde_json::{json, Value};
 
 pub use crate::adapters::llm::ToolDefinition;
 
+#[derive(Clone, Copy)]
 pub struct ToolExecutionContext<'a> {
+    /// Where notes are read and written: the vault, or a conversation branch's worktree.
     pub vault_path: &'a Path,
+    /// The vault itself. Its ignore and privacy rules apply wherever `vault_path` points.
+    pub vault_root: &'a Path,
     pub db_path: &'a Path,
     pub embedding_key: &'a str,
     pub embedding_model_id: &'a str,
@@ -14,15 +18,51 @@ pub struct ToolExecutionContext<'a> {
     pub search_provider: &'a str,
     pub searxng_base_url: &'a str,
     pub brave_api_key: &'a str,
+    /// Dry runs stage note writes under this run id instead of touching the vault.
+    pub staged_run_id: Option<&'a str>,
+    /// Agent runs commit their note writes once when the run ends, so tools skip their
+    /// own per-write commits.
+    pub defer_commits: bool,
 }
 
 pub trait ToolPort: Send + Sync {
     fn tool_definitions_for_llm(&self) -> Vec<ToolDefinition>;
     fn prompt_tool_lines(&self) -> Vec<String>;
+    /// Write tools run sequentially and count toward the run's write budget.
+    fn is_write_tool(&self, name: &str) -> bool;
+    /// Whether a dry run stages the tool's writes instead of applying them. Other write
+    /// tools, such as external MCP tools, would take effect, so dry runs leave them out.
+    fn stages_dry_run_writes(&self, _name: &str) -> bool {
+        false
+    }
+    /// Whether the tool's writes are read back and verified. Writes that cannot be, such
+    /// as those of external MCP tools, always wait for the user's approval.
+    fn verifies_writes(&self, _name: &str) -> bool {
+        true
+    }
+    /// Whether the tool works on a conversation branch's worktree. Tools that read the
+    /// vault history do not, and are left out of branch-mode runs.
+    fn available_in_branch(&self, _name: &str) -> bool {
+        true
+    }
+    /// Whether the tool works in a run that commits its writes once at the end. Tools
+    /// that commit on their own do not, and are left out of such runs.
+    fn available_with_deferred_commits(&self, _name: &str) -> bool {
+        true
+    }
+    /// Vault-relative notes a write call will change, where the tool can tell in advance.
+    fn write_paths(&self, _name: &str, _args: &Value) -> Vec<String> {
+        Vec::new()
+    }
     fn execute<'a>(
         &'a self,
         name: &'a str,
         args: Value,
         ctx: &'a ToolExecutionContext<'a>,
     ) -> BoxFuture<'a, Value>;
+    /// Proposed effect of a write call (`path`, `content`, `diff`), shown to the user
+    /// before it is approved. Tools that cannot predict their effect return `{}`.
+    fn preview_write(&self, _name: &str, _args: &Value, _ctx: &ToolExecutionContext<'_>) -> Value {
+        json!({})
+    }
 }
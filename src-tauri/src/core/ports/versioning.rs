@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+/// The agent run behind a vault commit, recorded on the commit as git trailers.
+pub struct RunCommit<'a> {
+    pub run_id: &'a str,
+    pub conversation_id: i64,
+    pub provider: &'a str,
+    pub model: &'a str,
+}
+
+pub trait VersioningPort: Send + Sync {
+    /// Commit the notes in `paths` as they are before the run first writes them, so the
+    /// run's own commit only holds the agent's changes.
+    fn snapshot_before_write(
+        &self,
+        vault_path: &Path,
+        run_id: &str,
+        paths: &[String],
+    ) -> Result<(), String>;
+    /// Commit every note the run wrote as one commit. Returns the commit id, or `None`
+    /// when nothing changed.
+    fn commit_run(
+        &self,
+        vault_path: &Path,
+        paths: &[String],
+        run: &RunCommit<'_>,
+    ) -> Result<Option<String>, String>;
+    /// Prepare the conversation's branch for a branch-mode run and return the shadow
+    /// worktree the run reads and writes instead of the vault.
+    fn open_branch(&self, vault_path: &Path, conversation_id: i64) -> Result<PathBuf, String>;
+    /// Commit the notes a branch-mode run wrote onto the conversation's branch.
+    fn commit_branch_run(
+        &self,
+        vault_path: &Path,
+        paths: &[String],
+        run: &RunCommit<'_>,
+    ) -> Result<Option<String>, String>;
+}
@@ -4,5 +4,15 @@ pub mod runtime;
 
 pub fn run() {
     env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("mcp-serve") {
+        if let Err(error) = runtime::mcp_serve::run(&args[1..]) {
+            eprintln!("meld mcp-serve: {error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     runtime::tauri_api::run();
 }
@@ -0,0 +1,65 @@
+//! Headless `meld mcp-serve` entry point: serves the vault tools over MCP stdio.
+
+use std::path::PathBuf;
+
+use crate::adapters::config::{Settings, VaultConfig};
+use crate::adapters::mcp::server::{McpServer, McpServerConfig};
+
+const USAGE: &str = "Usage: meld mcp-serve [--vault <path>]";
+
+fn parse_vault_arg(args: &[String]) -> Result<Option<String>, String> {
+    let mut vault = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some(value) = arg.strip_prefix("--vault=") {
+            vault = Some(value.to_string());
+        } else if arg == "--vault" {
+            let value = iter.next().ok_or_else(|| USAGE.to_string())?;
+            vault = Some(value.clone());
+        } else {
+            return Err(format!("Unknown argument '{arg}'. {USAGE}"));
+        }
+    }
+    Ok(vault.filter(|path| !path.trim().is_empty()))
+}
+
+async fn serve(args: &[String]) -> Result<(), String> {
+    let global_settings = Settings::load_global();
+    let vault_path = parse_vault_arg(args)?
+        .or_else(|| global_settings.vault_path.clone())
+        .ok_or_else(|| format!("No vault configured. {USAGE}"))?;
+    let vault = PathBuf::from(&vault_path);
+    if !vault.is_dir() {
+        return Err(format!("Vault not found: {vault_path}"));
+    }
+    crate::adapters::vault::ensure_vault_initialized(&vault).map_err(|e| e.to_string())?;
+
+    let vault_config = VaultConfig::load(&vault);
+    let mut settings = global_settings.merged_with_vault(&vault_config);
+    let embedding_provider = settings.embedding_provider();
+    let embedding_key =
+        crate::adapters::oauth::resolve_provider_credential(&mut settings, &embedding_provider)
+            .await
+            .unwrap_or_default();
+
+    let server = McpServer::new(McpServerConfig {
+        db_path: crate::adapters::vault::meld_dir(&vault).join("index.db"),
+        vault_path: vault,
+        embedding_key,
+        embedding_model_id: settings.embedding_model_id(),
+    });
+    log::info!("meld MCP server listening on stdio for vault {vault_path}");
+
+    server
+        .serve(
+            tokio::io::BufReader::new(tokio::io::stdin()),
+            tokio::io::stdout(),
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(serve(args))
+}
@@ -1 +1,2 @@
+pub mod mcp_serve;
 pub mod tauri_api;
@@ -52,6 +52,15 @@ fn finish_assistant_run(conversation_id: i64, token: u64) {
     }
 }
 
+/// Whether any assistant run is in flight. Its note writes are not committed until the
+/// run ends, so the vault watcher holds back user-edit snapshots meanwhile.
+pub(crate) fn has_active_runs() -> bool {
+    active_assistant_runs()
+        .lock()
+        .map(|active_runs| !active_runs.is_empty())
+        .unwrap_or(false)
+}
+
 pub(crate) fn cancel_active_run(conversation_id: i64) -> bool {
     if let Ok(mut active_runs) = active_assistant_runs().lock() {
         if let Some(active_run) = active_runs.remove(&conversation_id) {
@@ -103,7 +112,7 @@ fn extract_sources_from_tool_result(tool: &str, value: &serde_json::Value) -> Ve
                 }
             }
         }
-        "kb_read" | "kb_create" | "kb_update" => {
+        "kb_read" | "kb_create" | "kb_update" | "kb_patch" => {
             if let Some(path) = value
                 .pointer("/target/resolved_path")
                 .and_then(|v| v.as_str())
@@ -252,6 +261,7 @@ async fn execute_assistant_run(
     model: String,
     db_path: PathBuf,
     is_regeneration: bool,
+    dry_run: bool,
 ) -> Result<(), String> {
     let vault = Path::new(&vault_path);
     let note_count = crate::adapters::vault::list_md_files(vault)
@@ -272,6 +282,7 @@ async fn execute_assistant_run(
             .await
             .unwrap_or_default();
     let embedding_model_id = settings.embedding_model_id();
+    let history_token_window = settings.history_token_window();
     let tavily_api_key = settings.tavily_api_key();
     let search_provider = settings.search_provider();
     let searxng_base_url = settings.searxng_base_url();
@@ -286,8 +297,15 @@ async fn execute_assistant_run(
         .ok()
         .and_then(|db| db.get_conversation_folder_id(conversation_id).ok())
         .flatten();
+    let branch_mode = crate::adapters::vectordb::VectorDb::open(&db_path)
+        .ok()
+        .and_then(|db| db.resolve_conversation_branch_mode(conversation_id).ok())
+        .unwrap_or(false);
 
-    let tool_registry = crate::adapters::mcp::ToolRegistry::new(has_web_search);
+    let mut tool_registry = crate::adapters::mcp::ToolRegistry::new(has_web_search);
+    tool_registry
+        .mount_external_servers(&settings.mcp_servers())
+        .await;
     let tool_prompt_lines = ToolPort::prompt_tool_lines(&tool_registry);
     let instruction_sources = load_instruction_sources(vault, &db_path, folder_id);
     let composed_prompt = crate::core::agent::instructions::compose_system_prompt_with_metadata(
@@ -307,7 +325,12 @@ async fn execute_assistant_run(
             db_path.clone(),
         )),
         Arc::new(crate::adapters::emitter::TauriEmitter::new(app.clone())),
-    );
+    )
+    .with_approvals(
+        Arc::new(crate::adapters::approval::PendingApprovals::new()),
+        settings.write_approval_policy(),
+    )
+    .with_versioning(Arc::new(crate::adapters::git::GitVersioning));
 
     let run_result: Result<crate::core::agent::RunResult, _> = agent
         .run(crate::core::agent::RunRequest {
@@ -331,7 +354,12 @@ async fn execute_assistant_run(
             note_count,
             indexed_files: indexed_files.max(0) as usize,
             indexed_chunks: indexed_chunks.max(0) as usize,
-            budget: crate::core::agent::RunBudget::default(),
+            budget: crate::core::agent::RunBudget {
+                history_token_window,
+                ..crate::core::agent::RunBudget::default()
+            },
+            dry_run,
+            branch_mode,
         })
         .await;
     if let Ok(run) = &run_result {
@@ -347,7 +375,7 @@ async fn execute_assistant_run(
     }
 
     match run_result {
-        Ok(_) => {
+        Ok(run) => {
             let captured = capture.lock().map(|data| data.clone()).unwrap_or_default();
             let assistant_content = if captured.content.trim().is_empty() {
                 default_empty_assistant_message()
@@ -386,10 +414,21 @@ async fn execute_assistant_run(
 
             match persist_result {
                 Ok(_) => {
+                    let staged_changes = if dry_run {
+                        crate::adapters::vectordb::VectorDb::open(&db_path)
+                            .and_then(|db| db.list_staged_changes(&run.run_id))
+                            .map(|changes| changes.len())
+                            .ok()
+                    } else {
+                        None
+                    };
                     let done_payload = serde_json::json!({
                         "content": assistant_content,
                         "sources": sources,
                         "timestamp": chrono::Utc::now().to_rfc3339(),
+                        "run_id": run.run_id,
+                        "staged_changes": staged_changes,
+                        "branch_mode": branch_mode && !dry_run,
                     });
                     if let Err(error) = app.emit("chat:done", done_payload) {
                         log::warn!("failed to emit chat:done: {}", error);
@@ -422,6 +461,7 @@ pub(crate) fn spawn_assistant_task(
     model: String,
     db_path: PathBuf,
     is_regeneration: bool,
+    dry_run: bool,
 ) {
     let run_token = NEXT_ASSISTANT_RUN_TOKEN.fetch_add(1, Ordering::Relaxed);
     let (cancel_tx, mut cancel_rx) = tokio::sync::oneshot::channel::<()>();
@@ -449,6 +489,7 @@ pub(crate) fn spawn_assistant_task(
                 model,
                 db_path,
                 is_regeneration,
+                dry_run,
             ) => {
                 if let Err(error) = run_outcome {
                     log::warn!("assistant run failed: {}", error);
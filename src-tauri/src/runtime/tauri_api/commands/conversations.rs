@@ -1,7 +1,9 @@
+use std::path::Path;
 use tauri::AppHandle;
 
 use crate::adapters::config::Settings;
 use crate::adapters::providers::split_model_id;
+use crate::core::ports::approval::ApprovalDecision;
 
 use super::assistant::spawn_assistant_task;
 use super::shared::{
@@ -109,6 +111,104 @@ pub async fn get_run_events(
     db.get_run_events(normalized).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn list_staged_changes(
+    run_id: String,
+) -> Result<Vec<crate::adapters::vectordb::StagedChange>, String> {
+    let normalized = run_id.trim();
+    if normalized.is_empty() {
+        return Err("run_id is required".to_string());
+    }
+
+    let settings = Settings::load_global();
+    let db_path = current_db_path(&settings)?;
+    let db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    db.list_staged_changes(normalized)
+        .map_err(|e| e.to_string())
+}
+
+/// Pending staged change counts of a conversation's dry runs, keyed by run id.
+#[tauri::command]
+pub async fn list_conversation_staged_changes(
+    conversation_id: String,
+) -> Result<std::collections::BTreeMap<String, usize>, String> {
+    let parsed_id = parse_conversation_id(&conversation_id)?;
+    let settings = Settings::load_global();
+    let db_path = current_db_path(&settings)?;
+    let db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    db.staged_change_counts(parsed_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Apply the staged changes of a dry run (all, or only `paths`) in one vault commit.
+/// Returns the applied paths.
+#[tauri::command]
+pub async fn apply_staged_changes(
+    run_id: String,
+    paths: Option<Vec<String>>,
+) -> Result<Vec<String>, String> {
+    let normalized = run_id.trim();
+    if normalized.is_empty() {
+        return Err("run_id is required".to_string());
+    }
+
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.clone().ok_or("No vault configured")?;
+    let db_path = current_db_path(&settings)?;
+    let mut db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    let changes: Vec<_> = db
+        .list_staged_changes(normalized)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|change| {
+            paths
+                .as_ref()
+                .is_none_or(|paths| paths.contains(&change.path))
+        })
+        .collect();
+    if changes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let short_run_id: String = normalized.chars().take(8).collect();
+    let message = if changes.len() == 1 {
+        format!(
+            "meld: applied staged {} from run {short_run_id}",
+            changes[0].path
+        )
+    } else {
+        format!(
+            "meld: applied {} staged changes from run {short_run_id}",
+            changes.len()
+        )
+    };
+    crate::adapters::git::apply_staged_changes(Path::new(&vault_path), &changes, &message)
+        .map_err(|e| e.to_string())?;
+
+    let applied: Vec<String> = changes.into_iter().map(|change| change.path).collect();
+    db.remove_staged_changes(normalized, Some(&applied))
+        .map_err(|e| e.to_string())?;
+    Ok(applied)
+}
+
+/// Drop staged changes of a dry run (all, or only `paths`) without touching the vault.
+#[tauri::command]
+pub async fn discard_staged_changes(
+    run_id: String,
+    paths: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let normalized = run_id.trim();
+    if normalized.is_empty() {
+        return Err("run_id is required".to_string());
+    }
+
+    let settings = Settings::load_global();
+    let db_path = current_db_path(&settings)?;
+    let mut db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    db.remove_staged_changes(normalized, paths.as_deref())
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_conversation_messages(
     conversation_id: String,
@@ -165,6 +265,20 @@ pub async fn unarchive_conversation(conversation_id: String) -> Result<(), Strin
         .map_err(|e| e.to_string())
 }
 
+/// Send the conversation's agent writes to its own branch instead of the vault.
+#[tauri::command]
+pub async fn set_conversation_branch_mode(
+    conversation_id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let parsed_id = parse_conversation_id(&conversation_id)?;
+    let settings = Settings::load_global();
+    let db_path = current_db_path(&settings)?;
+    let mut db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    db.set_conversation_branch_mode(parsed_id, enabled)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn pin_conversation(conversation_id: String) -> Result<(), String> {
     let parsed_id = parse_conversation_id(&conversation_id)?;
@@ -205,12 +319,31 @@ pub async fn cancel_active_run(conversation_id: String) -> Result<bool, String>
     Ok(super::assistant::cancel_active_run(parsed_id))
 }
 
+/// Answer an `agent:approval_request`. Returns false when the run is no longer waiting.
+#[tauri::command]
+pub async fn respond_to_write_approval(
+    approval_id: String,
+    approved: bool,
+    reason: Option<String>,
+) -> Result<bool, String> {
+    let decision = if approved {
+        ApprovalDecision::Approved
+    } else {
+        ApprovalDecision::Rejected { reason }
+    };
+    Ok(crate::adapters::approval::resolve_approval(
+        &approval_id,
+        decision,
+    ))
+}
+
 #[tauri::command]
 pub async fn send_message(
     app: AppHandle,
     message: String,
     conversation_id: Option<String>,
     folder_id: Option<String>,
+    dry_run: Option<bool>,
 ) -> Result<SendMessageResponse, String> {
     let global_settings = Settings::load_global();
     let vault_path = global_settings
@@ -268,6 +401,7 @@ pub async fn send_message(
         model,
         db_path,
         false,
+        dry_run.unwrap_or(false),
     );
 
     Ok(SendMessageResponse {
@@ -339,6 +473,7 @@ pub async fn regenerate_last_response(
         model,
         db_path,
         true,
+        false,
     );
 
     Ok(SendMessageResponse {
@@ -395,6 +530,7 @@ pub async fn edit_user_message(
         model,
         db_path,
         true,
+        false,
     );
 
     Ok(SendMessageResponse {
@@ -79,6 +79,17 @@ pub async fn update_chat_folder(
     .map_err(|e| e.to_string())
 }
 
+/// Run agent writes of every conversation in the folder on per-conversation branches.
+#[tauri::command]
+pub async fn set_folder_branch_mode(folder_id: String, enabled: bool) -> Result<(), String> {
+    let parsed_id = parse_folder_id(&folder_id)?;
+    let settings = Settings::load_global();
+    let db_path = current_db_path(&settings)?;
+    let mut db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    db.set_folder_branch_mode(parsed_id, enabled)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn archive_chat_folder(folder_id: String) -> Result<(), String> {
     let parsed_id = parse_folder_id(&folder_id)?;
@@ -1,25 +1,140 @@
 use crate::adapters::config::Settings;
-use crate::adapters::git::HistoryEntry;
+use crate::adapters::git::{
+    BranchMerge, CommitDiff, ConflictStyle, HistoryPage, HistoryQuery, NoteBlame, RevertOutcome,
+};
 
+use super::shared::{current_db_path, parse_conversation_id, ConversationBranch};
+
+/// One page of vault history; pass the previous page's `next_cursor` as `query.after`.
 #[tauri::command]
-pub async fn get_history() -> Result<Vec<HistoryEntry>, String> {
+pub async fn get_history(query: Option<HistoryQuery>) -> Result<HistoryPage, String> {
     let settings = Settings::load_global();
     let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
-    let meld_git_dir = std::path::Path::new(vault_path).join(".meld").join(".git");
-    if !meld_git_dir.exists() {
-        return Ok(Vec::new());
+    if !crate::adapters::git::has_history(std::path::Path::new(vault_path)) {
+        return Ok(HistoryPage {
+            entries: Vec::new(),
+            next_cursor: None,
+        });
     }
 
-    crate::adapters::git::get_history(std::path::Path::new(vault_path), None, None)
+    crate::adapters::git::query_history(
+        std::path::Path::new(vault_path),
+        &query.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn revert_commit(
+    commit_id: String,
+    conflict_style: Option<String>,
+) -> Result<RevertOutcome, String> {
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+    let style = ConflictStyle::parse(conflict_style.as_deref())?;
+
+    crate::adapters::git::revert_commit(std::path::Path::new(vault_path), &commit_id, style)
+        .map_err(|e| e.to_string())
+}
+
+/// Undo every note change made by one agent run.
+#[tauri::command]
+pub async fn revert_run(
+    run_id: String,
+    conflict_style: Option<String>,
+) -> Result<RevertOutcome, String> {
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+    let style = ConflictStyle::parse(conflict_style.as_deref())?;
+
+    crate::adapters::git::revert_run(std::path::Path::new(vault_path), &run_id, style)
+        .map_err(|e| e.to_string())
+}
+
+/// Write one note back to its content at `commit_id`. `None` when it already matched.
+#[tauri::command]
+pub async fn restore_file(path: String, commit_id: String) -> Result<Option<CommitDiff>, String> {
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+    let path = crate::adapters::vault::normalize_note_path(&path)?;
+
+    crate::adapters::git::restore_file(std::path::Path::new(vault_path), &path, &commit_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn read_file_at(path: String, commit_id: String) -> Result<String, String> {
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+    let path = crate::adapters::vault::normalize_note_path(&path)?;
+
+    crate::adapters::git::read_file_at(std::path::Path::new(vault_path), &path, &commit_id)
+        .map(|(_, content)| content)
+        .map_err(|e| e.to_string())
+}
+
+/// Which lines of a note were last written by the agent and which by the user.
+#[tauri::command]
+pub async fn get_note_blame(path: String) -> Result<NoteBlame, String> {
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+    let path = crate::adapters::vault::normalize_note_path(&path)?;
+
+    crate::adapters::git::blame_note(std::path::Path::new(vault_path), &path)
+        .map_err(|e| e.to_string())
+}
+
+/// Branch-mode setting of a conversation and the diff of its branch against the vault.
+#[tauri::command]
+pub async fn get_conversation_branch(
+    conversation_id: String,
+) -> Result<ConversationBranch, String> {
+    let parsed_id = parse_conversation_id(&conversation_id)?;
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+    let db_path = current_db_path(&settings)?;
+    let db = crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    let branch_mode = db
+        .resolve_conversation_branch_mode(parsed_id)
+        .map_err(|e| e.to_string())?;
+    let own = db
+        .get_conversation_branch_mode(parsed_id)
+        .map_err(|e| e.to_string())?;
+
+    let vault = std::path::Path::new(vault_path);
+    let diff = if crate::adapters::git::has_history(vault) {
+        crate::adapters::git::sandbox_diff(vault, parsed_id).map_err(|e| e.to_string())?
+    } else {
+        None
+    };
+    Ok(ConversationBranch {
+        branch_mode,
+        inherited: branch_mode && !own,
+        diff,
+    })
+}
+
+/// Merge a conversation's branch into the vault. `None` when it had nothing to merge.
+#[tauri::command]
+pub async fn merge_conversation_branch(
+    conversation_id: String,
+) -> Result<Option<BranchMerge>, String> {
+    let parsed_id = parse_conversation_id(&conversation_id)?;
+    let settings = Settings::load_global();
+    let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
+
+    crate::adapters::git::merge_sandbox(std::path::Path::new(vault_path), parsed_id)
         .map_err(|e| e.to_string())
 }
 
+/// Drop a conversation's branch without merging it.
 #[tauri::command]
-pub async fn revert_commit(commit_id: String) -> Result<(), String> {
+pub async fn discard_conversation_branch(conversation_id: String) -> Result<bool, String> {
+    let parsed_id = parse_conversation_id(&conversation_id)?;
     let settings = Settings::load_global();
     let vault_path = settings.vault_path.as_ref().ok_or("No vault configured")?;
 
-    crate::adapters::git::revert_commit(std::path::Path::new(vault_path), &commit_id)
+    crate::adapters::git::discard_sandbox(std::path::Path::new(vault_path), parsed_id)
         .map_err(|e| e.to_string())
 }
 
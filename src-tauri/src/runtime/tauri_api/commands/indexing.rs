@@ -0,0 +1,897 @@
+//! Vault indexing. The watcher queues the exact notes that changed and a single worker
+//! reindexes only those; a full walk of the vault is kept for startup reconciliation and
+//! manual reindex. The worker can be paused or cancelled between notes, and the work it
+//! has left is checkpointed so a reindex interrupted by quitting resumes on the next start.
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+use crate::adapters::config::Settings;
+use crate::adapters::vault::PrivacyRules;
+use crate::adapters::vectordb::VectorDb;
+
+use super::shared::{resolve_provider_credential, IndexFileError, IndexProgress};
+
+/// Files embedded concurrently during reindex; each file is sent in provider-sized batches.
+const EMBEDDING_FILE_CONCURRENCY: usize = 4;
+/// Minimum time between checkpoint writes while notes are being embedded.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(2);
+const CHECKPOINT_FILE: &str = "index-checkpoint.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IndexJob {
+    /// Walk the whole vault.
+    Full,
+    /// Reindex these vault-relative notes: created, modified, deleted or renamed.
+    Paths(Vec<String>),
+}
+
+/// Pending index work. Events that arrive while the worker is busy are coalesced here and
+/// picked up by its next pass, so no change is dropped.
+#[derive(Debug, Default)]
+struct IndexQueue {
+    paths: BTreeSet<String>,
+    full: bool,
+    /// Paths of failed jobs, retried with the next job rather than in a loop.
+    retry: BTreeSet<String>,
+    running: bool,
+}
+
+impl IndexQueue {
+    /// Queue `paths`. Returns whether the caller must start the worker.
+    fn push_paths(&mut self, paths: impl IntoIterator<Item = String>) -> bool {
+        self.paths.extend(paths);
+        self.claim_worker()
+    }
+
+    /// Queue a full walk. Returns whether the caller must start the worker.
+    fn push_full(&mut self) -> bool {
+        self.full = true;
+        self.claim_worker()
+    }
+
+    fn claim_worker(&mut self) -> bool {
+        self.paths.append(&mut self.retry);
+        if self.running || (!self.full && self.paths.is_empty()) {
+            return false;
+        }
+        self.running = true;
+        true
+    }
+
+    /// Next job for the worker, or `None` once the queue is drained and the worker stops.
+    fn next_job(&mut self) -> Option<IndexJob> {
+        if self.full {
+            // A full walk covers every queued path.
+            self.full = false;
+            self.paths.clear();
+            return Some(IndexJob::Full);
+        }
+        if !self.paths.is_empty() {
+            return Some(IndexJob::Paths(
+                std::mem::take(&mut self.paths).into_iter().collect(),
+            ));
+        }
+        self.running = false;
+        None
+    }
+
+    fn fail(&mut self, job: IndexJob) {
+        match job {
+            IndexJob::Full => {}
+            IndexJob::Paths(paths) => self.retry_later(paths),
+        }
+    }
+
+    fn retry_later(&mut self, paths: impl IntoIterator<Item = String>) {
+        self.retry.extend(paths);
+    }
+
+    /// Drop every queued job, including retries.
+    fn cancel(&mut self) {
+        self.full = false;
+        self.paths.clear();
+        self.retry.clear();
+    }
+}
+
+static INDEX_QUEUE: LazyLock<Mutex<IndexQueue>> =
+    LazyLock::new(|| Mutex::new(IndexQueue::default()));
+
+/// What the worker does before its next note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexControl {
+    Running,
+    Paused,
+    /// Stop the current job and drop the queue. Reset once the worker stops.
+    Cancelled,
+}
+
+static INDEX_CONTROL: LazyLock<watch::Sender<IndexControl>> =
+    LazyLock::new(|| watch::channel(IndexControl::Running).0);
+
+/// Wait while the worker is paused. Returns false once it is cancelled.
+async fn proceed() -> bool {
+    let mut control = INDEX_CONTROL.subscribe();
+    match control
+        .wait_for(|state| *state != IndexControl::Paused)
+        .await
+    {
+        Ok(state) => *state == IndexControl::Running,
+        Err(_) => false,
+    }
+}
+
+/// Steer the running worker. Returns false when no worker is running or it is already
+/// being cancelled. A paused worker does not count as active indexing, so chat is not
+/// held back while it waits.
+fn set_index_control(state: IndexControl) -> bool {
+    let queue = INDEX_QUEUE.lock().expect("index queue lock poisoned");
+    if !queue.running || *INDEX_CONTROL.borrow() == IndexControl::Cancelled {
+        return false;
+    }
+    INDEX_CONTROL.send_replace(state);
+    crate::core::agent::set_indexing_active(state != IndexControl::Paused);
+    true
+}
+
+/// Pause the worker after the notes it is embedding. Returns whether one was running.
+pub(crate) fn pause_indexing() -> bool {
+    set_index_control(IndexControl::Paused)
+}
+
+/// Stop the worker after the notes it is embedding and drop the queued work and its
+/// checkpoint. Notes indexed so far are kept. Returns whether one was running.
+pub(crate) fn cancel_indexing() -> bool {
+    set_index_control(IndexControl::Cancelled)
+}
+
+/// Resume a paused worker, or restart the work left in the vault's checkpoint.
+pub(crate) async fn resume_indexing(app: &AppHandle) -> Result<(), String> {
+    if set_index_control(IndexControl::Running) {
+        return Ok(());
+    }
+    let Some(vault_root) = configured_vault_root() else {
+        return Ok(());
+    };
+    if !queue_checkpoint(&vault_root) {
+        return Ok(());
+    }
+    drain_index_queue(app).await
+}
+
+/// Index work that has not finished: the running job's remaining notes and everything
+/// queued behind it.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexCheckpoint {
+    full: bool,
+    paths: BTreeSet<String>,
+}
+
+impl IndexCheckpoint {
+    fn pending<'a>(
+        queue: &IndexQueue,
+        job_full: bool,
+        job_remaining: impl IntoIterator<Item = &'a String>,
+    ) -> Self {
+        Self {
+            full: job_full || queue.full,
+            paths: job_remaining
+                .into_iter()
+                .cloned()
+                .chain(queue.paths.iter().cloned())
+                .chain(queue.retry.iter().cloned())
+                .collect(),
+        }
+    }
+
+    fn file(vault_root: &Path) -> PathBuf {
+        crate::adapters::vault::meld_dir(vault_root).join(CHECKPOINT_FILE)
+    }
+
+    fn load(vault_root: &Path) -> Option<Self> {
+        let raw = std::fs::read_to_string(Self::file(vault_root)).ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    /// Persist the checkpoint, or remove it when no work is left.
+    fn store(&self, vault_root: &Path) {
+        let file = Self::file(vault_root);
+        let result = if *self == Self::default() {
+            match std::fs::remove_file(&file) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+                _ => Ok(()),
+            }
+        } else {
+            serde_json::to_string(self)
+                .map_err(std::io::Error::other)
+                .and_then(|raw| std::fs::write(&file, raw))
+        };
+        if let Err(error) = result {
+            log::warn!("Failed to save index checkpoint: {}", error);
+        }
+    }
+
+    /// Checkpoint the running job's remaining work together with the queue.
+    fn save<'a>(
+        vault_root: &Path,
+        job_full: bool,
+        job_remaining: impl IntoIterator<Item = &'a String>,
+    ) {
+        let checkpoint = {
+            let queue = INDEX_QUEUE.lock().expect("index queue lock poisoned");
+            Self::pending(&queue, job_full, job_remaining)
+        };
+        checkpoint.store(vault_root);
+    }
+}
+
+fn configured_vault_root() -> Option<PathBuf> {
+    Settings::load_global().vault_path.map(PathBuf::from)
+}
+
+/// Queue the work left in the vault's index checkpoint. Returns whether the caller must
+/// run [`drain_index_queue`].
+pub(crate) fn queue_checkpoint(vault_root: &Path) -> bool {
+    let Some(checkpoint) = IndexCheckpoint::load(vault_root) else {
+        return false;
+    };
+    let mut queue = INDEX_QUEUE.lock().expect("index queue lock poisoned");
+    queue.full |= checkpoint.full;
+    let start = queue.push_paths(checkpoint.paths);
+    if start {
+        crate::core::agent::set_indexing_active(true);
+    }
+    start
+}
+
+/// Queue notes reported by the watcher. Returns whether the caller must run
+/// [`drain_index_queue`]; otherwise the running worker picks them up.
+pub(crate) fn queue_changed_notes(paths: impl IntoIterator<Item = String>) -> bool {
+    let mut queue = INDEX_QUEUE.lock().expect("index queue lock poisoned");
+    let start = queue.push_paths(paths);
+    if start {
+        crate::core::agent::set_indexing_active(true);
+    }
+    start
+}
+
+/// Process queued index jobs until the queue is empty or the worker is cancelled.
+/// Returns the first job error.
+pub(crate) async fn drain_index_queue(app: &AppHandle) -> Result<(), String> {
+    let mut result = Ok(());
+    let checkpoint = loop {
+        let (job, checkpoint) = {
+            let mut queue = INDEX_QUEUE.lock().expect("index queue lock poisoned");
+            if *INDEX_CONTROL.borrow() == IndexControl::Cancelled {
+                queue.cancel();
+            }
+            let job = queue.next_job();
+            let checkpoint = job.is_none().then(|| {
+                crate::core::agent::set_indexing_active(false);
+                INDEX_CONTROL.send_replace(IndexControl::Running);
+                // Only failed notes waiting for a retry are left.
+                IndexCheckpoint::pending(&queue, false, [])
+            });
+            (job, checkpoint)
+        };
+        let Some(job) = job else {
+            break checkpoint;
+        };
+
+        let outcome = match &job {
+            IndexJob::Full => reindex_vault(app).await,
+            IndexJob::Paths(paths) => reindex_notes(app, paths).await,
+        };
+        if let Err(error) = outcome {
+            log::warn!("Index job failed: {}", error);
+            INDEX_QUEUE
+                .lock()
+                .expect("index queue lock poisoned")
+                .fail(job);
+            if result.is_ok() {
+                result = Err(error);
+            }
+        }
+    };
+
+    if let (Some(checkpoint), Some(vault_root)) = (checkpoint, configured_vault_root()) {
+        checkpoint.store(&vault_root);
+    }
+    let _ = app.emit("index:done", ());
+    result
+}
+
+/// Queue a full walk, e.g. after the ignore rules changed. Returns whether the caller must
+/// run [`drain_index_queue`].
+pub(crate) fn queue_full_reindex() -> bool {
+    let mut queue = INDEX_QUEUE.lock().expect("index queue lock poisoned");
+    let start = queue.push_full();
+    if start {
+        crate::core::agent::set_indexing_active(true);
+    }
+    start
+}
+
+/// Reconcile the whole index with the vault. While the worker is busy the walk is queued
+/// behind its current job and this returns immediately.
+pub(crate) async fn run_reindex_internal(app: &AppHandle) -> Result<(), String> {
+    if !queue_full_reindex() {
+        return Ok(());
+    }
+    drain_index_queue(app).await
+}
+
+fn relative_note_path(vault_root: &Path, file: &Path) -> String {
+    file.strip_prefix(vault_root)
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+struct IndexTarget {
+    vault_root: PathBuf,
+    settings: Settings,
+    db: VectorDb,
+}
+
+fn open_index_target() -> Result<IndexTarget, String> {
+    let global_settings = Settings::load_global();
+    let vault_path = global_settings
+        .vault_path
+        .clone()
+        .ok_or("No vault configured")?;
+    let vault_root = PathBuf::from(&vault_path);
+    let vault_config = crate::adapters::config::VaultConfig::load(&vault_root);
+    let settings = global_settings.merged_with_vault(&vault_config);
+    crate::adapters::vault::ensure_vault_initialized(&vault_root).map_err(|e| e.to_string())?;
+
+    let db_path = crate::adapters::vault::meld_dir(&vault_root).join("index.db");
+    let db = VectorDb::open(&db_path).map_err(|e| e.to_string())?;
+    Ok(IndexTarget {
+        vault_root,
+        settings,
+        db,
+    })
+}
+
+/// Read, chunk and embed one note. Failures are returned with the note's path so the
+/// job can record them and carry on. A note that turned private after it was queued is
+/// not sent to the provider and ends up with no chunks.
+async fn embed_file(
+    privacy: &PrivacyRules,
+    api_key: String,
+    embedding_model_id: String,
+    rel_path: String,
+    file: PathBuf,
+) -> (
+    String,
+    Result<
+        (
+            String,
+            Vec<crate::adapters::vectordb::PreparedChunkEmbedding>,
+        ),
+        String,
+    >,
+) {
+    let embedded = async {
+        let content = tokio::fs::read_to_string(&file)
+            .await
+            .map_err(|e| e.to_string())?;
+        let hash = crate::adapters::vault::file_hash(&content);
+        if privacy.is_private(&rel_path, &content) {
+            return Ok((hash, Vec::new()));
+        }
+        let chunks = crate::adapters::markdown::chunk_markdown(&content, 512, 50);
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let embeddings =
+            crate::adapters::embeddings::get_embeddings(&api_key, &embedding_model_id, &texts)
+                .await
+                .map_err(|e| format!("Failed to embed {rel_path}: {e}"))?;
+
+        let prepared_chunks = chunks
+            .into_iter()
+            .zip(embeddings)
+            .enumerate()
+            .map(
+                |(idx, (chunk, embedding))| crate::adapters::vectordb::PreparedChunkEmbedding {
+                    chunk_index: idx,
+                    heading_path: chunk.heading_path,
+                    content: chunk.content,
+                    char_start: chunk.char_start,
+                    char_end: chunk.char_end,
+                    embedding,
+                },
+            )
+            .collect();
+        Ok::<_, String>((hash, prepared_chunks))
+    }
+    .await;
+    (rel_path, embedded)
+}
+
+/// Refresh the wikilink graph. Runs before embedding so links stay current even
+/// without embedding credentials.
+fn index_note_links(
+    db: &mut VectorDb,
+    vault_root: &Path,
+    files: &[PathBuf],
+    active_paths: &HashSet<String>,
+) -> Result<(), String> {
+    for linked_path in db.list_link_notes().map_err(|e| e.to_string())? {
+        if !active_paths.contains(&linked_path) {
+            db.remove_note_links(&linked_path)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    for file in files {
+        let rel_path = relative_note_path(vault_root, file);
+        let Ok(content) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        let hash = crate::adapters::vault::file_hash(&content);
+        update_note_links(db, &rel_path, &content, &hash)?;
+    }
+
+    db.resolve_links().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn update_note_links(
+    db: &mut VectorDb,
+    rel_path: &str,
+    content: &str,
+    hash: &str,
+) -> Result<(), String> {
+    if db.link_note_is_current(rel_path, hash) {
+        return Ok(());
+    }
+    let links = crate::adapters::markdown::links::extract_wikilinks(content);
+    let aliases = crate::adapters::markdown::links::frontmatter_aliases(content);
+    db.replace_note_links(rel_path, hash, &aliases, &links)
+        .map_err(|e| e.to_string())
+}
+
+/// Counters of one index job, emitted as `index:progress`.
+#[derive(Default)]
+struct JobProgress {
+    progress: IndexProgress,
+    embedding_started: Option<Instant>,
+}
+
+impl JobProgress {
+    fn skip(&mut self) {
+        self.progress.skipped += 1;
+    }
+
+    fn error(&mut self, file: &str, error: String) {
+        log::warn!("Failed to index {}: {}", file, error);
+        self.progress.errors.push(IndexFileError {
+            file: file.to_string(),
+            error,
+        });
+    }
+
+    fn start_embedding(&mut self, total: usize) {
+        self.progress.total = total;
+        self.embedding_started = Some(Instant::now());
+    }
+
+    /// Count a note as processed, embedded or failed, and update throughput and ETA.
+    fn advance(&mut self, file: String, chunks: usize) {
+        self.progress.current += 1;
+        self.progress.file = file;
+        self.progress.embedded_chunks += chunks;
+        let elapsed = self
+            .embedding_started
+            .map(|started| started.elapsed().as_secs_f64())
+            .unwrap_or_default();
+        if elapsed > 0.0 {
+            let rate = self.progress.current as f64 / elapsed;
+            let remaining = self.progress.total.saturating_sub(self.progress.current);
+            self.progress.files_per_second = rate;
+            self.progress.eta_seconds = Some((remaining as f64 / rate).ceil() as u64);
+        }
+    }
+
+    fn emit(&self, app: &AppHandle) {
+        let _ = app.emit("index:progress", self.progress.clone());
+    }
+
+    /// Queue the notes that failed for a retry with the next change.
+    fn finish(self) {
+        if self.progress.errors.is_empty() {
+            return;
+        }
+        INDEX_QUEUE
+            .lock()
+            .expect("index queue lock poisoned")
+            .retry_later(self.progress.errors.into_iter().map(|error| error.file));
+    }
+}
+
+/// Embed `stale_files` with the configured model and replace their chunks. A note that
+/// cannot be read or embedded is recorded in the job's errors; the job carries on.
+async fn embed_stale_files(
+    app: &AppHandle,
+    vault_root: &Path,
+    db: &mut VectorDb,
+    settings: &mut Settings,
+    stale_files: Vec<(String, PathBuf)>,
+    mut progress: JobProgress,
+) -> Result<(), String> {
+    if stale_files.is_empty() {
+        progress.finish();
+        return Ok(());
+    }
+
+    // Chunks are only comparable with queries embedded by the same model, so reindex
+    // always uses the configured model and re-embeds anything produced by another one.
+    let embedding_provider = settings.embedding_provider();
+    let embedding_model_id = settings.embedding_model_id();
+    let api_key = resolve_provider_credential(settings, &embedding_provider)
+        .await
+        .map_err(|error| {
+            format!(
+                "Embedding provider '{embedding_provider}' is not available for reindex: {error}"
+            )
+        })?;
+    let dimension = crate::adapters::embeddings::probe_dimensions(&api_key, &embedding_model_id)
+        .await
+        .map_err(|error| {
+            format!("Embedding model '{embedding_model_id}' is not available: {error}")
+        })?;
+    log::info!(
+        "Reindexing {} files with {embedding_model_id} ({dimension} dimensions)",
+        stale_files.len()
+    );
+
+    let mut remaining: BTreeSet<String> = stale_files
+        .iter()
+        .map(|(rel_path, _)| rel_path.clone())
+        .collect();
+    IndexCheckpoint::save(vault_root, false, &remaining);
+    let mut checkpointed_at = Instant::now();
+    let privacy = PrivacyRules::load(vault_root);
+    progress.start_embedding(stale_files.len());
+    progress.emit(app);
+
+    // No new note is started while the worker is paused, and none after it is cancelled.
+    let mut embedded_files = futures::stream::iter(stale_files)
+        .then(|entry| async move { proceed().await.then_some(entry) })
+        .take_while(|entry| futures::future::ready(entry.is_some()))
+        .filter_map(futures::future::ready)
+        .map(|(rel_path, file)| {
+            embed_file(
+                &privacy,
+                api_key.clone(),
+                embedding_model_id.clone(),
+                rel_path,
+                file,
+            )
+        })
+        .buffer_unordered(EMBEDDING_FILE_CONCURRENCY);
+
+    while let Some((rel_path, embedded)) = embedded_files.next().await {
+        let chunks = match embedded {
+            Ok((hash, prepared_chunks)) => {
+                db.replace_file_chunks_atomically(
+                    &rel_path,
+                    &hash,
+                    &embedding_model_id,
+                    &prepared_chunks,
+                )
+                .map_err(|e| e.to_string())?;
+                prepared_chunks.len()
+            }
+            Err(error) => {
+                progress.error(&rel_path, error);
+                0
+            }
+        };
+        remaining.remove(&rel_path);
+        progress.advance(rel_path, chunks);
+        progress.emit(app);
+
+        if checkpointed_at.elapsed() >= CHECKPOINT_INTERVAL {
+            IndexCheckpoint::save(vault_root, false, &remaining);
+            checkpointed_at = Instant::now();
+        }
+    }
+
+    progress.finish();
+    Ok(())
+}
+
+/// Move the index of a renamed note instead of embedding it again: an indexed note with
+/// the same content that is no longer in the vault. Returns whether one was moved.
+fn adopt_renamed_note(
+    db: &mut VectorDb,
+    rel_path: &str,
+    hash: &str,
+    is_in_vault: impl Fn(&str) -> bool,
+) -> Result<bool, String> {
+    let vanished = db
+        .indexed_paths_with_hash(hash)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|indexed_path| indexed_path != rel_path && !is_in_vault(indexed_path));
+    let Some(vanished) = vanished else {
+        return Ok(false);
+    };
+    log::info!("Index follows rename {vanished} -> {rel_path}");
+    db.move_file_chunks(&vanished, rel_path)
+        .map_err(|e| e.to_string())
+}
+
+/// Full walk: reindex every note that changed, carrying renamed notes over, then drop
+/// notes that left the vault or are private.
+async fn reindex_vault(app: &AppHandle) -> Result<(), String> {
+    let IndexTarget {
+        vault_root,
+        mut settings,
+        mut db,
+    } = open_index_target()?;
+    IndexCheckpoint::save(&vault_root, true, []);
+    // The vector index is an accelerator; searches fall back to a full scan without it.
+    if let Err(error) = db.sync_vector_index() {
+        log::warn!("Failed to sync vector index: {error}");
+    }
+    let files = crate::adapters::vault::list_md_files(&vault_root).map_err(|e| e.to_string())?;
+
+    let active_paths: HashSet<String> = files
+        .iter()
+        .map(|file| relative_note_path(&vault_root, file))
+        .collect();
+    index_note_links(&mut db, &vault_root, &files, &active_paths)?;
+
+    let embedding_model_id = settings.embedding_model_id();
+    let privacy = PrivacyRules::load(&vault_root);
+    let mut progress = JobProgress::default();
+    let mut stale_files = Vec::new();
+    let mut private_paths = HashSet::new();
+    for file in &files {
+        if !proceed().await {
+            return Ok(());
+        }
+        let rel_path = relative_note_path(&vault_root, file);
+        let content = match std::fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(error) => {
+                progress.error(&rel_path, error.to_string());
+                continue;
+            }
+        };
+        if privacy.is_private(&rel_path, &content) {
+            private_paths.insert(rel_path);
+            progress.skip();
+            continue;
+        }
+        let hash = crate::adapters::vault::file_hash(&content);
+        if db.file_is_current(&rel_path, &hash, &embedding_model_id) {
+            progress.skip();
+            continue;
+        }
+        let moved = adopt_renamed_note(&mut db, &rel_path, &hash, |path| {
+            active_paths.contains(path)
+        })?;
+        if moved && db.file_is_current(&rel_path, &hash, &embedding_model_id) {
+            progress.skip();
+        } else {
+            stale_files.push((rel_path, file.clone()));
+        }
+    }
+
+    for indexed_path in db.list_indexed_files().map_err(|e| e.to_string())? {
+        if !active_paths.contains(&indexed_path) || private_paths.contains(&indexed_path) {
+            db.remove_file_chunks(&indexed_path)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    embed_stale_files(
+        app,
+        &vault_root,
+        &mut db,
+        &mut settings,
+        stale_files,
+        progress,
+    )
+    .await
+}
+
+/// Incremental pass over the notes the watcher reported. Notes that still exist are
+/// indexed first, so a rename reported as a removal plus a creation moves the index of
+/// the old path; any note that no longer exists is removed after, and so is the index of
+/// a note that is private now.
+async fn reindex_notes(app: &AppHandle, paths: &[String]) -> Result<(), String> {
+    let IndexTarget {
+        vault_root,
+        mut settings,
+        mut db,
+    } = open_index_target()?;
+    IndexCheckpoint::save(&vault_root, false, paths);
+    let embedding_model_id = settings.embedding_model_id();
+    let privacy = PrivacyRules::load(&vault_root);
+
+    let mut progress = JobProgress::default();
+    let mut stale_files = Vec::new();
+    let mut vanished = Vec::new();
+    for rel_path in paths {
+        if !proceed().await {
+            return Ok(());
+        }
+        let file = vault_root.join(rel_path);
+        let content = match std::fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                vanished.push(rel_path);
+                continue;
+            }
+            Err(error) => {
+                progress.error(rel_path, error.to_string());
+                continue;
+            }
+        };
+        let hash = crate::adapters::vault::file_hash(&content);
+        update_note_links(&mut db, rel_path, &content, &hash)?;
+        if privacy.is_private(rel_path, &content) {
+            db.remove_file_chunks(rel_path).map_err(|e| e.to_string())?;
+            progress.skip();
+            continue;
+        }
+        if db.file_is_current(rel_path, &hash, &embedding_model_id) {
+            progress.skip();
+            continue;
+        }
+        let moved = adopt_renamed_note(&mut db, rel_path, &hash, |path| {
+            vault_root.join(path).is_file()
+        })?;
+        if moved && db.file_is_current(rel_path, &hash, &embedding_model_id) {
+            progress.skip();
+        } else {
+            stale_files.push((rel_path.clone(), file));
+        }
+    }
+    for rel_path in vanished {
+        db.remove_file_chunks(rel_path).map_err(|e| e.to_string())?;
+        db.remove_note_links(rel_path).map_err(|e| e.to_string())?;
+    }
+    db.resolve_links().map_err(|e| e.to_string())?;
+
+    embed_stale_files(
+        app,
+        &vault_root,
+        &mut db,
+        &mut settings,
+        stale_files,
+        progress,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cancel_indexing, pause_indexing, proceed, set_index_control, IndexCheckpoint, IndexControl,
+        IndexJob, IndexQueue, INDEX_CONTROL, INDEX_QUEUE,
+    };
+    use crate::core::agent::{is_indexing_active, set_indexing_active};
+    use std::time::Duration;
+
+    #[test]
+    fn index_queue_coalesces_events_into_one_worker() {
+        let mut queue = IndexQueue::default();
+        assert!(queue.push_paths(["a.md".to_string()]));
+        // Events arriving while the worker runs are queued for its next pass.
+        assert!(!queue.push_paths(["b.md".to_string(), "a.md".to_string()]));
+        assert_eq!(
+            queue.next_job(),
+            Some(IndexJob::Paths(vec![
+                "a.md".to_string(),
+                "b.md".to_string()
+            ]))
+        );
+        assert!(!queue.push_paths(["c.md".to_string()]));
+        assert_eq!(
+            queue.next_job(),
+            Some(IndexJob::Paths(vec!["c.md".to_string()]))
+        );
+        assert_eq!(queue.next_job(), None);
+        assert!(queue.push_paths(["d.md".to_string()]));
+    }
+
+    #[test]
+    fn index_queue_full_walk_absorbs_paths_and_failures_retry_later() {
+        let mut queue = IndexQueue::default();
+        assert!(queue.push_paths(["a.md".to_string()]));
+        assert!(!queue.push_full());
+        assert_eq!(queue.next_job(), Some(IndexJob::Full));
+        assert_eq!(queue.next_job(), None);
+
+        assert!(queue.push_paths(["b.md".to_string()]));
+        let job = queue.next_job().expect("job");
+        queue.fail(job);
+        // A failed job is not retried in a loop, only with the next queued change.
+        assert_eq!(queue.next_job(), None);
+        assert!(queue.push_paths(["c.md".to_string()]));
+        assert_eq!(
+            queue.next_job(),
+            Some(IndexJob::Paths(vec![
+                "b.md".to_string(),
+                "c.md".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn index_checkpoint_keeps_the_running_job_and_the_queue_behind_it() {
+        let vault_root =
+            std::env::temp_dir().join(format!("meld-index-checkpoint-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&vault_root).expect("create vault");
+
+        let mut queue = IndexQueue::default();
+        assert!(queue.push_paths(["queued.md".to_string()]));
+        queue.retry_later(["failed.md".to_string()]);
+        let remaining = ["running.md".to_string()];
+        let checkpoint = IndexCheckpoint::pending(&queue, false, &remaining);
+        checkpoint.store(&vault_root);
+        let loaded = IndexCheckpoint::load(&vault_root).expect("checkpoint saved");
+        assert!(!loaded.full);
+        assert_eq!(
+            loaded.paths.into_iter().collect::<Vec<_>>(),
+            vec!["failed.md", "queued.md", "running.md"]
+        );
+
+        // A cancelled queue with nothing running leaves no checkpoint behind.
+        queue.cancel();
+        IndexCheckpoint::pending(&queue, false, []).store(&vault_root);
+        assert!(IndexCheckpoint::load(&vault_root).is_none());
+
+        let _ = std::fs::remove_dir_all(vault_root);
+    }
+
+    #[tokio::test]
+    async fn index_control_pauses_resumes_and_cancels_the_running_worker() {
+        // Nothing to steer without a worker.
+        assert!(!pause_indexing());
+        assert!(!cancel_indexing());
+
+        INDEX_QUEUE.lock().expect("index queue lock").running = true;
+        set_indexing_active(true);
+
+        assert!(pause_indexing());
+        assert_eq!(*INDEX_CONTROL.borrow(), IndexControl::Paused);
+        assert!(
+            !is_indexing_active(),
+            "a paused worker must not hold chat back"
+        );
+        let waiting = tokio::spawn(proceed());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished(), "the worker waits while paused");
+
+        // Resuming a paused worker is what `resume_indexing` does first.
+        assert!(set_index_control(IndexControl::Running));
+        assert!(waiting.await.expect("join"));
+        assert!(is_indexing_active());
+
+        assert!(pause_indexing());
+        let waiting = tokio::spawn(proceed());
+        assert!(cancel_indexing());
+        assert!(!waiting.await.expect("join"), "a cancelled worker stops");
+        assert!(!proceed().await);
+        // A worker being cancelled can no longer be paused or resumed.
+        assert!(!pause_indexing());
+        assert!(!set_index_control(IndexControl::Running));
+
+        INDEX_QUEUE.lock().expect("index queue lock").running = false;
+        INDEX_CONTROL.send_replace(IndexControl::Running);
+        set_indexing_active(false);
+    }
+}
@@ -3,6 +3,7 @@ pub(crate) mod conversations;
 pub(crate) mod dev;
 pub(crate) mod folders;
 pub(crate) mod history;
+mod indexing;
 pub(crate) mod settings;
 pub(crate) mod shared;
 pub(crate) mod vault;
@@ -1,4 +1,4 @@
-use crate::adapters::config::Settings;
+use crate::adapters::config::{ExternalMcpServerConfig, ProviderInstanceConfig, Settings};
 use crate::adapters::providers::ProviderCatalogEntry;
 
 use super::shared::{ensure_valid_provider_id, normalize_provider};
@@ -10,10 +10,27 @@ pub async fn get_config() -> Result<Settings, String> {
 
 #[tauri::command]
 pub async fn get_provider_catalog() -> Result<Vec<ProviderCatalogEntry>, String> {
-    let registry = crate::adapters::providers::ProviderRegistry::default();
+    let registry = crate::adapters::providers::ProviderRegistry::configured();
     Ok(registry.catalog())
 }
 
+/// Whether local provider `provider` can serve requests: its base URL is configured as a
+/// provider instance, or the runtime answers at its default address.
+#[tauri::command]
+pub async fn probe_local_provider(provider: String) -> Result<bool, String> {
+    let provider = normalize_provider(&provider);
+    if !crate::adapters::providers::is_local_provider(&provider) {
+        return Ok(false);
+    }
+    if Settings::load_global()
+        .provider_instance(&provider)
+        .is_some()
+    {
+        return Ok(true);
+    }
+    Ok(crate::adapters::providers::local_provider_reachable(&provider).await)
+}
+
 #[tauri::command]
 pub async fn set_api_key(provider: String, key: String) -> Result<(), String> {
     let provider = normalize_provider(&provider);
@@ -92,7 +109,7 @@ pub async fn set_embedding_model(provider: String, model: String) -> Result<(),
         return Err("model is required".to_string());
     }
 
-    let supports_embeddings = crate::adapters::providers::ProviderRegistry::default()
+    let supports_embeddings = crate::adapters::providers::ProviderRegistry::configured()
         .catalog()
         .iter()
         .any(|entry| entry.id == provider && entry.supports_embeddings);
@@ -119,7 +136,7 @@ pub async fn set_embedding_model(provider: String, model: String) -> Result<(),
                     provider
                 ));
             }
-        } else if !has_api_key {
+        } else if !has_api_key && settings.provider_requires_api_key(&provider) {
             return Err(format!(
                 "No API key configured for embedding provider '{}'",
                 provider
@@ -168,3 +185,18 @@ pub async fn set_searxng_base_url(url: String) -> Result<(), String> {
         Ok(())
     })
 }
+
+#[tauri::command]
+pub async fn set_mcp_servers(servers: Vec<ExternalMcpServerConfig>) -> Result<(), String> {
+    Settings::update_global(|settings| settings.set_mcp_servers(servers))
+}
+
+#[tauri::command]
+pub async fn set_provider_instances(instances: Vec<ProviderInstanceConfig>) -> Result<(), String> {
+    Settings::update_global(|settings| settings.set_provider_instances(instances))
+}
+
+#[tauri::command]
+pub async fn set_write_approval(mode: String, folders: Vec<String>) -> Result<(), String> {
+    Settings::update_global(|settings| settings.set_write_approval(&mode, folders))
+}
@@ -3,11 +3,36 @@ use std::path::{Path, PathBuf};
 
 use crate::adapters::config::Settings;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A conversation's branch-mode setting and what its branch would merge.
+#[derive(Debug, Serialize, Clone)]
+pub struct ConversationBranch {
+    /// Resolved through the conversation's folders.
+    pub branch_mode: bool,
+    /// Set by a folder rather than the conversation itself.
+    pub inherited: bool,
+    pub diff: Option<crate::adapters::git::BranchDiff>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct IndexProgress {
     pub current: usize,
     pub total: usize,
     pub file: String,
+    /// Chunks embedded by this job so far.
+    pub embedded_chunks: usize,
+    /// Notes left alone because their index was current.
+    pub skipped: usize,
+    /// Notes embedded per second since embedding started.
+    pub files_per_second: f64,
+    pub eta_seconds: Option<u64>,
+    /// Notes that could not be read or embedded; the job carries on without them.
+    pub errors: Vec<IndexFileError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexFileError {
+    pub file: String,
+    pub error: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -6,8 +6,9 @@ use walkdir::WalkDir;
 use crate::adapters::config::Settings;
 use crate::adapters::vault::VaultInfo;
 
+use super::indexing::{cancel_indexing, pause_indexing, resume_indexing, run_reindex_internal};
 use super::shared::{VaultEntry, VaultFileEntry};
-use super::watcher::{ensure_vault_watcher, run_reindex_internal, stop_vault_watcher};
+use super::watcher::{ensure_vault_watcher, stop_vault_watcher};
 
 fn normalize_relative_entry_path(path: &str) -> Result<String, String> {
     let normalized_slashes = path.trim().replace('\\', "/");
@@ -68,10 +69,6 @@ fn to_relative_under(root: &Path, path: &Path) -> Result<String, String> {
     Ok(relative.to_string_lossy().replace('\\', "/"))
 }
 
-fn is_hidden_or_ignored(name: &str) -> bool {
-    name.starts_with('.') || name == "node_modules"
-}
-
 fn file_modified_at_millis(path: &Path) -> Option<i64> {
     let modified = std::fs::metadata(path).ok()?.modified().ok()?;
     let duration = modified.duration_since(UNIX_EPOCH).ok()?;
@@ -173,6 +170,21 @@ pub async fn reindex(app: AppHandle) -> Result<(), String> {
     run_reindex_internal(&app).await
 }
 
+#[tauri::command]
+pub async fn pause_reindex() -> Result<bool, String> {
+    Ok(pause_indexing())
+}
+
+#[tauri::command]
+pub async fn resume_reindex(app: AppHandle) -> Result<(), String> {
+    resume_indexing(&app).await
+}
+
+#[tauri::command]
+pub async fn cancel_reindex() -> Result<bool, String> {
+    Ok(cancel_indexing())
+}
+
 #[tauri::command]
 pub async fn list_vault_files() -> Result<Vec<VaultFileEntry>, String> {
     let settings = Settings::load_global();
@@ -234,12 +246,13 @@ pub async fn list_vault_entries() -> Result<Vec<VaultEntry>, String> {
     let vault_path = settings.vault_path.ok_or("No vault configured")?;
     let vault_root = Path::new(&vault_path);
 
+    let ignore = crate::adapters::vault::VaultIgnore::load(vault_root);
     let mut entries: Vec<VaultEntry> = Vec::new();
 
     for entry in WalkDir::new(vault_root)
         .follow_links(true)
         .into_iter()
-        .filter_entry(|entry| !is_hidden_or_ignored(&entry.file_name().to_string_lossy()))
+        .filter_entry(|entry| !ignore.is_ignored(entry.path(), entry.file_type().is_dir()))
     {
         let entry = entry.map_err(|e| e.to_string())?;
         let entry_path = entry.path();
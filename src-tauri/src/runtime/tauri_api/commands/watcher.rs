@@ -1,3 +1,4 @@
+use notify_debouncer_full::notify::event::{EventKind, ModifyKind, RenameMode};
 use notify_debouncer_full::notify::RecursiveMode;
 use notify_debouncer_full::{new_debouncer, DebounceEventResult};
 use std::collections::HashSet;
@@ -6,11 +7,14 @@ use std::sync::{mpsc, LazyLock, Mutex};
 use std::thread::JoinHandle;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter};
+use walkdir::WalkDir;
 
 use crate::adapters::config::Settings;
-use crate::adapters::providers::split_model_id;
+use crate::adapters::vault::{PrivacyRules, VaultIgnore};
 
-use super::shared::{resolve_provider_credential, IndexProgress};
+use super::indexing::{
+    drain_index_queue, queue_changed_notes, queue_checkpoint, queue_full_reindex,
+};
 
 #[derive(Debug)]
 struct VaultWatcherHandle {
@@ -21,84 +25,84 @@ struct VaultWatcherHandle {
 
 static VAULT_WATCHER: LazyLock<Mutex<Option<VaultWatcherHandle>>> =
     LazyLock::new(|| Mutex::new(None));
-const EMBEDDING_MAX_ATTEMPTS: usize = 3;
-
-fn default_embedding_model_id_for_provider(provider: &str) -> Option<&'static str> {
-    match provider {
-        "google" => Some("google:gemini-embedding-001"),
-        "openai" => Some("openai:text-embedding-3-small"),
-        _ => None,
+/// Vault-relative path of a watched markdown file, if `path` is one.
+fn markdown_watch_relative_path(
+    vault_root: &Path,
+    ignore: &VaultIgnore,
+    path: &Path,
+) -> Option<String> {
+    if !is_markdown_watch_path(vault_root, ignore, path) {
+        return None;
     }
+    path.strip_prefix(vault_root)
+        .ok()
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
 }
 
-fn resolve_embedding_model_id_for_reindex(
-    current_provider: &str,
-    current_model_id: &str,
-    candidate_provider: &str,
-) -> Option<String> {
-    if candidate_provider.eq_ignore_ascii_case(current_provider) {
-        if let Ok((provider, model)) = split_model_id(current_model_id) {
-            let normalized_model = model.trim();
-            if provider.eq_ignore_ascii_case(candidate_provider) && !normalized_model.is_empty() {
-                return Some(format!(
-                    "{}:{}",
-                    candidate_provider.to_ascii_lowercase(),
-                    normalized_model
-                ));
-            }
-        }
+/// Notes under a directory renamed from `from` to `to`, at their old and new paths. The
+/// watcher only reports the directory itself; listing both paths of each note lets the
+/// indexer move its index instead of embedding it again, or drop it when the new path is
+/// excluded.
+fn renamed_directory_notes(
+    vault_root: &Path,
+    ignore: &VaultIgnore,
+    from: &Path,
+    to: &Path,
+) -> Vec<String> {
+    if !to.is_dir() {
+        return Vec::new();
     }
-
-    default_embedding_model_id_for_provider(candidate_provider).map(str::to_string)
+    WalkDir::new(to)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .strip_prefix(to)
+                .ok()
+                .map(|suffix| suffix.to_path_buf())
+        })
+        .flat_map(|suffix| [from.join(&suffix), to.join(&suffix)])
+        .filter_map(|path| markdown_watch_relative_path(vault_root, ignore, &path))
+        .collect()
 }
 
-async fn embed_chunk_with_retry(
-    api_key: &str,
-    embedding_model_id: &str,
-    content: &str,
-) -> Result<Vec<f32>, String> {
-    let mut attempt = 1usize;
-    loop {
-        match crate::adapters::embeddings::get_embedding(api_key, embedding_model_id, content).await
-        {
-            Ok(embedding) => return Ok(embedding),
-            Err(error) => {
-                if attempt >= EMBEDDING_MAX_ATTEMPTS {
-                    return Err(error.to_string());
-                }
-                let backoff_ms = 200_u64.saturating_mul(1_u64 << (attempt - 1));
-                log::warn!(
-                    "Embedding request failed (attempt {attempt}/{EMBEDDING_MAX_ATTEMPTS}), retrying in {backoff_ms}ms: {}",
-                    error
-                );
-                tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
-                attempt += 1;
-            }
+/// Run the index worker in the background, reporting a failure to the UI.
+fn spawn_index_drain(runtime: &tokio::runtime::Handle, app: &AppHandle) {
+    let app = app.clone();
+    runtime.spawn(async move {
+        if let Err(error) = drain_index_queue(&app).await {
+            log::warn!("Auto reindex failed: {}", error);
+            let _ = app.emit("index:error", error);
         }
+    });
+}
+
+/// Commit hand edits collected by the watcher. Waits while an assistant run is active:
+/// the run's own writes are still uncommitted and must land in the run commit instead.
+fn flush_user_edits(vault_root: &Path, pending: &mut HashSet<String>) {
+    if pending.is_empty() || super::assistant::has_active_runs() {
+        return;
+    }
+    let mut paths: Vec<String> = pending.drain().collect();
+    paths.sort();
+    if let Err(error) = crate::adapters::git::commit_user_edits(vault_root, &paths) {
+        log::warn!("Failed to snapshot user edits: {}", error);
     }
 }
 
-fn is_markdown_watch_path(vault_root: &Path, path: &Path) -> bool {
+fn is_markdown_watch_path(vault_root: &Path, ignore: &VaultIgnore, path: &Path) -> bool {
     let relative = match path.strip_prefix(vault_root) {
         Ok(value) => value,
         Err(_) => return false,
     };
-
-    let normalized = relative.to_string_lossy().replace('\\', "/");
-    if normalized.is_empty() {
-        return false;
-    }
-
-    if normalized == ".meld"
-        || normalized.starts_with(".meld/")
-        || normalized == ".git"
-        || normalized.starts_with(".git/")
-        || normalized.contains("/.git/")
-    {
+    if relative.as_os_str().is_empty() || ignore.is_ignored(relative, false) {
         return false;
     }
 
-    Path::new(&normalized)
+    relative
         .extension()
         .and_then(|value| value.to_str())
         .map(|ext| ext.eq_ignore_ascii_case("md"))
@@ -161,31 +165,65 @@ pub(crate) fn ensure_vault_watcher(app: &AppHandle) {
                 return;
             }
 
+            let vault_root = Path::new(&watch_path);
+            let mut ignore = VaultIgnore::load(vault_root);
+            let mut privacy = PrivacyRules::load(vault_root);
+            let mut pending_user_edits = HashSet::new();
             loop {
                 if stop_rx.try_recv().is_ok() {
+                    flush_user_edits(vault_root, &mut pending_user_edits);
                     break;
                 }
+                flush_user_edits(vault_root, &mut pending_user_edits);
 
                 match events_rx.recv_timeout(Duration::from_millis(500)) {
                     Ok(result) => match result {
                         Ok(events) => {
-                            let has_markdown_change = events.iter().any(|event| {
-                                event.paths.iter().any(|path| {
-                                    is_markdown_watch_path(Path::new(&watch_path), path)
+                            // New ignore or privacy rules can exclude or bring back any
+                            // note, so the whole vault is reconciled with them.
+                            if events
+                                .iter()
+                                .flat_map(|event| event.paths.iter())
+                                .any(|path| VaultIgnore::is_source(vault_root, path))
+                            {
+                                let reloaded = VaultIgnore::load(vault_root);
+                                let reloaded_privacy = PrivacyRules::load(vault_root);
+                                if (!reloaded.same_rules(&ignore) || reloaded_privacy != privacy)
+                                    && queue_full_reindex()
+                                {
+                                    spawn_index_drain(&runtime, &app_handle);
+                                }
+                                ignore = reloaded;
+                                privacy = reloaded_privacy;
+                            }
+
+                            let mut changed: Vec<String> = events
+                                .iter()
+                                .flat_map(|event| event.paths.iter())
+                                .filter_map(|path| {
+                                    markdown_watch_relative_path(vault_root, &ignore, path)
                                 })
-                            });
+                                .collect();
+                            for event in events.iter().filter(|event| {
+                                event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::Both))
+                            }) {
+                                if let [from, to] = event.paths.as_slice() {
+                                    changed.extend(renamed_directory_notes(
+                                        vault_root, &ignore, from, to,
+                                    ));
+                                }
+                            }
 
-                            if !has_markdown_change {
+                            if changed.is_empty() {
                                 continue;
                             }
+                            // Agent and revert writes are committed already and turn into
+                            // no-op snapshots; anything else is a hand edit.
+                            pending_user_edits.extend(changed.iter().cloned());
 
-                            let app_for_reindex = app_handle.clone();
-                            runtime.spawn(async move {
-                                if let Err(error) = run_reindex_internal(&app_for_reindex).await {
-                                    log::warn!("Auto reindex failed: {}", error);
-                                    let _ = app_for_reindex.emit("index:error", error);
-                                }
-                            });
+                            if queue_changed_notes(changed) {
+                                spawn_index_drain(&runtime, &app_handle);
+                            }
                         }
                         Err(errors) => {
                             for error in errors {
@@ -200,6 +238,11 @@ pub(crate) fn ensure_vault_watcher(app: &AppHandle) {
         })
         .expect("failed to spawn vault watcher thread");
 
+    // Pick up a reindex that was interrupted when the app quit.
+    if queue_checkpoint(Path::new(&vault_path)) {
+        spawn_index_drain(&tokio::runtime::Handle::current(), app);
+    }
+
     let mut guard = VAULT_WATCHER.lock().expect("vault watcher lock poisoned");
     *guard = Some(VaultWatcherHandle {
         vault_path,
@@ -207,159 +250,3 @@ pub(crate) fn ensure_vault_watcher(app: &AppHandle) {
         join_handle,
     });
 }
-
-pub(crate) async fn run_reindex_internal(app: &AppHandle) -> Result<(), String> {
-    if crate::core::agent::is_indexing_active() {
-        return Ok(());
-    }
-
-    crate::core::agent::set_indexing_active(true);
-    let result: Result<(), String> = async {
-        let global_settings = Settings::load_global();
-        let vault_path = global_settings
-            .vault_path
-            .clone()
-            .ok_or("No vault configured")?;
-        let vault_root = Path::new(&vault_path);
-        let vault_config = crate::adapters::config::VaultConfig::load(vault_root);
-        let mut settings = global_settings.merged_with_vault(&vault_config);
-        crate::adapters::vault::ensure_vault_initialized(vault_root).map_err(|e| e.to_string())?;
-
-        let files = crate::adapters::vault::list_md_files(vault_root).map_err(|e| e.to_string())?;
-        let db_path = crate::adapters::vault::meld_dir(vault_root).join("index.db");
-        let mut db =
-            crate::adapters::vectordb::VectorDb::open(&db_path).map_err(|e| e.to_string())?;
-
-        let mut active_paths = HashSet::with_capacity(files.len());
-        for file in &files {
-            let rel_path = file
-                .strip_prefix(vault_root)
-                .unwrap_or(file)
-                .to_string_lossy()
-                .replace('\\', "/");
-            active_paths.insert(rel_path);
-        }
-
-        let indexed_paths = db.list_indexed_files().map_err(|e| e.to_string())?;
-        for indexed_path in indexed_paths {
-            if !active_paths.contains(&indexed_path) {
-                db.remove_file_chunks(&indexed_path)
-                    .map_err(|e| e.to_string())?;
-            }
-        }
-
-        if files.is_empty() {
-            return Ok(());
-        }
-
-        let current_embedding_provider = settings.embedding_provider();
-        let current_embedding_model_id = settings.embedding_model_id();
-
-        let mut provider_candidates = Vec::with_capacity(3);
-        for provider in [
-            current_embedding_provider.clone(),
-            "google".to_string(),
-            "openai".to_string(),
-        ] {
-            let normalized = provider.trim().to_ascii_lowercase();
-            if normalized.is_empty()
-                || provider_candidates
-                    .iter()
-                    .any(|existing: &String| existing == &normalized)
-            {
-                continue;
-            }
-            provider_candidates.push(normalized);
-        }
-
-        let mut selected_embedding: Option<(String, String, String)> = None;
-        for provider in &provider_candidates {
-            let Some(model_id) = resolve_embedding_model_id_for_reindex(
-                &current_embedding_provider,
-                &current_embedding_model_id,
-                provider,
-            ) else {
-                continue;
-            };
-
-            match resolve_provider_credential(&mut settings, provider).await {
-                Ok(api_key) => {
-                    selected_embedding = Some((provider.clone(), api_key, model_id));
-                    break;
-                }
-                Err(error) => {
-                    log::debug!(
-                        "Embedding provider '{}' unavailable for reindex: {}",
-                        provider,
-                        error
-                    );
-                }
-            }
-        }
-
-        let (embedding_provider, api_key, embedding_model_id) = selected_embedding.ok_or_else(|| {
-            format!(
-                "No embedding credentials configured for reindex. Configure credentials for '{}', 'google', or 'openai'.",
-                current_embedding_provider.trim().to_ascii_lowercase()
-            )
-        })?;
-        if !embedding_provider.eq_ignore_ascii_case(&current_embedding_provider) {
-            log::warn!(
-                "Reindex falling back from embedding provider '{}' to '{}' due to available credentials.",
-                current_embedding_provider,
-                embedding_provider
-            );
-        }
-        let total = files.len();
-
-        for (i, file) in files.iter().enumerate() {
-            let rel_path = file
-                .strip_prefix(vault_root)
-                .unwrap_or(file)
-                .to_string_lossy()
-                .replace('\\', "/");
-
-            let _ = app.emit(
-                "index:progress",
-                IndexProgress {
-                    current: i + 1,
-                    total,
-                    file: rel_path.clone(),
-                },
-            );
-
-            let content = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
-            let hash = crate::adapters::vault::file_hash(&content);
-
-            if db.file_is_current(&rel_path, &hash) {
-                continue;
-            }
-
-            let chunks = crate::adapters::markdown::chunk_markdown(&content, 512, 50);
-            let mut prepared_chunks = Vec::with_capacity(chunks.len());
-
-            for (idx, chunk) in chunks.iter().enumerate() {
-                let embedding =
-                    embed_chunk_with_retry(&api_key, &embedding_model_id, &chunk.content).await?;
-                prepared_chunks.push(crate::adapters::vectordb::PreparedChunkEmbedding {
-                    chunk_index: idx,
-                    heading_path: chunk.heading_path.clone(),
-                    content: chunk.content.clone(),
-                    char_start: chunk.char_start,
-                    char_end: chunk.char_end,
-                    embedding,
-                });
-            }
-
-            db.replace_file_chunks_atomically(&rel_path, &hash, &prepared_chunks)
-                .map_err(|e| e.to_string())?;
-        }
-
-        Ok(())
-    }
-    .await;
-
-    crate::core::agent::set_indexing_active(false);
-    let _ = app.emit("index:done", ());
-    result
-}
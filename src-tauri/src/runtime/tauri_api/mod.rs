@@ -10,26 +10,36 @@ pub fn run() {
             commands::vault::select_vault,
             commands::vault::get_vault_info,
             commands::vault::reindex,
+            commands::vault::pause_reindex,
+            commands::vault::resume_reindex,
+            commands::vault::cancel_reindex,
             commands::conversations::create_conversation,
             commands::conversations::list_conversations,
             commands::conversations::list_archived_conversations,
             commands::conversations::list_runs,
             commands::conversations::get_run_events,
+            commands::conversations::list_staged_changes,
+            commands::conversations::list_conversation_staged_changes,
+            commands::conversations::apply_staged_changes,
+            commands::conversations::discard_staged_changes,
             commands::conversations::get_conversation_messages,
             commands::conversations::delete_message,
             commands::conversations::rename_conversation,
             commands::conversations::archive_conversation,
             commands::conversations::unarchive_conversation,
+            commands::conversations::set_conversation_branch_mode,
             commands::conversations::pin_conversation,
             commands::conversations::unpin_conversation,
             commands::conversations::reorder_conversations,
             commands::conversations::cancel_active_run,
+            commands::conversations::respond_to_write_approval,
             commands::conversations::send_message,
             commands::conversations::regenerate_last_response,
             commands::conversations::edit_user_message,
             commands::conversations::export_conversation,
             commands::settings::get_config,
             commands::settings::get_provider_catalog,
+            commands::settings::probe_local_provider,
             commands::settings::set_api_key,
             commands::settings::set_oauth_client,
             commands::settings::set_auth_mode,
@@ -42,8 +52,18 @@ pub fn run() {
             commands::settings::set_user_language,
             commands::settings::set_search_provider,
             commands::settings::set_searxng_base_url,
+            commands::settings::set_mcp_servers,
+            commands::settings::set_provider_instances,
+            commands::settings::set_write_approval,
             commands::history::get_history,
             commands::history::revert_commit,
+            commands::history::revert_run,
+            commands::history::restore_file,
+            commands::history::read_file_at,
+            commands::history::get_note_blame,
+            commands::history::get_conversation_branch,
+            commands::history::merge_conversation_branch,
+            commands::history::discard_conversation_branch,
             commands::history::open_file_external,
             commands::vault::list_vault_files,
             commands::vault::list_vault_entries,
@@ -58,6 +78,7 @@ pub fn run() {
             commands::folders::list_chat_folders,
             commands::folders::rename_chat_folder,
             commands::folders::update_chat_folder,
+            commands::folders::set_folder_branch_mode,
             commands::folders::archive_chat_folder,
             commands::folders::unarchive_chat_folder,
             commands::folders::pin_chat_folder,
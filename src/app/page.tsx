@@ -254,8 +254,12 @@ export default function Home() {
           fileCount={state.fileCount}
           modelId={modelId}
           isIndexing={state.isIndexing}
+          indexPaused={state.indexPaused}
           indexProgress={state.indexProgress}
           onReindex={actions.handleReindex}
+          onPauseIndex={actions.handlePauseReindex}
+          onResumeIndex={actions.handleResumeReindex}
+          onCancelIndex={actions.handleCancelReindex}
         />
       </div>
     </div>
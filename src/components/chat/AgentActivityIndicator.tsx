@@ -13,7 +13,9 @@ const toolLabels: Record<string, string> = {
   kb_read: "Reading note",
   kb_create: "Creating note",
   kb_update: "Updating note",
+  kb_patch: "Editing note",
   kb_list: "Listing notes",
+  kb_links: "Following links",
   web_search: "Searching the web",
 };
 
@@ -110,7 +112,7 @@ export default function AgentActivityIndicator({ activity }: Props) {
         if (step.tool === "kb_create" && path) {
           return `Created: ${trimForLabel(path, 44)}`;
         }
-        if (step.tool === "kb_update" && path) {
+        if ((step.tool === "kb_update" || step.tool === "kb_patch") && path) {
           return `Updated: ${trimForLabel(path, 44)}`;
         }
         if (step.tool === "kb_read" && path) {
@@ -156,6 +158,10 @@ export default function AgentActivityIndicator({ activity }: Props) {
       icon = "verify";
       break;
     }
+    case "awaiting_approval":
+      label = "Waiting for your approval...";
+      icon = "pulse";
+      break;
     case "responding":
       return null;
     default:
@@ -0,0 +1,158 @@
+"use client";
+
+import { useCallback, useEffect, useState } from "react";
+import { createPortal } from "react-dom";
+import { useAppStore } from "@/lib/store";
+import {
+  discardConversationBranch,
+  getConversationBranch,
+  mergeConversationBranch,
+  type BranchDiff,
+} from "@/lib/tauri";
+import { diffLineClass } from "./StagedChangesModal";
+
+interface Props {
+  open: boolean;
+  conversationId: string;
+  onClose: () => void;
+  onResolved?: () => void;
+}
+
+export default function BranchReviewModal({ open, conversationId, onClose, onResolved }: Props) {
+  const [diff, setDiff] = useState<BranchDiff | null>(null);
+  const [loading, setLoading] = useState(false);
+  const [busy, setBusy] = useState(false);
+  const showToast = useAppStore((state) => state.showToast);
+
+  const reload = useCallback(async () => {
+    setLoading(true);
+    try {
+      const branch = await getConversationBranch(conversationId);
+      setDiff(branch.diff);
+    } catch (error) {
+      showToast(`Failed to load branch: ${String(error)}`);
+    } finally {
+      setLoading(false);
+    }
+  }, [conversationId, showToast]);
+
+  useEffect(() => {
+    if (!open) return;
+    void reload();
+  }, [open, reload]);
+
+  useEffect(() => {
+    if (!open) return;
+    function handleKeydown(event: KeyboardEvent) {
+      if (event.key === "Escape") onClose();
+    }
+    window.addEventListener("keydown", handleKeydown);
+    return () => window.removeEventListener("keydown", handleKeydown);
+  }, [onClose, open]);
+
+  const act = async (merge: boolean) => {
+    setBusy(true);
+    try {
+      if (merge) {
+        const merged = await mergeConversationBranch(conversationId);
+        if (!merged) {
+          showToast("The branch had nothing to merge");
+        } else if (merged.conflicts.length > 0) {
+          showToast(`Merged with conflict markers in ${merged.conflicts.join(", ")}`);
+        } else {
+          const count = merged.files_changed.length;
+          showToast(`Merged ${count} note${count === 1 ? "" : "s"} into the vault`);
+        }
+      } else {
+        await discardConversationBranch(conversationId);
+        showToast("Branch discarded");
+      }
+      onResolved?.();
+      onClose();
+    } catch (error) {
+      showToast(String(error));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  if (!open || typeof document === "undefined") {
+    return null;
+  }
+
+  const hasChanges = !!diff && diff.files_changed.length > 0;
+
+  const dialog = (
+    <div className="fixed inset-0 z-50 flex items-center justify-center px-4">
+      <button
+        aria-label="Close branch review"
+        className="absolute inset-0 bg-scrim-50 animate-overlay-fade"
+        onClick={onClose}
+      />
+
+      <div className="relative flex max-h-[80vh] w-full max-w-3xl flex-col rounded-2xl border border-overlay-6 bg-bg-secondary/98 p-6 shadow-xl shadow-black/30 backdrop-blur-lg space-y-4 animate-dialog-in">
+        <div>
+          <h3 className="text-lg font-semibold">Branch changes</h3>
+          <p className="text-sm text-text-secondary">
+            {diff
+              ? `${diff.branch} has ${diff.commits} commit${diff.commits === 1 ? "" : "s"} not yet in your notes.`
+              : "This conversation has no branch yet."}
+          </p>
+        </div>
+
+        <div className="min-h-0 flex-1 space-y-2 overflow-y-auto">
+          {loading && !diff && <p className="text-xs text-text-muted">Loading...</p>}
+          {!loading && diff && !hasChanges && (
+            <p className="text-xs text-text-muted">The branch matches your notes.</p>
+          )}
+          {diff && hasChanges && (
+            <>
+              <div className="flex flex-wrap gap-1.5">
+                {diff.files_changed.map((path) => (
+                  <span
+                    key={path}
+                    className="rounded-lg bg-bg-tertiary/60 px-2 py-0.5 font-mono text-[11px] text-text-secondary"
+                  >
+                    {path}
+                  </span>
+                ))}
+              </div>
+              <pre className="max-h-96 overflow-auto rounded-xl border border-overlay-6 bg-bg-tertiary/40 p-3 text-xs leading-relaxed">
+                {diff.patch.split("\n").map((line, index) => (
+                  <div key={index} className={diffLineClass(line)}>
+                    {line || " "}
+                  </div>
+                ))}
+              </pre>
+            </>
+          )}
+        </div>
+
+        <div className="flex justify-end gap-2.5 pt-2">
+          <button
+            onClick={onClose}
+            className="px-4 py-2 text-sm rounded-xl border border-overlay-6 bg-bg-tertiary/60 text-text-secondary hover:text-text hover:bg-bg-tertiary transition-colors"
+          >
+            Close
+          </button>
+          <button
+            disabled={busy || !diff}
+            onClick={() => void act(false)}
+            className="px-4 py-2 text-sm rounded-xl border border-overlay-6 bg-bg-tertiary/60 text-error hover:bg-error/10 transition-colors disabled:opacity-50"
+          >
+            Discard branch
+          </button>
+          <button
+            disabled={busy || !hasChanges}
+            onClick={() => void act(true)}
+            className="px-4 py-2 text-sm rounded-xl bg-accent text-bg hover:opacity-90 transition-opacity disabled:opacity-50"
+          >
+            Merge into notes
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+
+  return createPortal(dialog, document.body);
+}
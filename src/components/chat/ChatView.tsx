@@ -9,6 +9,7 @@ import { setupEventListeners } from "@/lib/events";
 import RunTraceModal from "@/components/ui/RunTraceModal";
 import MessageBubble from "./MessageBubble";
 import MessageInput from "./MessageInput";
+import WriteApprovalCard from "./WriteApprovalCard";
 
 const QUICK_PROMPTS = [
   "What am I working on?",
@@ -45,6 +46,7 @@ function ChatView({
     agentActivity,
     timelineSteps,
     thinkingLog,
+    pendingApprovals,
   } = useAppStore(useShallow(selectChatViewState));
   const scrollContainerRef = useRef<HTMLDivElement>(null);
   const bottomRef = useRef<HTMLDivElement>(null);
@@ -103,7 +105,7 @@ function ChatView({
       behavior: isStreaming ? "auto" : "smooth",
       block: "end",
     });
-  }, [agentActivity, isStreaming, messages, streamingContent]);
+  }, [agentActivity, isStreaming, messages, pendingApprovals, streamingContent]);
 
   const handleScroll = () => {
     const container = scrollContainerRef.current;
@@ -209,6 +211,10 @@ function ChatView({
           />
         )}
 
+        {pendingApprovals.map((request) => (
+          <WriteApprovalCard key={request.approvalId} request={request} />
+        ))}
+
         <div ref={bottomRef} />
       </div>
 
@@ -31,7 +31,8 @@ import {
   MoreVertical,
 } from "lucide-react";
 import ConfirmDialog from "@/components/ui/ConfirmDialog";
-import { openFileExternal } from "@/lib/tauri";
+import StagedChangesModal from "@/components/chat/StagedChangesModal";
+import { describeRevertConflicts, openFileExternal, revertRun } from "@/lib/tauri";
 import {
   decodeWikilinkHref,
   resolveWikilinkPath,
@@ -123,6 +124,7 @@ function toActivityLabel(step: TimelineStep): string | null {
     case "kb_create":
       return path ? `Created ${path}${sizeSuffix}` : "Created a note";
     case "kb_update":
+    case "kb_patch":
       return path ? `Updated ${path}` : "Updated a note";
     case "web_search":
       return query ? `Searched the web for "${truncateText(query, 56)}"` : "Searched the web";
@@ -198,7 +200,8 @@ function toActivityDetail(step: TimelineStep): string | null {
       }
       return null;
     }
-    case "kb_update": {
+    case "kb_update":
+    case "kb_patch": {
       if (firstChange) return firstChange.path;
       return null;
     }
@@ -282,6 +285,7 @@ function summarizeTimeline(steps: TimelineStep[]): string {
         counts.create += 1;
         break;
       case "kb_update":
+      case "kb_patch":
         counts.update += 1;
         break;
       case "web_search":
@@ -384,6 +388,7 @@ function TimelineRowIcon({ row }: { row: UnifiedTimelineRow }) {
     case "kb_create":
       return <FilePlus className={`${cls} text-success/70`} strokeWidth={1.5} />;
     case "kb_update":
+    case "kb_patch":
       return <FilePen className={`${cls} text-text-muted`} strokeWidth={1.5} />;
     case "web_search":
       return <Globe className={`${cls} text-text-muted`} strokeWidth={1.5} />;
@@ -398,7 +403,9 @@ function liveActivityToRow(activity: AgentActivity): UnifiedTimelineRow | null {
     kb_read: "Reading note",
     kb_create: "Creating note",
     kb_update: "Updating note",
+    kb_patch: "Editing note",
     kb_list: "Listing notes",
+    kb_links: "Following links",
     web_search: "Searching the web",
   };
 
@@ -424,6 +431,8 @@ function liveActivityToRow(activity: AgentActivity): UnifiedTimelineRow | null {
       };
     case "verifying":
       return { id: "live", kind: "action", text: "Checking results...", time: "", ts: Date.now() };
+    case "awaiting_approval":
+      return { id: "live", kind: "action", tool: activity.tool, text: "Waiting for your approval...", time: "", ts: Date.now() };
     default:
       return null;
   }
@@ -654,15 +663,23 @@ function MessageBubble({
   const [savingEdit, setSavingEdit] = useState(false);
   const [pendingDelete, setPendingDelete] = useState(false);
   const [deleting, setDeleting] = useState(false);
+  const [pendingRevert, setPendingRevert] = useState(false);
+  const [reverting, setReverting] = useState(false);
   const [copied, setCopied] = useState(false);
   const [localError, setLocalError] = useState<string | null>(null);
   const [showTechMenu, setShowTechMenu] = useState(false);
   const [collapsed, setCollapsed] = useState(true);
+  const [showStagedChanges, setShowStagedChanges] = useState(false);
+  const [stagedRemaining, setStagedRemaining] = useState(message.stagedChanges ?? 0);
   const techMenuRef = useRef<HTMLDivElement | null>(null);
   const editTextareaRef = useRef<HTMLTextAreaElement>(null);
   const userBubbleRef = useRef<HTMLDivElement>(null);
   const [editBubbleWidth, setEditBubbleWidth] = useState<number | null>(null);
 
+  useEffect(() => {
+    setStagedRemaining(message.stagedChanges ?? 0);
+  }, [message.stagedChanges]);
+
   const isLongUserMessage = isUser && message.content.length > 300;
 
   const captureEditBubbleWidth = useCallback(() => {
@@ -727,6 +744,8 @@ function MessageBubble({
     !isStreamingMessage &&
     message.role === "assistant" &&
     Boolean(message.runId && onOpenRunTrace);
+  const canRevertRun =
+    !isStreamingMessage && message.role === "assistant" && Boolean(message.runId);
 
   const handleCopy = async () => {
     try {
@@ -778,6 +797,27 @@ function MessageBubble({
     }
   };
 
+  const confirmRevert = async () => {
+    if (!message.runId) return;
+    setReverting(true);
+    setLocalError(null);
+    try {
+      const outcome = await revertRun(message.runId);
+      setPendingRevert(false);
+      setShowTechMenu(false);
+      const conflicts = describeRevertConflicts(outcome);
+      if (conflicts) {
+        setLocalError(`Undone with conflicts:\n${conflicts}`);
+      }
+    } catch (error) {
+      console.error("Failed to undo run changes:", error);
+      setPendingRevert(false);
+      setLocalError(String(error));
+    } finally {
+      setReverting(false);
+    }
+  };
+
   const openReference = (target: string) => {
     const normalized = target.trim();
     if (!normalized) return;
@@ -885,7 +925,7 @@ function MessageBubble({
         </IconButton>
       )}
 
-      {(canDelete || canOpenRunTrace) && !isEditing && (
+      {(canDelete || canOpenRunTrace || canRevertRun) && !isEditing && (
         <div ref={techMenuRef} className="relative">
           <IconButton
             title="Technical actions"
@@ -909,6 +949,16 @@ function MessageBubble({
                   <span>↗</span>
                 </button>
               )}
+              {canRevertRun && (
+                <button
+                  type="button"
+                  onClick={() => setPendingRevert(true)}
+                  className="flex w-full items-center justify-between rounded-lg px-2 py-1.5 text-left text-xs text-text-muted transition-colors hover:bg-bg-tertiary hover:text-text"
+                >
+                  <span>Undo note changes</span>
+                  <span>↺</span>
+                </button>
+              )}
               {canDelete && (
                 <button
                   type="button"
@@ -1096,6 +1146,18 @@ function MessageBubble({
           )}
         </div>
 
+        {!isUser && message.runId && stagedRemaining > 0 && (
+          <div className="mt-2 flex justify-start">
+            <button
+              type="button"
+              onClick={() => setShowStagedChanges(true)}
+              className="rounded-full border border-accent/30 bg-accent/[0.08] px-3 py-1 text-xs text-accent transition-colors hover:border-accent/50 hover:bg-accent/[0.14]"
+            >
+              Review {stagedRemaining} staged change{stagedRemaining === 1 ? "" : "s"}
+            </button>
+          </div>
+        )}
+
         {/* Actions + timestamp — below message */}
         {!isStreamingMessage && (
           <div className={`mt-1 flex ${isUser ? "justify-end" : "justify-start"}`}>
@@ -1126,6 +1188,32 @@ function MessageBubble({
         )}
       </div>
 
+      {message.runId && (
+        <StagedChangesModal
+          open={showStagedChanges}
+          runId={message.runId}
+          onClose={() => setShowStagedChanges(false)}
+          onResolved={setStagedRemaining}
+        />
+      )}
+
+      <ConfirmDialog
+        open={pendingRevert}
+        title="Undo note changes?"
+        description="Reverts every note this answer created or edited in a single commit. Your own edits are kept."
+        confirmLabel={reverting ? "Undoing..." : "Undo changes"}
+        cancelLabel="Cancel"
+        destructive
+        onCancel={() => {
+          if (reverting) return;
+          setPendingRevert(false);
+        }}
+        onConfirm={() => {
+          if (reverting) return;
+          void confirmRevert();
+        }}
+      />
+
       <ConfirmDialog
         open={pendingDelete}
         title="Delete message?"
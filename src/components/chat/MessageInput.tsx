@@ -1,13 +1,20 @@
 "use client";
 
 import { useState, useRef, useCallback, useEffect, useLayoutEffect } from "react";
-import { SendHorizonal } from "lucide-react";
+import { FlaskConical, GitBranch, SendHorizonal } from "lucide-react";
 import { useShallow } from "zustand/react/shallow";
 import { useAppStore } from "@/lib/store";
 import { selectMessageInputState } from "@/state/selectors";
-import { cancelActiveRun, sendMessage } from "@/lib/tauri";
+import {
+  cancelActiveRun,
+  getConversationBranch,
+  sendMessage,
+  setConversationBranchMode,
+  type ConversationBranch,
+} from "@/lib/tauri";
 import { collectSourcesFromToolResults } from "@/lib/events";
 import { buildChatErrorToast } from "@/lib/chatErrors";
+import BranchReviewModal from "./BranchReviewModal";
 
 const MAX_TEXTAREA_HEIGHT = 240;
 
@@ -27,6 +34,41 @@ export default function MessageInput({
   const { isStreaming, streamSuppressed, isIndexing, activeConversationId } = useAppStore(
     useShallow(selectMessageInputState),
   );
+  const dryRunMode = useAppStore((state) => state.dryRunMode);
+  const setDryRunMode = useAppStore((state) => state.setDryRunMode);
+  const [branch, setBranch] = useState<ConversationBranch | null>(null);
+  const [branchReviewOpen, setBranchReviewOpen] = useState(false);
+
+  const loadBranch = useCallback(async () => {
+    if (!activeConversationId) {
+      setBranch(null);
+      return;
+    }
+    try {
+      setBranch(await getConversationBranch(activeConversationId));
+    } catch (error) {
+      console.warn("Failed to load conversation branch:", error);
+      setBranch(null);
+    }
+  }, [activeConversationId]);
+
+  // Refresh after each run, which may have committed to the branch.
+  useEffect(() => {
+    if (isStreaming) return;
+    void loadBranch();
+  }, [isStreaming, loadBranch]);
+
+  const toggleBranchMode = useCallback(async () => {
+    if (!activeConversationId || !branch || branch.inherited) return;
+    try {
+      await setConversationBranchMode(activeConversationId, !branch.branch_mode);
+      await loadBranch();
+    } catch (error) {
+      useAppStore.getState().showToast(String(error));
+    }
+  }, [activeConversationId, branch, loadBranch]);
+
+  const branchChanges = branch?.diff?.files_changed.length ?? 0;
 
   const resizeTextarea = useCallback(() => {
     const el = textareaRef.current;
@@ -67,7 +109,7 @@ export default function MessageInput({
       if (onSendMessage) {
         await onSendMessage(text);
       } else {
-        await sendMessage(text, activeConversationId);
+        await sendMessage(text, activeConversationId, null, store.dryRunMode);
       }
     } catch (error) {
       const toast = buildChatErrorToast(error);
@@ -185,6 +227,62 @@ export default function MessageInput({
           disabled={isIndexing || isStreaming}
         />
 
+        <button
+          type="button"
+          onClick={() => setDryRunMode(!dryRunMode)}
+          disabled={isStreaming}
+          aria-pressed={dryRunMode}
+          className={`flex h-[40px] shrink-0 items-center gap-1.5 rounded-full px-3 text-xs font-medium transition-colors duration-[120ms] disabled:cursor-not-allowed disabled:opacity-40 ${
+            dryRunMode
+              ? "bg-accent/15 text-accent"
+              : "text-text-muted hover:bg-overlay-10 hover:text-text-secondary"
+          }`}
+          title={
+            dryRunMode
+              ? "Dry run: note changes are staged for review instead of written"
+              : "Enable dry run to review note changes before they are written"
+          }
+        >
+          <FlaskConical className="h-3.5 w-3.5" />
+          Dry run
+        </button>
+
+        {activeConversationId && (
+          <button
+            type="button"
+            onClick={() => void toggleBranchMode()}
+            disabled={isStreaming || !branch || branch.inherited}
+            aria-pressed={branch?.branch_mode ?? false}
+            className={`flex h-[40px] shrink-0 items-center gap-1.5 rounded-full px-3 text-xs font-medium transition-colors duration-[120ms] disabled:cursor-not-allowed disabled:opacity-40 ${
+              branch?.branch_mode
+                ? "bg-accent/15 text-accent"
+                : "text-text-muted hover:bg-overlay-10 hover:text-text-secondary"
+            }`}
+            title={
+              branch?.inherited
+                ? "Branch mode is on for this conversation's folder"
+                : branch?.branch_mode
+                  ? "Branch mode: agent writes go to this conversation's branch until you merge it"
+                  : "Enable branch mode to review agent writes as a branch before merging"
+            }
+          >
+            <GitBranch className="h-3.5 w-3.5" />
+            Branch
+          </button>
+        )}
+
+        {activeConversationId && branchChanges > 0 && (
+          <button
+            type="button"
+            onClick={() => setBranchReviewOpen(true)}
+            disabled={isStreaming}
+            className="flex h-[40px] shrink-0 items-center rounded-full px-3 text-xs font-medium text-accent transition-colors duration-[120ms] hover:bg-accent/10 disabled:cursor-not-allowed disabled:opacity-40"
+            title="Review and merge this conversation's branch"
+          >
+            Review {branchChanges}
+          </button>
+        )}
+
         {isStreaming ? (
           <button
             type="button"
@@ -210,6 +308,15 @@ export default function MessageInput({
           </button>
         )}
       </div>
+
+      {activeConversationId && (
+        <BranchReviewModal
+          open={branchReviewOpen}
+          conversationId={activeConversationId}
+          onClose={() => setBranchReviewOpen(false)}
+          onResolved={() => void loadBranch()}
+        />
+      )}
     </div>
   );
 }
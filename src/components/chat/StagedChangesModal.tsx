@@ -0,0 +1,185 @@
+"use client";
+
+import { useCallback, useEffect, useState } from "react";
+import { createPortal } from "react-dom";
+import { useAppStore } from "@/lib/store";
+import {
+  applyStagedChanges,
+  discardStagedChanges,
+  listStagedChanges,
+  type StagedChange,
+} from "@/lib/tauri";
+
+export function diffLineClass(line: string): string {
+  if (line.startsWith("+++") || line.startsWith("---")) return "text-text-muted";
+  if (line.startsWith("+")) return "text-success";
+  if (line.startsWith("-")) return "text-error";
+  if (line.startsWith("@@")) return "text-text-muted";
+  return "text-text-secondary";
+}
+
+interface Props {
+  open: boolean;
+  runId: string;
+  onClose: () => void;
+  onResolved?: (remaining: number) => void;
+}
+
+export default function StagedChangesModal({ open, runId, onClose, onResolved }: Props) {
+  const [changes, setChanges] = useState<StagedChange[]>([]);
+  const [selected, setSelected] = useState<Set<string>>(new Set());
+  const [expanded, setExpanded] = useState<string | null>(null);
+  const [loading, setLoading] = useState(false);
+  const [busy, setBusy] = useState(false);
+  const showToast = useAppStore((state) => state.showToast);
+
+  const reload = useCallback(async () => {
+    setLoading(true);
+    try {
+      const next = await listStagedChanges(runId);
+      setChanges(next);
+      setSelected(new Set(next.map((change) => change.path)));
+      setExpanded((prev) => prev ?? next[0]?.path ?? null);
+      onResolved?.(next.length);
+    } catch (error) {
+      showToast(`Failed to load staged changes: ${String(error)}`);
+    } finally {
+      setLoading(false);
+    }
+  }, [onResolved, runId, showToast]);
+
+  useEffect(() => {
+    if (!open) return;
+    void reload();
+  }, [open, reload]);
+
+  useEffect(() => {
+    if (!open) return;
+    function handleKeydown(event: KeyboardEvent) {
+      if (event.key === "Escape") onClose();
+    }
+    window.addEventListener("keydown", handleKeydown);
+    return () => window.removeEventListener("keydown", handleKeydown);
+  }, [onClose, open]);
+
+  const toggle = (path: string) => {
+    setSelected((prev) => {
+      const next = new Set(prev);
+      if (next.has(path)) next.delete(path);
+      else next.add(path);
+      return next;
+    });
+  };
+
+  const act = async (apply: boolean) => {
+    const paths = [...selected];
+    if (paths.length === 0) return;
+    setBusy(true);
+    try {
+      if (apply) {
+        const applied = await applyStagedChanges(runId, paths);
+        showToast(`Applied ${applied.length} staged change${applied.length === 1 ? "" : "s"}`);
+      } else {
+        await discardStagedChanges(runId, paths);
+      }
+      await reload();
+    } catch (error) {
+      showToast(String(error));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  if (!open || typeof document === "undefined") {
+    return null;
+  }
+
+  const dialog = (
+    <div className="fixed inset-0 z-50 flex items-center justify-center px-4">
+      <button
+        aria-label="Close staged changes"
+        className="absolute inset-0 bg-scrim-50 animate-overlay-fade"
+        onClick={onClose}
+      />
+
+      <div className="relative flex max-h-[80vh] w-full max-w-3xl flex-col rounded-2xl border border-overlay-6 bg-bg-secondary/98 p-6 shadow-xl shadow-black/30 backdrop-blur-lg space-y-4 animate-dialog-in">
+        <div>
+          <h3 className="text-lg font-semibold">Staged changes</h3>
+          <p className="text-sm text-text-secondary">
+            This dry run did not touch your notes. Apply the changes you want to keep.
+          </p>
+        </div>
+
+        <div className="min-h-0 flex-1 space-y-2 overflow-y-auto">
+          {loading && changes.length === 0 && (
+            <p className="text-xs text-text-muted">Loading...</p>
+          )}
+          {!loading && changes.length === 0 && (
+            <p className="text-xs text-text-muted">No staged changes left for this run.</p>
+          )}
+          {changes.map((change) => (
+            <div key={change.path} className="rounded-xl border border-overlay-6 bg-bg-tertiary/40">
+              <div className="flex items-center gap-2 px-3 py-2">
+                <input
+                  type="checkbox"
+                  checked={selected.has(change.path)}
+                  onChange={() => toggle(change.path)}
+                  disabled={busy}
+                />
+                <button
+                  type="button"
+                  onClick={() => setExpanded((prev) => (prev === change.path ? null : change.path))}
+                  className="flex flex-1 items-center justify-between text-left text-xs"
+                >
+                  <span className="font-mono text-text">{change.path}</span>
+                  <span className="text-text-muted">
+                    {change.action === "create" ? "new note" : "edit"}
+                  </span>
+                </button>
+              </div>
+              {expanded === change.path && (
+                <pre className="max-h-72 overflow-auto border-t border-overlay-6 p-3 text-xs leading-relaxed">
+                  {(change.diff.trim() ? change.diff : change.content)
+                    .split("\n")
+                    .map((line, index) => (
+                      <div
+                        key={index}
+                        className={change.diff.trim() ? diffLineClass(line) : "text-text-secondary"}
+                      >
+                        {line || " "}
+                      </div>
+                    ))}
+                </pre>
+              )}
+            </div>
+          ))}
+        </div>
+
+        <div className="flex justify-end gap-2.5 pt-2">
+          <button
+            onClick={onClose}
+            className="px-4 py-2 text-sm rounded-xl border border-overlay-6 bg-bg-tertiary/60 text-text-secondary hover:text-text hover:bg-bg-tertiary transition-colors"
+          >
+            Close
+          </button>
+          <button
+            disabled={busy || selected.size === 0}
+            onClick={() => void act(false)}
+            className="px-4 py-2 text-sm rounded-xl border border-overlay-6 bg-bg-tertiary/60 text-error hover:bg-error/10 transition-colors disabled:opacity-50"
+          >
+            Discard selected
+          </button>
+          <button
+            disabled={busy || selected.size === 0}
+            onClick={() => void act(true)}
+            className="px-4 py-2 text-sm rounded-xl bg-accent text-bg hover:opacity-90 transition-opacity disabled:opacity-50"
+          >
+            Apply selected
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+
+  return createPortal(dialog, document.body);
+}
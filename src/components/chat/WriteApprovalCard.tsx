@@ -0,0 +1,94 @@
+"use client";
+
+import { useState } from "react";
+import { useAppStore, type WriteApprovalRequest } from "@/lib/store";
+import { respondToWriteApproval } from "@/lib/tauri";
+
+const toolVerbs: Record<string, string> = {
+  kb_create: "create",
+  kb_update: "update",
+  kb_patch: "edit",
+};
+
+function diffLineClass(line: string): string {
+  if (line.startsWith("+++") || line.startsWith("---")) return "text-text-muted";
+  if (line.startsWith("+")) return "text-success";
+  if (line.startsWith("-")) return "text-error";
+  if (line.startsWith("@@")) return "text-text-muted";
+  return "text-text-secondary";
+}
+
+interface Props {
+  request: WriteApprovalRequest;
+}
+
+export default function WriteApprovalCard({ request }: Props) {
+  const [submitting, setSubmitting] = useState(false);
+  const [reason, setReason] = useState("");
+  const removePendingApproval = useAppStore((state) => state.removePendingApproval);
+  const showToast = useAppStore((state) => state.showToast);
+
+  const verb = toolVerbs[request.tool];
+  const title = verb && request.path
+    ? `Allow meld to ${verb} ${request.path}?`
+    : `Allow ${request.tool} to write?`;
+  const body = request.diff?.trim()
+    ? request.diff
+    : request.content ?? JSON.stringify(request.args, null, 2);
+
+  const respond = async (approved: boolean) => {
+    setSubmitting(true);
+    try {
+      const delivered = await respondToWriteApproval(
+        request.approvalId,
+        approved,
+        approved ? undefined : reason.trim() || undefined,
+      );
+      if (!delivered) {
+        removePendingApproval(request.approvalId);
+      }
+    } catch (error) {
+      showToast(`Failed to send approval: ${String(error)}`);
+    } finally {
+      setSubmitting(false);
+    }
+  };
+
+  return (
+    <div className="rounded-xl border border-border/60 bg-bg-secondary/80 p-4 space-y-3 shadow-md shadow-black/10 animate-fade-in">
+      <p className="text-sm font-medium">{title}</p>
+      {request.error && (
+        <p className="text-xs text-error">This write will fail: {request.error}</p>
+      )}
+      <pre className="max-h-72 overflow-auto rounded-lg bg-bg-tertiary/60 p-3 text-xs leading-relaxed">
+        {body.split("\n").map((line, index) => (
+          <div key={index} className={request.diff ? diffLineClass(line) : "text-text-secondary"}>
+            {line || " "}
+          </div>
+        ))}
+      </pre>
+      <input
+        value={reason}
+        onChange={(event) => setReason(event.target.value)}
+        placeholder="Reason for rejecting (optional, shared with the agent)"
+        className="w-full rounded-lg border border-overlay-6 bg-bg-tertiary/40 px-3 py-1.5 text-xs outline-none focus:border-accent/60"
+      />
+      <div className="flex justify-end gap-2">
+        <button
+          disabled={submitting}
+          onClick={() => void respond(false)}
+          className="px-3 py-1.5 text-xs rounded-lg border border-overlay-6 bg-bg-tertiary/60 text-text-secondary hover:text-text hover:bg-bg-tertiary transition-colors disabled:opacity-50"
+        >
+          Reject
+        </button>
+        <button
+          disabled={submitting}
+          onClick={() => void respond(true)}
+          className="px-3 py-1.5 text-xs rounded-lg bg-accent text-bg hover:opacity-90 transition-colors disabled:opacity-50"
+        >
+          Approve
+        </button>
+      </div>
+    </div>
+  );
+}
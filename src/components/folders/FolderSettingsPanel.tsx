@@ -15,6 +15,7 @@ import {
   getProviderCatalog,
   renameChatFolder,
   updateChatFolder,
+  setFolderBranchMode,
   archiveChatFolder,
   getFolderInstructionChain,
   type FolderPayload,
@@ -306,6 +307,20 @@ export default function FolderSettingsPanel({
     [defaultModelProvider, saveDefaultModelId],
   );
 
+  const handleBranchModeChange = useCallback(
+    async (enabled: boolean) => {
+      try {
+        await setFolderBranchMode(folderId, enabled);
+        if (!isMountedRef.current) return;
+        setFolder((prev) => (prev ? { ...prev, branch_mode: enabled } : prev));
+        await notifyFolderUpdated();
+      } catch (error) {
+        console.error("Failed to update folder branch mode:", error);
+      }
+    },
+    [folderId, notifyFolderUpdated],
+  );
+
   const handleArchive = useCallback(async () => {
     try {
       await archiveChatFolder(folderId);
@@ -457,6 +472,20 @@ export default function FolderSettingsPanel({
           />
         </div>
 
+        <div>
+          <label className="flex items-center gap-2 text-xs font-medium text-text-secondary">
+            <input
+              type="checkbox"
+              checked={folder.branch_mode}
+              onChange={(e) => void handleBranchModeChange(e.target.checked)}
+            />
+            Branch mode
+          </label>
+          <p className="mt-1.5 text-[11px] text-text-muted">
+            Agent writes in this folder&apos;s chats go to a branch per conversation. Review and merge each branch into your notes from the chat. Applies to subfolders too.
+          </p>
+        </div>
+
         {/* Instruction chain preview */}
         {(loadingInstructionChain || instructionChain.length > 0 || instructionChainError) && (
           <div>
@@ -3,12 +3,27 @@
 import { useEffect, useState } from "react";
 import { X } from "lucide-react";
 import { useAppStore } from "@/lib/store";
-import { getHistory, revertCommit, type HistoryEntry } from "@/lib/tauri";
+import {
+  describeRevertConflicts,
+  getHistory,
+  restoreFile,
+  revertCommit,
+  type ConflictStyle,
+  type HistoryEntry,
+  type HistoryQuery,
+} from "@/lib/tauri";
+
+type AuthorFilter = "all" | NonNullable<HistoryQuery["author"]>;
 
 export default function HistoryPanel() {
   const [entries, setEntries] = useState<HistoryEntry[]>([]);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [conflictStyle, setConflictStyle] = useState<ConflictStyle>("markers");
+  const [conflictReport, setConflictReport] = useState<string | null>(null);
+  const [nextCursor, setNextCursor] = useState<string | null>(null);
+  const [authorFilter, setAuthorFilter] = useState<AuthorFilter>("all");
+  const [messageFilter, setMessageFilter] = useState("");
 
   useEffect(() => {
     if (!error) return;
@@ -18,24 +33,47 @@ export default function HistoryPanel() {
     return () => window.clearTimeout(timeoutId);
   }, [error]);
 
+  function historyQuery(after?: string): HistoryQuery {
+    return {
+      after,
+      author: authorFilter === "all" ? undefined : authorFilter,
+      message: messageFilter.trim() || undefined,
+    };
+  }
+
   async function loadHistory() {
     try {
-      const history = await getHistory();
-      setEntries(history);
+      const page = await getHistory(historyQuery());
+      setEntries(page.entries);
+      setNextCursor(page.next_cursor);
     } catch (e) {
       console.error("Failed to load history:", e);
     }
     setLoading(false);
   }
 
+  async function loadMore() {
+    if (!nextCursor) return;
+    try {
+      const page = await getHistory(historyQuery(nextCursor));
+      setEntries((current) => [...current, ...page.entries]);
+      setNextCursor(page.next_cursor);
+    } catch (e) {
+      console.error("Failed to load history:", e);
+      setError(`Failed to load history: ${String(e)}`);
+    }
+  }
+
   useEffect(() => {
-    // eslint-disable-next-line react-hooks/set-state-in-effect -- initial data fetch on mount
+    // eslint-disable-next-line react-hooks/set-state-in-effect -- refetch when filters change
     loadHistory();
-  }, []);
+    // eslint-disable-next-line react-hooks/exhaustive-deps -- loadHistory reads the filters
+  }, [authorFilter, messageFilter]);
 
   async function handleRevert(commitId: string) {
     try {
-      await revertCommit(commitId);
+      const outcome = await revertCommit(commitId, conflictStyle);
+      setConflictReport(describeRevertConflicts(outcome));
       await loadHistory();
     } catch (e) {
       console.error("Failed to revert:", e);
@@ -43,6 +81,16 @@ export default function HistoryPanel() {
     }
   }
 
+  async function handleRestore(path: string, commitId: string) {
+    try {
+      await restoreFile(path, commitId);
+      await loadHistory();
+    } catch (e) {
+      console.error("Failed to restore:", e);
+      setError(`Failed to restore ${path}: ${String(e)}`);
+    }
+  }
+
   return (
     <div className="p-6 space-y-6 h-full overflow-y-auto scrollbar-visible max-w-xl mx-auto">
       <div className="flex items-center justify-between">
@@ -55,6 +103,51 @@ export default function HistoryPanel() {
         </button>
       </div>
 
+      <div className="flex items-center gap-2">
+        <input
+          value={messageFilter}
+          onChange={(event) => setMessageFilter(event.target.value)}
+          placeholder="Search commit messages"
+          className="flex-1 rounded-lg border border-overlay-6 bg-bg-tertiary/60 px-2.5 py-1 text-xs text-text placeholder:text-text-muted"
+        />
+        <select
+          value={authorFilter}
+          onChange={(event) => setAuthorFilter(event.target.value as AuthorFilter)}
+          className="rounded-lg border border-overlay-6 bg-bg-tertiary/60 px-2 py-1 text-xs text-text"
+        >
+          <option value="all">Everyone</option>
+          <option value="agent">meld</option>
+          <option value="user">You</option>
+        </select>
+      </div>
+
+      <label className="flex items-center justify-between gap-3 text-xs text-text-secondary">
+        <span>When a revert conflicts with later edits</span>
+        <select
+          value={conflictStyle}
+          onChange={(event) => setConflictStyle(event.target.value as ConflictStyle)}
+          className="rounded-lg border border-overlay-6 bg-bg-tertiary/60 px-2 py-1 text-text"
+        >
+          <option value="markers">Add conflict markers</option>
+          <option value="copy">Save a conflict copy</option>
+        </select>
+      </label>
+
+      {conflictReport && (
+        <div className="rounded-xl border border-warning/40 bg-warning/[0.08] px-3.5 py-2.5 text-sm text-warning space-y-1">
+          <div className="flex items-start justify-between gap-3">
+            <span>Reverted with conflicts:</span>
+            <button
+              onClick={() => setConflictReport(null)}
+              className="text-text-muted hover:text-text transition-colors"
+            >
+              <X className="h-3.5 w-3.5" />
+            </button>
+          </div>
+          <pre className="whitespace-pre-wrap font-mono text-xs">{conflictReport}</pre>
+        </div>
+      )}
+
       {error && (
         <div className="rounded-xl border border-error/40 bg-error/[0.08] px-3.5 py-2.5 text-sm text-error">
           {error}
@@ -77,7 +170,18 @@ export default function HistoryPanel() {
               key={entry.id}
               className="p-4 bg-bg-secondary/40 border border-overlay-5 rounded-xl space-y-2.5"
             >
-              <p className="text-sm">{entry.message}</p>
+              <div className="flex items-start justify-between gap-3">
+                <p className="text-sm">{entry.message}</p>
+                <span
+                  className={`shrink-0 text-[10px] px-2 py-0.5 rounded-full ${
+                    entry.author === "user"
+                      ? "bg-overlay-10 text-text-secondary"
+                      : "bg-accent/10 text-accent"
+                  }`}
+                >
+                  {entry.author === "user" ? "You" : "meld"}
+                </span>
+              </div>
               <div className="flex items-center justify-between">
                 <span className="text-xs text-text-muted">
                   {new Date(entry.timestamp * 1000).toLocaleString()}
@@ -92,17 +196,27 @@ export default function HistoryPanel() {
               {entry.files_changed.length > 0 && (
                 <div className="flex flex-wrap gap-1.5">
                   {entry.files_changed.map((file) => (
-                    <span
+                    <button
                       key={file}
-                      className="text-[11px] px-2 py-0.5 bg-bg-tertiary rounded-lg text-text-muted"
+                      onClick={() => handleRestore(file, entry.id)}
+                      title={`Restore ${file} to this version`}
+                      className="text-[11px] px-2 py-0.5 bg-bg-tertiary rounded-lg text-text-muted hover:text-text transition-colors"
                     >
                       {file}
-                    </span>
+                    </button>
                   ))}
                 </div>
               )}
             </div>
           ))}
+          {nextCursor && (
+            <button
+              onClick={loadMore}
+              className="w-full text-xs py-2 text-text-muted hover:text-text hover:bg-overlay-5 rounded-lg transition-colors"
+            >
+              Load older changes
+            </button>
+          )}
         </div>
       )}
     </div>
@@ -17,6 +17,13 @@ import Select from "@/components/ui/Select";
 import MeldLogo from "@/components/ui/MeldLogo";
 import WindowControls from "@/components/ui/WindowControls";
 import { setupEventListeners } from "@/lib/events";
+import {
+  DEFAULT_EMBEDDING_MODELS,
+  EMBEDDING_PROVIDER_PRIORITY,
+  LLM_PROVIDER_PRIORITY,
+  providerHasCredential,
+  uniqueLower,
+} from "@/lib/providerCredentials";
 
 type Step = "welcome" | "folder" | "apikey" | "indexing" | "ready";
 
@@ -27,52 +34,9 @@ const DEFAULT_CHAT_MODELS: Record<string, string> = {
   openrouter: "qwen/qwen3-235b-a22b-thinking-2507",
 };
 
-const DEFAULT_EMBEDDING_MODELS: Record<string, string> = {
-  openai: "text-embedding-3-small",
-  google: "gemini-embedding-001",
-};
-
-const LLM_PROVIDER_PRIORITY = ["google", "openai", "anthropic", "openrouter"];
-const EMBEDDING_PROVIDER_PRIORITY = ["google", "openai"];
-
-const LEGACY_PROVIDER_KEY_FIELDS: Partial<Record<string, keyof Config>> = {
-  openai: "openai_api_key",
-  anthropic: "anthropic_api_key",
-  google: "google_api_key",
-};
-
 const EMBEDDING_KEY_REQUIRED_MESSAGE =
   "Add an OpenAI or Google API key for embeddings before indexing.";
 
-function uniqueNonEmpty(values: string[]): string[] {
-  const unique: string[] = [];
-  for (const raw of values) {
-    const normalized = raw.trim().toLowerCase();
-    if (!normalized || unique.includes(normalized)) continue;
-    unique.push(normalized);
-  }
-  return unique;
-}
-
-function providerHasCredential(config: Config, provider: string): boolean {
-  const normalizedProvider = provider.trim().toLowerCase();
-  if (!normalizedProvider) return false;
-
-  if (config.auth_modes?.[normalizedProvider] === "oauth") {
-    const oauthTokens = config.oauth_tokens as Record<string, unknown> | undefined;
-    return Boolean(oauthTokens?.[normalizedProvider]);
-  }
-
-  const keyFromMap = config.api_keys?.[normalizedProvider];
-  const legacyField = LEGACY_PROVIDER_KEY_FIELDS[normalizedProvider];
-  const legacyValue =
-    legacyField && typeof config[legacyField] === "string"
-      ? config[legacyField]
-      : null;
-  const resolved = keyFromMap ?? legacyValue;
-  return typeof resolved === "string" && resolved.trim().length > 0;
-}
-
 function hasAnyLlmCredential(config: Config): boolean {
   return LLM_PROVIDER_PRIORITY.some((provider) =>
     providerHasCredential(config, provider),
@@ -93,7 +57,7 @@ async function alignModelProviders(
   const currentChatProvider = (
     config.chat_provider?.trim().toLowerCase() || "openai"
   );
-  const chatCandidates = uniqueNonEmpty([
+  const chatCandidates = uniqueLower([
     preferred,
     currentChatProvider,
     ...LLM_PROVIDER_PRIORITY,
@@ -112,7 +76,7 @@ async function alignModelProviders(
   const currentEmbeddingProvider = (
     config.embedding_provider?.trim().toLowerCase() || "openai"
   );
-  const embeddingCandidates = uniqueNonEmpty([
+  const embeddingCandidates = uniqueLower([
     preferred,
     currentEmbeddingProvider,
     ...EMBEDDING_PROVIDER_PRIORITY,
@@ -173,7 +173,7 @@ export default function SettingsPanel() {
 
     try {
       const config = await getConfig();
-      const resolvedEmbeddingProvider = resolveEmbeddingProviderForIndexing(config);
+      const resolvedEmbeddingProvider = await resolveEmbeddingProviderForIndexing(config);
       const currentEmbeddingProvider =
         config.embedding_provider?.trim().toLowerCase() || "openai";
 
@@ -1,14 +1,51 @@
 "use client";
 
-import { RefreshCw } from "lucide-react";
+import { Pause, Play, RefreshCw, X } from "lucide-react";
+import type { IndexProgress } from "@/lib/tauri";
 
 interface StatusBarProps {
   vaultPath: string | null;
   fileCount: number;
   modelId: string;
   isIndexing: boolean;
-  indexProgress: { current: number; total: number; file: string } | null;
+  indexPaused?: boolean;
+  indexProgress: IndexProgress | null;
   onReindex?: () => Promise<void> | void;
+  onPauseIndex?: () => Promise<void> | void;
+  onResumeIndex?: () => Promise<void> | void;
+  onCancelIndex?: () => Promise<void> | void;
+}
+
+function formatEta(seconds: number): string {
+  if (seconds < 60) return `${seconds}s`;
+  const minutes = Math.floor(seconds / 60);
+  if (minutes < 60) return `${minutes}m ${seconds % 60}s`;
+  return `${Math.floor(minutes / 60)}h ${minutes % 60}m`;
+}
+
+function progressDetails(progress: IndexProgress): string[] {
+  const details: string[] = [];
+  if (progress.files_per_second > 0) {
+    details.push(`${progress.files_per_second.toFixed(1)}/s`);
+  }
+  if (progress.eta_seconds !== null && progress.current < progress.total) {
+    details.push(`ETA ${formatEta(progress.eta_seconds)}`);
+  }
+  if (progress.errors.length > 0) {
+    details.push(
+      `${progress.errors.length} ${progress.errors.length === 1 ? "error" : "errors"}`,
+    );
+  }
+  return details;
+}
+
+function progressTitle(progress: IndexProgress): string {
+  const lines = [
+    progress.file,
+    `${progress.embedded_chunks} chunks embedded · ${progress.skipped} notes already current`,
+    ...progress.errors.map((error) => `${error.file}: ${error.error}`),
+  ];
+  return lines.filter(Boolean).join("\n");
 }
 
 export default function StatusBar({
@@ -16,12 +53,19 @@ export default function StatusBar({
   fileCount,
   modelId,
   isIndexing,
+  indexPaused = false,
   indexProgress,
   onReindex,
+  onPauseIndex,
+  onResumeIndex,
+  onCancelIndex,
 }: StatusBarProps) {
   const indexLabel = isIndexing
-    ? `Indexing... ${indexProgress ? `${indexProgress.current}/${indexProgress.total}` : ""
-      }`.trim()
+    ? [
+      `${indexPaused ? "Paused" : "Indexing..."} ${indexProgress ? `${indexProgress.current}/${indexProgress.total}` : ""
+        }`.trim(),
+      ...(indexProgress ? progressDetails(indexProgress) : []),
+    ].join(" · ")
     : `Indexed · ${fileCount} notes`;
 
   return (
@@ -33,9 +77,42 @@ export default function StatusBar({
         {vaultPath ?? "No vault selected"}
       </span>
 
-      <span className="pill-badge shrink-0 text-text-muted">
+      <span
+        className="pill-badge shrink-0 text-text-muted"
+        title={isIndexing && indexProgress ? progressTitle(indexProgress) : undefined}
+      >
         <span className={`pill-dot ${isIndexing ? "bg-warning/80" : "bg-success/75"}`} />
         {indexLabel}
+        {isIndexing && (onPauseIndex || onResumeIndex) && (
+          <button
+            type="button"
+            onClick={() => {
+              void (indexPaused ? onResumeIndex?.() : onPauseIndex?.());
+            }}
+            className="ml-1 inline-flex h-4 w-4 items-center justify-center rounded text-text-muted transition-all duration-[120ms] hover:text-text"
+            title={indexPaused ? "Resume indexing" : "Pause indexing"}
+            aria-label={indexPaused ? "Resume indexing" : "Pause indexing"}
+          >
+            {indexPaused ? (
+              <Play className="h-3 w-3" strokeWidth={1.8} />
+            ) : (
+              <Pause className="h-3 w-3" strokeWidth={1.8} />
+            )}
+          </button>
+        )}
+        {isIndexing && onCancelIndex && (
+          <button
+            type="button"
+            onClick={() => {
+              void onCancelIndex();
+            }}
+            className="inline-flex h-4 w-4 items-center justify-center rounded text-text-muted transition-all duration-[120ms] hover:text-text"
+            title="Cancel indexing"
+            aria-label="Cancel indexing"
+          >
+            <X className="h-3 w-3" strokeWidth={1.8} />
+          </button>
+        )}
         <button
           type="button"
           onClick={() => {
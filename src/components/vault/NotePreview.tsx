@@ -6,7 +6,7 @@ import {
   type MouseEvent as ReactMouseEvent,
   type ReactNode,
 } from "react";
-import { useMemo, useState } from "react";
+import { useEffect, useMemo, useState } from "react";
 import matter from "gray-matter";
 import ReactMarkdown from "react-markdown";
 import rehypeHighlight from "rehype-highlight";
@@ -16,8 +16,16 @@ import {
   resolveWikilinkPath,
   transformWikilinks,
 } from "@/lib/wikilinks";
-import { openFileExternal } from "@/lib/tauri";
-import { X, ExternalLink, ChevronLeft, ChevronRight, Copy, Check } from "lucide-react";
+import { getNoteBlame, openFileExternal, type NoteBlame } from "@/lib/tauri";
+import {
+  X,
+  ExternalLink,
+  ChevronLeft,
+  ChevronRight,
+  Copy,
+  Check,
+  Sparkles,
+} from "lucide-react";
 import FrontmatterBlock from "@/components/vault/FrontmatterBlock";
 
 interface NotePreviewProps {
@@ -157,11 +165,13 @@ function MarkdownCodeBlock({
 function ToolbarIconButton({
   label,
   disabled,
+  active,
   onClick,
   children,
 }: {
   label: string;
   disabled?: boolean;
+  active?: boolean;
   onClick: () => void;
   children: ReactNode;
 }) {
@@ -170,7 +180,8 @@ function ToolbarIconButton({
       type="button"
       onClick={onClick}
       disabled={disabled}
-      className="flex h-8 w-8 items-center justify-center rounded-lg border border-transparent text-text-muted transition-colors hover:border-border/30 hover:bg-bg-tertiary/50 hover:text-text focus-visible:border-border-focus focus-visible:shadow-[0_0_0_1px_var(--color-border-focus)] outline-none disabled:cursor-not-allowed disabled:opacity-40"
+      aria-pressed={active}
+      className="flex h-8 w-8 items-center justify-center rounded-lg border border-transparent text-text-muted transition-colors aria-pressed:bg-accent/10 aria-pressed:text-accent hover:border-border/30 hover:bg-bg-tertiary/50 hover:text-text focus-visible:border-border-focus focus-visible:shadow-[0_0_0_1px_var(--color-border-focus)] outline-none disabled:cursor-not-allowed disabled:opacity-40"
       title={label}
       aria-label={label}
     >
@@ -179,6 +190,48 @@ function ToolbarIconButton({
   );
 }
 
+function blameTitle(hunk: NoteBlame["hunks"][number]): string {
+  if (hunk.author === "user") {
+    return hunk.commit_id ? "Written by you" : "Written by you (not yet in history)";
+  }
+  const model = [hunk.provider, hunk.model].filter(Boolean).join(" / ");
+  return model ? `Written by meld (${model})` : "Written by meld";
+}
+
+function BlameView({ blame }: { blame: NoteBlame }) {
+  const total = blame.agent_lines + blame.user_lines;
+  const agentShare = total > 0 ? Math.round((blame.agent_lines / total) * 100) : 0;
+  return (
+    <div className="space-y-3">
+      <p className="text-xs text-text-muted">
+        {blame.agent_lines} of {total} lines ({agentShare}%) last written by meld
+      </p>
+      <div className="font-mono text-[12px] leading-relaxed">
+        {blame.hunks.map((hunk) => (
+          <div
+            key={hunk.start_line}
+            title={blameTitle(hunk)}
+            className={`border-l-2 pl-3 ${
+              hunk.author === "user"
+                ? "border-transparent text-text-secondary"
+                : "border-accent/60 bg-accent/[0.06] text-text"
+            }`}
+          >
+            {hunk.lines.map((line, index) => (
+              <div key={index} className="flex gap-3">
+                <span className="w-8 shrink-0 select-none text-right text-text-muted/60">
+                  {hunk.start_line + index}
+                </span>
+                <span className="whitespace-pre-wrap break-words">{line || " "}</span>
+              </div>
+            ))}
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
+
 export default function NotePreview({
   notePath,
   content,
@@ -191,6 +244,28 @@ export default function NotePreview({
   onOpenInEditor,
 }: NotePreviewProps) {
   const parsedNote = useMemo(() => parseNoteContent(content), [content]);
+  const [showAuthorship, setShowAuthorship] = useState(false);
+  const [blame, setBlame] = useState<NoteBlame | null>(null);
+  const [blameError, setBlameError] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!showAuthorship || !notePath) return;
+    let cancelled = false;
+    getNoteBlame(notePath)
+      .then((result) => {
+        if (cancelled) return;
+        setBlame(result);
+        setBlameError(null);
+      })
+      .catch((error) => {
+        if (cancelled) return;
+        setBlame(null);
+        setBlameError(String(error));
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [showAuthorship, notePath, content]);
   const isExternalMarkdownHref = (href: string): boolean =>
     /^(https?:\/\/|mailto:|tel:)/i.test(href.trim());
 
@@ -253,6 +328,14 @@ export default function NotePreview({
             >
               <ExternalLink className="h-3.5 w-3.5" strokeWidth={1.6} />
             </ToolbarIconButton>
+            <ToolbarIconButton
+              label={showAuthorship ? "Hide AI authorship" : "Show AI authorship"}
+              disabled={!notePath || loading}
+              active={showAuthorship}
+              onClick={() => setShowAuthorship((current) => !current)}
+            >
+              <Sparkles className="h-3.5 w-3.5" strokeWidth={1.6} />
+            </ToolbarIconButton>
             <ToolbarIconButton
               label="Back"
               disabled={!canGoBack}
@@ -284,6 +367,16 @@ export default function NotePreview({
           <div className="flex h-full items-center justify-center text-sm text-text-muted">
             Unable to load note preview.
           </div>
+        ) : showAuthorship ? (
+          blameError ? (
+            <div className="text-sm text-error">{blameError}</div>
+          ) : blame ? (
+            <BlameView blame={blame} />
+          ) : (
+            <div className="flex h-full items-center justify-center">
+              <div className="h-5 w-5 animate-spin rounded-full border-2 border-text-muted/60 border-t-transparent" />
+            </div>
+          )
         ) : (
           <div className="space-y-4">
             <FrontmatterBlock data={parsedNote.frontmatter} />
@@ -82,7 +82,7 @@ export default function VaultSwitcherScreen({
 
       await setupEventListeners();
       const config = await getConfig();
-      const resolvedEmbeddingProvider = resolveEmbeddingProviderForIndexing(config);
+      const resolvedEmbeddingProvider = await resolveEmbeddingProviderForIndexing(config);
       const currentEmbeddingProvider =
         config.embedding_provider?.trim().toLowerCase() || "openai";
       if (!resolvedEmbeddingProvider) {
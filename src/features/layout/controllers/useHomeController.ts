@@ -19,6 +19,7 @@ import {
   getConversationMessages,
   getVaultInfo,
   listArchivedConversations,
+  listConversationStagedChanges,
   listConversations,
   listVaultEntries,
   listVaultFiles,
@@ -29,6 +30,9 @@ import {
   regenerateLastResponse,
   reorderConversations,
   reindex,
+  pauseReindex,
+  resumeReindex,
+  cancelReindex,
   renameConversation,
   sendMessage,
   setEmbeddingModel,
@@ -62,6 +66,18 @@ import {
 } from "@/lib/providerCredentials";
 import { buildIndexingDisabledToast, buildReindexErrorToast } from "@/lib/chatErrors";
 
+/** Persisted messages of a conversation, with the dry-run changes still awaiting review. */
+async function loadConversationMessages(conversationId: string): Promise<Message[]> {
+  const [messages, stagedCounts] = await Promise.all([
+    getConversationMessages(conversationId),
+    listConversationStagedChanges(conversationId).catch((error) => {
+      console.error("Failed to load staged changes:", error);
+      return {} as Record<string, number>;
+    }),
+  ]);
+  return messages.map((message) => normalizeMessage(message, stagedCounts));
+}
+
 export function useHomeController() {
   const {
     isOnboarded,
@@ -73,6 +89,7 @@ export function useHomeController() {
     chatProvider,
     chatModel,
     isIndexing,
+    indexPaused,
     indexProgress,
     conversations,
     activeConversationId,
@@ -108,6 +125,7 @@ export function useHomeController() {
       chatProvider: state.chatProvider,
       chatModel: state.chatModel,
       isIndexing: state.isIndexing,
+      indexPaused: state.indexPaused,
       indexProgress: state.indexProgress,
       conversations: state.conversations,
       activeConversationId: state.activeConversationId,
@@ -440,8 +458,7 @@ export function useHomeController() {
       setPendingFolderForNewChatId(null);
       setActiveConversation(conversationId);
       try {
-        const result = await getConversationMessages(conversationId);
-        setMessages(result.map((message) => normalizeMessage(message)));
+        setMessages(await loadConversationMessages(conversationId));
       } catch (error) {
         console.error("Failed to load conversation messages:", error);
         useAppStore.getState().showToast("Failed to load messages");
@@ -532,14 +549,14 @@ export function useHomeController() {
         message,
         store.activeConversationId,
         pendingFolderId,
+        store.dryRunMode,
       );
       const conversationId = String(response.conversation_id);
       if (!sameConversation(store.activeConversationId, conversationId)) {
         store.setActiveConversation(conversationId);
       }
 
-      const persistedMessages = await getConversationMessages(conversationId);
-      setMessages(persistedMessages.map((item) => normalizeMessage(item)));
+      setMessages(await loadConversationMessages(conversationId));
       await loadConversations();
       setPendingFolderForNewChatId(null);
     },
@@ -572,10 +589,9 @@ export function useHomeController() {
           normalizedAssistantId,
         );
 
-        const refreshed = await getConversationMessages(
-          String(store.activeConversationId),
+        setMessages(
+          await loadConversationMessages(String(store.activeConversationId)),
         );
-        setMessages(refreshed.map((item) => normalizeMessage(item)));
         await loadConversations();
       } catch (error) {
         store.setStreaming(false);
@@ -665,7 +681,7 @@ export function useHomeController() {
 
     try {
       const config = await getConfig();
-      const resolvedEmbeddingProvider = resolveEmbeddingProviderForIndexing(config);
+      const resolvedEmbeddingProvider = await resolveEmbeddingProviderForIndexing(config);
       const currentEmbeddingProvider =
         config.embedding_provider?.trim().toLowerCase() || "openai";
 
@@ -698,6 +714,35 @@ export function useHomeController() {
     }
   }, []);
 
+  const handlePauseReindex = useCallback(async () => {
+    try {
+      if (await pauseReindex()) {
+        useAppStore.getState().setIndexPaused(true);
+      }
+    } catch (error) {
+      console.error("Failed to pause reindex:", error);
+    }
+  }, []);
+
+  const handleResumeReindex = useCallback(async () => {
+    useAppStore.getState().setIndexPaused(false);
+    try {
+      await resumeReindex();
+    } catch (error) {
+      console.error("Failed to resume reindex:", error);
+      const toast = buildReindexErrorToast(error);
+      useAppStore.getState().showToast(toast.message, toast.options);
+    }
+  }, []);
+
+  const handleCancelReindex = useCallback(async () => {
+    try {
+      await cancelReindex();
+    } catch (error) {
+      console.error("Failed to cancel reindex:", error);
+    }
+  }, []);
+
   const handleReorderConversations = useCallback(
     async (conversationIds: Conversation["id"][]) => {
       const normalizedIds = conversationIds
@@ -764,8 +809,9 @@ export function useHomeController() {
         return;
       }
 
-      const refreshed = await getConversationMessages(store.activeConversationId);
-      const normalizedMessages = refreshed.map((message) => normalizeMessage(message));
+      const normalizedMessages = await loadConversationMessages(
+        store.activeConversationId,
+      );
       setMessages(normalizedMessages);
 
       const activeConversation = store.conversations.find((conversation) =>
@@ -1164,6 +1210,7 @@ export function useHomeController() {
       chatProvider,
       chatModel,
       isIndexing,
+      indexPaused,
       indexProgress,
       conversations,
       activeConversationId,
@@ -1223,6 +1270,9 @@ export function useHomeController() {
       handleDeleteMessage,
       handleEditMessage,
       handleReindex,
+      handlePauseReindex,
+      handleResumeReindex,
+      handleCancelReindex,
     },
   };
 }
@@ -192,6 +192,7 @@ const parseTimeline = (value: unknown): TimelineStep[] | undefined => {
 
 export const normalizeMessage = (
   message: ConversationMessagePayload,
+  stagedCounts: Record<string, number> = {},
 ): Message => {
   const role =
     message.role === "assistant" || message.role === "tool"
@@ -221,6 +222,10 @@ export const normalizeMessage = (
     sources: parseStringArray(message.sources),
     toolCalls: parsedToolCalls,
     timelineSteps: parsedTimeline,
+    stagedChanges:
+      role === "assistant" && runId && stagedCounts[runId] > 0
+        ? stagedCounts[runId]
+        : undefined,
   };
 };
 
@@ -127,7 +127,7 @@ export function buildReindexErrorToast(raw: unknown): ChatErrorToast {
 export function buildIndexingDisabledToast(): ChatErrorToast {
   return {
     message:
-      "Background indexing is disabled. Add OpenAI or Google key in Settings.",
+      "Background indexing is disabled. Add OpenAI or Google key in Settings, or start Ollama or LM Studio.",
     options: { action: "open_settings", durationMs: 9000 },
   };
 }
@@ -1,6 +1,7 @@
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { useAppStore, type AppState, type ToolResultEvent } from "./store";
 import { buildChatErrorToast, buildReindexErrorToast } from "@/lib/chatErrors";
+import type { IndexProgress } from "@/lib/tauri";
 
 let unlisteners: UnlistenFn[] = [];
 let activeSetup: Promise<void> | null = null;
@@ -53,7 +54,12 @@ function extractSourcesFromToolResult(tool: string, rawResult: unknown): string[
     }
   }
 
-  if (tool === "kb_read" || tool === "kb_create" || tool === "kb_update") {
+  if (
+    tool === "kb_read" ||
+    tool === "kb_create" ||
+    tool === "kb_update" ||
+    tool === "kb_patch"
+  ) {
     const target = parsed.target;
     if (target && typeof target === "object") {
       const resolvedPath = (target as Record<string, unknown>).resolved_path;
@@ -112,6 +118,7 @@ interface ChatDonePayload {
   sources?: unknown;
   thinking_summary?: string;
   timestamp?: string | number;
+  staged_changes?: number;
 }
 
 function parseChatDonePayload(payload: unknown): ChatDonePayload {
@@ -198,6 +205,7 @@ async function doSetupEventListeners() {
         toolCallLog: [] as AppState["toolCallLog"],
         toolResultsLog: [] as AppState["toolResultsLog"],
         timelineSteps: [] as AppState["timelineSteps"],
+        pendingApprovals: [] as AppState["pendingApprovals"],
         streamSuppressed: false,
       };
 
@@ -250,6 +258,10 @@ async function doSetupEventListeners() {
             state.toolCallLog.length > 0 ? [...state.toolCallLog] : undefined,
           timelineSteps:
             state.timelineSteps.length > 0 ? [...state.timelineSteps] : undefined,
+          stagedChanges:
+            typeof payload.staged_changes === "number" && payload.staged_changes > 0
+              ? payload.staged_changes
+              : undefined,
         };
 
         useAppStore.setState((s) => ({
@@ -274,6 +286,7 @@ async function doSetupEventListeners() {
         toolCallLog: [] as AppState["toolCallLog"],
         toolResultsLog: [] as AppState["toolResultsLog"],
         timelineSteps: [] as AppState["timelineSteps"],
+        pendingApprovals: [] as AppState["pendingApprovals"],
         streamSuppressed: false,
       };
 
@@ -299,6 +312,7 @@ async function doSetupEventListeners() {
         toolCallLog: [],
         toolResultsLog: [],
         timelineSteps: [],
+        pendingApprovals: [],
         streamSuppressed: false,
       });
     }),
@@ -307,7 +321,7 @@ async function doSetupEventListeners() {
   /* ── Agent lifecycle ─────────────────────────────────── */
 
   unlisteners.push(
-    await listen<{ state: string; iteration?: number }>(
+    await listen<{ state: string; iteration?: number; reason?: string }>(
       "agent:run_state",
       (event) => {
         const p = event.payload;
@@ -326,6 +340,12 @@ async function doSetupEventListeners() {
           useAppStore
             .getState()
             .setAgentActivity({ type: "tool", iteration: p.iteration });
+        } else if (s === "awaiting_approval") {
+          useAppStore.getState().setAgentActivity({
+            type: "awaiting_approval",
+            tool: p.reason,
+            iteration: p.iteration,
+          });
         } else if (s === "verifying") {
           useAppStore
             .getState()
@@ -433,6 +453,43 @@ async function doSetupEventListeners() {
     }),
   );
 
+  /* ── Write approvals ─────────────────────────────────── */
+
+  unlisteners.push(
+    await listen<{
+      run_id?: string;
+      approval_id: string;
+      tool: string;
+      args?: Record<string, unknown>;
+      preview?: {
+        path?: string;
+        exists?: boolean;
+        content?: string;
+        diff?: string;
+        error?: string;
+      };
+    }>("agent:approval_request", (event) => {
+      const p = event.payload;
+      useAppStore.getState().addPendingApproval({
+        approvalId: p.approval_id,
+        runId: p.run_id,
+        tool: p.tool,
+        args: p.args ?? {},
+        path: p.preview?.path,
+        exists: p.preview?.exists,
+        content: p.preview?.content,
+        diff: p.preview?.diff,
+        error: p.preview?.error,
+      });
+    }),
+  );
+
+  unlisteners.push(
+    await listen<{ approval_id: string }>("agent:approval_decision", (event) => {
+      useAppStore.getState().removePendingApproval(event.payload.approval_id);
+    }),
+  );
+
   /* ── Agent timeline ──────────────────────────────────── */
 
   unlisteners.push(
@@ -473,17 +530,16 @@ async function doSetupEventListeners() {
   /* ── Indexing ────────────────────────────────────────── */
 
   unlisteners.push(
-    await listen<{ current: number; total: number; file: string }>(
-      "index:progress",
-      (event) => {
-        useAppStore.getState().setIndexProgress(event.payload);
-      },
-    ),
+    await listen<IndexProgress>("index:progress", (event) => {
+      useAppStore.getState().setIndexing(true);
+      useAppStore.getState().setIndexProgress(event.payload);
+    }),
   );
 
   unlisteners.push(
     await listen("index:done", () => {
       useAppStore.getState().setIndexing(false);
+      useAppStore.getState().setIndexPaused(false);
       useAppStore.getState().setIndexProgress(null);
     }),
   );
@@ -494,6 +550,7 @@ async function doSetupEventListeners() {
       const toast = buildReindexErrorToast(event.payload);
       store.showToast(toast.message, toast.options);
       store.setIndexing(false);
+      store.setIndexPaused(false);
       store.setIndexProgress(null);
     }),
   );
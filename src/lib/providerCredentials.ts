@@ -1,4 +1,4 @@
-import type { Config } from "@/lib/tauri";
+import { probeLocalProvider, type Config } from "@/lib/tauri";
 
 const LEGACY_PROVIDER_KEY_FIELDS: Partial<Record<string, keyof Config>> = {
   openai: "openai_api_key",
@@ -6,14 +6,22 @@ const LEGACY_PROVIDER_KEY_FIELDS: Partial<Record<string, keyof Config>> = {
   google: "google_api_key",
 };
 
-const EMBEDDING_PROVIDER_PRIORITY = ["google", "openai"];
+export const LLM_PROVIDER_PRIORITY = ["google", "openai", "anthropic", "openrouter"];
+
+export const EMBEDDING_PROVIDER_PRIORITY = ["google", "openai", "ollama", "lm_studio"];
+
+// Local runtimes run on this machine and need no API key, but are only usable once
+// their base URL is configured or they answer a probe.
+const LOCAL_PROVIDERS = ["ollama", "lm_studio"];
 
 export const DEFAULT_EMBEDDING_MODELS: Record<string, string> = {
   openai: "text-embedding-3-small",
   google: "gemini-embedding-001",
+  ollama: "nomic-embed-text",
+  lm_studio: "text-embedding-nomic-embed-text-v1.5",
 };
 
-function uniqueLower(values: string[]): string[] {
+export function uniqueLower(values: string[]): string[] {
   const unique: string[] = [];
   for (const raw of values) {
     const normalized = raw.trim().toLowerCase();
@@ -23,9 +31,20 @@ function uniqueLower(values: string[]): string[] {
   return unique;
 }
 
+function hasConfiguredBaseUrl(config: Config, provider: string): boolean {
+  return (config.provider_instances ?? []).some(
+    (instance) =>
+      instance.id.trim().toLowerCase() === provider &&
+      instance.base_url.trim().length > 0,
+  );
+}
+
 export function providerHasCredential(config: Config, provider: string): boolean {
   const normalizedProvider = provider.trim().toLowerCase();
   if (!normalizedProvider) return false;
+  if (LOCAL_PROVIDERS.includes(normalizedProvider)) {
+    return hasConfiguredBaseUrl(config, normalizedProvider);
+  }
 
   if (config.auth_modes?.[normalizedProvider] === "oauth") {
     const oauthTokens = config.oauth_tokens as Record<string, unknown> | undefined;
@@ -42,20 +61,28 @@ export function providerHasCredential(config: Config, provider: string): boolean
   return typeof resolved === "string" && resolved.trim().length > 0;
 }
 
-export function resolveEmbeddingProviderForIndexing(config: Config): string | null {
+async function localProviderReachable(provider: string): Promise<boolean> {
+  if (!LOCAL_PROVIDERS.includes(provider)) return false;
+  try {
+    return await probeLocalProvider(provider);
+  } catch {
+    return false;
+  }
+}
+
+export async function resolveEmbeddingProviderForIndexing(
+  config: Config,
+): Promise<string | null> {
   const currentEmbeddingProvider = (
     config.embedding_provider?.trim().toLowerCase() || "openai"
   );
   const candidates = uniqueLower([
     currentEmbeddingProvider,
     ...EMBEDDING_PROVIDER_PRIORITY,
-  ]);
-  return (
-    candidates.find(
-      (provider) =>
-        Boolean(DEFAULT_EMBEDDING_MODELS[provider]) &&
-        providerHasCredential(config, provider),
-    ) ?? null
-  );
+  ]).filter((provider) => Boolean(DEFAULT_EMBEDDING_MODELS[provider]));
+  for (const provider of candidates) {
+    if (providerHasCredential(config, provider)) return provider;
+    if (await localProviderReachable(provider)) return provider;
+  }
+  return null;
 }
-
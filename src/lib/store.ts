@@ -1,4 +1,5 @@
 import { create } from "zustand";
+import type { IndexProgress } from "@/lib/tauri";
 
 /* ── Types ─────────────────────────────────────────────── */
 
@@ -52,6 +53,7 @@ export interface Message {
   sources?: string[];
   toolCalls?: ToolCallEvent[];
   timelineSteps?: TimelineStep[];
+  stagedChanges?: number;
 }
 
 export interface Conversation {
@@ -85,13 +87,28 @@ export type AgentActivity =
   | { type: "thinking"; thinkingSummary?: string; iteration?: number }
   | { type: "tool"; tool?: string; iteration?: number }
   | { type: "verifying"; tool?: string; iteration?: number }
+  | { type: "awaiting_approval"; tool?: string; iteration?: number }
   | { type: "responding" };
 
+export interface WriteApprovalRequest {
+  approvalId: string;
+  runId?: string;
+  tool: string;
+  args: Record<string, unknown>;
+  path?: string;
+  exists?: boolean;
+  content?: string;
+  diff?: string;
+  error?: string;
+}
+
 export interface HistoryEntry {
   id: string;
   message: string;
   timestamp: number;
   files_changed: string[];
+  author: "meld" | "user";
+  run_id: string | null;
 }
 
 export type ToastAction = "open_settings";
@@ -135,6 +152,7 @@ export interface AppState {
   isStreaming: boolean;
   streamingContent: string;
   streamSuppressed: boolean;
+  dryRunMode: boolean;
 
   // Agent
   agentActivity: AgentActivity | null;
@@ -143,10 +161,12 @@ export interface AppState {
   toolCallLog: ToolCallEvent[];
   toolResultsLog: ToolResultEvent[];
   timelineSteps: TimelineStep[];
+  pendingApprovals: WriteApprovalRequest[];
 
   // Indexing
   isIndexing: boolean;
-  indexProgress: { current: number; total: number; file: string } | null;
+  indexPaused: boolean;
+  indexProgress: IndexProgress | null;
 
   // Config
   chatProvider: string;
@@ -189,6 +209,7 @@ export interface AppState {
   newChat: () => void;
   setStreaming: (v: boolean) => void;
   setStreamSuppressed: (v: boolean) => void;
+  setDryRunMode: (v: boolean) => void;
   appendStreamingContent: (chunk: string) => void;
   clearStreamingContent: () => void;
 
@@ -198,6 +219,8 @@ export interface AppState {
   addThinkingLog: (entry: ThinkingEntry) => void;
   clearThinkingLog: () => void;
   addToolCallLog: (entry: ToolCallEvent) => void;
+  addPendingApproval: (request: WriteApprovalRequest) => void;
+  removePendingApproval: (approvalId: string) => void;
   clearToolCallLog: () => void;
   addToolResultLog: (entry: ToolResultEvent) => void;
   clearToolResultLog: () => void;
@@ -206,9 +229,8 @@ export interface AppState {
 
   // Actions — indexing
   setIndexing: (v: boolean) => void;
-  setIndexProgress: (
-    p: { current: number; total: number; file: string } | null,
-  ) => void;
+  setIndexPaused: (v: boolean) => void;
+  setIndexProgress: (p: IndexProgress | null) => void;
 
   // Toast
   toastMessage: string | null;
@@ -251,13 +273,16 @@ export const useAppStore = create<AppState>((set) => ({
   isStreaming: false,
   streamingContent: "",
   streamSuppressed: false,
+  dryRunMode: false,
   agentActivity: null,
   latestThinkingSummary: null,
   thinkingLog: [],
   toolCallLog: [],
   toolResultsLog: [],
   timelineSteps: [],
+  pendingApprovals: [],
   isIndexing: false,
+  indexPaused: false,
   indexProgress: null,
   chatProvider: "openai",
   chatModel: "gpt-4o",
@@ -366,6 +391,7 @@ export const useAppStore = create<AppState>((set) => ({
       toolCallLog: [],
       toolResultsLog: [],
       timelineSteps: [],
+      pendingApprovals: [],
     }),
   newChat: () =>
     set({
@@ -379,9 +405,11 @@ export const useAppStore = create<AppState>((set) => ({
       toolCallLog: [],
       toolResultsLog: [],
       timelineSteps: [],
+      pendingApprovals: [],
     }),
   setStreaming: (v) => set({ isStreaming: v }),
   setStreamSuppressed: (v) => set({ streamSuppressed: v }),
+  setDryRunMode: (v) => set({ dryRunMode: v }),
   appendStreamingContent: (chunk) => {
     pendingChunks += chunk;
     if (!flushScheduled) {
@@ -419,6 +447,19 @@ export const useAppStore = create<AppState>((set) => ({
   addToolCallLog: (entry) =>
     set((s) => ({ toolCallLog: [...s.toolCallLog, entry] })),
   clearToolCallLog: () => set({ toolCallLog: [] }),
+  addPendingApproval: (request) =>
+    set((s) => ({
+      pendingApprovals: [
+        ...s.pendingApprovals.filter((entry) => entry.approvalId !== request.approvalId),
+        request,
+      ],
+    })),
+  removePendingApproval: (approvalId) =>
+    set((s) => ({
+      pendingApprovals: s.pendingApprovals.filter(
+        (entry) => entry.approvalId !== approvalId,
+      ),
+    })),
   addToolResultLog: (entry) =>
     set((s) => ({ toolResultsLog: [...s.toolResultsLog, entry] })),
   clearToolResultLog: () => set({ toolResultsLog: [] }),
@@ -428,6 +469,7 @@ export const useAppStore = create<AppState>((set) => ({
 
   // Indexing
   setIndexing: (v) => set({ isIndexing: v }),
+  setIndexPaused: (v) => set({ indexPaused: v }),
   setIndexProgress: (p) => set({ indexProgress: p }),
 
   // Toast
@@ -8,6 +8,22 @@ export interface VaultInfo {
   total_size_bytes: number;
 }
 
+export interface IndexFileError {
+  file: string;
+  error: string;
+}
+
+export interface IndexProgress {
+  current: number;
+  total: number;
+  file: string;
+  embedded_chunks: number;
+  skipped: number;
+  files_per_second: number;
+  eta_seconds: number | null;
+  errors: IndexFileError[];
+}
+
 export interface VaultFileEntry {
   path: string;
   relative_path: string;
@@ -39,6 +55,7 @@ export interface FolderPayload {
   icon: string | null;
   custom_instruction: string | null;
   default_model_id: string | null;
+  branch_mode: boolean;
   parent_id: string | null;
   pinned: boolean;
   archived: boolean;
@@ -82,12 +99,24 @@ export interface Config {
   search_provider: string | null;
   searxng_base_url: string | null;
   recent_vaults: string[];
+  provider_instances: ProviderInstanceConfig[];
   openai_api_key: string | null;
   anthropic_api_key: string | null;
   google_api_key: string | null;
   tavily_api_key: string | null;
 }
 
+/** OpenAI-compatible endpoint registered as provider `id`; a built-in id overrides its URL. */
+export interface ProviderInstanceConfig {
+  id: string;
+  display_name: string | null;
+  base_url: string;
+  api_key: string | null;
+  headers: Record<string, string>;
+  chat: boolean;
+  embeddings: boolean;
+}
+
 export interface ProviderCatalogEntry {
   id: string;
   display_name: string;
@@ -110,6 +139,91 @@ export interface HistoryEntry {
   message: string;
   timestamp: number;
   files_changed: string[];
+  author: "meld" | "user";
+  run_id: string | null;
+}
+
+export interface HistoryQuery {
+  path?: string;
+  /** `next_cursor` of the previous page. */
+  after?: string;
+  limit?: number;
+  author?: "agent" | "user";
+  /** Unix seconds, inclusive. */
+  since?: number;
+  until?: number;
+  message?: string;
+}
+
+export interface HistoryPage {
+  entries: HistoryEntry[];
+  next_cursor: string | null;
+}
+
+export interface CommitDiff {
+  id: string;
+  message: string;
+  timestamp: number;
+  files_changed: string[];
+  patch: string;
+}
+
+/** A conversation branch compared with the vault history it forked from. */
+export interface BranchDiff {
+  branch: string;
+  base_id: string;
+  head_id: string;
+  commits: number;
+  files_changed: string[];
+  patch: string;
+}
+
+export interface BranchMerge {
+  commit_id: string;
+  fast_forward: boolean;
+  files_changed: string[];
+  conflicts: string[];
+}
+
+export interface ConversationBranch {
+  branch_mode: boolean;
+  /** Branch mode comes from a folder, not the conversation itself. */
+  inherited: boolean;
+  diff: BranchDiff | null;
+}
+
+export interface BlameHunk {
+  /** 1-based line number of the first line. */
+  start_line: number;
+  lines: string[];
+  author: "meld" | "user";
+  /** null for hand edits not snapshotted yet. */
+  commit_id: string | null;
+  timestamp: number | null;
+  run_id: string | null;
+  provider: string | null;
+  model: string | null;
+}
+
+export interface NoteBlame {
+  path: string;
+  hunks: BlameHunk[];
+  agent_lines: number;
+  user_lines: number;
+}
+
+export type ConflictStyle = "markers" | "copy";
+
+export interface RevertFileOutcome {
+  path: string;
+  status: "reverted" | "conflict" | "conflict_copy" | "kept";
+  conflict_copy?: string;
+}
+
+export interface RevertOutcome {
+  commit_id: string;
+  files: RevertFileOutcome[];
+  conflicts: number;
 }
 
 export interface RunSummaryPayload {
@@ -155,6 +269,18 @@ export async function reindex(): Promise<void> {
   return invoke("reindex");
 }
 
+export async function pauseReindex(): Promise<boolean> {
+  return invoke<boolean>("pause_reindex");
+}
+
+export async function resumeReindex(): Promise<void> {
+  return invoke("resume_reindex");
+}
+
+export async function cancelReindex(): Promise<boolean> {
+  return invoke<boolean>("cancel_reindex");
+}
+
 export async function listVaultFiles(): Promise<VaultFileEntry[]> {
   return invoke<VaultFileEntry[]>("list_vault_files");
 }
@@ -239,6 +365,13 @@ export async function unarchiveConversation(
   return invoke("unarchive_conversation", { conversationId });
 }
 
+export async function setConversationBranchMode(
+  conversationId: string,
+  enabled: boolean,
+): Promise<void> {
+  return invoke("set_conversation_branch_mode", { conversationId, enabled });
+}
+
 export async function pinConversation(
   conversationId: string,
 ): Promise<void> {
@@ -263,6 +396,64 @@ export async function cancelActiveRun(
   return invoke<boolean>("cancel_active_run", { conversationId });
 }
 
+export async function respondToWriteApproval(
+  approvalId: string,
+  approved: boolean,
+  reason?: string,
+): Promise<boolean> {
+  return invoke<boolean>("respond_to_write_approval", {
+    approvalId,
+    approved,
+    reason: reason ?? null,
+  });
+}
+
+export interface StagedChange {
+  run_id: string;
+  path: string;
+  action: "create" | "edit";
+  hash_before: string | null;
+  hash_after: string;
+  content: string;
+  diff: string;
+  updated_at: string;
+}
+
+export async function listStagedChanges(
+  runId: string,
+): Promise<StagedChange[]> {
+  return invoke<StagedChange[]>("list_staged_changes", { runId });
+}
+
+/** Pending staged change counts of a conversation's dry runs, keyed by run id. */
+export async function listConversationStagedChanges(
+  conversationId: string,
+): Promise<Record<string, number>> {
+  return invoke<Record<string, number>>("list_conversation_staged_changes", {
+    conversationId,
+  });
+}
+
+export async function applyStagedChanges(
+  runId: string,
+  paths?: string[] | null,
+): Promise<string[]> {
+  return invoke<string[]>("apply_staged_changes", {
+    runId,
+    paths: paths ?? null,
+  });
+}
+
+export async function discardStagedChanges(
+  runId: string,
+  paths?: string[] | null,
+): Promise<number> {
+  return invoke<number>("discard_staged_changes", {
+    runId,
+    paths: paths ?? null,
+  });
+}
+
 export async function exportConversation(
   conversationId: string,
   filePath: string,
@@ -281,11 +472,13 @@ export async function sendMessage(
   message: string,
   conversationId?: string | null,
   folderId?: string | null,
+  dryRun?: boolean,
 ): Promise<SendMessageResponse> {
   return invoke<SendMessageResponse>("send_message", {
     message,
     conversationId: conversationId ?? null,
     folderId: folderId ?? null,
+    dryRun: dryRun ?? false,
   });
 }
 
@@ -337,6 +530,11 @@ export async function getProviderCatalog(): Promise<ProviderCatalogEntry[]> {
   return invoke<ProviderCatalogEntry[]>("get_provider_catalog");
 }
 
+/** Whether a local runtime (Ollama, LM Studio) is configured or answers on this machine. */
+export async function probeLocalProvider(provider: string): Promise<boolean> {
+  return invoke<boolean>("probe_local_provider", { provider });
+}
+
 export async function setApiKey(
   provider: string,
   key: string,
@@ -412,6 +610,13 @@ export async function setSearxngBaseUrl(url: string): Promise<void> {
   return invoke("set_searxng_base_url", { url });
 }
 
+export async function setWriteApproval(
+  mode: "auto" | "always" | "outside_folders",
+  folders: string[],
+): Promise<void> {
+  return invoke("set_write_approval", { mode, folders });
+}
+
 /* ── Chat Folders ──────────────────────────────────────── */
 
 export async function createChatFolder(
@@ -455,6 +660,13 @@ export async function updateChatFolder(
   });
 }
 
+export async function setFolderBranchMode(
+  folderId: string,
+  enabled: boolean,
+): Promise<void> {
+  return invoke("set_folder_branch_mode", { folderId, enabled });
+}
+
 export async function archiveChatFolder(folderId: string): Promise<void> {
   return invoke("archive_chat_folder", { folderId });
 }
@@ -507,12 +719,69 @@ export async function migrateChatFoldersFromLocal(
 
 /* ── History ───────────────────────────────────────────── */
 
-export async function getHistory(): Promise<HistoryEntry[]> {
-  return invoke<HistoryEntry[]>("get_history");
+export async function getHistory(query?: HistoryQuery): Promise<HistoryPage> {
+  return invoke<HistoryPage>("get_history", { query });
+}
+
+export async function revertCommit(
+  commitId: string,
+  conflictStyle?: ConflictStyle,
+): Promise<RevertOutcome> {
+  return invoke<RevertOutcome>("revert_commit", { commitId, conflictStyle });
+}
+
+export async function revertRun(
+  runId: string,
+  conflictStyle?: ConflictStyle,
+): Promise<RevertOutcome> {
+  return invoke<RevertOutcome>("revert_run", { runId, conflictStyle });
+}
+
+/** Restore one note to its content at `commitId`; null when it already matched. */
+export async function restoreFile(
+  path: string,
+  commitId: string,
+): Promise<CommitDiff | null> {
+  return invoke<CommitDiff | null>("restore_file", { path, commitId });
 }
 
-export async function revertCommit(commitId: string): Promise<void> {
-  return invoke("revert_commit", { commitId });
+export async function readFileAt(path: string, commitId: string): Promise<string> {
+  return invoke<string>("read_file_at", { path, commitId });
+}
+
+export async function getNoteBlame(path: string): Promise<NoteBlame> {
+  return invoke<NoteBlame>("get_note_blame", { path });
+}
+
+export async function getConversationBranch(
+  conversationId: string,
+): Promise<ConversationBranch> {
+  return invoke<ConversationBranch>("get_conversation_branch", { conversationId });
+}
+
+/** Merge a conversation's branch into the vault; null when it had nothing to merge. */
+export async function mergeConversationBranch(
+  conversationId: string,
+): Promise<BranchMerge | null> {
+  return invoke<BranchMerge | null>("merge_conversation_branch", { conversationId });
+}
+
+export async function discardConversationBranch(
+  conversationId: string,
+): Promise<boolean> {
+  return invoke<boolean>("discard_conversation_branch", { conversationId });
+}
+
+/** One line per conflicted note, or null when the revert merged cleanly. */
+export function describeRevertConflicts(outcome: RevertOutcome): string | null {
+  const lines = outcome.files
+    .filter((file) => file.status === "conflict" || file.status === "conflict_copy")
+    .map((file) =>
+      file.conflict_copy
+        ? `${file.path}: reverted lines saved to ${file.conflict_copy}`
+        : `${file.path}: conflict markers added`,
+    );
+  return lines.length > 0 ? lines.join("\n") : null;
 }
 
 /* ── Misc ──────────────────────────────────────────────── */
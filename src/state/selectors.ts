@@ -7,12 +7,14 @@ export const selectChatViewState = (state: AppState) => ({
   agentActivity: state.agentActivity,
   timelineSteps: state.timelineSteps,
   thinkingLog: state.thinkingLog,
+  pendingApprovals: state.pendingApprovals,
 });
 
 export const selectMessageInputState = (state: AppState) => ({
   isStreaming: state.isStreaming,
   streamSuppressed: state.streamSuppressed,
-  isIndexing: state.isIndexing,
+  // A paused index leaves chat usable.
+  isIndexing: state.isIndexing && !state.indexPaused,
   activeConversationId: state.activeConversationId,
 });
 